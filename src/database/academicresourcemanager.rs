@@ -40,29 +40,40 @@ impl AcademicResourceManager {
         
        
 
-        info!("Applying schema...");
-        // create migrate the schema
-        db.run_script(
-            SCHEMA,
-            Default::default(),
-            ScriptMutability::Mutable,
-        )
-        .map_err(|e| {
-            error!("Failed to apply schema: {}", e);
-            e
-        })?;
-
-        info!("Applying HNSW index...");
-        // Create HNSW index for vector search
-        db.run_script(
-            HNSW_INDEX,
-            Default::default(),
-            ScriptMutability::Mutable,
-        )
-        .map_err(|e| {
-            error!("Failed to create HNSW index: {}", e);
-            e
-        })?;
+        // persistent databases already carry the schema when reopened
+        let relations = db.run_script("::relations", Default::default(), ScriptMutability::Immutable)?;
+        let has_schema = relations
+            .rows
+            .iter()
+            .any(|r| r.first().and_then(|v| v.get_str()) == Some("entity"));
+
+        if has_schema {
+            info!("Schema already present, skipping creation.");
+        } else {
+            info!("Applying schema...");
+            // create migrate the schema
+            db.run_script(
+                SCHEMA,
+                Default::default(),
+                ScriptMutability::Mutable,
+            )
+            .map_err(|e| {
+                error!("Failed to apply schema: {}", e);
+                e
+            })?;
+
+            info!("Applying HNSW index...");
+            // Create HNSW index for vector search
+            db.run_script(
+                HNSW_INDEX,
+                Default::default(),
+                ScriptMutability::Mutable,
+            )
+            .map_err(|e| {
+                error!("Failed to create HNSW index: {}", e);
+                e
+            })?;
+        }
         info!("Database initialized successfully.");
 
 
@@ -163,6 +174,18 @@ mod tests {
         assert_eq!(arm.path.unwrap(), PathBuf::from(path));
     }
 
+    #[test]
+    fn test_academic_resource_manager_reopen() {
+        let path = "test1_db_reopen.sqlite";
+        remove_if_exists(path);
+
+        let arm = AcademicResourceManager::new(Engine::SQLite, path).unwrap();
+        drop(arm);
+        // the schema already exists on the second open
+        let arm = AcademicResourceManager::new(Engine::SQLite, path);
+        assert!(arm.is_ok());
+    }
+
     #[test]
     fn test_academic_resource_manager_display() {
         let path = "test1_db_display.sqlite";
@@ -0,0 +1,491 @@
+use std::collections::BTreeMap;
+use serde_json::{json, Map, Value};
+use crate::database::academicresourcemanager::AcademicResourceManager;
+use crate::database::error::RepositoryError;
+use crate::database::records::{from_json, to_json, Edge, Entity};
+use crate::domain::{Affiliation, AffiliationPeriod, Author, Name, Orcid};
+use crate::utils::text::slugify;
+
+pub const AUTHOR_KIND: &str = "author";
+pub const INSTITUTION_KIND: &str = "institution";
+pub const AUTHORED: &str = "authored";
+pub const EDITED: &str = "edited";
+pub const AFFILIATED_WITH: &str = "affiliated_with";
+// Key of the history periods on an `affiliated_with` edge, next to the current affiliation's fields.
+pub const PERIODS: &str = "periods";
+
+// ORCID is the only identity we trust; without it the id is derived from the name.
+pub fn author_id(author: &Author) -> String {
+    match &author.orcid {
+        Some(orcid) => format!("author:orcid:{}", orcid.as_str()),
+        None => {
+            let name = &author.name;
+            let key = [name.family(), name.suffix.clone().unwrap_or_default(), name.given()].join(" ");
+            format!("author:{}", slugify(&key))
+        }
+    }
+}
+
+pub fn institution_id(name: &str) -> String {
+    format!("institution:{}", slugify(name))
+}
+
+// 1-based position of a contributor edge; edges without one sort last.
+fn position(edge: &Edge) -> u64 {
+    edge.props
+        .as_ref()
+        .and_then(|p| p.get("position"))
+        .and_then(Value::as_u64)
+        .unwrap_or(u64::MAX)
+}
+
+// The current affiliation and the periods of the history with no years.
+fn undated(author: Author) -> Vec<AffiliationPeriod> {
+    let periods = author.history.into_iter().filter(|p| !p.is_dated());
+    author.affiliation.map(AffiliationPeriod::new).into_iter().chain(periods).collect()
+}
+
+fn props_map(props: Option<Value>) -> Map<String, Value> {
+    match props {
+        Some(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+/// Props of an `affiliated_with` edge once `affiliation` is the current one at its institution:
+/// the fields of the previous current affiliation are replaced, the periods in `props` are kept.
+pub fn with_current_affiliation(props: Option<Value>, affiliation: &Affiliation) -> Result<Value, RepositoryError> {
+    let mut props = props_map(props);
+    props.retain(|k, _| k == PERIODS);
+    props.extend(props_map(Some(to_json(affiliation)?)));
+    Ok(Value::Object(props))
+}
+
+// Props merged in from aliases (or set by other importers) on `current` survive a re-save; the
+// ones derived from `author` are rewritten, and a stored ORCID is kept when `author` has none.
+fn author_entity(id: &str, author: &Author, history: &[AffiliationPeriod], current: Option<Entity>) -> Result<Entity, RepositoryError> {
+    let (mut props, uri) = match current {
+        Some(Entity { props: Some(Value::Object(props)), uri, .. }) => (props, uri),
+        Some(entity) => (Map::new(), entity.uri),
+        None => (Map::new(), None),
+    };
+    props.insert("name".to_string(), to_json(&author.name)?);
+    if let Some(orcid) = &author.orcid {
+        props.insert("orcid".to_string(), json!(orcid.as_str()));
+    }
+    // an affiliation without an institution has no node to point at, keep it on the author
+    match author.affiliation.as_ref().filter(|a| a.institution.is_none()) {
+        Some(affiliation) => props.insert("affiliation".to_string(), to_json(affiliation)?),
+        None => props.remove("affiliation"),
+    };
+    match history.is_empty() {
+        true => props.remove("history"),
+        false => props.insert("history".to_string(), to_json(&history)?),
+    };
+    let mut builder = Entity::builder()
+        .id(id)
+        .kind(AUTHOR_KIND)
+        .title(author.name.to_string())
+        .props(Value::Object(props));
+    if let Some(uri) = author.orcid.as_ref().map(Orcid::url).or(uri) {
+        builder = builder.uri(uri);
+    }
+    builder.build()
+}
+
+impl AcademicResourceManager {
+    /// Upserts an author node with its affiliation edges and tags, returning the author id.
+    /// Authors merged away by the resolver are not recreated: the id resolves to the survivor,
+    /// whose record is left as it is. Re-saving a survivor keeps what the merge gave it, such
+    /// as the alias's ORCID.
+    ///
+    /// The current affiliation replaces the previous one. Periods of the history replace the
+    /// stored history only when the author carries one, so importers that know nothing about
+    /// it leave it alone. An institution has a single edge for the current affiliation and
+    /// every period spent there.
+    pub fn save_author(&self, author: &Author) -> Result<String, RepositoryError> {
+        let id = author_id(author);
+        let survivor = self.resolve_alias(&id)?;
+        if survivor != id {
+            return Ok(survivor);
+        }
+
+        // one edge per institution, holding the current affiliation and the periods spent there
+        let replace_history = !author.history.is_empty();
+        let mut edges: BTreeMap<String, Map<String, Value>> = BTreeMap::new();
+        for old in self.edges_from(&id, Some(AFFILIATED_WITH))? {
+            let mut props = props_map(old.props);
+            props.retain(|k, _| k == PERIODS && !replace_history);
+            edges.insert(old.dst, props);
+        }
+        // periods without an institution live on the author node
+        let mut unplaced: Vec<AffiliationPeriod> = match replace_history {
+            true => Vec::new(),
+            false => self.load_history_props(&id)?,
+        };
+        let mut periods: BTreeMap<String, Vec<AffiliationPeriod>> = BTreeMap::new();
+        for period in &author.history {
+            match &period.affiliation.institution {
+                Some(institution) => periods.entry(self.institution_node(institution)?).or_default().push(period.clone()),
+                None => unplaced.push(period.clone()),
+            }
+        }
+        for (inst_id, spells) in periods {
+            edges.entry(inst_id).or_default().insert(PERIODS.to_string(), to_json(&spells)?);
+        }
+        if let Some(affiliation) = &author.affiliation
+            && let Some(institution) = &affiliation.institution
+        {
+            let props = edges.entry(self.institution_node(institution)?).or_default();
+            props.extend(props_map(Some(to_json(affiliation)?)));
+        }
+        for (inst_id, props) in edges {
+            match props.is_empty() {
+                true => self.delete_edge(&id, &inst_id, AFFILIATED_WITH)?,
+                false => self.upsert_edge(&Edge::new(&id, &inst_id, AFFILIATED_WITH).with_props(Value::Object(props)))?,
+            }
+        }
+        let current = self.get_entity(&id)?.filter(|e| e.kind == AUTHOR_KIND);
+        self.upsert_entity(&author_entity(&id, author, &unplaced, current)?)?;
+
+        let current = self.tags_of(&id)?;
+        for tag in current.iter().filter(|t| !author.tags.contains(t)) {
+            self.untag_entity(&id, tag)?;
+        }
+        for tag in author.tags.iter().filter(|t| !current.contains(t)) {
+            self.tag_entity(&id, tag)?;
+        }
+        Ok(id)
+    }
+
+    // The institution node for a free-text name, created on first use.
+    fn institution_node(&self, institution: &str) -> Result<String, RepositoryError> {
+        let inst_id = self.resolve_alias(&institution_id(institution))?;
+        // keep whatever an earlier import already knows about the institution
+        if self.get_entity(&inst_id)?.is_none() {
+            let node = Entity::builder()
+                .id(&inst_id)
+                .kind(INSTITUTION_KIND)
+                .title(institution)
+                .build()?;
+            self.upsert_entity(&node)?;
+        }
+        Ok(inst_id)
+    }
+
+    fn load_history_props(&self, id: &str) -> Result<Vec<AffiliationPeriod>, RepositoryError> {
+        match self.get_entity(id)?.and_then(|e| e.props).and_then(|mut p| p.get_mut("history").map(Value::take)) {
+            Some(history) => from_json(&history),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn load_author(&self, id: &str) -> Result<Option<Author>, RepositoryError> {
+        let Some(entity) = self.get_entity(id)? else {
+            return Ok(None);
+        };
+        if entity.kind != AUTHOR_KIND {
+            return Err(RepositoryError::Conversion(format!("entity `{id}` is a {}, not an author", entity.kind)));
+        }
+        let props = entity.props.unwrap_or(Value::Null);
+        let name: Name = match props.get("name") {
+            Some(n) => from_json(n)?,
+            None => return Err(RepositoryError::Conversion(format!("author `{id}` has no name"))),
+        };
+        let orcid = props
+            .get("orcid")
+            .and_then(Value::as_str)
+            .map(|o| Orcid::parse(o).map_err(|e| RepositoryError::Conversion(e.to_string())))
+            .transpose()?;
+
+        let mut history: Vec<AffiliationPeriod> = match props.get("history") {
+            Some(h) => from_json(h)?,
+            None => Vec::new(),
+        };
+        let mut affiliation: Option<Affiliation> = None;
+        for edge in self.edges_from(id, Some(AFFILIATED_WITH))? {
+            let mut fields = props_map(edge.props);
+            if let Some(periods) = fields.remove(PERIODS) {
+                history.extend(from_json::<Vec<AffiliationPeriod>>(&periods)?);
+            }
+            if affiliation.is_none() && !fields.is_empty() {
+                affiliation = Some(from_json(&Value::Object(fields))?);
+            }
+        }
+        let affiliation = match affiliation {
+            Some(affiliation) => Some(affiliation),
+            None => props.get("affiliation").map(from_json).transpose()?,
+        };
+        history.sort_by_key(|p| (p.start, p.end));
+
+        Ok(Some(Author {
+            name,
+            orcid,
+            affiliation,
+            history,
+            tags: self.tags_of(id)?,
+        }))
+    }
+
+    /// Affiliations of an author during `year`: the periods covering it or, when none does,
+    /// the undated affiliations.
+    pub fn affiliations_at(&self, author_id: &str, year: i64) -> Result<Vec<AffiliationPeriod>, RepositoryError> {
+        let author = self.load_author(author_id)?.ok_or_else(|| RepositoryError::NotFound(author_id.to_string()))?;
+        let dated: Vec<AffiliationPeriod> = author
+            .history
+            .iter()
+            .filter(|p| p.is_dated() && p.covers(year))
+            .cloned()
+            .collect();
+        Ok(if dated.is_empty() { undated(author) } else { dated })
+    }
+
+    /// Where the author was when publishing `work_id`, going by the year of the work. Works
+    /// without a year give the undated affiliations.
+    pub fn affiliations_for_work(&self, author_id: &str, work_id: &str) -> Result<Vec<AffiliationPeriod>, RepositoryError> {
+        let work = self.get_entity(work_id)?.ok_or_else(|| RepositoryError::NotFound(work_id.to_string()))?;
+        match work.year {
+            Some(year) => self.affiliations_at(author_id, year),
+            None => {
+                let author = self.load_author(author_id)?.ok_or_else(|| RepositoryError::NotFound(author_id.to_string()))?;
+                Ok(undated(author))
+            }
+        }
+    }
+
+    /// Every author of `work_id`, in author-list order, with their affiliations at the time.
+    pub fn work_affiliations(&self, work_id: &str) -> Result<Vec<(String, Vec<AffiliationPeriod>)>, RepositoryError> {
+        let mut edges = self.edges_to(work_id, Some(AUTHORED))?;
+        edges.sort_by_key(position);
+        let mut out = Vec::with_capacity(edges.len());
+        for edge in edges {
+            let affiliations = self.affiliations_for_work(&edge.src, work_id)?;
+            out.push((edge.src, affiliations));
+        }
+        Ok(out)
+    }
+
+    /// Links an author to a work; `position` is 1-based in the author list.
+    pub fn link_author(&self, author_id: &str, work_id: &str, position: usize) -> Result<(), RepositoryError> {
+        self.link_contributor(author_id, work_id, AUTHORED, position)
+    }
+
+    fn link_contributor(&self, person_id: &str, work_id: &str, role: &str, position: usize) -> Result<(), RepositoryError> {
+        let edge = Edge::new(person_id, work_id, role).with_props(json!({ "position": position }));
+        self.upsert_edge(&edge)
+    }
+
+    /// Saves `authors` and makes them the ordered author list of `work_id`.
+    pub fn save_work_authors(&self, work_id: &str, authors: &[Author]) -> Result<Vec<String>, RepositoryError> {
+        self.save_contributors(work_id, authors, AUTHORED)
+    }
+
+    /// Same as `save_work_authors` for the editor list.
+    pub fn save_work_editors(&self, work_id: &str, editors: &[Author]) -> Result<Vec<String>, RepositoryError> {
+        self.save_contributors(work_id, editors, EDITED)
+    }
+
+    fn save_contributors(&self, work_id: &str, people: &[Author], role: &str) -> Result<Vec<String>, RepositoryError> {
+        for old in self.edges_to(work_id, Some(role))? {
+            self.delete_edge(&old.src, &old.dst, &old.kind)?;
+        }
+        let mut ids = Vec::with_capacity(people.len());
+        for (i, person) in people.iter().enumerate() {
+            let id = self.save_author(person)?;
+            self.link_contributor(&id, work_id, role, i + 1)?;
+            ids.push(id);
+        }
+        Ok(ids)
+    }
+
+    /// Authors of a work in author-list order.
+    pub fn authors_of(&self, work_id: &str) -> Result<Vec<Author>, RepositoryError> {
+        self.contributors_of(work_id, AUTHORED)
+    }
+
+    pub fn editors_of(&self, work_id: &str) -> Result<Vec<Author>, RepositoryError> {
+        self.contributors_of(work_id, EDITED)
+    }
+
+    fn contributors_of(&self, work_id: &str, role: &str) -> Result<Vec<Author>, RepositoryError> {
+        let mut edges = self.edges_to(work_id, Some(role))?;
+        edges.sort_by_key(position);
+        let mut people = Vec::with_capacity(edges.len());
+        for edge in edges {
+            if let Some(person) = self.load_author(&edge.src)? {
+                people.push(person);
+            }
+        }
+        Ok(people)
+    }
+
+    pub fn works_of(&self, author_id: &str) -> Result<Vec<Entity>, RepositoryError> {
+        let mut works = Vec::new();
+        for edge in self.edges_from(author_id, Some(AUTHORED))? {
+            if let Some(work) = self.get_entity(&edge.dst)? {
+                works.push(work);
+            }
+        }
+        Ok(works)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Engine;
+
+    fn arm() -> AcademicResourceManager {
+        AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap()
+    }
+
+    fn jane() -> Author {
+        Author::builder()
+            .name_from_str("Jane H. Smith").unwrap()
+            .orcid_from_str("0000-0002-1825-0097").unwrap()
+            .affiliation_from_str("University X; Department Y; 123 Street; Country Z").unwrap()
+            .tags(vec!["physics".to_string()]).unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_author_roundtrip() {
+        let arm = arm();
+        let author = jane();
+        let id = arm.save_author(&author).unwrap();
+        assert_eq!(id, "author:orcid:0000-0002-1825-0097");
+
+        let loaded = arm.load_author(&id).unwrap().unwrap();
+        assert_eq!(loaded.name, author.name);
+        assert_eq!(loaded.orcid, author.orcid);
+        assert_eq!(loaded.affiliation, author.affiliation);
+        assert_eq!(loaded.tags, author.tags);
+
+        let inst = arm.get_entity(&institution_id("University X")).unwrap().unwrap();
+        assert_eq!(inst.kind, INSTITUTION_KIND);
+    }
+
+    #[test]
+    fn test_author_resave_replaces_tags_and_affiliation() {
+        let arm = arm();
+        let mut author = jane();
+        let id = arm.save_author(&author).unwrap();
+
+        author.tags = vec!["astronomy".to_string()];
+        author.affiliation = Some(Affiliation::parse("; Department Z"));
+        arm.save_author(&author).unwrap();
+
+        let loaded = arm.load_author(&id).unwrap().unwrap();
+        assert_eq!(loaded.tags, vec!["astronomy"]);
+        assert_eq!(loaded.affiliation, author.affiliation);
+        assert!(arm.edges_from(&id, Some(AFFILIATED_WITH)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_work_authors_order() {
+        let arm = arm();
+        let work = Entity::builder().id("w1").kind("paper").title("Work").build().unwrap();
+        arm.upsert_entity(&work).unwrap();
+
+        let second = Author::builder().name_from_str("John Doe").unwrap().build().unwrap();
+        let ids = arm.save_work_authors("w1", &[jane(), second]).unwrap();
+        assert_eq!(ids[1], "author:doe-john");
+
+        let authors = arm.authors_of("w1").unwrap();
+        assert_eq!(authors.len(), 2);
+        assert_eq!(authors[0].name.last, "Smith");
+        assert_eq!(authors[1].name.last, "Doe");
+        assert_eq!(arm.works_of(&ids[1]).unwrap()[0].id, "w1");
+    }
+
+    #[test]
+    fn test_load_author_wrong_kind() {
+        let arm = arm();
+        let work = Entity::builder().id("w1").kind("paper").build().unwrap();
+        arm.upsert_entity(&work).unwrap();
+        assert!(matches!(arm.load_author("w1"), Err(RepositoryError::Conversion(_))));
+        assert!(arm.load_author("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_affiliation_history() {
+        let arm = arm();
+        let period = |institution: &str, start, end, role: &str| {
+            AffiliationPeriod::new(Affiliation::parse(institution)).years(start, end).unwrap().role(role)
+        };
+        let author = Author::builder()
+            .name_from_str("Jane Smith").unwrap()
+            .affiliation(Affiliation::parse("ETH Zurich")).unwrap()
+            .affiliation_period(period("CERN", Some(2015), Some(2018), "postdoc")).unwrap()
+            .affiliation_period(period("ETH Zurich", Some(2019), None, "professor")).unwrap()
+            .affiliation_period(period("; Theory Group", Some(2012), Some(2014), "student")).unwrap()
+            .build()
+            .unwrap();
+        let id = arm.save_author(&author).unwrap();
+        let loaded = arm.load_author(&id).unwrap().unwrap();
+        assert_eq!(loaded.affiliation, author.affiliation);
+        assert_eq!(loaded.history.iter().map(|p| p.start).collect::<Vec<_>>(), vec![Some(2012), Some(2015), Some(2019)]);
+        assert_eq!(arm.edges_from(&id, Some(AFFILIATED_WITH)).unwrap().len(), 2);
+
+        let paper = |wid: &str, year: Option<i64>| {
+            let mut builder = Entity::builder().id(wid).kind("journal_article").title(wid);
+            if let Some(year) = year {
+                builder = builder.year(year);
+            }
+            arm.upsert_entity(&builder.build().unwrap()).unwrap();
+            arm.save_work_authors(wid, std::slice::from_ref(&author)).unwrap();
+        };
+        paper("w2016", Some(2016));
+        paper("w2022", Some(2022));
+        paper("w1990", Some(1990));
+        paper("undated", None);
+        let at = |wid: &str| -> Vec<Option<String>> {
+            let found = arm.affiliations_for_work(&id, wid).unwrap();
+            found.into_iter().map(|p| p.role.or(p.affiliation.institution)).collect()
+        };
+        assert_eq!(at("w2016"), vec![Some("postdoc".to_string())]);
+        assert_eq!(at("w2022"), vec![Some("professor".to_string())]);
+        assert_eq!(at("w1990"), vec![Some("ETH Zurich".to_string())]);
+        assert_eq!(at("undated"), vec![Some("ETH Zurich".to_string())]);
+        assert_eq!(arm.work_affiliations("w2016").unwrap()[0].0, id);
+
+        // an import that knows nothing about the history keeps it
+        let plain = Author::builder().name_from_str("Jane Smith").unwrap().build().unwrap();
+        arm.save_author(&plain).unwrap();
+        assert_eq!(arm.load_author(&id).unwrap().unwrap().history, loaded.history);
+        assert!(matches!(arm.affiliations_for_work(&id, "missing"), Err(RepositoryError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_two_periods_at_one_institution() {
+        let arm = arm();
+        let period = |start, end| AffiliationPeriod::new(Affiliation::parse("CERN")).years(Some(start), Some(end)).unwrap();
+        let author = Author::builder()
+            .name_from_str("Jane Smith").unwrap()
+            .affiliation_period(period(2010, 2012)).unwrap()
+            .affiliation_period(period(2016, 2018)).unwrap()
+            .build()
+            .unwrap();
+        let id = arm.save_author(&author).unwrap();
+        let edges = arm.edges_from(&id, Some(AFFILIATED_WITH)).unwrap();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].props.as_ref().unwrap()[PERIODS].as_array().unwrap().len(), 2);
+
+        // becoming current there keeps both periods on the same edge
+        let mut back = Author::builder().name_from_str("Jane Smith").unwrap().build().unwrap();
+        back.affiliation = Some(Affiliation::parse("CERN"));
+        arm.save_author(&back).unwrap();
+        let loaded = arm.load_author(&id).unwrap().unwrap();
+        assert_eq!(loaded.history.iter().map(|p| p.start).collect::<Vec<_>>(), vec![Some(2010), Some(2016)]);
+        assert_eq!(loaded.affiliation, back.affiliation);
+        assert_eq!(arm.affiliations_at(&id, 2011).unwrap()[0].end, Some(2012));
+        assert_eq!(arm.edges_from(&id, Some(AFFILIATED_WITH)).unwrap().len(), 1);
+
+        // leaving it drops the current affiliation but not the periods
+        arm.save_author(&Author::builder().name_from_str("Jane Smith").unwrap().build().unwrap()).unwrap();
+        let loaded = arm.load_author(&id).unwrap().unwrap();
+        assert_eq!((loaded.affiliation, loaded.history.len()), (None, 2));
+    }
+}
@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RepositoryError {
+    #[error("Database error: {0}")]
+    Database(cozo::Error),
+    #[error("Missing field: {0}")]
+    MissingField(&'static str),
+    #[error("Row conversion error: {0}")]
+    Conversion(String),
+    #[error("Already exists: {0}")]
+    AlreadyExists(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+}
+
+impl From<cozo::Error> for RepositoryError {
+    fn from(e: cozo::Error) -> Self {
+        RepositoryError::Database(e)
+    }
+}
@@ -0,0 +1,158 @@
+use serde_json::{json, Map, Value};
+use crate::database::academicresourcemanager::AcademicResourceManager;
+use crate::database::authors::{institution_id, INSTITUTION_KIND};
+use crate::database::error::RepositoryError;
+use crate::database::records::{from_json, Edge, Entity};
+use crate::domain::institution::Institution;
+use crate::domain::ror::RorId;
+
+/// Edge from a part of an organization (a department, a lab) to the organization it belongs to.
+pub const PART_OF: &str = "part_of";
+
+pub fn ror_institution_id(ror: &RorId) -> String {
+    format!("institution:ror:{}", ror.as_str())
+}
+
+impl AcademicResourceManager {
+    /// Upserts an institution node, returning its id: `institution:ror:<id>` when the ROR id is
+    /// known, the slug of the name otherwise. Every name and alias becomes an `alias_of` edge, so
+    /// affiliations naming any of them land on this node; acronyms only when no other institution
+    /// claimed them first.
+    pub fn save_institution(&self, institution: &Institution) -> Result<String, RepositoryError> {
+        let slug = institution_id(&institution.name);
+        let id = match &institution.ror {
+            Some(ror) => ror_institution_id(ror),
+            None => self.resolve_alias(&slug)?,
+        };
+        let existing = self.get_entity(&id)?;
+        // reached through an alias: the node keeps its own name
+        let title = match &existing {
+            Some(entity) if institution.ror.is_none() && id != slug => entity.title.clone(),
+            _ => institution.name.clone(),
+        };
+        let mut props = match existing.and_then(|e| e.props) {
+            Some(Value::Object(map)) => map,
+            _ => Map::new(),
+        };
+        if let Value::Object(fields) = json!({
+            "ror": institution.ror,
+            "aliases": institution.aliases,
+            "acronyms": institution.acronyms,
+            "country_code": institution.country_code,
+            "types": institution.types,
+        }) {
+            props.extend(fields.into_iter().filter(|(_, v)| !v.is_null()));
+        }
+        let mut builder = Entity::builder()
+            .id(&id)
+            .kind(INSTITUTION_KIND)
+            .title(title)
+            .props(Value::Object(props));
+        if let Some(ror) = &institution.ror {
+            builder = builder.uri(ror.url());
+        }
+        self.upsert_entity(&builder.build()?)?;
+
+        let names = institution.names().filter(|n| !institution.acronyms.iter().any(|a| a == n));
+        for name in names {
+            self.claim_alias(&institution_id(name), &id, INSTITUTION_KIND, name, true)?;
+        }
+        for acronym in &institution.acronyms {
+            self.claim_alias(&institution_id(acronym), &id, INSTITUTION_KIND, acronym, false)?;
+        }
+        Ok(id)
+    }
+
+    pub fn load_institution(&self, id: &str) -> Result<Option<Institution>, RepositoryError> {
+        let Some(entity) = self.get_entity(id)? else {
+            return Ok(None);
+        };
+        if entity.kind != INSTITUTION_KIND {
+            return Err(RepositoryError::Conversion(format!("entity `{id}` is a {}, not an institution", entity.kind)));
+        }
+        let props = entity.props.unwrap_or(Value::Null);
+        let list = |name: &str| -> Result<Vec<String>, RepositoryError> {
+            props.get(name).map(from_json).transpose().map(Option::unwrap_or_default)
+        };
+        Ok(Some(Institution {
+            name: entity.title,
+            ror: props.get("ror").map(from_json).transpose()?,
+            aliases: list("aliases")?,
+            acronyms: list("acronyms")?,
+            country_code: props.get("country_code").and_then(Value::as_str).map(str::to_string),
+            types: list("types")?,
+        }))
+    }
+
+    /// The institution node a free-text institution name refers to, following aliases.
+    pub fn find_institution(&self, name: &str) -> Result<Option<String>, RepositoryError> {
+        let id = self.resolve_alias(&institution_id(name))?;
+        Ok(self.get_entity(&id)?.filter(|e| e.kind == INSTITUTION_KIND).map(|e| e.id))
+    }
+
+    /// Records that `child` (e.g. a department) is part of `parent`.
+    pub fn set_parent_institution(&self, child: &str, parent: &str) -> Result<(), RepositoryError> {
+        self.upsert_edge(&Edge::new(child, parent, PART_OF))
+    }
+
+    pub fn parent_institutions(&self, id: &str) -> Result<Vec<String>, RepositoryError> {
+        Ok(self.edges_from(id, Some(PART_OF))?.into_iter().map(|e| e.dst).collect())
+    }
+
+    pub fn child_institutions(&self, id: &str) -> Result<Vec<String>, RepositoryError> {
+        Ok(self.edges_to(id, Some(PART_OF))?.into_iter().map(|e| e.src).collect())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::authors::AFFILIATED_WITH;
+    use crate::database::Engine;
+    use crate::domain::{Affiliation, Author};
+
+    #[test]
+    fn test_institution_registry() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let author = Author::builder()
+            .name_from_str("Jane Smith").unwrap()
+            .affiliation(Affiliation::parse("Massachusetts Institute of Technology; Department of Physics")).unwrap()
+            .build()
+            .unwrap();
+        let jane = arm.save_author(&author).unwrap();
+
+        let mit = Institution {
+            ror: Some(RorId::parse("042nb2s44").unwrap()),
+            acronyms: vec!["MIT".to_string()],
+            country_code: Some("US".to_string()),
+            ..Institution::new("Massachusetts Institute of Technology")
+        };
+        let id = arm.save_institution(&mit).unwrap();
+        assert_eq!(id, "institution:ror:042nb2s44");
+        assert_eq!(arm.load_institution(&id).unwrap().unwrap(), mit);
+        // the free-text node was folded into the registry entry
+        assert!(arm.get_entity("institution:massachusetts-institute-of-technology").unwrap().is_none());
+        assert_eq!(arm.edges_from(&jane, Some(AFFILIATED_WITH)).unwrap()[0].dst, id);
+
+        // a later affiliation by acronym reaches the same node
+        let bob = Author::builder()
+            .name_from_str("Bob Lee").unwrap()
+            .affiliation(Affiliation::parse("MIT; CSAIL")).unwrap()
+            .build()
+            .unwrap();
+        let bob = arm.save_author(&bob).unwrap();
+        assert_eq!(arm.edges_from(&bob, Some(AFFILIATED_WITH)).unwrap()[0].dst, id);
+        assert_eq!(arm.find_institution("mit").unwrap(), Some(id.clone()));
+
+        // acronyms already claimed by another institution stay with it
+        let other = Institution { acronyms: vec!["MIT".to_string()], ..Institution::new("Manipal Institute of Technology") };
+        arm.save_institution(&other).unwrap();
+        assert_eq!(arm.find_institution("MIT").unwrap(), Some(id.clone()));
+
+        let csail = arm.save_institution(&Institution::new("CSAIL")).unwrap();
+        arm.set_parent_institution(&csail, &id).unwrap();
+        assert_eq!(arm.parent_institutions(&csail).unwrap(), vec![id.clone()]);
+        assert_eq!(arm.child_institutions(&id).unwrap(), vec![csail]);
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+use cozo::DataValue;
+use serde_json::{json, Map, Value};
+use crate::database::academicresourcemanager::AcademicResourceManager;
+use crate::database::error::RepositoryError;
+use crate::database::records::{from_json, Edge, Entity};
+use crate::database::repository::params;
+use crate::domain::method::Method;
+use crate::utils::text::slugify;
+
+pub const METHOD_KIND: &str = "method";
+/// Edge from a work to a method it applies.
+pub const USES_METHOD: &str = "uses_method";
+/// Edge from a work to a method it first described.
+pub const INTRODUCES_METHOD: &str = "introduces_method";
+/// Edge from a method to the more general method it refines.
+pub const VARIANT_OF: &str = "variant_of";
+/// Work prop naming the field the work belongs to.
+pub const WORK_FIELD: &str = "field";
+
+pub fn method_id(name: &str) -> String {
+    format!("method:{}", slugify(name))
+}
+
+// Rules binding `desc[m]` to `$method` and every method that is, transitively, a variant of it.
+const DESCENDANTS: &str = r#"
+    desc[m] := m = $method
+    desc[m] := desc[p], *edge{src: m, dst: p, kind: $variant_of}
+"#;
+
+impl AcademicResourceManager {
+    /// Upserts a method node under the slug of its name, or the node an alias already points to.
+    /// Aliases become `alias_of` edges, the parent a `variant_of` edge (creating the parent when
+    /// unknown) and the canonical reference an `introduces_method` edge.
+    pub fn save_method(&self, method: &Method) -> Result<String, RepositoryError> {
+        let id = self.resolve_alias(&method_id(&method.name))?;
+        let existing = self.get_entity(&id)?;
+        let title = match &existing {
+            Some(entity) if id != method_id(&method.name) => entity.title.clone(),
+            _ => method.name.clone(),
+        };
+        let mut props = match existing.and_then(|e| e.props) {
+            Some(Value::Object(map)) => map,
+            _ => Map::new(),
+        };
+        if let Value::Object(fields) = json!({
+            "aliases": method.aliases,
+            "field": method.field,
+            "reference": method.reference,
+        }) {
+            props.extend(fields.into_iter().filter(|(_, v)| !v.is_null()));
+        }
+        let entity = Entity::builder().id(&id).kind(METHOD_KIND).title(title).props(Value::Object(props)).build()?;
+        self.upsert_entity(&entity)?;
+
+        for name in method.names() {
+            self.claim_alias(&method_id(name), &id, METHOD_KIND, name, true)?;
+        }
+        if let Some(parent) = method.parent.as_deref().filter(|p| !p.is_empty()) {
+            let pid = self.node_for(&method_id(parent), METHOD_KIND, parent)?;
+            if pid != id {
+                self.replace_edge(&id, &pid, VARIANT_OF)?;
+            }
+        }
+        if let Some(work) = &method.reference {
+            self.upsert_edge(&Edge::new(work, &id, INTRODUCES_METHOD))?;
+        }
+        Ok(id)
+    }
+
+    pub fn load_method(&self, id: &str) -> Result<Option<Method>, RepositoryError> {
+        let Some(entity) = self.get_entity(id)? else {
+            return Ok(None);
+        };
+        if entity.kind != METHOD_KIND {
+            return Err(RepositoryError::Conversion(format!("entity `{id}` is a {}, not a method", entity.kind)));
+        }
+        let props = entity.props.unwrap_or(Value::Null);
+        let parent = match self.edges_from(id, Some(VARIANT_OF))?.first() {
+            Some(edge) => self.get_entity(&edge.dst)?.map(|e| e.title),
+            None => None,
+        };
+        let text = |name: &str| props.get(name).and_then(Value::as_str).map(str::to_string);
+        Ok(Some(Method {
+            name: entity.title.clone(),
+            aliases: props.get("aliases").map(from_json).transpose()?.unwrap_or_default(),
+            parent,
+            field: text("field"),
+            reference: text("reference"),
+        }))
+    }
+
+    /// The method node a name or alias refers to.
+    pub fn find_method(&self, name: &str) -> Result<Option<String>, RepositoryError> {
+        let id = self.resolve_alias(&method_id(name))?;
+        Ok(self.get_entity(&id)?.filter(|e| e.kind == METHOD_KIND).map(|e| e.id))
+    }
+
+    /// Records that `work` uses the method named `method`, creating the method when unknown.
+    /// Returns the method id.
+    pub fn tag_method(&self, work: &str, method: &str) -> Result<String, RepositoryError> {
+        let id = self.node_for(&method_id(method), METHOD_KIND, method)?;
+        self.upsert_edge(&Edge::new(work, &id, USES_METHOD))?;
+        Ok(id)
+    }
+
+    /// Records that `work` introduced the method named `method`.
+    pub fn introduce_method(&self, work: &str, method: &str) -> Result<String, RepositoryError> {
+        let id = self.node_for(&method_id(method), METHOD_KIND, method)?;
+        self.upsert_edge(&Edge::new(work, &id, INTRODUCES_METHOD))?;
+        Ok(id)
+    }
+
+    /// Drops the `uses_method` edge from `work` to the method named `method`, if any.
+    pub fn untag_method(&self, work: &str, method: &str) -> Result<(), RepositoryError> {
+        match self.find_method(method)? {
+            Some(id) => self.delete_edge(work, &id, USES_METHOD),
+            None => Ok(()),
+        }
+    }
+
+    /// Methods a work uses or introduced.
+    pub fn methods_of(&self, work: &str) -> Result<Vec<String>, RepositoryError> {
+        let mut methods: Vec<String> = self
+            .edges_from(work, Some(USES_METHOD))?
+            .into_iter()
+            .chain(self.edges_from(work, Some(INTRODUCES_METHOD))?)
+            .map(|e| e.dst)
+            .collect();
+        methods.sort();
+        methods.dedup();
+        Ok(methods)
+    }
+
+    /// `method` and every method that is, directly or through others, a variant of it.
+    pub fn method_descendants(&self, method: &str) -> Result<Vec<String>, RepositoryError> {
+        let script = format!("{DESCENDANTS} ?[m] := desc[m]");
+        self.query_ids(
+            &script,
+            params([("method", DataValue::from(method)), ("variant_of", DataValue::from(VARIANT_OF))]),
+        )
+    }
+
+    /// Works using or introducing `method` or any of its descendants.
+    pub fn works_using_method(&self, method: &str) -> Result<Vec<String>, RepositoryError> {
+        let script = format!(
+            "{DESCENDANTS}
+            ?[id] := desc[m], *edge{{src: id, dst: m, kind: $uses}}
+            ?[id] := desc[m], *edge{{src: id, dst: m, kind: $introduces}}"
+        );
+        self.query_ids(
+            &script,
+            params([
+                ("method", DataValue::from(method)),
+                ("variant_of", DataValue::from(VARIANT_OF)),
+                ("uses", DataValue::from(USES_METHOD)),
+                ("introduces", DataValue::from(INTRODUCES_METHOD)),
+            ]),
+        )
+    }
+
+    /// Fields of the works using `method` or its descendants, with how many works each; works
+    /// without a field are left out.
+    pub fn fields_using_method(&self, method: &str) -> Result<BTreeMap<String, usize>, RepositoryError> {
+        let mut fields = BTreeMap::new();
+        for work in self.works_using_method(method)? {
+            let field = self.get_entity(&work)?.and_then(|e| e.props).and_then(|p| p.get(WORK_FIELD).cloned());
+            if let Some(Value::String(field)) = field {
+                *fields.entry(field).or_insert(0) += 1;
+            }
+        }
+        Ok(fields)
+    }
+
+    /// Venues where works using `method` or its descendants appeared, with how many works each.
+    pub fn venues_using_method(&self, method: &str) -> Result<BTreeMap<String, usize>, RepositoryError> {
+        self.venues_of(&self.works_using_method(method)?)
+    }
+
+    pub fn set_work_field(&self, work: &str, field: &str) -> Result<(), RepositoryError> {
+        let mut entity = self.get_entity(work)?.ok_or_else(|| RepositoryError::NotFound(work.to_string()))?;
+        let mut props = match entity.props.take() {
+            Some(Value::Object(map)) => map,
+            _ => Map::new(),
+        };
+        props.insert(WORK_FIELD.to_string(), Value::String(field.to_string()));
+        entity.props = Some(Value::Object(props));
+        self.upsert_entity(&entity)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Engine;
+    use crate::domain::sources::{Reference, JOURNAL_ARTICLE};
+
+    fn work(arm: &AcademicResourceManager, title: &str, venue: &str, field: &str) -> String {
+        let reference = Reference {
+            kind: JOURNAL_ARTICLE.to_string(),
+            title: title.to_string(),
+            venue: Some(venue.to_string()),
+            ..Default::default()
+        };
+        let id = arm.save_reference(&reference).unwrap();
+        arm.set_work_field(&id, field).unwrap();
+        id
+    }
+
+    #[test]
+    fn test_method_roundtrip() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let paper = work(&arm, "Equation of State Calculations", "J. Chem. Phys.", "physics");
+        let method = Method {
+            aliases: vec!["MCMC".to_string()],
+            parent: Some("Monte Carlo method".to_string()),
+            field: Some("statistics".to_string()),
+            reference: Some(paper.clone()),
+            ..Method::new("Markov chain Monte Carlo")
+        };
+        let id = arm.save_method(&method).unwrap();
+        assert_eq!(arm.load_method(&id).unwrap().unwrap(), method);
+        assert_eq!(arm.find_method("mcmc").unwrap(), Some(id.clone()));
+        assert_eq!(arm.methods_of(&paper).unwrap(), vec![id.clone()]);
+        // tagging by alias reaches the same node
+        let other = work(&arm, "Bayesian Phylogenetics", "Syst. Biol.", "biology");
+        assert_eq!(arm.tag_method(&other, "MCMC").unwrap(), id);
+        // and so does untagging
+        arm.untag_method(&other, "MCMC").unwrap();
+        assert!(arm.methods_of(&other).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_works_using_method_descendants() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        for (name, parent) in [("Markov chain Monte Carlo", "Monte Carlo method"), ("Gibbs sampling", "Markov chain Monte Carlo")] {
+            arm.save_method(&Method { parent: Some(parent.to_string()), ..Method::new(name) }).unwrap();
+        }
+        let root = arm.find_method("Monte Carlo method").unwrap().unwrap();
+        let mut descendants = arm.method_descendants(&root).unwrap();
+        descendants.sort();
+        assert_eq!(descendants, vec![method_id("Gibbs sampling"), method_id("Markov chain Monte Carlo"), root.clone()]);
+
+        let a = work(&arm, "Topic Models", "JMLR", "computer science");
+        let b = work(&arm, "Phylogenies", "Syst. Biol.", "biology");
+        let c = work(&arm, "Neutron Transport", "Nucl. Sci. Eng.", "physics");
+        let unrelated = work(&arm, "Graph Theory", "JMLR", "mathematics");
+        arm.tag_method(&a, "Gibbs sampling").unwrap();
+        arm.tag_method(&b, "Markov chain Monte Carlo").unwrap();
+        arm.introduce_method(&c, "Monte Carlo method").unwrap();
+        arm.tag_method(&unrelated, "Spectral clustering").unwrap();
+
+        let mut works = arm.works_using_method(&root).unwrap();
+        works.sort();
+        let mut expected = vec![a, b.clone(), c];
+        expected.sort();
+        assert_eq!(works, expected);
+        assert_eq!(arm.works_using_method(&method_id("Markov chain Monte Carlo")).unwrap().len(), 2);
+        assert_eq!(
+            arm.fields_using_method(&root).unwrap(),
+            BTreeMap::from([("biology".to_string(), 1), ("computer science".to_string(), 1), ("physics".to_string(), 1)])
+        );
+        assert_eq!(arm.venues_using_method(&root).unwrap().len(), 3);
+
+        arm.untag_method(&b, "Markov chain Monte Carlo").unwrap();
+        assert_eq!(arm.works_using_method(&root).unwrap().len(), 2);
+    }
+}
@@ -1,5 +1,16 @@
 
 pub mod schema;
 pub mod academicresourcemanager;
+pub mod error;
+pub mod records;
+pub mod repository;
+pub mod authors;
+pub mod institutions;
+pub mod methods;
+pub mod references;
+pub mod topics;
+pub mod venues;
 pub use schema::{SCHEMA, HNSW_INDEX};
-pub use academicresourcemanager::{AcademicResourceManager, Engine};
\ No newline at end of file
+pub use academicresourcemanager::{AcademicResourceManager, Engine};
+pub use error::RepositoryError;
+pub use records::{Entity, EntityBuilder, Edge};
@@ -0,0 +1,274 @@
+use cozo::{DataValue, JsonData};
+use serde_json::Value;
+use crate::database::error::RepositoryError;
+
+// Typed rows of the relations declared in `schema.rs`.
+// Column order of `to_row`/`TryFrom` must match the `*_COLUMNS` constants in `repository.rs`.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+    pub id: String,
+    pub kind: String,
+    pub title: String,
+    pub autors: String,
+    pub uri: Option<String>,
+    pub year: Option<i64>,
+    pub props: Option<Value>,
+}
+
+impl Entity {
+    pub fn builder() -> EntityBuilder {
+        EntityBuilder::default()
+    }
+
+    pub fn to_row(&self) -> Vec<DataValue> {
+        vec![
+            DataValue::from(self.id.as_str()),
+            DataValue::from(self.kind.as_str()),
+            DataValue::from(self.title.as_str()),
+            DataValue::from(self.autors.as_str()),
+            opt_str(&self.uri),
+            self.year.map_or(DataValue::Null, DataValue::from),
+            opt_json(&self.props),
+        ]
+    }
+}
+
+impl TryFrom<&[DataValue]> for Entity {
+    type Error = RepositoryError;
+
+    fn try_from(row: &[DataValue]) -> Result<Self, Self::Error> {
+        if row.len() != 7 {
+            return Err(RepositoryError::Conversion(format!("entity row has {} columns, expected 7", row.len())));
+        }
+        Ok(Entity {
+            id: req_str(&row[0], "id")?,
+            kind: req_str(&row[1], "kind")?,
+            title: req_str(&row[2], "title")?,
+            autors: req_str(&row[3], "autors")?,
+            uri: get_opt_str(&row[4], "uri")?,
+            year: get_opt_int(&row[5], "year")?,
+            props: get_opt_json(&row[6]),
+        })
+    }
+}
+
+
+#[derive(Debug, Default)]
+pub struct EntityBuilder {
+    id: Option<String>,
+    kind: Option<String>,
+    title: Option<String>,
+    autors: Option<String>,
+    uri: Option<String>,
+    year: Option<i64>,
+    props: Option<Value>,
+}
+
+impl EntityBuilder {
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = non_empty(id.into());
+        self
+    }
+
+    pub fn kind(mut self, kind: impl Into<String>) -> Self {
+        self.kind = non_empty(kind.into());
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = non_empty(title.into());
+        self
+    }
+
+    pub fn autors(mut self, autors: impl Into<String>) -> Self {
+        self.autors = non_empty(autors.into());
+        self
+    }
+
+    pub fn uri(mut self, uri: impl Into<String>) -> Self {
+        self.uri = non_empty(uri.into());
+        self
+    }
+
+    pub fn year(mut self, year: i64) -> Self {
+        self.year = Some(year);
+        self
+    }
+
+    pub fn props(mut self, props: Value) -> Self {
+        self.props = match props {
+            Value::Null => None,
+            v => Some(v),
+        };
+        self
+    }
+
+    pub fn build(self) -> Result<Entity, RepositoryError> {
+        let id = self.id.ok_or(RepositoryError::MissingField("id"))?;
+        let kind = self.kind.ok_or(RepositoryError::MissingField("kind"))?;
+        let title = self.title.unwrap_or_default();
+        let autors = self.autors.unwrap_or_default();
+        Ok(Entity {
+            id,
+            kind,
+            title,
+            autors,
+            uri: self.uri,
+            year: self.year,
+            props: self.props,
+        })
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub src: String,
+    pub dst: String,
+    pub kind: String,
+    pub props: Option<Value>,
+}
+
+impl Edge {
+    pub fn new(src: impl Into<String>, dst: impl Into<String>, kind: impl Into<String>) -> Self {
+        Edge {
+            src: src.into(),
+            dst: dst.into(),
+            kind: kind.into(),
+            props: None,
+        }
+    }
+
+    pub fn with_props(mut self, props: Value) -> Self {
+        self.props = match props {
+            Value::Null => None,
+            v => Some(v),
+        };
+        self
+    }
+
+    pub fn to_row(&self) -> Vec<DataValue> {
+        vec![
+            DataValue::from(self.src.as_str()),
+            DataValue::from(self.dst.as_str()),
+            DataValue::from(self.kind.as_str()),
+            opt_json(&self.props),
+        ]
+    }
+}
+
+impl TryFrom<&[DataValue]> for Edge {
+    type Error = RepositoryError;
+
+    fn try_from(row: &[DataValue]) -> Result<Self, Self::Error> {
+        if row.len() != 4 {
+            return Err(RepositoryError::Conversion(format!("edge row has {} columns, expected 4", row.len())));
+        }
+        Ok(Edge {
+            src: req_str(&row[0], "src")?,
+            dst: req_str(&row[1], "dst")?,
+            kind: req_str(&row[2], "kind")?,
+            props: get_opt_json(&row[3]),
+        })
+    }
+}
+
+
+pub(crate) fn to_json<T: serde::Serialize>(value: &T) -> Result<Value, RepositoryError> {
+    serde_json::to_value(value).map_err(|e| RepositoryError::Conversion(e.to_string()))
+}
+
+pub(crate) fn from_json<T: serde::de::DeserializeOwned>(value: &Value) -> Result<T, RepositoryError> {
+    serde_json::from_value(value.clone()).map_err(|e| RepositoryError::Conversion(e.to_string()))
+}
+
+fn non_empty(s: String) -> Option<String> {
+    match s.as_str() {
+        "" => None,
+        _ => Some(s),
+    }
+}
+
+fn opt_str(v: &Option<String>) -> DataValue {
+    v.as_deref().map_or(DataValue::Null, DataValue::from)
+}
+
+fn opt_json(v: &Option<Value>) -> DataValue {
+    v.as_ref().map_or(DataValue::Null, |j| DataValue::Json(JsonData(j.clone())))
+}
+
+pub(crate) fn req_str(v: &DataValue, column: &str) -> Result<String, RepositoryError> {
+    v.get_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| RepositoryError::Conversion(format!("column `{column}` is not a string: {v:?}")))
+}
+
+fn get_opt_str(v: &DataValue, column: &str) -> Result<Option<String>, RepositoryError> {
+    match v {
+        DataValue::Null => Ok(None),
+        _ => req_str(v, column).map(Some),
+    }
+}
+
+fn get_opt_int(v: &DataValue, column: &str) -> Result<Option<i64>, RepositoryError> {
+    match v {
+        DataValue::Null => Ok(None),
+        _ => v
+            .get_int()
+            .map(Some)
+            .ok_or_else(|| RepositoryError::Conversion(format!("column `{column}` is not an integer: {v:?}"))),
+    }
+}
+
+fn get_opt_json(v: &DataValue) -> Option<Value> {
+    // `Json?` columns are coerced on write, so anything but `Json` here is a null
+    match v {
+        DataValue::Json(JsonData(j)) => Some(j.clone()),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_entity_builder_and_row_roundtrip() {
+        let entity = Entity::builder()
+            .id("doi:10.1000/xyz")
+            .kind("paper")
+            .title("A Study")
+            .autors("Jane Smith")
+            .year(2021)
+            .props(json!({"doi": "10.1000/xyz"}))
+            .build()
+            .unwrap();
+        assert!(entity.uri.is_none());
+
+        let row = entity.to_row();
+        let back = Entity::try_from(row.as_slice()).unwrap();
+        assert_eq!(back, entity);
+    }
+
+    #[test]
+    fn test_entity_builder_missing_id() {
+        let result = Entity::builder().kind("paper").build();
+        assert!(matches!(result, Err(RepositoryError::MissingField("id"))));
+    }
+
+    #[test]
+    fn test_edge_row_roundtrip() {
+        let edge = Edge::new("a", "b", "cites").with_props(json!({"weight": 1}));
+        let back = Edge::try_from(edge.to_row().as_slice()).unwrap();
+        assert_eq!(back, edge);
+    }
+
+    #[test]
+    fn test_entity_row_wrong_arity() {
+        let row = vec![DataValue::from("only id")];
+        assert!(matches!(Entity::try_from(row.as_slice()), Err(RepositoryError::Conversion(_))));
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::BTreeMap;
+use serde_json::{Map, Value};
+use crate::database::academicresourcemanager::AcademicResourceManager;
+use crate::database::error::RepositoryError;
+use crate::database::records::{from_json, to_json, Edge, Entity};
+use crate::domain::sources::Reference;
+use crate::domain::types::Work;
+use crate::utils::text::slugify;
+
+pub const VENUE_KIND: &str = "venue";
+pub const PUBLISHED_IN: &str = "published_in";
+pub const CITES: &str = "cites";
+// Prop listing the tags the last import added as keywords, so a re-import can drop stale ones.
+const KEYWORDS: &str = "keywords";
+
+pub fn normalize_doi(doi: &str) -> String {
+    let doi = doi.trim();
+    let lower = doi.to_lowercase();
+    let stripped = ["https://doi.org/", "http://doi.org/", "https://dx.doi.org/", "http://dx.doi.org/", "doi:"]
+        .iter()
+        .find_map(|p| lower.strip_prefix(p))
+        .unwrap_or(&lower);
+    stripped.trim().to_string()
+}
+
+// DOI when there is one, otherwise year and title so the same work imported twice lands on one row.
+pub fn work_id(reference: &Reference) -> String {
+    if let Some(doi) = reference.doi().filter(|d| !d.trim().is_empty()) {
+        return format!("doi:{}", normalize_doi(doi));
+    }
+    let slug = slugify(&reference.title);
+    match (slug.is_empty(), reference.year, &reference.key) {
+        (false, Some(year), _) => format!("work:{year}-{slug}"),
+        (false, None, _) => format!("work:{slug}"),
+        (true, _, Some(key)) => format!("work:key:{}", slugify(key)),
+        (true, _, None) => "work:untitled".to_string(),
+    }
+}
+
+pub fn venue_id(name: &str) -> String {
+    format!("venue:{}", slugify(name))
+}
+
+/// The work entity row for `reference`, on top of the props `props` already holds. Links to
+/// authors, the venue and tags are not part of it.
+pub fn reference_entity(id: &str, reference: &Reference, mut props: Map<String, Value>) -> Result<Entity, RepositoryError> {
+    if let Some(key) = &reference.key {
+        props.entry("citation_key").or_insert_with(|| Value::String(key.clone()));
+    }
+    props.insert("fields".to_string(), to_json(&reference.fields)?);
+    for (name, value) in &reference.extra {
+        props.insert(name.clone(), value.clone());
+    }
+
+    let autors = reference
+        .authors
+        .iter()
+        .map(|a| a.name.to_string())
+        .collect::<Vec<_>>()
+        .join("; ");
+    let mut builder = Entity::builder()
+        .id(id)
+        .kind(&reference.kind)
+        .title(&reference.title)
+        .autors(autors)
+        .props(Value::Object(props));
+    if let Some(uri) = &reference.uri {
+        builder = builder.uri(uri);
+    }
+    if let Some(year) = reference.year {
+        builder = builder.year(year);
+    }
+    builder.build()
+}
+
+impl AcademicResourceManager {
+    /// Saves a work with its authors, editors, venue and keywords, returning the work id.
+    /// Props written by earlier imports or exports (e.g. a citation key) are kept.
+    pub fn save_reference(&self, reference: &Reference) -> Result<String, RepositoryError> {
+        self.save_reference_at(&work_id(reference), reference)
+    }
+
+    /// Like `save_reference`, for importers that already know which entity a record belongs to.
+    pub fn save_reference_at(&self, id: &str, reference: &Reference) -> Result<String, RepositoryError> {
+        let id = id.to_string();
+        let mut props = match self.get_entity(&id)?.and_then(|e| e.props) {
+            Some(Value::Object(map)) => map,
+            _ => Map::new(),
+        };
+        let stale: Vec<String> = match props.insert(KEYWORDS.to_string(), to_json(&reference.keywords)?) {
+            Some(old) => from_json(&old)?,
+            None => Vec::new(),
+        };
+        self.upsert_entity(&reference_entity(&id, reference, props)?)?;
+
+        self.save_work_authors(&id, &reference.authors)?;
+        self.save_work_editors(&id, &reference.editors)?;
+
+        for old in self.edges_from(&id, Some(PUBLISHED_IN))? {
+            self.delete_edge(&old.src, &old.dst, &old.kind)?;
+        }
+        if let Some(venue) = reference.venue.as_deref().filter(|v| !v.is_empty()) {
+            let vid = self.venue_for(venue, reference.field("issn"))?;
+            self.upsert_edge(&Edge::new(&id, &vid, PUBLISHED_IN))?;
+        }
+
+        // tags from other sources (topics, collections) are not keywords and stay
+        for keyword in stale.iter().filter(|k| !reference.keywords.contains(k)) {
+            self.untag_entity(&id, keyword)?;
+        }
+        for keyword in &reference.keywords {
+            self.tag_entity(&id, keyword)?;
+        }
+        Ok(id)
+    }
+
+    /// Tags the last import of `id` added as keywords, without tags from other sources.
+    pub fn keywords_of(&self, id: &str) -> Result<Vec<String>, RepositoryError> {
+        match self.get_entity(id)?.and_then(|e| e.props).and_then(|p| p.get(KEYWORDS).cloned()) {
+            Some(keywords) => from_json(&keywords),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Citation keys remembered in entity props, mapped to the id holding them.
+    pub fn citation_keys(&self) -> Result<BTreeMap<String, String>, RepositoryError> {
+        self.prop_index("citation_key")
+    }
+
+    /// String values of the top-level prop `name`, mapped to the id of the entity holding them.
+    pub fn prop_index(&self, name: &str) -> Result<BTreeMap<String, String>, RepositoryError> {
+        let mut index = BTreeMap::new();
+        for entity in self.list_entities(None)? {
+            if let Some(value) = entity.props.as_ref().and_then(|p| p.get(name)).and_then(Value::as_str) {
+                index.insert(value.to_string(), entity.id.clone());
+            }
+        }
+        Ok(index)
+    }
+
+    pub fn set_citation_key(&self, id: &str, key: &str) -> Result<(), RepositoryError> {
+        let Some(mut entity) = self.get_entity(id)? else {
+            return Err(RepositoryError::Conversion(format!("no entity `{id}`")));
+        };
+        let mut props = match entity.props.take() {
+            Some(Value::Object(map)) => map,
+            _ => Map::new(),
+        };
+        props.insert("citation_key".to_string(), Value::String(key.to_string()));
+        entity.props = Some(Value::Object(props));
+        self.upsert_entity(&entity)
+    }
+
+    pub fn load_reference(&self, id: &str) -> Result<Option<Reference>, RepositoryError> {
+        let Some(entity) = self.get_entity(id)? else {
+            return Ok(None);
+        };
+        let mut props = match entity.props {
+            Some(Value::Object(map)) => map,
+            _ => Map::new(),
+        };
+        let fields: BTreeMap<String, String> = match props.remove("fields") {
+            Some(f) => from_json(&f)?,
+            None => BTreeMap::new(),
+        };
+        let key = props.remove("citation_key").and_then(|k| k.as_str().map(str::to_string));
+        props.remove(KEYWORDS);
+        let venue = match self.edges_from(id, Some(PUBLISHED_IN))?.first() {
+            Some(edge) => self.get_entity(&edge.dst)?.map(|v| v.title),
+            None => None,
+        };
+        Ok(Some(Reference {
+            key,
+            kind: entity.kind,
+            title: entity.title,
+            authors: self.authors_of(id)?,
+            editors: self.editors_of(id)?,
+            year: entity.year,
+            venue,
+            uri: entity.uri,
+            keywords: self.tags_of(id)?,
+            fields,
+            extra: props.into_iter().collect(),
+        }))
+    }
+
+    /// Saves a typed work through its `Reference` form, so it reads back with either loader.
+    pub fn save_work(&self, work: &Work) -> Result<String, RepositoryError> {
+        self.save_reference(&Reference::from(work))
+    }
+
+    pub fn load_work(&self, id: &str) -> Result<Option<Work>, RepositoryError> {
+        self.load_reference(id)?
+            .map(Work::try_from)
+            .transpose()
+            .map_err(|e| RepositoryError::Conversion(e.to_string()))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Engine;
+    use crate::domain::Author;
+
+    #[test]
+    fn test_work_id() {
+        let mut r = Reference { title: "A Study of Things".to_string(), year: Some(2020), ..Default::default() };
+        assert_eq!(work_id(&r), "work:2020-a-study-of-things");
+        r.fields.insert("doi".to_string(), "https://doi.org/10.1000/ABC".to_string());
+        assert_eq!(work_id(&r), "doi:10.1000/abc");
+    }
+
+    #[test]
+    fn test_reference_roundtrip_keeps_citation_key() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let author = Author::builder().name_from_str("Jane Smith").unwrap().build().unwrap();
+        let mut r = Reference {
+            key: Some("smith2020".to_string()),
+            kind: "journal_article".to_string(),
+            title: "A Study".to_string(),
+            authors: vec![author],
+            year: Some(2020),
+            venue: Some("Nature".to_string()),
+            keywords: vec!["physics".to_string()],
+            ..Default::default()
+        };
+        r.fields.insert("volume".to_string(), "7".to_string());
+        let id = arm.save_reference(&r).unwrap();
+        assert_eq!(arm.load_reference(&id).unwrap().unwrap(), r);
+
+        // a later import under another key does not change the remembered one
+        let mut again = r.clone();
+        again.key = Some("other".to_string());
+        arm.save_reference(&again).unwrap();
+        assert_eq!(arm.load_reference(&id).unwrap().unwrap().key.as_deref(), Some("smith2020"));
+    }
+
+    #[test]
+    fn test_reimport_replaces_keywords() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let mut r = Reference {
+            kind: "journal_article".to_string(),
+            title: "A Study".to_string(),
+            keywords: vec!["physics".to_string(), "optics".to_string()],
+            ..Default::default()
+        };
+        let id = arm.save_reference(&r).unwrap();
+        arm.tag_entity(&id, "to-read").unwrap();
+
+        r.keywords = vec!["physics".to_string(), "lasers".to_string()];
+        arm.save_reference(&r).unwrap();
+        assert_eq!(arm.tags_of(&id).unwrap(), vec!["lasers", "physics", "to-read"]);
+    }
+
+    #[test]
+    fn test_work_roundtrip() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let work = Work::builder()
+            .kind(crate::domain::WorkKind::Thesis).unwrap()
+            .title("On Graphs").unwrap()
+            .author(Author::builder().name_from_str("Jane Smith").unwrap().build().unwrap()).unwrap()
+            .editor(Author::builder().name_from_str("Bob Lee").unwrap().build().unwrap()).unwrap()
+            .year(2019).unwrap()
+            .venue("MIT Press").unwrap()
+            .school("MIT").unwrap()
+            .degree("PhD").unwrap()
+            .keyword("graphs").unwrap()
+            .build()
+            .unwrap();
+        let id = arm.save_work(&work).unwrap();
+        assert_eq!(arm.load_work(&id).unwrap(), Some(work));
+        assert_eq!(arm.load_reference(&id).unwrap().unwrap().field("school"), Some("MIT"));
+    }
+}
@@ -0,0 +1,684 @@
+use std::collections::{BTreeMap, BTreeSet};
+use cozo::{DataValue, NamedRows, ScriptMutability, Vector};
+use log::debug;
+use serde_json::{json, Map, Value};
+use crate::database::academicresourcemanager::AcademicResourceManager;
+use crate::database::error::RepositoryError;
+use crate::database::records::{req_str, Edge, Entity};
+
+const ENTITY_COLUMNS: &str = "id, kind, title, autors, uri, year, props";
+const ENTITY_SPEC: &str = "{id => kind, title, autors, uri, year, props}";
+const EDGE_COLUMNS: &str = "src, dst, kind, props";
+const EDGE_SPEC: &str = "{src, dst, kind => props}";
+
+// Left behind by `merge_entities`, from the merged-away id to the entity that absorbed it.
+pub const ALIAS_OF: &str = "alias_of";
+
+/// Length of the vectors `entity_vec` holds (SPECTER embeddings).
+pub const EMBEDDING_DIM: usize = 768;
+
+pub type Params = BTreeMap<String, DataValue>;
+
+pub fn params<const N: usize>(pairs: [(&str, DataValue); N]) -> Params {
+    pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect()
+}
+
+fn rows_param(rows: Vec<Vec<DataValue>>) -> DataValue {
+    DataValue::List(rows.into_iter().map(DataValue::List).collect())
+}
+
+impl AcademicResourceManager {
+    pub fn run_mutable(&self, script: &str, params: Params) -> Result<NamedRows, RepositoryError> {
+        debug!("Running mutable script: {}", script);
+        Ok(self.db.run_script(script, params, ScriptMutability::Mutable)?)
+    }
+
+    pub fn run_immutable(&self, script: &str, params: Params) -> Result<NamedRows, RepositoryError> {
+        debug!("Running immutable script: {}", script);
+        Ok(self.db.run_script(script, params, ScriptMutability::Immutable)?)
+    }
+
+    // ---- entities ----
+
+    /// Inserts a new entity, failing with `AlreadyExists` if the id is taken.
+    pub fn insert_entity(&self, entity: &Entity) -> Result<(), RepositoryError> {
+        if self.get_entity(&entity.id)?.is_some() {
+            return Err(RepositoryError::AlreadyExists(entity.id.clone()));
+        }
+        let script = format!("?[{ENTITY_COLUMNS}] <- $rows :insert entity {ENTITY_SPEC}");
+        self.run_mutable(&script, params([("rows", rows_param(vec![entity.to_row()]))]))?;
+        Ok(())
+    }
+
+    pub fn upsert_entity(&self, entity: &Entity) -> Result<(), RepositoryError> {
+        self.upsert_entities(std::slice::from_ref(entity))
+    }
+
+    pub fn upsert_entities(&self, entities: &[Entity]) -> Result<(), RepositoryError> {
+        if entities.is_empty() {
+            return Ok(());
+        }
+        let rows = entities.iter().map(Entity::to_row).collect();
+        let script = format!("?[{ENTITY_COLUMNS}] <- $rows :put entity {ENTITY_SPEC}");
+        self.run_mutable(&script, params([("rows", rows_param(rows))]))?;
+        Ok(())
+    }
+
+    pub fn get_entity(&self, id: &str) -> Result<Option<Entity>, RepositoryError> {
+        let script = format!("?[{ENTITY_COLUMNS}] := *entity{{{ENTITY_COLUMNS}}}, id = $id");
+        let result = self.run_immutable(&script, params([("id", DataValue::from(id))]))?;
+        result.rows.first().map(|r| Entity::try_from(r.as_slice())).transpose()
+    }
+
+    /// The entities among `ids` that exist, in id order.
+    pub fn get_entities(&self, ids: &[String]) -> Result<Vec<Entity>, RepositoryError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let list = DataValue::List(ids.iter().map(|id| DataValue::from(id.as_str())).collect());
+        let script = format!("?[{ENTITY_COLUMNS}] := *entity{{{ENTITY_COLUMNS}}}, is_in(id, $ids)");
+        let result = self.run_immutable(&script, params([("ids", list)]))?;
+        result.rows.iter().map(|r| Entity::try_from(r.as_slice())).collect()
+    }
+
+    /// Lists entities, optionally restricted to one `kind`.
+    pub fn list_entities(&self, kind: Option<&str>) -> Result<Vec<Entity>, RepositoryError> {
+        let result = match kind {
+            Some(k) => {
+                let script = format!("?[{ENTITY_COLUMNS}] := *entity{{{ENTITY_COLUMNS}}}, kind = $kind");
+                self.run_immutable(&script, params([("kind", DataValue::from(k))]))?
+            }
+            None => {
+                let script = format!("?[{ENTITY_COLUMNS}] := *entity{{{ENTITY_COLUMNS}}}");
+                self.run_immutable(&script, Params::new())?
+            }
+        };
+        result.rows.iter().map(|r| Entity::try_from(r.as_slice())).collect()
+    }
+
+    /// Entities matching any of `conditions`, cozo expressions over the entity columns
+    /// (`id`, `kind`, `title`, `autors`, `uri`, `year`, `props`), so filtering happens in the engine.
+    pub fn entities_where(&self, conditions: &[String], params: Params) -> Result<Vec<Entity>, RepositoryError> {
+        if conditions.is_empty() {
+            return Ok(Vec::new());
+        }
+        let script = conditions
+            .iter()
+            .map(|c| format!("?[{ENTITY_COLUMNS}] := *entity{{{ENTITY_COLUMNS}}}, {c}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let result = self.run_immutable(&script, params)?;
+        result.rows.iter().map(|r| Entity::try_from(r.as_slice())).collect()
+    }
+
+    /// Deletes an entity together with its edges, tag links and embedding.
+    pub fn delete_entity(&self, id: &str) -> Result<(), RepositoryError> {
+        let script = r#"
+            {
+                ?[src, dst, kind] := *edge{src, dst, kind}, src = $id
+                :rm edge {src, dst, kind}
+            }
+            {
+                ?[src, dst, kind] := *edge{src, dst, kind}, dst = $id
+                :rm edge {src, dst, kind}
+            }
+            {
+                ?[entity_id, tag_name] := *entity_tag{entity_id, tag_name}, entity_id = $id
+                :rm entity_tag {entity_id, tag_name}
+            }
+            {
+                ?[entity_id] <- [[$id]]
+                :rm entity_vec {entity_id}
+            }
+            {
+                ?[id] <- [[$id]]
+                :rm entity {id}
+            }
+        "#;
+        self.run_mutable(script, params([("id", DataValue::from(id))]))?;
+        Ok(())
+    }
+
+    // ---- edges ----
+
+    pub fn insert_edge(&self, edge: &Edge) -> Result<(), RepositoryError> {
+        if self.get_edge(&edge.src, &edge.dst, &edge.kind)?.is_some() {
+            return Err(RepositoryError::AlreadyExists(format!("{} -[{}]-> {}", edge.src, edge.kind, edge.dst)));
+        }
+        let script = format!("?[{EDGE_COLUMNS}] <- $rows :insert edge {EDGE_SPEC}");
+        self.run_mutable(&script, params([("rows", rows_param(vec![edge.to_row()]))]))?;
+        Ok(())
+    }
+
+    pub fn upsert_edge(&self, edge: &Edge) -> Result<(), RepositoryError> {
+        self.upsert_edges(std::slice::from_ref(edge))
+    }
+
+    pub fn upsert_edges(&self, edges: &[Edge]) -> Result<(), RepositoryError> {
+        if edges.is_empty() {
+            return Ok(());
+        }
+        let rows = edges.iter().map(Edge::to_row).collect();
+        let script = format!("?[{EDGE_COLUMNS}] <- $rows :put edge {EDGE_SPEC}");
+        self.run_mutable(&script, params([("rows", rows_param(rows))]))?;
+        Ok(())
+    }
+
+    pub fn get_edge(&self, src: &str, dst: &str, kind: &str) -> Result<Option<Edge>, RepositoryError> {
+        let script = format!(
+            "?[{EDGE_COLUMNS}] := *edge{{{EDGE_COLUMNS}}}, src = $src, dst = $dst, kind = $kind"
+        );
+        let result = self.run_immutable(
+            &script,
+            params([
+                ("src", DataValue::from(src)),
+                ("dst", DataValue::from(dst)),
+                ("kind", DataValue::from(kind)),
+            ]),
+        )?;
+        result.rows.first().map(|r| Edge::try_from(r.as_slice())).transpose()
+    }
+
+    pub fn delete_edge(&self, src: &str, dst: &str, kind: &str) -> Result<(), RepositoryError> {
+        let script = "?[src, dst, kind] <- [[$src, $dst, $kind]] :rm edge {src, dst, kind}";
+        self.run_mutable(
+            script,
+            params([
+                ("src", DataValue::from(src)),
+                ("dst", DataValue::from(dst)),
+                ("kind", DataValue::from(kind)),
+            ]),
+        )?;
+        Ok(())
+    }
+
+    /// Outgoing edges of `src`, optionally restricted to one `kind`.
+    pub fn edges_from(&self, src: &str, kind: Option<&str>) -> Result<Vec<Edge>, RepositoryError> {
+        self.edges_by("src", src, kind)
+    }
+
+    /// Incoming edges of `dst`, optionally restricted to one `kind`.
+    pub fn edges_to(&self, dst: &str, kind: Option<&str>) -> Result<Vec<Edge>, RepositoryError> {
+        self.edges_by("dst", dst, kind)
+    }
+
+    /// Outgoing edges of `kind` from any of `srcs`.
+    pub fn edges_from_any(&self, srcs: &[String], kind: &str) -> Result<Vec<Edge>, RepositoryError> {
+        if srcs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let list = DataValue::List(srcs.iter().map(|id| DataValue::from(id.as_str())).collect());
+        let script = format!("?[{EDGE_COLUMNS}] := *edge{{{EDGE_COLUMNS}}}, is_in(src, $srcs), kind = $kind");
+        let result = self.run_immutable(&script, params([("srcs", list), ("kind", DataValue::from(kind))]))?;
+        result.rows.iter().map(|r| Edge::try_from(r.as_slice())).collect()
+    }
+
+    fn edges_by(&self, column: &str, value: &str, kind: Option<&str>) -> Result<Vec<Edge>, RepositoryError> {
+        let mut p = params([("value", DataValue::from(value))]);
+        let mut script = format!("?[{EDGE_COLUMNS}] := *edge{{{EDGE_COLUMNS}}}, {column} = $value");
+        if let Some(k) = kind {
+            script.push_str(", kind = $kind");
+            p.insert("kind".to_string(), DataValue::from(k));
+        }
+        let result = self.run_immutable(&script, p)?;
+        result.rows.iter().map(|r| Edge::try_from(r.as_slice())).collect()
+    }
+
+    /// Entity ids within `depth` hops of `id` along edges of `kind`, in either direction.
+    /// The start id comes first, then ids in breadth-first order.
+    pub fn neighbourhood(&self, id: &str, kind: &str, depth: usize) -> Result<Vec<String>, RepositoryError> {
+        let mut seen = vec![id.to_string()];
+        let mut frontier = vec![id.to_string()];
+        for _ in 0..depth {
+            let mut next = Vec::new();
+            for node in &frontier {
+                let outgoing = self.edges_from(node, Some(kind))?.into_iter().map(|e| e.dst);
+                let incoming = self.edges_to(node, Some(kind))?.into_iter().map(|e| e.src);
+                for other in outgoing.chain(incoming) {
+                    if !seen.contains(&other) {
+                        seen.push(other.clone());
+                        next.push(other);
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+        Ok(seen)
+    }
+
+    /// Runs a read-only query and returns its first column as ids.
+    pub fn query_ids(&self, script: &str, params: Params) -> Result<Vec<String>, RepositoryError> {
+        let result = self.run_immutable(script, params)?;
+        result
+            .rows
+            .iter()
+            .map(|r| match r.first() {
+                Some(v) => req_str(v, "id"),
+                None => Err(RepositoryError::Conversion("query returned no columns".to_string())),
+            })
+            .collect()
+    }
+
+    // ---- tags ----
+
+    pub fn add_tag(&self, name: &str) -> Result<(), RepositoryError> {
+        self.run_mutable("?[name] <- [[$name]] :put tag {name}", params([("name", DataValue::from(name))]))?;
+        Ok(())
+    }
+
+    pub fn list_tags(&self) -> Result<Vec<String>, RepositoryError> {
+        let result = self.run_immutable("?[name] := *tag{name}", Params::new())?;
+        result.rows.iter().map(|r| req_str(&r[0], "name")).collect()
+    }
+
+    /// Deletes a tag and removes it from every entity.
+    pub fn delete_tag(&self, name: &str) -> Result<(), RepositoryError> {
+        let script = r#"
+            {
+                ?[entity_id, tag_name] := *entity_tag{entity_id, tag_name}, tag_name = $name
+                :rm entity_tag {entity_id, tag_name}
+            }
+            {
+                ?[name] <- [[$name]]
+                :rm tag {name}
+            }
+        "#;
+        self.run_mutable(script, params([("name", DataValue::from(name))]))?;
+        Ok(())
+    }
+
+    /// Tags an entity, creating the tag if needed.
+    pub fn tag_entity(&self, entity_id: &str, tag: &str) -> Result<(), RepositoryError> {
+        let script = r#"
+            {
+                ?[name] <- [[$tag]]
+                :put tag {name}
+            }
+            {
+                ?[entity_id, tag_name] <- [[$entity_id, $tag]]
+                :put entity_tag {entity_id, tag_name}
+            }
+        "#;
+        self.run_mutable(
+            script,
+            params([("entity_id", DataValue::from(entity_id)), ("tag", DataValue::from(tag))]),
+        )?;
+        Ok(())
+    }
+
+    pub fn untag_entity(&self, entity_id: &str, tag: &str) -> Result<(), RepositoryError> {
+        let script = "?[entity_id, tag_name] <- [[$entity_id, $tag]] :rm entity_tag {entity_id, tag_name}";
+        self.run_mutable(
+            script,
+            params([("entity_id", DataValue::from(entity_id)), ("tag", DataValue::from(tag))]),
+        )?;
+        Ok(())
+    }
+
+    pub fn tags_of(&self, entity_id: &str) -> Result<Vec<String>, RepositoryError> {
+        let script = "?[tag_name] := *entity_tag{entity_id, tag_name}, entity_id = $entity_id";
+        let result = self.run_immutable(script, params([("entity_id", DataValue::from(entity_id))]))?;
+        result.rows.iter().map(|r| req_str(&r[0], "tag_name")).collect()
+    }
+
+    pub fn entities_with_tag(&self, tag: &str) -> Result<Vec<Entity>, RepositoryError> {
+        let script = format!(
+            "?[{ENTITY_COLUMNS}] := *entity_tag{{entity_id: id, tag_name}}, tag_name = $tag, *entity{{{ENTITY_COLUMNS}}}"
+        );
+        let result = self.run_immutable(&script, params([("tag", DataValue::from(tag))]))?;
+        result.rows.iter().map(|r| Entity::try_from(r.as_slice())).collect()
+    }
+
+    // ---- batches ----
+
+    /// Upserts entities, edges and entity tags in one transaction, for bulk loaders. Tags named
+    /// in `tags` or `entity_tags` are created as needed.
+    pub fn upsert_batch(
+        &self,
+        entities: &[Entity],
+        edges: &[Edge],
+        tags: &[String],
+        entity_tags: &[(String, String)],
+    ) -> Result<(), RepositoryError> {
+        let names: BTreeSet<&str> = tags.iter().chain(entity_tags.iter().map(|(_, t)| t)).map(String::as_str).collect();
+        let script = format!(
+            r#"
+            {{
+                ?[{ENTITY_COLUMNS}] <- $entities
+                :put entity {ENTITY_SPEC}
+            }}
+            {{
+                ?[{EDGE_COLUMNS}] <- $edges
+                :put edge {EDGE_SPEC}
+            }}
+            {{
+                ?[name] <- $tags
+                :put tag {{name}}
+            }}
+            {{
+                ?[entity_id, tag_name] <- $entity_tags
+                :put entity_tag {{entity_id, tag_name}}
+            }}
+        "#
+        );
+        let pair = |(id, tag): &(String, String)| vec![DataValue::from(id.as_str()), DataValue::from(tag.as_str())];
+        self.run_mutable(
+            &script,
+            params([
+                ("entities", rows_param(entities.iter().map(Entity::to_row).collect())),
+                ("edges", rows_param(edges.iter().map(Edge::to_row).collect())),
+                ("tags", rows_param(names.into_iter().map(|n| vec![DataValue::from(n)]).collect())),
+                ("entity_tags", rows_param(entity_tags.iter().map(pair).collect())),
+            ]),
+        )?;
+        Ok(())
+    }
+
+    // ---- embeddings ----
+
+    /// Stores the embedding of an entity, replacing any earlier one.
+    pub fn upsert_embedding(&self, entity_id: &str, embedding: &[f32]) -> Result<(), RepositoryError> {
+        if embedding.len() != EMBEDDING_DIM {
+            return Err(RepositoryError::Conversion(format!(
+                "embedding of `{entity_id}` has {} dimensions, expected {EMBEDDING_DIM}",
+                embedding.len()
+            )));
+        }
+        let values = embedding.iter().map(|v| DataValue::from(f64::from(*v))).collect();
+        self.run_mutable(
+            "?[entity_id, embedding] <- [[$id, vec($embedding)]] :put entity_vec {entity_id => embedding}",
+            params([("id", DataValue::from(entity_id)), ("embedding", DataValue::List(values))]),
+        )?;
+        Ok(())
+    }
+
+    pub fn get_embedding(&self, entity_id: &str) -> Result<Option<Vec<f32>>, RepositoryError> {
+        let result = self.run_immutable(
+            "?[embedding] := *entity_vec{entity_id, embedding}, entity_id = $id",
+            params([("id", DataValue::from(entity_id))]),
+        )?;
+        match result.rows.first().and_then(|r| r.first()) {
+            Some(DataValue::Vec(Vector::F32(values))) => Ok(Some(values.to_vec())),
+            Some(other) => Err(RepositoryError::Conversion(format!("unexpected embedding value {other:?}"))),
+            None => Ok(None),
+        }
+    }
+
+    // ---- merging ----
+
+    /// Follows `alias_of` edges to the entity that absorbed `id`; ids never merged come back as is.
+    pub fn resolve_alias(&self, id: &str) -> Result<String, RepositoryError> {
+        let mut current = id.to_string();
+        // merges point old aliases at the new survivor, so chains stay short; the bound only guards cycles
+        for _ in 0..8 {
+            match self.edges_from(&current, Some(ALIAS_OF))?.into_iter().next() {
+                Some(edge) => current = edge.dst,
+                None => break,
+            }
+        }
+        Ok(current)
+    }
+
+    /// The node `id` resolves to, created with `kind` and `title` when missing.
+    pub(crate) fn node_for(&self, id: &str, kind: &str, title: &str) -> Result<String, RepositoryError> {
+        let id = self.resolve_alias(id)?;
+        if self.get_entity(&id)?.is_none() {
+            self.upsert_entity(&Entity::builder().id(&id).kind(kind).title(title).build()?)?;
+        }
+        Ok(id)
+    }
+
+    /// Makes `dst` the only target of `kind` edges from `src`.
+    pub(crate) fn replace_edge(&self, src: &str, dst: &str, kind: &str) -> Result<(), RepositoryError> {
+        for old in self.edges_from(src, Some(kind))? {
+            if old.dst != dst {
+                self.delete_edge(&old.src, &old.dst, &old.kind)?;
+            }
+        }
+        self.upsert_edge(&Edge::new(src, dst, kind))
+    }
+
+    /// Points `alias` (the slug id of a name) at `id`. When `claim` is set, a node of `kind`
+    /// already holding the alias is merged in and an alias pointing elsewhere is taken over;
+    /// otherwise both are left alone.
+    pub(crate) fn claim_alias(&self, alias: &str, id: &str, kind: &str, name: &str, claim: bool) -> Result<(), RepositoryError> {
+        if alias == id {
+            return Ok(());
+        }
+        let current = self.resolve_alias(alias)?;
+        if current != alias && (!claim || current == id) {
+            return Ok(());
+        }
+        match self.get_entity(alias)? {
+            Some(entity) if claim && entity.kind == kind => self.merge_entities(id, alias),
+            Some(_) => Ok(()),
+            None => {
+                for old in self.edges_from(alias, Some(ALIAS_OF))? {
+                    self.delete_edge(&old.src, &old.dst, &old.kind)?;
+                }
+                self.upsert_edge(&Edge::new(alias, id, ALIAS_OF).with_props(json!({ "title": name })))
+            }
+        }
+    }
+
+    /// Folds `duplicate` into `survivor`. Every edge from or to the duplicate is moved over (where
+    /// the survivor already has the same edge, its own wins), tags and props the survivor lacks
+    /// are copied, and the duplicate is replaced by an `alias_of` edge so its id stays resolvable.
+    pub fn merge_entities(&self, survivor: &str, duplicate: &str) -> Result<(), RepositoryError> {
+        if survivor == duplicate {
+            return Ok(());
+        }
+        let mut target = self.get_entity(survivor)?.ok_or_else(|| RepositoryError::NotFound(survivor.to_string()))?;
+        let merged = self.get_entity(duplicate)?.ok_or_else(|| RepositoryError::NotFound(duplicate.to_string()))?;
+
+        let outgoing = self.edges_from(duplicate, None)?.into_iter().map(|e| Edge { src: survivor.to_string(), ..e });
+        let incoming = self.edges_to(duplicate, None)?.into_iter().map(|e| Edge { dst: survivor.to_string(), ..e });
+        let mut moved = Vec::new();
+        for edge in outgoing.chain(incoming) {
+            if edge.src != edge.dst && self.get_edge(&edge.src, &edge.dst, &edge.kind)?.is_none() {
+                moved.push(edge);
+            }
+        }
+        self.upsert_edges(&moved)?;
+        for tag in self.tags_of(duplicate)? {
+            self.tag_entity(survivor, &tag)?;
+        }
+
+        if let Some(Value::Object(extra)) = merged.props {
+            let mut props = match target.props.take() {
+                Some(Value::Object(map)) => map,
+                _ => Map::new(),
+            };
+            for (key, value) in extra {
+                props.entry(key).or_insert(value);
+            }
+            target.props = Some(Value::Object(props));
+        }
+        target.uri = target.uri.or(merged.uri);
+        target.year = target.year.or(merged.year);
+        self.upsert_entity(&target)?;
+
+        self.delete_entity(duplicate)?;
+        self.upsert_edge(&Edge::new(duplicate, survivor, ALIAS_OF).with_props(json!({ "title": merged.title })))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Engine;
+    use serde_json::json;
+
+    fn arm() -> AcademicResourceManager {
+        AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap()
+    }
+
+    fn paper(id: &str, title: &str) -> Entity {
+        Entity::builder()
+            .id(id)
+            .kind("paper")
+            .title(title)
+            .autors("Jane Smith")
+            .year(2020)
+            .props(json!({"venue": "Nature"}))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_entity_crud() {
+        let arm = arm();
+        let p = paper("p1", "First");
+        arm.insert_entity(&p).unwrap();
+        assert_eq!(arm.get_entity("p1").unwrap(), Some(p.clone()));
+        assert!(matches!(arm.insert_entity(&p), Err(RepositoryError::AlreadyExists(_))));
+
+        let mut updated = p.clone();
+        updated.title = "First (revised)".to_string();
+        arm.upsert_entity(&updated).unwrap();
+        assert_eq!(arm.get_entity("p1").unwrap().unwrap().title, "First (revised)");
+
+        let book = Entity::builder().id("b1").kind("book").title("Book").build().unwrap();
+        arm.upsert_entities(&[paper("p2", "Second"), book]).unwrap();
+        assert_eq!(arm.list_entities(None).unwrap().len(), 3);
+        assert_eq!(arm.list_entities(Some("paper")).unwrap().len(), 2);
+
+        arm.delete_entity("p1").unwrap();
+        assert!(arm.get_entity("p1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_edges() {
+        let arm = arm();
+        arm.upsert_entities(&[paper("p1", "A"), paper("p2", "B"), paper("p3", "C")]).unwrap();
+        arm.insert_edge(&Edge::new("p1", "p2", "cites")).unwrap();
+        arm.upsert_edge(&Edge::new("p1", "p3", "cites").with_props(json!({"context": "intro"}))).unwrap();
+        arm.upsert_edge(&Edge::new("p3", "p2", "cites")).unwrap();
+        assert!(arm.insert_edge(&Edge::new("p1", "p2", "cites")).is_err());
+
+        assert_eq!(arm.edges_from("p1", None).unwrap().len(), 2);
+        assert_eq!(arm.edges_from("p1", Some("extends")).unwrap().len(), 0);
+        assert_eq!(arm.edges_to("p2", Some("cites")).unwrap().len(), 2);
+        let e = arm.get_edge("p1", "p3", "cites").unwrap().unwrap();
+        assert_eq!(e.props, Some(json!({"context": "intro"})));
+
+        arm.delete_edge("p1", "p3", "cites").unwrap();
+        assert!(arm.get_edge("p1", "p3", "cites").unwrap().is_none());
+
+        // deleting an entity removes the edges touching it
+        arm.delete_entity("p2").unwrap();
+        assert!(arm.edges_from("p1", None).unwrap().is_empty());
+        assert!(arm.edges_from("p3", None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_neighbourhood_and_query_ids() {
+        let arm = arm();
+        arm.upsert_entities(&[paper("p1", "A"), paper("p2", "B"), paper("p3", "C"), paper("p4", "D")]).unwrap();
+        arm.upsert_edges(&[
+            Edge::new("p1", "p2", "cites"),
+            Edge::new("p3", "p2", "cites"),
+            Edge::new("p3", "p4", "cites"),
+        ]).unwrap();
+        assert_eq!(arm.neighbourhood("p1", "cites", 1).unwrap(), vec!["p1", "p2"]);
+        assert_eq!(arm.neighbourhood("p1", "cites", 2).unwrap(), vec!["p1", "p2", "p3"]);
+        assert_eq!(arm.neighbourhood("p1", "cites", 5).unwrap().len(), 4);
+
+        let ids = arm
+            .query_ids("?[id] := *entity{id, title}, title = $t", params([("t", DataValue::from("C"))]))
+            .unwrap();
+        assert_eq!(ids, vec!["p3"]);
+    }
+
+    #[test]
+    fn test_tags() {
+        let arm = arm();
+        arm.upsert_entities(&[paper("p1", "A"), paper("p2", "B")]).unwrap();
+        arm.add_tag("physics").unwrap();
+        arm.tag_entity("p1", "monte-carlo").unwrap();
+        arm.tag_entity("p2", "monte-carlo").unwrap();
+        arm.tag_entity("p1", "physics").unwrap();
+
+        assert_eq!(arm.list_tags().unwrap(), vec!["monte-carlo", "physics"]);
+        assert_eq!(arm.tags_of("p1").unwrap(), vec!["monte-carlo", "physics"]);
+        assert_eq!(arm.entities_with_tag("monte-carlo").unwrap().len(), 2);
+
+        arm.untag_entity("p1", "physics").unwrap();
+        assert_eq!(arm.tags_of("p1").unwrap(), vec!["monte-carlo"]);
+
+        arm.delete_tag("monte-carlo").unwrap();
+        assert!(arm.tags_of("p2").unwrap().is_empty());
+        assert_eq!(arm.list_tags().unwrap(), vec!["physics"]);
+    }
+
+    #[test]
+    fn test_upsert_batch() {
+        let arm = arm();
+        arm.upsert_entity(&paper("p0", "Old")).unwrap();
+        let tags = [("p1".to_string(), "physics".to_string()), ("p2".to_string(), "physics".to_string())];
+        arm.upsert_batch(&[paper("p1", "A"), paper("p2", "B")], &[Edge::new("p1", "p2", "cites")], &["optics".to_string()], &tags)
+            .unwrap();
+        assert_eq!(arm.entities_with_tag("physics").unwrap().len(), 2);
+        assert_eq!(arm.list_tags().unwrap(), vec!["optics", "physics"]);
+        assert!(arm.get_edge("p1", "p2", "cites").unwrap().is_some());
+        arm.upsert_batch(&[], &[], &[], &[]).unwrap();
+
+        let ids = ["p0", "p2", "p9"].map(str::to_string);
+        let found: Vec<(String, String)> = arm.get_entities(&ids).unwrap().into_iter().map(|e| (e.id, e.title)).collect();
+        assert_eq!(found, [("p0".to_string(), "Old".to_string()), ("p2".to_string(), "B".to_string())]);
+    }
+
+    #[test]
+    fn test_embeddings() {
+        let arm = arm();
+        arm.upsert_entity(&paper("p1", "A")).unwrap();
+        assert_eq!(arm.get_embedding("p1").unwrap(), None);
+        let embedding: Vec<f32> = (0..EMBEDDING_DIM).map(|i| i as f32 / 8.0).collect();
+        arm.upsert_embedding("p1", &embedding).unwrap();
+        assert_eq!(arm.get_embedding("p1").unwrap(), Some(embedding));
+        assert!(matches!(arm.upsert_embedding("p1", &[1.0, 2.0]), Err(RepositoryError::Conversion(_))));
+
+        arm.delete_entity("p1").unwrap();
+        assert_eq!(arm.get_embedding("p1").unwrap(), None);
+    }
+
+    #[test]
+    fn test_merge_entities() {
+        let arm = arm();
+        let mut keep = paper("p1", "Quantum Things");
+        keep.props = Some(json!({ "fields": { "volume": "1" } }));
+        keep.year = None;
+        let mut dup = paper("p2", "Quantum things");
+        dup.props = Some(json!({ "fields": {}, "citation_key": "q" }));
+        dup.year = Some(2021);
+        arm.upsert_entities(&[keep, dup, paper("p3", "C"), paper("old", "Older alias")]).unwrap();
+        arm.upsert_edges(&[
+            Edge::new("p3", "p2", "cites"),
+            Edge::new("p3", "p1", "cites").with_props(json!({ "page": 3 })),
+            Edge::new("p2", "p3", "cites"),
+            Edge::new("p2", "p1", "cites"),
+            Edge::new("old", "p2", ALIAS_OF),
+        ]).unwrap();
+        arm.tag_entity("p2", "physics").unwrap();
+
+        arm.merge_entities("p1", "p2").unwrap();
+        assert!(arm.get_entity("p2").unwrap().is_none());
+        let merged = arm.get_entity("p1").unwrap().unwrap();
+        assert_eq!(merged.props.unwrap(), json!({ "fields": { "volume": "1" }, "citation_key": "q" }));
+        assert_eq!(merged.year, Some(2021));
+        assert_eq!(arm.tags_of("p1").unwrap(), vec!["physics"]);
+
+        // the survivor's own edge wins, the self-citation is dropped
+        assert_eq!(arm.get_edge("p3", "p1", "cites").unwrap().unwrap().props, Some(json!({ "page": 3 })));
+        assert!(arm.get_edge("p1", "p3", "cites").unwrap().is_some());
+        assert!(arm.get_edge("p1", "p1", "cites").unwrap().is_none());
+        assert_eq!(arm.resolve_alias("p2").unwrap(), "p1");
+        assert_eq!(arm.resolve_alias("old").unwrap(), "p1");
+        assert_eq!(arm.resolve_alias("p3").unwrap(), "p3");
+        assert!(matches!(arm.merge_entities("p1", "p2"), Err(RepositoryError::NotFound(_))));
+    }
+}
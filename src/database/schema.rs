@@ -1,4 +1,6 @@
+// Each statement needs its own block, otherwise cozo only runs the last one.
 pub const SCHEMA: &str = r#"
+{
 :create entity {
     
     id: String,
@@ -10,7 +12,9 @@ pub const SCHEMA: &str = r#"
     year: Int?,           
     props: Json?      
 }
+}
 
+{
 :create edge {
     src: String,
     dst: String,
@@ -19,23 +23,30 @@ pub const SCHEMA: &str = r#"
     =>
     props: Json?      
 }
+}
 
+{
 :create tag {
     name: String,
 }
+}
 
 
+{
 :create entity_tag {
     entity_id: String,
     tag_name: String, 
     =>
 }
+}
 
+{
 :create entity_vec {
     entity_id: String,
     =>            
     embedding: <F32; 768>          
 }
+}
 "#;
 
 
@@ -0,0 +1,195 @@
+use cozo::DataValue;
+use serde_json::{json, Map, Value};
+use crate::database::academicresourcemanager::AcademicResourceManager;
+use crate::database::error::RepositoryError;
+use crate::database::records::{from_json, Edge, Entity};
+use crate::database::repository::{params, ALIAS_OF};
+use crate::domain::topic::Topic;
+use crate::utils::text::slugify;
+
+pub const TOPIC_KIND: &str = "topic";
+/// Edge from a topic to a broader one (`skos:broader`); narrower topics are its sources.
+pub const BROADER: &str = "broader";
+
+pub fn topic_id(label: &str) -> String {
+    format!("topic:{}", slugify(label))
+}
+
+impl AcademicResourceManager {
+    /// Upserts a topic node under the slug of its preferred label and makes the label a tag.
+    /// Alternative labels become `alias_of` edges unless another topic uses them already; a
+    /// preferred label always wins over an alternative one. Broader topics missing from the graph
+    /// are created bare, and the `broader` edges are replaced by the ones given.
+    pub fn save_topic(&self, topic: &Topic) -> Result<String, RepositoryError> {
+        let id = topic_id(&topic.label);
+        for old in self.edges_from(&id, Some(ALIAS_OF))? {
+            self.delete_edge(&old.src, &old.dst, &old.kind)?;
+        }
+        let mut props = match self.get_entity(&id)?.and_then(|e| e.props) {
+            Some(Value::Object(map)) => map,
+            _ => Map::new(),
+        };
+        if let Value::Object(fields) = json!({ "alt_labels": topic.alt_labels, "scheme": topic.scheme }) {
+            props.extend(fields.into_iter().filter(|(_, v)| !v.is_null()));
+        }
+        let mut builder = Entity::builder().id(&id).kind(TOPIC_KIND).title(&topic.label).props(Value::Object(props));
+        if let Some(uri) = &topic.uri {
+            builder = builder.uri(uri);
+        }
+        self.upsert_entity(&builder.build()?)?;
+        self.add_tag(&topic.label)?;
+
+        for label in &topic.alt_labels {
+            self.claim_alias(&topic_id(label), &id, TOPIC_KIND, label, false)?;
+        }
+        let mut broader = Vec::new();
+        for label in &topic.broader {
+            let bid = self.node_for(&topic_id(label), TOPIC_KIND, label)?;
+            if bid != id {
+                self.add_tag(label)?;
+                broader.push(bid);
+            }
+        }
+        for old in self.edges_from(&id, Some(BROADER))? {
+            if !broader.contains(&old.dst) {
+                self.delete_edge(&old.src, &old.dst, &old.kind)?;
+            }
+        }
+        for bid in &broader {
+            self.upsert_edge(&Edge::new(&id, bid, BROADER))?;
+        }
+        Ok(id)
+    }
+
+    pub fn load_topic(&self, id: &str) -> Result<Option<Topic>, RepositoryError> {
+        let Some(entity) = self.get_entity(id)? else {
+            return Ok(None);
+        };
+        if entity.kind != TOPIC_KIND {
+            return Err(RepositoryError::Conversion(format!("entity `{id}` is a {}, not a topic", entity.kind)));
+        }
+        let props = entity.props.unwrap_or(Value::Null);
+        let mut broader = Vec::new();
+        for edge in self.edges_from(id, Some(BROADER))? {
+            broader.extend(self.get_entity(&edge.dst)?.map(|e| e.title));
+        }
+        Ok(Some(Topic {
+            label: entity.title,
+            alt_labels: props.get("alt_labels").map(from_json).transpose()?.unwrap_or_default(),
+            broader,
+            uri: entity.uri,
+            scheme: props.get("scheme").and_then(Value::as_str).map(str::to_string),
+        }))
+    }
+
+    /// The topic a preferred or alternative label refers to.
+    pub fn find_topic(&self, label: &str) -> Result<Option<String>, RepositoryError> {
+        let id = self.resolve_alias(&topic_id(label))?;
+        Ok(self.get_entity(&id)?.filter(|e| e.kind == TOPIC_KIND).map(|e| e.id))
+    }
+
+    /// Tags an entity with the preferred label of the topic `label` names, or with `label` itself
+    /// when no topic has it. Returns the tag used.
+    pub fn tag_topic(&self, entity_id: &str, label: &str) -> Result<String, RepositoryError> {
+        let tag = match self.find_topic(label)? {
+            Some(id) => self.get_entity(&id)?.map_or(label.to_string(), |e| e.title),
+            None => label.to_string(),
+        };
+        self.tag_entity(entity_id, &tag)?;
+        Ok(tag)
+    }
+
+    pub fn broader_topics(&self, id: &str) -> Result<Vec<String>, RepositoryError> {
+        Ok(self.edges_from(id, Some(BROADER))?.into_iter().map(|e| e.dst).collect())
+    }
+
+    pub fn narrower_topics(&self, id: &str) -> Result<Vec<String>, RepositoryError> {
+        Ok(self.edges_to(id, Some(BROADER))?.into_iter().map(|e| e.src).collect())
+    }
+
+    /// `id` and every topic below it.
+    pub fn topic_descendants(&self, id: &str) -> Result<Vec<String>, RepositoryError> {
+        let script = r#"
+            desc[t] := t = $topic
+            desc[t] := desc[b], *edge{src: t, dst: b, kind: $broader}
+            ?[t] := desc[t]
+        "#;
+        self.query_ids(script, params([("topic", DataValue::from(id)), ("broader", DataValue::from(BROADER))]))
+    }
+
+    /// Entities tagged with `label`, a synonym of it, or any topic below it: with `Monte Carlo`
+    /// under `stochastic methods`, works tagged `Monte Carlo` or `MC` show up for the latter.
+    /// Labels that are not topics fall back to a plain tag lookup.
+    pub fn entities_with_topic(&self, label: &str) -> Result<Vec<Entity>, RepositoryError> {
+        let Some(topic) = self.find_topic(label)? else {
+            return self.entities_with_tag(label);
+        };
+        let mut tags = Vec::new();
+        for id in self.topic_descendants(&topic)? {
+            if let Some(topic) = self.load_topic(&id)? {
+                tags.push(topic.label);
+                tags.extend(topic.alt_labels);
+            }
+        }
+        let mut entities: Vec<Entity> = Vec::new();
+        for tag in tags {
+            for entity in self.entities_with_tag(&tag)? {
+                if !entities.iter().any(|e| e.id == entity.id) {
+                    entities.push(entity);
+                }
+            }
+        }
+        Ok(entities)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Engine;
+
+    fn work(arm: &AcademicResourceManager, id: &str) -> String {
+        arm.upsert_entity(&Entity::builder().id(id).kind("journal_article").title(id).build().unwrap()).unwrap();
+        id.to_string()
+    }
+
+    #[test]
+    fn test_topic_hierarchy() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let monte_carlo = Topic {
+            alt_labels: vec!["MC".to_string()],
+            broader: vec!["Stochastic methods".to_string()],
+            uri: Some("http://example.org/mc".to_string()),
+            ..Topic::new("Monte Carlo")
+        };
+        let mc = arm.save_topic(&monte_carlo).unwrap();
+        arm.save_topic(&Topic { broader: vec!["Monte Carlo".to_string()], ..Topic::new("Importance sampling") }).unwrap();
+        assert_eq!(arm.load_topic(&mc).unwrap().unwrap(), monte_carlo);
+        let stochastic = arm.find_topic("stochastic methods").unwrap().unwrap();
+        assert_eq!(arm.narrower_topics(&stochastic).unwrap(), vec![mc.clone()]);
+        assert_eq!(arm.topic_descendants(&stochastic).unwrap().len(), 3);
+
+        let a = work(&arm, "work:a");
+        let b = work(&arm, "work:b");
+        let c = work(&arm, "work:c");
+        assert_eq!(arm.tag_topic(&a, "MC").unwrap(), "Monte Carlo");
+        arm.tag_entity(&b, "MC").unwrap();
+        arm.tag_topic(&c, "Importance sampling").unwrap();
+        work(&arm, "work:d");
+        arm.tag_entity("work:d", "Graphs").unwrap();
+
+        let ids = |label: &str| {
+            let mut ids: Vec<String> = arm.entities_with_topic(label).unwrap().into_iter().map(|e| e.id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids("Stochastic methods"), vec![a.clone(), b.clone(), c.clone()]);
+        assert_eq!(ids("MC"), vec![a, b, c]);
+        assert_eq!(ids("Graphs"), vec!["work:d".to_string()]);
+
+        // a preferred label takes its id back from an alternative label
+        arm.save_topic(&Topic::new("MC")).unwrap();
+        assert_eq!(arm.find_topic("MC").unwrap(), Some(topic_id("MC")));
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use cozo::DataValue;
+use serde_json::{json, Map, Value};
+use crate::database::academicresourcemanager::AcademicResourceManager;
+use crate::database::error::RepositoryError;
+use crate::database::records::{from_json, Entity};
+use crate::database::references::{venue_id, PUBLISHED_IN, VENUE_KIND};
+use crate::database::repository::params;
+use crate::domain::venue::{Issn, Venue, VenueKind};
+use crate::utils::text::slugify;
+
+pub const PUBLISHER_KIND: &str = "publisher";
+/// Edge from a venue to its publisher.
+pub const PUBLISHED_BY: &str = "published_by";
+/// Edge from a conference edition (or a volume of a book series) to its series.
+pub const IN_SERIES: &str = "in_series";
+
+pub fn issn_venue_id(issn: &Issn) -> String {
+    format!("venue:issn:{}", issn.as_str())
+}
+
+pub fn publisher_id(name: &str) -> String {
+    format!("publisher:{}", slugify(name))
+}
+
+impl AcademicResourceManager {
+    /// Upserts a venue node, returning its id: `venue:issn:<issn>` when an ISSN is known (print
+    /// first), the slug of the name otherwise. Names, the abbreviation and the other ISSN become
+    /// `alias_of` edges, so works saved with any of them as venue land on this node.
+    pub fn save_venue(&self, venue: &Venue) -> Result<String, RepositoryError> {
+        let slug = venue_id(&venue.name);
+        let issn = venue.issn.as_ref().or(venue.eissn.as_ref());
+        let id = match issn {
+            Some(issn) => issn_venue_id(issn),
+            None => self.resolve_alias(&slug)?,
+        };
+        let existing = self.get_entity(&id)?;
+        let title = match &existing {
+            Some(entity) if issn.is_none() && id != slug => entity.title.clone(),
+            _ => venue.name.clone(),
+        };
+        let mut props = match existing.as_ref().and_then(|e| e.props.clone()) {
+            Some(Value::Object(map)) => map,
+            _ => Map::new(),
+        };
+        if let Value::Object(fields) = json!({
+            "type": venue.kind,
+            "issn": venue.issn,
+            "eissn": venue.eissn,
+            "abbreviation": venue.abbreviation,
+            "aliases": venue.aliases,
+        }) {
+            props.extend(fields.into_iter().filter(|(_, v)| !v.is_null()));
+        }
+        let mut builder = Entity::builder().id(&id).kind(VENUE_KIND).title(title).props(Value::Object(props));
+        if let Some(year) = venue.edition_year.or(existing.and_then(|e| e.year)) {
+            builder = builder.year(year);
+        }
+        self.upsert_entity(&builder.build()?)?;
+
+        for name in venue.names() {
+            self.claim_alias(&venue_id(name), &id, VENUE_KIND, name, true)?;
+        }
+        if let (Some(_), Some(eissn)) = (&venue.issn, &venue.eissn) {
+            self.claim_alias(&issn_venue_id(eissn), &id, VENUE_KIND, eissn.as_str(), true)?;
+        }
+
+        if let Some(publisher) = venue.publisher.as_deref().filter(|p| !p.is_empty()) {
+            let pid = self.node_for(&publisher_id(publisher), PUBLISHER_KIND, publisher)?;
+            self.replace_edge(&id, &pid, PUBLISHED_BY)?;
+        }
+        if let Some(series) = venue.series.as_deref().filter(|s| !s.is_empty()) {
+            let sid = self.resolve_alias(&venue_id(series))?;
+            if sid != id {
+                if self.get_entity(&sid)?.is_none() {
+                    self.save_venue(&Venue::new(series, venue.kind))?;
+                }
+                self.replace_edge(&id, &sid, IN_SERIES)?;
+            }
+        }
+        Ok(id)
+    }
+
+    /// The venue node a work published in `name` links to: a registered venue holding the ISSN
+    /// or the name, else a bare node for the name.
+    pub fn venue_for(&self, name: &str, issn: Option<&str>) -> Result<String, RepositoryError> {
+        if let Some(issn) = issn.and_then(|i| Issn::parse(i).ok()) {
+            let id = self.resolve_alias(&issn_venue_id(&issn))?;
+            if self.get_entity(&id)?.is_some() {
+                return Ok(id);
+            }
+        }
+        self.node_for(&venue_id(name), VENUE_KIND, name)
+    }
+
+    pub fn load_venue(&self, id: &str) -> Result<Option<Venue>, RepositoryError> {
+        let Some(entity) = self.get_entity(id)? else {
+            return Ok(None);
+        };
+        if entity.kind != VENUE_KIND {
+            return Err(RepositoryError::Conversion(format!("entity `{id}` is a {}, not a venue", entity.kind)));
+        }
+        let props = entity.props.unwrap_or(Value::Null);
+        let target_title = |kind: &str| -> Result<Option<String>, RepositoryError> {
+            match self.edges_from(id, Some(kind))?.first() {
+                Some(edge) => Ok(self.get_entity(&edge.dst)?.map(|e| e.title)),
+                None => Ok(None),
+            }
+        };
+        Ok(Some(Venue {
+            name: entity.title,
+            kind: props.get("type").map(from_json).transpose()?.unwrap_or(VenueKind::Other),
+            issn: props.get("issn").map(from_json).transpose()?,
+            eissn: props.get("eissn").map(from_json).transpose()?,
+            abbreviation: props.get("abbreviation").and_then(Value::as_str).map(str::to_string),
+            aliases: props.get("aliases").map(from_json).transpose()?.unwrap_or_default(),
+            publisher: target_title(PUBLISHED_BY)?,
+            series: target_title(IN_SERIES)?,
+            edition_year: entity.year,
+        }))
+    }
+
+    /// The venue node a name, abbreviation or ISSN refers to, following aliases.
+    pub fn find_venue(&self, name: &str) -> Result<Option<String>, RepositoryError> {
+        let id = match Issn::parse(name) {
+            Ok(issn) => issn_venue_id(&issn),
+            Err(_) => venue_id(name),
+        };
+        let id = self.resolve_alias(&id)?;
+        Ok(self.get_entity(&id)?.filter(|e| e.kind == VENUE_KIND).map(|e| e.id))
+    }
+
+    pub fn works_in_venue(&self, id: &str) -> Result<Vec<String>, RepositoryError> {
+        Ok(self.edges_to(id, Some(PUBLISHED_IN))?.into_iter().map(|e| e.src).collect())
+    }
+
+    pub fn venue_editions(&self, series: &str) -> Result<Vec<String>, RepositoryError> {
+        Ok(self.edges_to(series, Some(IN_SERIES))?.into_iter().map(|e| e.src).collect())
+    }
+
+    /// Works published in a series directly or in any of its editions.
+    pub fn works_in_series(&self, series: &str) -> Result<Vec<String>, RepositoryError> {
+        let script = r#"
+            venue[v] := v = $series
+            venue[v] := *edge{src: v, dst: $series, kind: $in_series}
+            ?[id] := venue[v], *edge{src: id, dst: v, kind: $published_in}
+        "#;
+        self.query_ids(
+            script,
+            params([
+                ("series", DataValue::from(series)),
+                ("in_series", DataValue::from(IN_SERIES)),
+                ("published_in", DataValue::from(PUBLISHED_IN)),
+            ]),
+        )
+    }
+
+    /// The venues `works` appeared in, with how many of them each holds.
+    pub fn venues_of(&self, works: &[String]) -> Result<BTreeMap<String, usize>, RepositoryError> {
+        let mut venues = BTreeMap::new();
+        for work in works {
+            for edge in self.edges_from(work, Some(PUBLISHED_IN))? {
+                *venues.entry(edge.dst).or_insert(0) += 1;
+            }
+        }
+        Ok(venues)
+    }
+
+    pub fn venues_of_publisher(&self, publisher: &str) -> Result<Vec<String>, RepositoryError> {
+        Ok(self.edges_to(publisher, Some(PUBLISHED_BY))?.into_iter().map(|e| e.src).collect())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Engine;
+    use crate::domain::sources::{Reference, CONFERENCE_PAPER, JOURNAL_ARTICLE};
+
+    fn paper(arm: &AcademicResourceManager, kind: &str, title: &str, venue: &str) -> String {
+        let reference = Reference {
+            kind: kind.to_string(),
+            title: title.to_string(),
+            venue: Some(venue.to_string()),
+            year: Some(2023),
+            ..Default::default()
+        };
+        arm.save_reference(&reference).unwrap()
+    }
+
+    #[test]
+    fn test_venue_registry() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let early = paper(&arm, JOURNAL_ARTICLE, "Early Letter", "Phys. Rev. Lett.");
+
+        let prl = Venue {
+            issn: Some(Issn::parse("0031-9007").unwrap()),
+            eissn: Some(Issn::parse("1079-7114").unwrap()),
+            abbreviation: Some("Phys. Rev. Lett.".to_string()),
+            publisher: Some("American Physical Society".to_string()),
+            ..Venue::new("Physical Review Letters", VenueKind::Journal)
+        };
+        let id = arm.save_venue(&prl).unwrap();
+        assert_eq!(id, "venue:issn:0031-9007");
+        assert_eq!(arm.load_venue(&id).unwrap().unwrap(), prl);
+        // the bare node made by the earlier import was folded in
+        assert_eq!(arm.works_in_venue(&id).unwrap(), vec![early.clone()]);
+        assert_eq!(arm.find_venue("1079-7114").unwrap(), Some(id.clone()));
+        assert_eq!(arm.venues_of_publisher(&publisher_id("American Physical Society")).unwrap(), vec![id.clone()]);
+
+        let late = paper(&arm, JOURNAL_ARTICLE, "Late Letter", "Physical Review Letters");
+        assert_eq!(arm.venues_of(&[early, late]).unwrap(), BTreeMap::from([(id, 2)]));
+    }
+
+    #[test]
+    fn test_conference_series() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let edition = Venue { series: Some("NeurIPS".to_string()), edition_year: Some(2023), ..Venue::new("NeurIPS 2023", VenueKind::Conference) };
+        let eid = arm.save_venue(&edition).unwrap();
+        let series = arm.find_venue("NeurIPS").unwrap().unwrap();
+        assert_eq!(arm.load_venue(&series).unwrap().unwrap().kind, VenueKind::Conference);
+        assert_eq!(arm.venue_editions(&series).unwrap(), vec![eid.clone()]);
+        assert_eq!(arm.load_venue(&eid).unwrap().unwrap(), edition);
+
+        let a = paper(&arm, CONFERENCE_PAPER, "Paper A", "NeurIPS 2023");
+        let b = paper(&arm, CONFERENCE_PAPER, "Paper B", "NeurIPS");
+        paper(&arm, CONFERENCE_PAPER, "Paper C", "ICML 2023");
+        let mut works = arm.works_in_series(&series).unwrap();
+        works.sort();
+        assert_eq!(works, vec![a, b]);
+    }
+}
@@ -1,34 +1,250 @@
-#[derive(Debug, Clone, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+use crate::domain::author::AuthorError;
+use crate::domain::country::country_code;
+use crate::utils::text::fold;
+
+// Folded words and phrases marking a part of an affiliation string.
+const DEPARTMENT_WORDS: [&str; 21] = [
+    "department", "dept", "departement", "departamento", "dipartimento", "division", "faculty", "fakultat",
+    "faculte", "laboratory", "laboratoire", "lab", "group", "section", "chair", "lehrstuhl", "abteilung",
+    "fachbereich", "unit", "programme", "program",
+];
+const DEPARTMENT_PHRASES: [&str; 4] = ["school of", "center for", "centre for", "graduate school"];
+const INSTITUTION_WORDS: [&str; 33] = [
+    "university", "universitat", "universite", "universidad", "universita", "universidade", "universiteit",
+    "uniwersytet", "college", "institute", "institut", "instituto", "istituto", "academy", "akademie",
+    "hospital", "clinic", "klinikum", "polytechnic", "politecnico", "ecole", "hochschule", "observatory",
+    "foundation", "inc", "ltd", "gmbh", "corporation", "council", "agency", "museum", "cnrs", "cern",
+];
+const INSTITUTION_PHRASES: [&str; 4] = ["national laboratory", "medical school", "research center", "research centre"];
+const STREET_WORDS: [&str; 24] = [
+    "street", "st", "road", "rd", "avenue", "ave", "boulevard", "blvd", "lane", "drive", "strasse", "str",
+    "weg", "platz", "allee", "rue", "via", "calle", "avenida", "box", "campus", "building", "bldg", "route",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Part {
+    Department,
+    Institution,
+    Address,
+    Unknown,
+}
+
+fn classify(segment: &str) -> Part {
+    let folded = fold(segment);
+    let words: Vec<&str> = folded.split_whitespace().collect();
+    let has_word = |list: &[&str]| words.iter().any(|w| list.contains(w));
+    let has_phrase = |list: &[&str]| list.iter().any(|p| folded.contains(p));
+    let starts_department = words.first().is_some_and(|w| DEPARTMENT_WORDS.contains(w))
+        || DEPARTMENT_PHRASES.iter().any(|p| folded.starts_with(p));
+
+    if has_phrase(&INSTITUTION_PHRASES) {
+        Part::Institution
+    } else if starts_department || (has_word(&DEPARTMENT_WORDS) && !has_word(&INSTITUTION_WORDS)) {
+        Part::Department
+    } else if has_word(&INSTITUTION_WORDS) {
+        Part::Institution
+    } else if segment.chars().any(|c| c.is_ascii_digit()) || has_word(&STREET_WORDS) {
+        Part::Address
+    } else {
+        Part::Unknown
+    }
+}
+
+// Folded country names that are also states or provinces ("Atlanta, Georgia"); they still give
+// the country, with less confidence.
+const AMBIGUOUS_COUNTRIES: [&str; 2] = ["georgia", "luxembourg"];
+
+// Country of the last segment: either the whole segment or the words after a postcode, whose
+// preceding part ("MA 02139 USA") is handed back as address. Anything else, such as the
+// "Jersey" of "New Jersey", is not a country.
+fn trailing_country(segment: &str) -> Option<(String, &'static str, Option<String>)> {
+    let words: Vec<&str> = segment.split_whitespace().collect();
+    let postcode = words.iter().rposition(|w| w.chars().any(|c| c.is_ascii_digit())).map_or(0, |i| i + 1);
+    if postcode == words.len() {
+        return None;
+    }
+    let (rest, name) = words.split_at(postcode);
+    let name = name.join(" ");
+    let code = country_code(&name)?;
+    Some((name.trim_end_matches('.').to_string(), code, Some(rest.join(" ")).filter(|r| !r.is_empty())))
+}
+
+fn joined(parts: Vec<&str>) -> Option<String> {
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Affiliation {
     pub institution: Option<String>,
     pub department: Option<String>,
     pub address: Option<String>,
     pub country: Option<String>,
+    /// ISO 3166-1 alpha-2 code of `country`, when it is a known country
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country_code: Option<String>,
+}
+
+/// An affiliation read from free text, with how sure the parser is of the assignment: 1.0 when
+/// every part was recognized, lower when parts were placed by position only.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedAffiliation {
+    pub affiliation: Affiliation,
+    pub confidence: f64,
 }
 
 impl Affiliation {
+    /// Parses either the positional `institution; department; address; country` form or a
+    /// comma-separated string as found in Crossref and PubMed. Never fails; see `analyze` for
+    /// the error and confidence.
     pub fn parse(affil_str: &str) -> Self {
-        // Simple parsing logic, can be improved with more sophisticated parsing
+        match Affiliation::analyze(affil_str) {
+            Ok(parsed) => parsed.affiliation,
+            Err(_) => Affiliation {
+                institution: Some(affil_str.trim().to_string()).filter(|s| !s.is_empty()),
+                department: None,
+                address: None,
+                country: None,
+                country_code: None,
+            },
+        }
+    }
+
+    /// Like `parse`, reporting the confidence of the assignment. Fails on empty strings and on
+    /// comma-separated strings in which no part can stand for an institution or department.
+    pub fn analyze(affil_str: &str) -> Result<ParsedAffiliation, AuthorError> {
+        if affil_str.trim().is_empty() {
+            return Err(AuthorError::AffiliationParsingError("empty affiliation".to_string()));
+        }
+        if affil_str.contains(';') {
+            return Ok(ParsedAffiliation { affiliation: Affiliation::positional(affil_str), confidence: 1.0 });
+        }
+
+        let mut segments: Vec<&str> = affil_str.split(',').map(str::trim).filter(|s| !s.is_empty()).collect();
+        let mut country = None;
+        let mut locality = None;
+        if segments.len() > 1
+            && let Some((name, code, rest)) = segments.last().and_then(|s| trailing_country(s))
+        {
+            segments.pop();
+            country = Some((name, code));
+            locality = rest;
+        }
+        let parts: Vec<Part> = segments.iter().map(|s| classify(s)).collect();
+
+        // sub-units come first, so the last institution-like part is the institution itself
+        let recognized = parts.iter().rposition(|p| *p == Part::Institution);
+        let fallback = || {
+            let start = parts.iter().rposition(|p| *p == Part::Department).map_or(0, |i| i + 1);
+            (start..parts.len()).find(|i| parts[*i] == Part::Unknown)
+        };
+        let institution = recognized.or_else(fallback);
+        if institution.is_none() && !parts.contains(&Part::Department) {
+            return Err(AuthorError::AffiliationParsingError(format!("no institution or department in `{}`", affil_str.trim())));
+        }
+
+        let mut department = Vec::new();
+        let mut address = Vec::new();
+        let mut score = 0.0;
+        for (i, (segment, part)) in segments.iter().zip(&parts).enumerate() {
+            let after = institution.is_some_and(|inst| i > inst);
+            match part {
+                _ if Some(i) == institution => score += if recognized.is_some() { 1.0 } else { 0.25 },
+                Part::Department | Part::Institution => {
+                    department.push(*segment);
+                    score += 1.0;
+                }
+                Part::Address => {
+                    address.push(*segment);
+                    score += 1.0;
+                }
+                // cities and states follow the institution; unknown parts before it are a guess
+                Part::Unknown if after => {
+                    address.push(*segment);
+                    score += 0.5;
+                }
+                Part::Unknown => department.push(*segment),
+            }
+        }
+        if let Some(locality) = locality.as_deref() {
+            address.push(locality);
+        }
+        let total = segments.len() + usize::from(country.is_some());
+        score += match &country {
+            Some((name, _)) if AMBIGUOUS_COUNTRIES.contains(&fold(name).as_str()) => 0.25,
+            Some(_) => 1.0,
+            None => 0.0,
+        };
+
+        Ok(ParsedAffiliation {
+            affiliation: Affiliation {
+                institution: institution.map(|i| segments[i].to_string()),
+                department: joined(department),
+                address: joined(address),
+                country_code: country.as_ref().map(|(_, code)| code.to_string()),
+                country: country.map(|(name, _)| name),
+            },
+            confidence: score / total as f64,
+        })
+    }
+
+    fn positional(affil_str: &str) -> Self {
         let parts: Vec<&str> = affil_str.split(';').map(|s| s.trim()).collect();
-        let institution = parts.get(0)
-            .map(|s| s.to_string())
-            .filter(|s| !s.is_empty());
-        let department = parts.get(1)
-            .map(|s| s.to_string())
-            .filter(|s| !s.is_empty());
-        let address = parts.get(2)
-            .map(|s| s.to_string())
-            .filter(|s| !s.is_empty());
-        let country = parts.get(3)
-            .map(|s| s.to_string())
-            .filter(|s| !s.is_empty());
+        let part = |i: usize| parts.get(i).map(|s| s.to_string()).filter(|s| !s.is_empty());
+        let country = part(3);
         Affiliation {
-            institution,
-            department,
-            address,
+            institution: part(0),
+            department: part(1),
+            address: part(2),
+            country_code: country.as_deref().and_then(country_code).map(str::to_string),
             country,
         }
+    }
+}
+
+/// An affiliation held over a span of years, as far as they are known, e.g. a postdoc position.
+/// Stored in the `periods` list of the `affiliated_with` edge to its institution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AffiliationPeriod {
+    #[serde(flatten)]
+    pub affiliation: Affiliation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<i64>,
+    /// last year of the affiliation, inclusive; `None` while it lasts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+}
+
+impl AffiliationPeriod {
+    pub fn new(affiliation: Affiliation) -> Self {
+        AffiliationPeriod { affiliation, start: None, end: None, role: None }
+    }
 
+    pub fn years(mut self, start: Option<i64>, end: Option<i64>) -> Result<Self, AuthorError> {
+        if let (Some(s), Some(e)) = (start, end)
+            && s > e
+        {
+            return Err(AuthorError::InvalidPeriod { start: s, end: e });
+        }
+        self.start = start;
+        self.end = end;
+        Ok(self)
+    }
+
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.role = Some(role.into());
+        self
+    }
+
+    pub fn is_dated(&self) -> bool {
+        self.start.is_some() || self.end.is_some()
+    }
+
+    /// Whether the period includes `year`; open ends include everything on their side.
+    pub fn covers(&self, year: i64) -> bool {
+        self.start.is_none_or(|s| s <= year) && self.end.is_none_or(|e| year <= e)
     }
 }
 
@@ -44,6 +260,7 @@ mod tests {
         assert_eq!(affil.department.unwrap(), "Department of Testing");
         assert_eq!(affil.address.unwrap(), "123 Test St");
         assert_eq!(affil.country.unwrap(), "Testland");
+        assert!(affil.country_code.is_none());
 
         let affil_str_partial = "Institute of Samples; ;456 Sample Rd";
         let affil_partial = Affiliation::parse(affil_str_partial);
@@ -54,4 +271,76 @@ mod tests {
         assert_eq!(affil_partial.address.unwrap(), "456 Sample Rd");
         assert!(affil_partial.country.is_none());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_affiliation_comma_separated() {
+        let parsed = Affiliation::analyze("Dept. of Physics, University of X, 123 Road, City, Germany").unwrap();
+        let affil = parsed.affiliation;
+        assert_eq!(affil.department.as_deref(), Some("Dept. of Physics"));
+        assert_eq!(affil.institution.as_deref(), Some("University of X"));
+        assert_eq!(affil.address.as_deref(), Some("123 Road, City"));
+        assert_eq!((affil.country.as_deref(), affil.country_code.as_deref()), (Some("Germany"), Some("DE")));
+        assert!(parsed.confidence > 0.8 && parsed.confidence < 1.0);
+
+        let affil = Affiliation::parse("Institute of Physics, Chinese Academy of Sciences, Beijing 100190, P.R. China");
+        assert_eq!(affil.institution.as_deref(), Some("Chinese Academy of Sciences"));
+        assert_eq!(affil.department.as_deref(), Some("Institute of Physics"));
+        assert_eq!(affil.address.as_deref(), Some("Beijing 100190"));
+        assert_eq!(affil.country_code.as_deref(), Some("CN"));
+
+        let affil = Affiliation::parse("Department of Chemistry, Massachusetts Institute of Technology, Cambridge, MA 02139 USA");
+        assert_eq!(affil.institution.as_deref(), Some("Massachusetts Institute of Technology"));
+        assert_eq!(affil.address.as_deref(), Some("Cambridge, MA 02139"));
+        assert_eq!(affil.country_code.as_deref(), Some("US"));
+    }
+
+    #[test]
+    fn test_affiliation_confidence_and_errors() {
+        // no keyword: the first part is taken as the institution, with low confidence
+        let parsed = Affiliation::analyze("Google, Mountain View, CA, USA").unwrap();
+        assert_eq!(parsed.affiliation.institution.as_deref(), Some("Google"));
+        assert!(parsed.confidence < 0.7);
+        assert!(Affiliation::analyze("CERN").unwrap().confidence > 0.9);
+
+        // a country only when it is the whole last part or follows a postcode
+        let parsed = Affiliation::analyze("Princeton University, Princeton, New Jersey").unwrap();
+        assert_eq!(parsed.affiliation.country_code, None);
+        assert_eq!(parsed.affiliation.address.as_deref(), Some("Princeton, New Jersey"));
+        let parsed = Affiliation::analyze("University of New Mexico, Albuquerque, New Mexico").unwrap();
+        assert_eq!((parsed.affiliation.country, parsed.affiliation.country_code), (None, None));
+        let affil = Affiliation::parse("Los Alamos National Laboratory, Los Alamos, NM 87545 USA");
+        assert_eq!(affil.country_code.as_deref(), Some("US"));
+        assert_eq!(affil.address.as_deref(), Some("Los Alamos, NM 87545"));
+        // Georgia is a state as well as a country
+        let georgia = Affiliation::analyze("Emory University, Atlanta, Georgia").unwrap();
+        assert_eq!(georgia.affiliation.country_code.as_deref(), Some("GE"));
+        let germany = Affiliation::analyze("Heidelberg University, Heidelberg, Germany").unwrap();
+        assert!(georgia.confidence < 0.7 && germany.confidence > georgia.confidence);
+
+        assert!(matches!(Affiliation::analyze("  "), Err(AuthorError::AffiliationParsingError(_))));
+        assert!(matches!(Affiliation::analyze("12 Main Street, Germany"), Err(AuthorError::AffiliationParsingError(_))));
+        // a failed analysis keeps the text rather than dropping it
+        assert_eq!(Affiliation::parse("12 Main Street, Germany").institution.as_deref(), Some("12 Main Street, Germany"));
+    }
+
+    #[test]
+    fn test_affiliation_period() {
+        let period = AffiliationPeriod::new(Affiliation::parse("CERN"))
+            .years(Some(2015), Some(2018))
+            .unwrap()
+            .role("postdoc");
+        assert!(period.covers(2015) && period.covers(2018) && !period.covers(2019));
+        assert!(AffiliationPeriod::new(Affiliation::parse("CERN")).years(Some(2020), None).unwrap().covers(2030));
+        assert_eq!(
+            AffiliationPeriod::new(Affiliation::parse("CERN")).years(Some(2020), Some(2019)),
+            Err(AuthorError::InvalidPeriod { start: 2020, end: 2019 })
+        );
+
+        // flattened: an undated edge written before periods existed still reads back
+        let json = serde_json::to_value(&period).unwrap();
+        assert_eq!(json["institution"], "CERN");
+        assert_eq!(json["start"], 2015);
+        let old: AffiliationPeriod = serde_json::from_value(serde_json::json!({"institution": "CERN", "department": null, "address": null, "country": null})).unwrap();
+        assert!(!old.is_dated());
+    }
+}
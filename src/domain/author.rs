@@ -1,38 +1,264 @@
+use std::fmt;
+use std::str::FromStr;
 use thiserror::Error;
-use crate::domain::affiliation::Affiliation;
-#[derive(Debug, Clone, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+use crate::domain::affiliation::{Affiliation, AffiliationPeriod};
+use crate::domain::orcid::OrcidError;
+use crate::utils::text::{fold, similarity as text_similarity};
+pub use crate::domain::orcid::Orcid;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Name {
     pub first: String,
     pub middle: Option<String>,
+    // `van`, `von der`, `de la`: part of the family name but not of its sort key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub particle: Option<String>,
     pub last: String,
+    // `Jr.`, `III`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+}
+
+// Generational suffixes; `V` and `X` are left out, they are more often initials or mononyms.
+const SUFFIXES: &[&str] = &["jr", "sr", "jnr", "snr", "ii", "iii", "iv", "vi", "vii", "viii", "ix"];
+
+fn is_suffix(word: &str) -> bool {
+    SUFFIXES.contains(&word.trim_end_matches('.').to_lowercase().as_str())
+}
+
+// Particles are told apart from names by case, as BibTeX does: `van`, `de`, `d'`.
+fn is_particle(word: &str) -> bool {
+    word.chars().find(|c| c.is_alphabetic()).is_some_and(char::is_lowercase)
+}
+
+// `J.R.R.` -> `J.`, `R.`, `R.`; hyphenated initials (`J.-P.`) stay as they are.
+fn split_initials(word: &str) -> Vec<String> {
+    let letters: Vec<&str> = word.strip_suffix('.').map_or(vec![], |w| w.split('.').collect());
+    match letters.len() > 1 && letters.iter().all(|l| l.chars().count() == 1 && l.chars().all(char::is_uppercase)) {
+        true => letters.iter().map(|l| format!("{l}.")).collect(),
+        false => vec![word.to_string()],
+    }
+}
+
+fn joined(words: &[String]) -> Option<String> {
+    Some(words.join(" ")).filter(|w| !w.is_empty())
 }
 
 impl Name {
     pub fn builder() -> NameBuilder {
         NameBuilder::default()
     }
-}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Orcid(String);
+    /// Parses a personal name written naturally (`Ludwig van Beethoven Jr.`, `J.R.R. Tolkien`),
+    /// inverted (`García Márquez, Gabriel`, `van Beethoven, Jr., Ludwig`) or as a mononym.
+    /// Lowercase words before the last name are particles, and compound initials are split.
+    pub fn parse(name_str: &str) -> Result<Name, AuthorError> {
+        let parts: Vec<&str> = name_str.split(',').map(str::trim).collect();
+        match parts.as_slice() {
+            [whole] => Name::natural(whole, None),
+            [head, tail] if !tail.is_empty() && tail.split_whitespace().all(is_suffix) => Name::natural(head, Some(tail)),
+            [family, given] => Name::from_parts(family, given, None),
+            [family, suffix, given] => Name::from_parts(family, given, Some(suffix)),
+            _ => Err(AuthorError::InvalidName(format!("too many commas in `{}`", name_str.trim()))),
+        }
+    }
+
+    fn natural(text: &str, suffix: Option<&str>) -> Result<Name, AuthorError> {
+        let mut words: Vec<String> = text.split_whitespace().flat_map(split_initials).collect();
+        let mut suffix = suffix.map(str::to_string);
+        if suffix.is_none() && words.len() > 1 && words.last().is_some_and(|w| is_suffix(w)) {
+            suffix = words.pop();
+        }
+        let Some(last) = words.pop() else {
+            return Err(AuthorError::MissingName);
+        };
+        if words.is_empty() {
+            return Ok(Name { first: String::new(), middle: None, particle: None, last, suffix });
+        }
+        let first = words.remove(0);
+        // `Charles de la Vallée Poussin`: the particle runs to the last lowercase word
+        let (middle, particle, last) = match words.iter().position(|w| is_particle(w)) {
+            Some(start) => {
+                let end = words.iter().rposition(|w| is_particle(w)).unwrap_or(start) + 1;
+                let mut family = words[end..].to_vec();
+                family.push(last);
+                (joined(&words[..start]), joined(&words[start..end]), family.join(" "))
+            }
+            None => (joined(&words), None, last),
+        };
+        Ok(Name { first, middle, particle, last, suffix })
+    }
+
+    /// Builds a name from its family and given parts, as bibliographic formats store them; a
+    /// family name without a given name is taken literally (`World Health Organization`).
+    pub fn from_parts(family: &str, given: &str, suffix: Option<&str>) -> Result<Name, AuthorError> {
+        let family: Vec<String> = family.split_whitespace().map(str::to_string).collect();
+        if family.is_empty() {
+            return Err(AuthorError::MissingName);
+        }
+        let suffix = suffix.map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+        if given.trim().is_empty() {
+            return Ok(Name { first: String::new(), middle: None, particle: None, last: family.join(" "), suffix });
+        }
+        let split = family[..family.len() - 1].iter().take_while(|w| is_particle(w)).count();
+        let mut given: Vec<String> = given.split_whitespace().flat_map(split_initials).collect();
+        let mut particle = joined(&family[..split]);
+        // `Beethoven, Ludwig van`
+        let trailing = given.iter().skip(1).rev().take_while(|w| is_particle(w)).count();
+        if particle.is_none() && trailing > 0 {
+            particle = joined(&given.split_off(given.len() - trailing));
+        }
+        let first = given.remove(0);
+        Ok(Name {
+            first,
+            middle: joined(&given),
+            particle,
+            last: family[split..].join(" "),
+            suffix,
+        })
+    }
+
+    /// The family name with its particle, `van Beethoven`.
+    pub fn family(&self) -> String {
+        match &self.particle {
+            Some(p) => format!("{p} {}", self.last),
+            None => self.last.clone(),
+        }
+    }
+
+    /// First and middle names, `Martin Luther`.
+    pub fn given(&self) -> String {
+        [Some(self.first.as_str()), self.middle.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Folded last name, without particle: `García Márquez` -> `garcia marquez`.
+    pub fn last_key(&self) -> String {
+        fold(&self.last)
+    }
+
+    /// Folded given names split into words; hyphenated names count as several (`jean`, `paul`).
+    pub fn given_keys(&self) -> Vec<String> {
+        fold(&self.given()).split_whitespace().map(str::to_string).collect()
+    }
+
+    /// Lowercase initials of the given names, `jh` for `Jane H.`.
+    pub fn initials(&self) -> String {
+        self.given_keys().iter().filter_map(|w| w.chars().next()).collect()
+    }
+
+    /// Blocking key for candidate duplicates: folded last name and first initial, `smith|j`.
+    pub fn match_key(&self) -> String {
+        format!("{}|{}", self.last_key(), self.initials().chars().next().map(String::from).unwrap_or_default())
+    }
+
+    /// The name with given names reduced to initials, `J. H. Doe`.
+    pub fn abbreviated(&self) -> String {
+        let given = self.given();
+        let initials = given
+            .split_whitespace()
+            .map(|w| {
+                w.split('-')
+                    .filter_map(|part| part.chars().find(|c| c.is_alphabetic()))
+                    .map(|c| format!("{c}."))
+                    .collect::<Vec<_>>()
+                    .join("-")
+            })
+            .filter(|i| !i.is_empty());
+        let parts: Vec<String> = initials
+            .chain(self.particle.clone())
+            .chain([self.last.clone()])
+            .chain(self.suffix.clone())
+            .collect();
+        parts.join(" ")
+    }
 
-impl Orcid{
-    fn parse(orcid_str: &str) -> Result<Self, AuthorError> {
-        // Simple validation logic for ORCID
-        let parts: Vec<&str> = orcid_str.split('-').collect();
-        if parts.len() != 4 || !parts.iter().all(|part| part.len() == 4 && part.chars().all(|c| c.is_digit(10))) {
-            return Err(AuthorError::InvalidOrcid);
+    /// How likely two names denote the same person, from 0.0 to 1.0. Last names must be close;
+    /// given names may be abbreviated or missing on one side (`Jane H. Smith` / `J. Smith`),
+    /// but two different spelled-out given names rule a match out.
+    pub fn similarity(&self, other: &Name) -> f64 {
+        let (a, b) = (self.last_key(), other.last_key());
+        let last = match (a.split_whitespace().next(), b.split_whitespace().next()) {
+            _ if a == b => 1.0,
+            // one side dropped the second part of a compound surname
+            (Some(x), Some(y)) if x == y && (a.contains(' ') || b.contains(' ')) => 0.9,
+            _ => text_similarity(&a, &b),
+        };
+        if last < 0.8 {
+            return 0.0;
+        }
+        let (a, b) = (self.given_keys(), other.given_keys());
+        if a.is_empty() || b.is_empty() {
+            // mononym, corporate name or a bare surname: the last name is all we have
+            return last * if a.is_empty() && b.is_empty() { 1.0 } else { 0.7 };
+        }
+        let mut given = 1.0;
+        for i in 0..a.len().max(b.len()) {
+            given *= match (a.get(i), b.get(i)) {
+                (Some(x), Some(y)) if x == y => 1.0,
+                (Some(x), Some(y)) if x.chars().count() == 1 || y.chars().count() == 1 => {
+                    match x.chars().next() == y.chars().next() {
+                        true => 0.9,
+                        false => 0.0,
+                    }
+                }
+                // `Jon` / `Jonathan`
+                (Some(x), Some(y)) if x.starts_with(y.as_str()) || y.starts_with(x.as_str()) => 0.8,
+                (Some(_), Some(_)) => 0.0,
+                // a middle name only one side records
+                _ => 0.95,
+            };
         }
-        Ok(Orcid(orcid_str.to_string()))
+        last * given
+    }
+
+    /// `similarity` at or above `COMPATIBLE_NAMES`.
+    pub fn is_compatible(&self, other: &Name) -> bool {
+        self.similarity(other) >= COMPATIBLE_NAMES
     }
 }
 
+/// Score from which `Name::similarity` considers two names the same person.
+pub const COMPATIBLE_NAMES: f64 = 0.8;
+
+impl FromStr for Name {
+    type Err = AuthorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Name::parse(s)
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // mononyms and corporate authors only have a last name
+        let parts: Vec<&str> = [
+            Some(self.first.as_str()),
+            self.middle.as_deref(),
+            self.particle.as_deref(),
+            Some(self.last.as_str()),
+            self.suffix.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|p| !p.is_empty())
+        .collect();
+        write!(f, "{}", parts.join(" "))
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct NameBuilder {
     first: Option<String>,
     middle: Option<String>,
+    particle: Option<String>,
     last: Option<String>,
+    suffix: Option<String>,
 }
 
 impl NameBuilder {
@@ -52,6 +278,14 @@ impl NameBuilder {
         self
     }
 
+    pub fn particle(mut self, particle: impl Into<String>) -> Self {
+        self.particle = match particle.into().as_str() {
+            "" => None,
+            s => Some(s.to_string()),
+        };
+        self
+    }
+
     pub fn last(mut self, last: impl Into<String>) -> Self {
         self.last = match last.into().as_str() {
             "" => None,
@@ -60,6 +294,14 @@ impl NameBuilder {
         self
     }
 
+    pub fn suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.suffix = match suffix.into().as_str() {
+            "" => None,
+            s => Some(s.to_string()),
+        };
+        self
+    }
+
     pub fn build(self) -> Result<Name, AuthorError> {
         let first = self.first.ok_or(AuthorError::MissingName)?;
         let last = self.last.ok_or(AuthorError::MissingName)?;
@@ -67,17 +309,21 @@ impl NameBuilder {
         Ok(Name {
             first,
             middle,
+            particle: self.particle,
             last,
+            suffix: self.suffix,
         })
     }
 }
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Author {
     pub name: Name,
     pub orcid: Option<Orcid>,
     pub affiliation: Option<Affiliation>,
+    /// earlier and current affiliations with their years
+    pub history: Vec<AffiliationPeriod>,
     pub tags: Vec<String>,
 }
 
@@ -93,6 +339,7 @@ pub struct AuthorBuilder {
     name: Option<Name>,
     orcid: Option<Orcid>,
     affiliation: Option<Affiliation>,
+    history: Vec<AffiliationPeriod>,
     tags: Vec<String>,
 }
 
@@ -105,16 +352,7 @@ impl AuthorBuilder {
 
 
     pub fn name_from_str(mut self, name_str: &str) -> Result<Self, AuthorError> {
-        let name_parts: Vec<&str> = name_str.split_whitespace().collect();
-        if name_parts.is_empty() {
-            return Err(AuthorError::MissingName);
-        }
-        let name: Name = match name_parts.len() {
-            2 => Name::builder().first(name_parts[0]).last(name_parts[1]).build()?,
-            3 => Name::builder().first(name_parts[0]).middle(name_parts[1]).last(name_parts[2]).build()?,
-            _ => return Err(AuthorError::MissingName),
-        };
-
+        let name = Name::parse(name_str)?;
         self = self.name(name)?;
         Ok(self)
     }
@@ -131,6 +369,14 @@ impl AuthorBuilder {
         Ok(self)
     }
 
+    /// Like `orcid_from_str`, for importers: a malformed ORCID is dropped rather than the author.
+    pub fn orcid_if_valid(mut self, orcid_str: &str) -> Self {
+        if let Ok(orcid) = Orcid::parse(orcid_str) {
+            self.orcid = Some(orcid);
+        }
+        self
+    }
+
 
 
     pub fn affiliation(mut self, affiliation: Affiliation) -> Result<Self, AuthorError> {
@@ -139,11 +385,16 @@ impl AuthorBuilder {
     }
 
     pub fn affiliation_from_str(mut self, affil_str: &str) -> Result<Self, AuthorError> {
-        let affiliation = Affiliation::parse(affil_str);
+        let affiliation = Affiliation::analyze(affil_str)?.affiliation;
         self = self.affiliation(affiliation)?;
         Ok(self)
     }   
 
+    pub fn affiliation_period(mut self, period: AffiliationPeriod) -> Result<Self, AuthorError> {
+        self.history.push(period);
+        Ok(self)
+    }
+
     pub fn tags(mut self, tags: Vec<String>) -> Result<Self, AuthorError> {
         self.tags = tags;
         Ok(self)
@@ -153,6 +404,7 @@ impl AuthorBuilder {
         let name = self.name.ok_or(AuthorError::MissingName)?;
         let orcid =self.orcid;
         let affiliation = self.affiliation;
+        let history = self.history;
         let tags = self.tags;
         
         Ok(
@@ -160,6 +412,7 @@ impl AuthorBuilder {
             name,
             orcid,
             affiliation,
+            history,
             tags,
         })
     }
@@ -171,12 +424,16 @@ impl AuthorBuilder {
 
 #[derive(Error, Debug, PartialEq, Eq)]
 pub enum AuthorError {
-    #[error("Invalid ORCID format")]
-    InvalidOrcid,
+    #[error("Invalid ORCID iD: {0}")]
+    InvalidOrcid(#[from] OrcidError),
     #[error("Missing author name")]
     MissingName,
-    #[error("Affiliation parsing error")]
-    AffiliationParsingError,
+    #[error("Invalid name: {0}")]
+    InvalidName(String),
+    #[error("Affiliation parsing error: {0}")]
+    AffiliationParsingError(String),
+    #[error("Affiliation period ends in {end}, before it starts in {start}")]
+    InvalidPeriod { start: i64, end: i64 },
     #[error("General author error: {0}")]
     General(String),
 }
@@ -211,24 +468,18 @@ mod tests {
 
         assert_eq!(author.name.first, "Jane");
         assert_eq!(author.name.last, "Smith");
-        assert_eq!(author.orcid.unwrap().0, "0000-0001-2345-6789");
+        assert_eq!(author.orcid.unwrap().as_str(), "0000-0001-2345-6789");
         assert_eq!(author.affiliation.unwrap().institution.unwrap(), "University X");
         assert_eq!(author.tags, vec!["Physics", "Astronomy"]);
     }
 
 
     #[test]
-    fn test_orcid_parse_valid() {
-        let orcid_str = "0000-0002-1825-0097";
-        let orcid = Orcid::parse(orcid_str).unwrap();
-        assert_eq!(orcid.0, orcid_str);
-    }
-
-    #[test]
-    fn test_orcid_parse_invalid() {
-        let orcid_str = "0000-0002-1825-009X";
-        let result = Orcid::parse(orcid_str);
-        assert!(result.is_err());
+    fn test_orcid_from_str_reports_reason() {
+        let result = Author::builder().orcid_from_str("0000-0002-1825-009X");
+        let err = result.err().unwrap();
+        assert_eq!(err, AuthorError::InvalidOrcid(OrcidError::Checksum { expected: '7', found: 'X' }));
+        assert_eq!(err.to_string(), "Invalid ORCID iD: check digit is X, expected 7");
     }
 
     #[test]
@@ -244,7 +495,9 @@ mod tests {
             Name {
                 first: "John".to_string(),
                 middle: Some("H.".to_string()),
+                particle: None,
                 last: "Doe".to_string(),
+                suffix: None,
             }
         );
     }
@@ -260,5 +513,86 @@ mod tests {
         let result = Name::builder().first("John").build();
         assert_eq!(result, Err(AuthorError::MissingName));
     }
-}
 
+    fn parts(name: &str) -> (String, Option<String>, Option<String>, String, Option<String>) {
+        let n = Name::parse(name).unwrap();
+        (n.first, n.middle, n.particle, n.last, n.suffix)
+    }
+
+    fn some(s: &str) -> Option<String> {
+        Some(s.to_string())
+    }
+
+    #[test]
+    fn test_name_parse_forms() {
+        assert_eq!(
+            parts("Ludwig van Beethoven Jr."),
+            ("Ludwig".into(), None, some("van"), "Beethoven".into(), some("Jr."))
+        );
+        assert_eq!(parts("García Márquez, Gabriel"), ("Gabriel".into(), None, None, "García Márquez".into(), None));
+        assert_eq!(parts("J.R.R. Tolkien"), ("J.".into(), some("R. R."), None, "Tolkien".into(), None));
+        assert_eq!(parts("Aristotle"), ("".into(), None, None, "Aristotle".into(), None));
+        assert_eq!(
+            parts("van der Waals, Johannes Diderik"),
+            ("Johannes".into(), some("Diderik"), some("van der"), "Waals".into(), None)
+        );
+        assert_eq!(
+            parts("Charles de la Vallée Poussin"),
+            ("Charles".into(), None, some("de la"), "Vallée Poussin".into(), None)
+        );
+        assert_eq!(
+            parts("King, Jr., Martin Luther"),
+            ("Martin".into(), some("Luther"), None, "King".into(), some("Jr."))
+        );
+        assert_eq!(parts("Martin Luther King, Jr."), parts("King, Jr., Martin Luther"));
+        assert_eq!(parts("John Smith III").4, some("III"));
+        assert_eq!(parts("Beethoven, Ludwig van").2, some("van"));
+        assert_eq!(parts("Jean-Paul Sartre-Dupont"), ("Jean-Paul".into(), None, None, "Sartre-Dupont".into(), None));
+        assert_eq!(parts("  Jane   H.  Smith "), ("Jane".into(), some("H."), None, "Smith".into(), None));
+    }
+
+    #[test]
+    fn test_name_parse_errors_and_display() {
+        assert_eq!(Name::parse("   "), Err(AuthorError::MissingName));
+        assert_eq!(Name::parse(", Jane"), Err(AuthorError::MissingName));
+        assert!(matches!(Name::parse("a, b, c, d"), Err(AuthorError::InvalidName(_))));
+        let name: Name = "van Beethoven, Jr., Ludwig".parse().unwrap();
+        assert_eq!(name.to_string(), "Ludwig van Beethoven Jr.");
+        assert_eq!(name.family(), "van Beethoven");
+        let author = Author::builder().name_from_str("Madonna").unwrap().build().unwrap();
+        assert_eq!(author.name.last, "Madonna");
+    }
+
+    fn name(s: &str) -> Name {
+        Name::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_name_keys() {
+        let n = name("García Márquez, Gabriel José");
+        assert_eq!(n.last_key(), "garcia marquez");
+        assert_eq!(n.initials(), "gj");
+        assert_eq!(n.match_key(), "garcia marquez|g");
+        assert_eq!(name("Jean-Paul Sartre").given_keys(), vec!["jean", "paul"]);
+        assert_eq!(name("Jean-Paul van der Berg Jr.").abbreviated(), "J.-P. van der Berg Jr.");
+        assert_eq!(name("Jane Helen Doe").abbreviated(), "J. H. Doe");
+        assert_eq!(name("Aristotle").match_key(), "aristotle|");
+        assert_eq!(name("Мельник, Олена").match_key(), "melnik|o");
+    }
+
+    #[test]
+    fn test_name_similarity() {
+        let jane = name("Jane H. Smith");
+        assert!(jane.is_compatible(&name("J. Smith")));
+        assert!(jane.is_compatible(&name("SMITH, Jane")));
+        assert!(name("Jane Johnson").is_compatible(&name("Jane Johnsen")));
+        assert_eq!(jane.similarity(&jane), 1.0);
+        assert!(jane.similarity(&name("J. Smith")) < 1.0);
+        assert!(!jane.is_compatible(&name("John Smith")));
+        assert!(!jane.is_compatible(&name("J. K. Smith")));
+        assert!(!jane.is_compatible(&name("Jane H. Jones")));
+        assert!(name("Gabriel García Márquez").similarity(&name("García, Gabriel")) == 0.0);
+        assert!(name("García Márquez, Gabriel").is_compatible(&name("García, Gabriel")));
+        assert!(name("Erwin Schrödinger").is_compatible(&name("E. Schroedinger")));
+    }
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use crate::domain::ror::RorId;
+
+/// An organization authors are affiliated with: a university, a lab, a company, or a part of one.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Institution {
+    pub name: String,
+    pub ror: Option<RorId>,
+    /// other names, including translations
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub acronyms: Vec<String>,
+    pub country_code: Option<String>,
+    /// ROR organization types, lowercase (education, facility, company, ...)
+    #[serde(default)]
+    pub types: Vec<String>,
+}
+
+impl Institution {
+    pub fn new(name: impl Into<String>) -> Self {
+        Institution { name: name.into(), ..Default::default() }
+    }
+
+    /// The name followed by aliases and acronyms.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.name.as_str())
+            .chain(self.aliases.iter().map(String::as_str))
+            .chain(self.acronyms.iter().map(String::as_str))
+    }
+}
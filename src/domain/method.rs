@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// A methodology, algorithm or technique that works introduce and use.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Method {
+    pub name: String,
+    /// other names, including abbreviations (`MCMC`)
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// the method this one is a variant of
+    pub parent: Option<String>,
+    /// the field the method comes from
+    pub field: Option<String>,
+    /// id of the work that introduced the method
+    pub reference: Option<String>,
+}
+
+impl Method {
+    pub fn new(name: impl Into<String>) -> Self {
+        Method { name: name.into(), ..Default::default() }
+    }
+
+    /// The name followed by aliases.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.name.as_str()).chain(self.aliases.iter().map(String::as_str))
+    }
+}
@@ -1,6 +1,20 @@
 pub mod author;
 pub mod affiliation;
+pub mod country;
+pub mod institution;
+pub mod method;
 pub mod types;
+pub mod venue;
 pub mod orcid;
+pub mod ror;
+pub mod sources;
+pub mod topic;
 pub use author::{Author, AuthorError,Name,Orcid};
-pub use affiliation::Affiliation;
\ No newline at end of file
+pub use affiliation::{Affiliation, AffiliationPeriod, ParsedAffiliation};
+pub use orcid::OrcidError;
+pub use institution::Institution;
+pub use method::Method;
+pub use ror::{RorId, RorError};
+pub use topic::Topic;
+pub use types::{ArxivId, Doi, Identifiers, Isbn, Pmid, Swhid, Work, WorkBuilder, WorkDetails, WorkError, WorkKind};
+pub use venue::{Issn, IssnError, Venue, VenueKind};
@@ -0,0 +1,154 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+const URL_PREFIXES: [&str; 4] = ["https://orcid.org/", "http://orcid.org/", "orcid.org/", "orcid:"];
+
+/// Why a string is not an ORCID iD.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum OrcidError {
+    #[error("expected 16 digits, found {0}")]
+    Length(usize),
+    #[error("unexpected character `{0}`")]
+    Character(char),
+    #[error("digits must be grouped as 0000-0000-0000-000X")]
+    Grouping,
+    #[error("check digit is {found}, expected {expected}")]
+    Checksum { expected: char, found: char },
+}
+
+// ISO 7064 MOD 11-2 over the first 15 digits; 10 is written as `X`.
+fn check_digit(digits: &[u32]) -> char {
+    let total = digits.iter().fold(0, |total, d| (total + d) * 2);
+    match (12 - total % 11) % 11 {
+        10 => 'X',
+        n => char::from_digit(n, 10).unwrap_or('0'),
+    }
+}
+
+/// An ORCID iD, always held in its canonical `0000-0002-1825-009X` form.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Orcid(String);
+
+impl Orcid {
+    /// Accepts the canonical form, the bare 16 characters and `https://orcid.org/` URLs, and
+    /// verifies the check digit.
+    pub fn parse(orcid_str: &str) -> Result<Self, OrcidError> {
+        let trimmed = orcid_str.trim();
+        let lower = trimmed.to_ascii_lowercase();
+        let id = URL_PREFIXES
+            .iter()
+            .find(|p| lower.starts_with(*p))
+            .map_or(trimmed, |p| &trimmed[p.len()..]);
+
+        let chars: Vec<char> = id.chars().filter(|c| *c != '-').collect();
+        if let Some(c) = chars.iter().find(|c| !c.is_ascii_digit() && !matches!(c, 'X' | 'x')) {
+            return Err(OrcidError::Character(*c));
+        }
+        if chars.len() != 16 {
+            return Err(OrcidError::Length(chars.len()));
+        }
+        if id.contains('-') && id.split('-').map(str::len).ne([4, 4, 4, 4]) {
+            return Err(OrcidError::Grouping);
+        }
+        let digits: Vec<u32> = chars[..15]
+            .iter()
+            .map(|c| c.to_digit(10).ok_or(OrcidError::Character(*c)))
+            .collect::<Result<_, _>>()?;
+        let expected = check_digit(&digits);
+        let found = chars[15].to_ascii_uppercase();
+        if found != expected {
+            return Err(OrcidError::Checksum { expected, found });
+        }
+
+        let canonical: String = chars[..15].iter().chain([&found]).collect();
+        let groups: Vec<&str> = (0..4).map(|i| &canonical[i * 4..i * 4 + 4]).collect();
+        Ok(Orcid(groups.join("-")))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn url(&self) -> String {
+        format!("https://orcid.org/{}", self.0)
+    }
+}
+
+impl fmt::Display for Orcid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Orcid {
+    type Err = OrcidError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Orcid::parse(s)
+    }
+}
+
+impl Serialize for Orcid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Orcid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Orcid::parse(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orcid_parse_valid() {
+        let orcid_str = "0000-0002-1825-0097";
+        let orcid = Orcid::parse(orcid_str).unwrap();
+        assert_eq!(orcid.as_str(), orcid_str);
+        // check digit 10 is written as X
+        assert_eq!(Orcid::parse("0000-0002-1694-233X").unwrap().as_str(), "0000-0002-1694-233X");
+    }
+
+    #[test]
+    fn test_orcid_parse_invalid() {
+        assert_eq!(
+            Orcid::parse("0000-0002-1825-009X"),
+            Err(OrcidError::Checksum { expected: '7', found: 'X' })
+        );
+        assert_eq!(Orcid::parse("0000-0002-1825-009"), Err(OrcidError::Length(15)));
+        assert_eq!(Orcid::parse("0000-0002-1825-0O97"), Err(OrcidError::Character('O')));
+        assert_eq!(Orcid::parse("000X-0002-1825-0097"), Err(OrcidError::Character('X')));
+        assert_eq!(Orcid::parse("00000-002-1825-0097"), Err(OrcidError::Grouping));
+    }
+
+    #[test]
+    fn test_orcid_normalization() {
+        for input in [
+            "https://orcid.org/0000-0002-1694-233X",
+            "http://orcid.org/0000-0002-1694-233x",
+            " orcid.org/0000-0002-1694-233X ",
+            "000000021694233x",
+        ] {
+            let orcid: Orcid = input.parse().unwrap();
+            assert_eq!(orcid.to_string(), "0000-0002-1694-233X");
+        }
+        assert_eq!(Orcid::parse("0000000218250097").unwrap().url(), "https://orcid.org/0000-0002-1825-0097");
+    }
+
+    #[test]
+    fn test_orcid_serde() {
+        let orcid = Orcid::parse("0000000218250097").unwrap();
+        assert_eq!(serde_json::to_string(&orcid).unwrap(), "\"0000-0002-1825-0097\"");
+        let back: Orcid = serde_json::from_str("\"https://orcid.org/0000-0002-1825-0097\"").unwrap();
+        assert_eq!(back, orcid);
+        assert!(serde_json::from_str::<Orcid>("\"0000-0002-1825-0098\"").is_err());
+    }
+}
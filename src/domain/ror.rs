@@ -0,0 +1,112 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+const URL_PREFIXES: [&str; 3] = ["https://ror.org/", "http://ror.org/", "ror.org/"];
+// Crockford base32 without I, L, O and U.
+const ALPHABET: &str = "0123456789abcdefghjkmnpqrstvwxyz";
+
+/// Why a string is not a ROR id.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RorError {
+    #[error("expected 9 characters, found {0}")]
+    Length(usize),
+    #[error("ROR ids start with 0")]
+    Prefix,
+    #[error("unexpected character `{0}`")]
+    Character(char),
+    #[error("checksum is {found}, expected {expected:02}")]
+    Checksum { expected: u64, found: String },
+}
+
+/// A Research Organization Registry id such as `042nb2s44`, held without the URL.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RorId(String);
+
+impl RorId {
+    /// Accepts the bare id and `https://ror.org/` URLs, and verifies the ISO 7064 MOD 97-10
+    /// checksum in the last two digits.
+    pub fn parse(ror_str: &str) -> Result<Self, RorError> {
+        let lower = ror_str.trim().to_lowercase();
+        let id = URL_PREFIXES.iter().find_map(|p| lower.strip_prefix(p)).unwrap_or(&lower);
+        // the checks below slice by byte
+        if let Some(c) = id.chars().find(|c| !c.is_ascii()) {
+            return Err(RorError::Character(c));
+        }
+        if id.len() != 9 {
+            return Err(RorError::Length(id.len()));
+        }
+        if !id.starts_with('0') {
+            return Err(RorError::Prefix);
+        }
+        let mut value: u64 = 0;
+        for c in id[..7].chars() {
+            let digit = ALPHABET.find(c).ok_or(RorError::Character(c))?;
+            value = value * 32 + digit as u64;
+        }
+        if let Some(c) = id[7..].chars().find(|c| !c.is_ascii_digit()) {
+            return Err(RorError::Character(c));
+        }
+        let expected = 98 - (value * 100) % 97;
+        if id[7..].parse::<u64>().ok() != Some(expected) {
+            return Err(RorError::Checksum { expected, found: id[7..].to_string() });
+        }
+        Ok(RorId(id.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn url(&self) -> String {
+        format!("https://ror.org/{}", self.0)
+    }
+}
+
+impl fmt::Display for RorId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for RorId {
+    type Err = RorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RorId::parse(s)
+    }
+}
+
+impl Serialize for RorId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for RorId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        RorId::parse(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ror_parse() {
+        for input in ["042nb2s44", "https://ror.org/042nb2s44", " ROR.org/042NB2S44 "] {
+            assert_eq!(RorId::parse(input).unwrap().as_str(), "042nb2s44");
+        }
+        assert_eq!(RorId::parse("01ggx4157").unwrap().url(), "https://ror.org/01ggx4157");
+        assert_eq!(RorId::parse("042nb2s45"), Err(RorError::Checksum { expected: 44, found: "45".to_string() }));
+        assert_eq!(RorId::parse("142nb2s44"), Err(RorError::Prefix));
+        assert_eq!(RorId::parse("042nb2s4"), Err(RorError::Length(8)));
+        assert_eq!(RorId::parse("042ib2s44"), Err(RorError::Character('i')));
+        assert_eq!(RorId::parse("00éééé123"), Err(RorError::Character('é')));
+        assert_eq!(RorId::parse("042nb2sé4"), Err(RorError::Character('é')));
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use thiserror::Error;
+use crate::database::{AcademicResourceManager, RepositoryError};
+use crate::domain::author::{Author, AuthorError};
+
+pub mod bibtex;
+pub mod csl;
+pub mod docx;
+pub mod hayagriva;
+pub mod typst;
+pub mod zotero;
+pub mod keys;
+pub mod ris;
+pub mod skos;
+
+// Values stored in `entity.kind` for works.
+pub const JOURNAL_ARTICLE: &str = "journal_article";
+pub const CONFERENCE_PAPER: &str = "conference_paper";
+pub const PROCEEDINGS: &str = "proceedings";
+pub const BOOK: &str = "book";
+pub const CHAPTER: &str = "chapter";
+pub const THESIS: &str = "thesis";
+pub const PREPRINT: &str = "preprint";
+pub const REPORT: &str = "report";
+pub const SOFTWARE: &str = "software";
+pub const DATASET: &str = "dataset";
+pub const WEBPAGE: &str = "webpage";
+pub const MISC: &str = "misc";
+
+pub fn is_work_kind(kind: &str) -> bool {
+    matches!(
+        kind,
+        JOURNAL_ARTICLE | CONFERENCE_PAPER | PROCEEDINGS | BOOK | CHAPTER | THESIS | PREPRINT | REPORT
+            | SOFTWARE | DATASET | WEBPAGE | MISC
+    )
+}
+
+pub const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August", "September", "October",
+    "November", "December",
+];
+
+// 1-based month from a number or an English (possibly abbreviated) month name.
+pub fn month_number(s: &str) -> Option<u32> {
+    let s = s.trim();
+    if let Ok(n) = s.parse::<u32>() {
+        return (1..=12).contains(&n).then_some(n);
+    }
+    let lower = s.to_lowercase();
+    if lower.len() < 3 {
+        return None;
+    }
+    MONTHS
+        .iter()
+        .position(|m| m.to_lowercase().starts_with(&lower))
+        .map(|i| i as u32 + 1)
+}
+
+// First run of digits, if it is a four digit year.
+pub fn year_from(s: &str) -> Option<i64> {
+    let digits: String = s
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(char::is_ascii_digit)
+        .collect();
+    match digits.len() {
+        4 => digits.parse().ok(),
+        _ => None,
+    }
+}
+
+// A bibliographic record in a format-neutral shape. Importers produce it, exporters consume it,
+// and `AcademicResourceManager::save_reference`/`load_reference` map it onto the graph.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Reference {
+    pub key: Option<String>,
+    pub kind: String,
+    pub title: String,
+    pub authors: Vec<Author>,
+    pub editors: Vec<Author>,
+    pub year: Option<i64>,
+    pub venue: Option<String>,
+    pub uri: Option<String>,
+    pub keywords: Vec<String>,
+    // remaining fields, lowercase names (doi, volume, pages, publisher, ...)
+    pub fields: BTreeMap<String, String>,
+    // format specific data stored as entity props next to `fields` (e.g. unknown RIS tags)
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl Reference {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+
+    pub fn doi(&self) -> Option<&str> {
+        self.field("doi")
+    }
+}
+
+// A problem with a single entry; the rest of the input is still imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryError {
+    pub line: usize,
+    pub key: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for EntryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.key {
+            Some(k) => write!(f, "line {} ({}): {}", self.line, k, self.message),
+            None => write!(f, "line {}: {}", self.line, self.message),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Parsed {
+    pub references: Vec<Reference>,
+    pub errors: Vec<EntryError>,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: Vec<String>,
+    pub errors: Vec<EntryError>,
+}
+
+#[derive(Error, Debug)]
+pub enum SourceError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Repository error: {0}")]
+    Repository(#[from] RepositoryError),
+    #[error("Author error: {0}")]
+    Author(#[from] AuthorError),
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] sqlite::Error),
+    #[error("Zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Format error: {0}")]
+    Format(String),
+}
+
+// Saves every parsed reference; entry errors from parsing are carried over into the report.
+pub fn import_parsed(arm: &AcademicResourceManager, parsed: Parsed) -> Result<ImportReport, SourceError> {
+    let mut report = ImportReport { imported: Vec::new(), errors: parsed.errors };
+    for reference in &parsed.references {
+        report.imported.push(arm.save_reference(reference)?);
+    }
+    Ok(report)
+}
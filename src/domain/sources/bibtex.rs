@@ -0,0 +1,940 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use log::warn;
+use crate::database::AcademicResourceManager;
+use crate::database::references::CITES;
+use crate::database::repository::Params;
+use crate::domain::author::{Author, Name};
+use crate::domain::sources::keys::{keyed_references, KeyFormat};
+use crate::domain::sources::{
+    import_parsed, year_from, EntryError, ImportReport, Parsed, Reference, SourceError, BOOK, CHAPTER,
+    CONFERENCE_PAPER, DATASET, JOURNAL_ARTICLE, MISC, PREPRINT, PROCEEDINGS, REPORT, SOFTWARE,
+    THESIS, WEBPAGE, MONTHS,
+};
+use crate::utils::latex;
+
+// An entry after `@string` expansion, values still LaTeX-encoded.
+#[derive(Debug, Clone)]
+struct RawEntry {
+    line: usize,
+    entry_type: String,
+    key: String,
+    fields: Vec<(String, String)>,
+}
+
+impl RawEntry {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.fields.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+}
+
+struct Failure {
+    line: usize,
+    key: Option<String>,
+    message: String,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    macros: HashMap<String, String>,
+}
+
+impl Parser {
+    fn new(input: &str) -> Self {
+        let macros = MONTHS
+            .iter()
+            .map(|m| (m[..3].to_lowercase(), m.to_string()))
+            .collect();
+        Parser { chars: input.chars().collect(), pos: 0, line: 1, macros }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    // Moves past the next `@`; text between entries is a comment.
+    fn seek_entry(&mut self) -> bool {
+        while let Some(c) = self.bump() {
+            if c == '@' {
+                return true;
+            }
+        }
+        false
+    }
+
+    // After a broken entry, continue at the next line that starts with `@`.
+    fn recover(&mut self) {
+        while self.pos < self.chars.len() {
+            if self.chars[self.pos] == '@' {
+                let line_start = self.chars[..self.pos]
+                    .iter()
+                    .rev()
+                    .take_while(|c| **c != '\n')
+                    .all(|c| c.is_whitespace());
+                if line_start {
+                    return;
+                }
+            }
+            self.bump();
+        }
+    }
+
+    fn fail(&self, key: Option<&str>, message: impl Into<String>) -> Failure {
+        Failure { line: self.line, key: key.map(str::to_string), message: message.into() }
+    }
+
+    fn identifier(&mut self) -> String {
+        let mut s = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || "_-:.+/".contains(c) {
+                s.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        s
+    }
+
+    fn expect(&mut self, expected: char, key: Option<&str>) -> Result<(), Failure> {
+        self.skip_ws();
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => Err(self.fail(key, format!("expected `{expected}`, found `{c}`"))),
+            None => Err(self.fail(key, format!("expected `{expected}`, found end of input"))),
+        }
+    }
+
+    // Content of a `{...}` group, outer braces removed, inner ones kept.
+    fn braced(&mut self, key: Option<&str>) -> Result<String, Failure> {
+        let start_line = self.line;
+        self.bump();
+        let mut depth = 1;
+        let mut s = String::new();
+        while let Some(c) = self.bump() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(s);
+                    }
+                }
+                _ => {}
+            }
+            s.push(c);
+        }
+        Err(Failure {
+            line: start_line,
+            key: key.map(str::to_string),
+            message: "unbalanced braces".to_string(),
+        })
+    }
+
+    fn quoted(&mut self, key: Option<&str>) -> Result<String, Failure> {
+        let start_line = self.line;
+        self.bump();
+        let mut depth = 0;
+        let mut s = String::new();
+        while let Some(c) = self.bump() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                '"' if depth == 0 => return Ok(s),
+                _ => {}
+            }
+            s.push(c);
+        }
+        Err(Failure {
+            line: start_line,
+            key: key.map(str::to_string),
+            message: "unterminated quoted value".to_string(),
+        })
+    }
+
+    // value := part ('#' part)*
+    fn value(&mut self, key: Option<&str>) -> Result<String, Failure> {
+        let mut out = String::new();
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                Some('{') => out.push_str(&self.braced(key)?),
+                Some('"') => out.push_str(&self.quoted(key)?),
+                Some(c) if c.is_ascii_digit() => {
+                    while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                        out.push(self.bump().unwrap_or_default());
+                    }
+                }
+                Some(c) if c.is_alphabetic() => {
+                    let name = self.identifier().to_lowercase();
+                    match self.macros.get(&name) {
+                        Some(v) => out.push_str(v),
+                        None => return Err(self.fail(key, format!("undefined string `{name}`"))),
+                    }
+                }
+                Some(c) => return Err(self.fail(key, format!("unexpected `{c}` in value"))),
+                None => return Err(self.fail(key, "unexpected end of input in value")),
+            }
+            self.skip_ws();
+            if self.peek() == Some('#') {
+                self.bump();
+            } else {
+                return Ok(out);
+            }
+        }
+    }
+
+    fn skip_group(&mut self, close: char) -> Result<(), Failure> {
+        let mut depth = 1;
+        let open = if close == '}' { '{' } else { '(' };
+        while let Some(c) = self.bump() {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(());
+                }
+            }
+        }
+        Err(self.fail(None, "unterminated @comment"))
+    }
+
+    // Parses one entry after its `@`. `Ok(None)` for @comment, @string and @preamble.
+    fn entry(&mut self) -> Result<Option<RawEntry>, Failure> {
+        let line = self.line;
+        let entry_type = self.identifier().to_lowercase();
+        if entry_type.is_empty() {
+            return Err(self.fail(None, "missing entry type after `@`"));
+        }
+        self.skip_ws();
+        let close = match self.peek() {
+            Some('{') => '}',
+            Some('(') => ')',
+            _ => return Err(self.fail(None, format!("expected `{{` after @{entry_type}"))),
+        };
+        self.bump();
+
+        match entry_type.as_str() {
+            "comment" => {
+                self.skip_group(close)?;
+                return Ok(None);
+            }
+            "preamble" => {
+                self.value(None)?;
+                self.expect(close, None)?;
+                return Ok(None);
+            }
+            "string" => {
+                self.skip_ws();
+                let name = self.identifier().to_lowercase();
+                if name.is_empty() {
+                    return Err(self.fail(None, "missing @string name"));
+                }
+                self.expect('=', None)?;
+                let value = self.value(None)?;
+                self.expect(close, None)?;
+                self.macros.insert(name, value);
+                return Ok(None);
+            }
+            _ => {}
+        }
+
+        self.skip_ws();
+        let mut key = String::new();
+        while let Some(c) = self.peek() {
+            if c == ',' || c == close || c.is_whitespace() {
+                break;
+            }
+            key.push(c);
+            self.bump();
+        }
+        if key.is_empty() {
+            return Err(self.fail(None, format!("missing citation key in @{entry_type}")));
+        }
+        let k = Some(key.as_str());
+
+        let mut fields = Vec::new();
+        self.skip_ws();
+        match self.peek() {
+            Some(',') => {
+                self.bump();
+            }
+            Some(c) if c == close => {}
+            _ => return Err(self.fail(k, "expected `,` after citation key")),
+        }
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                Some(c) if c == close => {
+                    self.bump();
+                    break;
+                }
+                None => return Err(self.fail(k, "unterminated entry")),
+                _ => {}
+            }
+            let name = self.identifier().to_lowercase();
+            if name.is_empty() {
+                let found = self.peek().map_or("end of input".to_string(), |c| format!("`{c}`"));
+                return Err(self.fail(k, format!("expected field name, found {found}")));
+            }
+            self.expect('=', k)?;
+            let value = self.value(k)?;
+            fields.push((name, value));
+            self.skip_ws();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some(c) if c == close => {}
+                Some(c) => return Err(self.fail(k, format!("expected `,` or `{close}`, found `{c}`"))),
+                None => return Err(self.fail(k, "unterminated entry")),
+            }
+        }
+        Ok(Some(RawEntry { line, entry_type, key, fields }))
+    }
+}
+
+// Fills fields missing in a child from its `crossref` parent. The parent's title becomes the
+// child's booktitle, as BibTeX does for proceedings and collections.
+fn resolve_crossrefs(entries: &mut [RawEntry]) {
+    let index: HashMap<String, usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, e)| (e.key.to_lowercase(), i))
+        .collect();
+    for i in 0..entries.len() {
+        let Some(parent_key) = entries[i].get("crossref").map(str::to_lowercase) else {
+            continue;
+        };
+        let Some(&p) = index.get(&parent_key) else {
+            warn!("{}: crossref to unknown entry `{}`", entries[i].key, parent_key);
+            continue;
+        };
+        let parent = entries[p].clone();
+        let child = &mut entries[i];
+        for (name, value) in parent.fields {
+            match name.as_str() {
+                "crossref" | "ids" => {}
+                "title" => {
+                    if !child.has("booktitle") {
+                        child.fields.push(("booktitle".to_string(), value));
+                    }
+                }
+                _ => {
+                    if !child.has(&name) {
+                        child.fields.push((name, value));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Splits `s` on whitespace and `,` outside braces; commas are returned as their own token.
+fn tokens(s: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut cur = String::new();
+    let mut depth = 0;
+    for c in s.chars() {
+        match c {
+            '{' => {
+                depth += 1;
+                cur.push(c);
+            }
+            '}' => {
+                depth -= 1;
+                cur.push(c);
+            }
+            ',' if depth == 0 => {
+                if !cur.is_empty() {
+                    out.push(std::mem::take(&mut cur));
+                }
+                out.push(",".to_string());
+            }
+            c if c.is_whitespace() && depth == 0 => {
+                if !cur.is_empty() {
+                    out.push(std::mem::take(&mut cur));
+                }
+            }
+            _ => cur.push(c),
+        }
+    }
+    if !cur.is_empty() {
+        out.push(cur);
+    }
+    out
+}
+
+// BibTeX treats a word as lowercase ("von" part) when its first letter outside braces is lowercase.
+fn is_lowercase_word(word: &str) -> bool {
+    if word.starts_with('{') {
+        return false;
+    }
+    latex::decode(word)
+        .chars()
+        .find(|c| c.is_alphabetic())
+        .is_some_and(char::is_lowercase)
+}
+
+fn join_decoded(words: &[String]) -> String {
+    latex::decode(&words.join(" "))
+}
+
+// Splits `von Last` words; the von part runs from the first to the last lowercase word, but never
+// takes the final word.
+fn split_von(words: &[String]) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let candidates = &words[..words.len().saturating_sub(1)];
+    match candidates.iter().position(|w| is_lowercase_word(w)) {
+        Some(start) => {
+            let end = candidates.iter().rposition(|w| is_lowercase_word(w)).unwrap_or(start) + 1;
+            (words[..start].to_vec(), words[start..end].to_vec(), words[end..].to_vec())
+        }
+        None => (Vec::new(), Vec::new(), words.to_vec()),
+    }
+}
+
+/// Parses one BibTeX name: `First von Last`, `von Last, First` or `von Last, Jr, First`.
+pub fn parse_name(raw: &str) -> Result<Name, String> {
+    let toks = tokens(raw);
+    let parts: Vec<Vec<String>> = toks
+        .split(|t| t == ",")
+        .map(|p| p.to_vec())
+        .collect();
+    let (first_words, von_words, last_words, jr_words) = match parts.as_slice() {
+        [words] => {
+            if words.is_empty() {
+                return Err("empty name".to_string());
+            }
+            let (first, von, last) = split_von(words);
+            // without a von part the last word alone is the last name
+            match von.is_empty() {
+                true => (words[..words.len() - 1].to_vec(), von, words[words.len() - 1..].to_vec(), Vec::new()),
+                false => (first, von, last, Vec::new()),
+            }
+        }
+        [family, jr @ .., first] if jr.len() <= 1 => {
+            let (before, von, last) = split_von(family);
+            let mut last_words = before;
+            last_words.extend(last);
+            (first.clone(), von, last_words, jr.concat())
+        }
+        _ => return Err(format!("too many commas in name `{raw}`")),
+    };
+    if last_words.is_empty() {
+        return Err(format!("missing last name in `{raw}`"));
+    }
+    let first = first_words.first().map(|w| latex::decode(w)).unwrap_or_default();
+    let optional = |words: &[String]| Some(join_decoded(words)).filter(|w| !w.is_empty());
+    Ok(Name {
+        first,
+        middle: first_words.get(1..).and_then(optional),
+        particle: optional(&von_words),
+        last: join_decoded(&last_words),
+        suffix: optional(&jr_words),
+    })
+}
+
+/// Splits an `and`-separated name list; a trailing `and others` is dropped.
+pub fn parse_names(raw: &str) -> Result<Vec<Author>, String> {
+    let mut names = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let toks = tokens(raw);
+    for t in toks.iter().chain(std::iter::once(&"and".to_string())) {
+        if t.eq_ignore_ascii_case("and") {
+            if current.is_empty() {
+                continue;
+            }
+            let raw_name = current
+                .join(" ")
+                .replace(" , ", ", ");
+            current.clear();
+            if raw_name == "others" {
+                continue;
+            }
+            let name = parse_name(&raw_name)?;
+            let author = Author::builder()
+                .name(name)
+                .and_then(|b| b.build())
+                .map_err(|e| e.to_string())?;
+            names.push(author);
+        } else {
+            current.push(t.clone());
+        }
+    }
+    Ok(names)
+}
+
+fn kind_for(entry: &RawEntry) -> &'static str {
+    let is_eprint = entry
+        .get("eprinttype")
+        .or(entry.get("archiveprefix"))
+        .is_some_and(|t| t.eq_ignore_ascii_case("arxiv"));
+    match entry.entry_type.as_str() {
+        "article" if is_eprint && !entry.has("journal") && !entry.has("journaltitle") => PREPRINT,
+        "article" | "periodical" => JOURNAL_ARTICLE,
+        "inproceedings" | "conference" => CONFERENCE_PAPER,
+        "proceedings" | "mvproceedings" => PROCEEDINGS,
+        "book" | "mvbook" | "booklet" | "collection" | "mvcollection" | "manual" => BOOK,
+        "inbook" | "incollection" | "bookinbook" | "suppbook" => CHAPTER,
+        "phdthesis" | "mastersthesis" | "thesis" => THESIS,
+        "techreport" | "report" => REPORT,
+        "software" => SOFTWARE,
+        "dataset" => DATASET,
+        "online" | "electronic" | "www" => WEBPAGE,
+        _ if is_eprint => PREPRINT,
+        _ => MISC,
+    }
+}
+
+fn to_reference(entry: &RawEntry) -> Result<Reference, Failure> {
+    let fail = |message: String| Failure { line: entry.line, key: Some(entry.key.clone()), message };
+
+    let authors = match entry.get("author") {
+        Some(a) => parse_names(a).map_err(|e| fail(format!("author: {e}")))?,
+        None => Vec::new(),
+    };
+    let editors = match entry.get("editor") {
+        Some(e) => parse_names(e).map_err(|e| fail(format!("editor: {e}")))?,
+        None => Vec::new(),
+    };
+
+    let mut fields: BTreeMap<String, String> = BTreeMap::new();
+    for (name, value) in &entry.fields {
+        match name.as_str() {
+            "author" | "editor" => {}
+            _ => {
+                fields.insert(name.clone(), latex::decode(value));
+            }
+        }
+    }
+    let title = fields.remove("title").unwrap_or_default();
+    let year = match fields.remove("year") {
+        Some(y) => Some(year_from(&y).ok_or_else(|| fail(format!("invalid year `{y}`")))?),
+        None => fields.get("date").and_then(|d| year_from(d)),
+    };
+    let keywords = fields
+        .remove("keywords")
+        .map(|k| {
+            k.split([',', ';'])
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    if matches!(entry.entry_type.as_str(), "phdthesis" | "mastersthesis") && !fields.contains_key("type") {
+        fields.insert("type".to_string(), entry.entry_type.clone());
+    }
+
+    let venue = ["journaltitle", "journal", "booktitle"]
+        .iter()
+        .find_map(|f| fields.get(*f))
+        .cloned();
+    let uri = fields
+        .get("url")
+        .cloned()
+        .or_else(|| fields.get("doi").map(|d| format!("https://doi.org/{d}")));
+
+    Ok(Reference {
+        key: Some(entry.key.clone()),
+        kind: kind_for(entry).to_string(),
+        title,
+        authors,
+        editors,
+        year,
+        venue,
+        uri,
+        keywords,
+        fields,
+        extra: BTreeMap::new(),
+    })
+}
+
+/// Parses a BibTeX/BibLaTeX document. Broken entries are reported in `errors` and skipped.
+pub fn parse(input: &str) -> Parsed {
+    let mut parser = Parser::new(input);
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    while parser.seek_entry() {
+        let start = parser.line;
+        match parser.entry() {
+            Ok(Some(entry)) => entries.push(entry),
+            Ok(None) => {}
+            Err(f) => {
+                // report the entry's first line, mention where parsing actually stopped
+                let message = match f.line == start {
+                    true => f.message,
+                    false => format!("{} (at line {})", f.message, f.line),
+                };
+                errors.push(EntryError { line: start, key: f.key, message });
+                parser.recover();
+            }
+        }
+    }
+    resolve_crossrefs(&mut entries);
+
+    let mut references = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        match to_reference(entry) {
+            Ok(r) => references.push(r),
+            Err(f) => errors.push(EntryError { line: f.line, key: f.key, message: f.message }),
+        }
+    }
+    errors.sort_by_key(|e| e.line);
+    Parsed { references, errors }
+}
+
+pub fn import_str(arm: &AcademicResourceManager, input: &str) -> Result<ImportReport, SourceError> {
+    import_parsed(arm, parse(input))
+}
+
+pub fn import_file(arm: &AcademicResourceManager, path: impl AsRef<Path>) -> Result<ImportReport, SourceError> {
+    let input = std::fs::read_to_string(path)?;
+    import_str(arm, &input)
+}
+
+// ---- export ----
+
+// Works only: people, institutions and venues have no BibTeX form.
+fn entry_type(reference: &Reference) -> Option<&'static str> {
+    Some(match reference.kind.as_str() {
+        JOURNAL_ARTICLE => "article",
+        CONFERENCE_PAPER => "inproceedings",
+        PROCEEDINGS => "proceedings",
+        BOOK => "book",
+        CHAPTER if reference.fields.contains_key("booktitle") || reference.venue.is_some() => "incollection",
+        CHAPTER => "inbook",
+        THESIS if reference.field("type") == Some("mastersthesis") => "mastersthesis",
+        THESIS => "phdthesis",
+        REPORT => "techreport",
+        PREPRINT | SOFTWARE | DATASET | WEBPAGE | MISC => "misc",
+        _ => return None,
+    })
+}
+
+fn format_name(author: &Author) -> String {
+    let name = &author.name;
+    let given = name.given();
+    if given.is_empty() && name.particle.is_none() && name.suffix.is_none() {
+        // corporate or mononym: protect it from being split into first/last
+        return format!("{{{}}}", latex::encode(&name.last));
+    }
+    let family = latex::encode(&name.family());
+    match &name.suffix {
+        Some(suffix) => format!("{}, {}, {}", family, latex::encode(suffix), latex::encode(&given)),
+        None => format!("{}, {}", family, latex::encode(&given)),
+    }
+}
+
+fn format_names(people: &[Author]) -> String {
+    people.iter().map(format_name).collect::<Vec<_>>().join(" and ")
+}
+
+/// Renders one reference as a BibTeX entry under `key`.
+pub fn format_entry(reference: &Reference, key: &str) -> Option<String> {
+    let entry_type = entry_type(reference)?;
+    let mut fields: Vec<(String, String)> = Vec::new();
+    if !reference.authors.is_empty() {
+        fields.push(("author".to_string(), format_names(&reference.authors)));
+    }
+    if !reference.editors.is_empty() {
+        fields.push(("editor".to_string(), format_names(&reference.editors)));
+    }
+    if !reference.title.is_empty() {
+        fields.push(("title".to_string(), latex::encode(&reference.title)));
+    }
+    let venue_field = match entry_type {
+        "article" => Some("journal"),
+        "inproceedings" | "incollection" => Some("booktitle"),
+        _ => None,
+    };
+    let has_venue_field = ["journal", "journaltitle", "booktitle"]
+        .iter()
+        .any(|f| reference.fields.contains_key(*f));
+    if let (Some(field), Some(venue), false) = (venue_field, &reference.venue, has_venue_field) {
+        fields.push((field.to_string(), latex::encode(venue)));
+    }
+    if let Some(year) = reference.year {
+        fields.push(("year".to_string(), year.to_string()));
+    }
+    for (name, value) in &reference.fields {
+        // the entry type already says which kind of thesis it is
+        if name == "type" && matches!(value.as_str(), "phdthesis" | "mastersthesis") {
+            continue;
+        }
+        if name == "crossref" {
+            continue;
+        }
+        let value = match name.as_str() {
+            "url" | "doi" | "eprint" | "file" => value.clone(),
+            _ => latex::encode(value),
+        };
+        fields.push((name.clone(), value));
+    }
+    if !reference.keywords.is_empty() {
+        let keywords: Vec<String> = reference.keywords.iter().map(|k| latex::encode(k)).collect();
+        fields.push(("keywords".to_string(), keywords.join(", ")));
+    }
+
+    let mut out = format!("@{entry_type}{{{key},\n");
+    for (name, value) in &fields {
+        out.push_str(&format!("  {name} = {{{value}}},\n"));
+    }
+    out.push_str("}\n");
+    Some(out)
+}
+
+/// Exports the works among `ids` as a `.bib` document, keyed as described in [`keyed_references`].
+pub fn export(arm: &AcademicResourceManager, ids: &[String], format: &KeyFormat) -> Result<String, SourceError> {
+    let entries: Vec<String> = keyed_references(arm, ids, format)?
+        .iter()
+        .filter_map(|(key, reference)| format_entry(reference, key))
+        .collect();
+    Ok(entries.join("\n"))
+}
+
+pub fn export_tag(arm: &AcademicResourceManager, tag: &str, format: &KeyFormat) -> Result<String, SourceError> {
+    let ids: Vec<String> = arm.entities_with_tag(tag)?.into_iter().map(|e| e.id).collect();
+    export(arm, &ids, format)
+}
+
+/// Exports the works whose ids a Datalog query returns in its first column.
+pub fn export_query(
+    arm: &AcademicResourceManager,
+    script: &str,
+    params: Params,
+    format: &KeyFormat,
+) -> Result<String, SourceError> {
+    let ids = arm.query_ids(script, params)?;
+    export(arm, &ids, format)
+}
+
+/// Exports `id` and every work within `depth` citation hops of it.
+pub fn export_citation_neighbourhood(
+    arm: &AcademicResourceManager,
+    id: &str,
+    depth: usize,
+    format: &KeyFormat,
+) -> Result<String, SourceError> {
+    let ids = arm.neighbourhood(id, CITES, depth)?;
+    export(arm, &ids, format)
+}
+
+pub fn export_file(
+    arm: &AcademicResourceManager,
+    ids: &[String],
+    format: &KeyFormat,
+    path: impl AsRef<Path>,
+) -> Result<(), SourceError> {
+    std::fs::write(path, export(arm, ids, format)?)?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Engine;
+
+    const SAMPLE: &str = r#"
+@string{ prl = "Physical Review Letters" }
+@comment{ this {is} ignored }
+
+@article{smith2020,
+  author  = {Smith, Jane H. and Garc{\'\i}a M{\'a}rquez, Gabriel and others},
+  title   = {The {RNA} World \& Beyond},
+  journal = prl,
+  year    = 2020,
+  month   = jan,
+  doi     = {10.1000/XYZ.1},
+  keywords = {origin of life, RNA}
+}
+
+@inproceedings{doe2019,
+  author = "John von Neumann and van Beethoven, Jr., Ludwig and {World Health Organization}",
+  title = "Games" # " and Music",
+  crossref = {proc2019},
+  pages = {1--10}
+}
+
+@proceedings{proc2019,
+  title = {Proceedings of the Symposium},
+  year = {2019},
+  publisher = {ACM}
+}
+"#;
+
+    #[test]
+    fn test_parse_sample() {
+        let parsed = parse(SAMPLE);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        assert_eq!(parsed.references.len(), 3);
+
+        let smith = &parsed.references[0];
+        assert_eq!(smith.kind, JOURNAL_ARTICLE);
+        assert_eq!(smith.title, "The RNA World & Beyond");
+        assert_eq!(smith.venue.as_deref(), Some("Physical Review Letters"));
+        assert_eq!(smith.year, Some(2020));
+        assert_eq!(smith.field("month"), Some("January"));
+        assert_eq!(smith.keywords, vec!["origin of life", "RNA"]);
+        assert_eq!(smith.authors.len(), 2);
+        assert_eq!(smith.authors[0].name.last, "Smith");
+        assert_eq!(smith.authors[0].name.middle.as_deref(), Some("H."));
+        assert_eq!(smith.authors[1].name.last, "García Márquez");
+
+        let doe = &parsed.references[1];
+        assert_eq!(doe.kind, CONFERENCE_PAPER);
+        assert_eq!(doe.title, "Games and Music");
+        assert_eq!(doe.year, Some(2019));
+        assert_eq!(doe.venue.as_deref(), Some("Proceedings of the Symposium"));
+        assert_eq!(doe.field("publisher"), Some("ACM"));
+        assert_eq!(doe.field("pages"), Some("1–10"));
+        assert_eq!(doe.authors[0].name.last, "Neumann");
+        assert_eq!(doe.authors[0].name.particle.as_deref(), Some("von"));
+        assert_eq!(doe.authors[1].name.first, "Ludwig");
+        assert_eq!(doe.authors[1].name.last, "Beethoven");
+        assert_eq!(doe.authors[1].name.suffix.as_deref(), Some("Jr."));
+        assert_eq!(doe.authors[2].name.last, "World Health Organization");
+    }
+
+    #[test]
+    fn test_parse_name_forms() {
+        let n = parse_name("Ludwig van Beethoven").unwrap();
+        assert_eq!((n.first.as_str(), n.family().as_str()), ("Ludwig", "van Beethoven"));
+        let n = parse_name("van Beethoven, Ludwig").unwrap();
+        assert_eq!((n.first.as_str(), n.particle.as_deref(), n.last.as_str()), ("Ludwig", Some("van"), "Beethoven"));
+        let n = parse_name("Charles Louis de la Vall{\\'e}e Poussin").unwrap();
+        assert_eq!((n.middle.as_deref(), n.particle.as_deref()), (Some("Louis"), Some("de la")));
+        assert_eq!(n.last, "Vallée Poussin");
+        let n = parse_name("King, Jr, Martin Luther").unwrap();
+        assert_eq!((n.last.as_str(), n.suffix.as_deref()), ("King", Some("Jr")));
+        assert_eq!(n.middle.as_deref(), Some("Luther"));
+        let n = parse_name("Aristotle").unwrap();
+        assert_eq!((n.first.as_str(), n.last.as_str()), ("", "Aristotle"));
+    }
+
+    #[test]
+    fn test_errors_report_line_and_continue() {
+        let input = "@article{ok1, title={Fine}, year={2001}}\n\
+                     @article{bad, title={Unclosed, year={2002}}\n\
+                     @article{bad2, title = undefinedmacro}\n\
+                     @article{ok2, title={Also fine}, year={2003}}\n";
+        let parsed = parse(input);
+        assert_eq!(parsed.references.len(), 2);
+        assert_eq!(parsed.references[1].key.as_deref(), Some("ok2"));
+        assert_eq!(parsed.errors.len(), 2);
+        assert_eq!(parsed.errors[0].line, 2);
+        assert_eq!(parsed.errors[1].line, 3);
+        assert_eq!(parsed.errors[1].key.as_deref(), Some("bad2"));
+    }
+
+    #[test]
+    fn test_import_into_graph() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let report = import_str(&arm, SAMPLE).unwrap();
+        assert_eq!(report.imported.len(), 3);
+        assert!(report.errors.is_empty());
+
+        let smith_id = &report.imported[0];
+        assert_eq!(smith_id, "doi:10.1000/xyz.1");
+        let loaded = arm.load_reference(smith_id).unwrap().unwrap();
+        assert_eq!(loaded.title, "The RNA World & Beyond");
+        assert_eq!(loaded.authors.len(), 2);
+        assert_eq!(loaded.venue.as_deref(), Some("Physical Review Letters"));
+        assert_eq!(arm.entities_with_tag("RNA").unwrap().len(), 1);
+    }
+
+    fn work(title: &str, year: i64, author: &str) -> Reference {
+        Reference {
+            kind: JOURNAL_ARTICLE.to_string(),
+            title: title.to_string(),
+            authors: vec![Author::builder().name_from_str(author).unwrap().build().unwrap()],
+            year: Some(year),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_export_keys_are_unique_and_stable() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let a = arm.save_reference(&work("Quantum Things", 2020, "Jane Smith")).unwrap();
+        let b = arm.save_reference(&work("Quantum Stuff", 2020, "Bob Smith")).unwrap();
+        let c = arm.save_reference(&work("Quantum Matters", 2020, "Ann Smith")).unwrap();
+        let ids = vec![a.clone(), b.clone(), c.clone()];
+
+        let first = export(&arm, &ids, &KeyFormat::default()).unwrap();
+        let parsed = parse(&first);
+        let keys: Vec<_> = parsed.references.iter().map(|r| r.key.clone().unwrap()).collect();
+        assert_eq!(keys, vec!["smith2020quantum", "smith2020quantuma", "smith2020quantumb"]);
+
+        // reversed order and a new colliding work do not change keys already handed out
+        let d = arm.save_reference(&work("Quantum Again", 2020, "Zoe Smith")).unwrap();
+        let again = export(&arm, &[d, c, b, a], &KeyFormat::default()).unwrap();
+        let keys: Vec<_> = parse(&again).references.iter().map(|r| r.key.clone().unwrap()).collect();
+        assert_eq!(keys, vec!["smith2020quantumc", "smith2020quantumb", "smith2020quantuma", "smith2020quantum"]);
+    }
+
+    #[test]
+    fn test_export_escapes_and_roundtrips() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let report = import_str(&arm, SAMPLE).unwrap();
+        let out = export(&arm, &report.imported, &KeyFormat::default()).unwrap();
+        assert!(out.contains("@article{smith2020,"), "{out}");
+        assert!(out.contains("title = {The RNA World \\& Beyond}"), "{out}");
+        assert!(out.contains("{World Health Organization}"), "{out}");
+
+        let parsed = parse(&out);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let original = parse(SAMPLE);
+        for (back, before) in parsed.references.iter().zip(&original.references) {
+            assert_eq!(back.title, before.title);
+            assert_eq!(back.year, before.year);
+            assert_eq!(back.venue, before.venue);
+            assert_eq!(back.authors, before.authors);
+            assert_eq!(back.doi(), before.doi());
+        }
+    }
+
+    #[test]
+    fn test_export_citation_neighbourhood() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let a = arm.save_reference(&work("Root", 2020, "Jane Smith")).unwrap();
+        let b = arm.save_reference(&work("Cited", 2010, "Bob Jones")).unwrap();
+        let c = arm.save_reference(&work("Far", 2000, "Ann Lee")).unwrap();
+        arm.upsert_edge(&crate::database::Edge::new(&a, &b, CITES)).unwrap();
+        arm.upsert_edge(&crate::database::Edge::new(&b, &c, CITES)).unwrap();
+        let out = export_citation_neighbourhood(&arm, &a, 1, &KeyFormat::default()).unwrap();
+        assert_eq!(parse(&out).references.len(), 2);
+        assert!(!out.contains("Far"));
+
+        let script = "?[id] := *entity{id, kind, year}, kind == 'journal_article', coalesce(year, 9999) < 2015";
+        let out = export_query(&arm, script, Params::new(), &KeyFormat::default()).unwrap();
+        assert_eq!(parse(&out).references.len(), 2);
+        assert!(!out.contains("Root"));
+    }
+}
@@ -0,0 +1,477 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use serde_json::value::RawValue;
+use serde_json::{json, Map, Value};
+use crate::database::AcademicResourceManager;
+use crate::domain::author::{Author, Name};
+use crate::domain::sources::keys::{keyed_references, KeyFormat};
+use crate::domain::sources::{
+    import_parsed, month_number, year_from, EntryError, ImportReport, Parsed, Reference, SourceError, BOOK,
+    CHAPTER, CONFERENCE_PAPER, DATASET, JOURNAL_ARTICLE, MISC, MONTHS, PREPRINT, PROCEEDINGS, REPORT, SOFTWARE,
+    THESIS, WEBPAGE,
+};
+
+// CSL variables stored under another name in `Reference::fields` (BibTeX names, so that
+// records imported from either format look the same in the graph).
+const FIELD_NAMES: &[(&str, &str)] = &[
+    ("DOI", "doi"),
+    ("URL", "url"),
+    ("ISBN", "isbn"),
+    ("ISSN", "issn"),
+    ("PMID", "pmid"),
+    ("PMCID", "pmcid"),
+    ("page", "pages"),
+    ("publisher-place", "address"),
+    ("collection-title", "series"),
+    ("title-short", "shorttitle"),
+    ("event-title", "eventtitle"),
+    ("genre", "type"),
+];
+
+// CSL variables kept under their own name; only these (and `FIELD_NAMES`) are exported.
+const PASSTHROUGH: &[&str] = &[
+    "abstract", "annote", "archive", "archive_location", "archive-place", "authority", "call-number",
+    "chapter-number", "collection-number", "dimensions", "division", "edition", "event-place",
+    "jurisdiction", "language", "medium", "note", "number-of-pages", "number-of-volumes",
+    "original-publisher", "original-publisher-place", "original-title", "part", "publisher",
+    "references", "reviewed-title", "scale", "section", "source", "status", "version", "volume",
+];
+
+fn kind_for(csl_type: &str) -> &'static str {
+    match csl_type {
+        "article-journal" | "article-magazine" | "article-newspaper" | "periodical" | "review"
+        | "review-book" => JOURNAL_ARTICLE,
+        "paper-conference" => CONFERENCE_PAPER,
+        "book" | "classic" => BOOK,
+        "chapter" | "entry" | "entry-dictionary" | "entry-encyclopedia" => CHAPTER,
+        "thesis" => THESIS,
+        "report" => REPORT,
+        "article" => PREPRINT,
+        "software" => SOFTWARE,
+        "dataset" => DATASET,
+        "webpage" | "post" | "post-weblog" => WEBPAGE,
+        _ => MISC,
+    }
+}
+
+fn csl_type(kind: &str) -> &'static str {
+    match kind {
+        JOURNAL_ARTICLE => "article-journal",
+        CONFERENCE_PAPER => "paper-conference",
+        PROCEEDINGS | BOOK => "book",
+        CHAPTER => "chapter",
+        THESIS => "thesis",
+        REPORT => "report",
+        PREPRINT => "article",
+        SOFTWARE => "software",
+        DATASET => "dataset",
+        WEBPAGE => "webpage",
+        _ => "document",
+    }
+}
+
+// Strings and numbers are both common for the same variable (`"volume": 3` vs `"3"`).
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn parse_name(value: &Value) -> Result<Author, String> {
+    let part = |key: &str| value.get(key).and_then(scalar);
+    let name = match (part("literal"), part("family"), part("given")) {
+        (Some(literal), _, _) => Name { first: String::new(), middle: None, particle: None, last: literal, suffix: None },
+        (None, Some(family), given) => {
+            let given = given.unwrap_or_default();
+            let mut words = given.split_whitespace();
+            let first = words.next().unwrap_or_default().to_string();
+            let middle = words.collect::<Vec<_>>().join(" ");
+            let particle = [part("dropping-particle"), part("non-dropping-particle")]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ");
+            Name {
+                first,
+                middle: Some(middle).filter(|m| !m.is_empty()),
+                particle: Some(particle).filter(|p| !p.is_empty()),
+                last: family,
+                suffix: part("suffix"),
+            }
+        }
+        (None, None, Some(given)) => Name { first: String::new(), middle: None, particle: None, last: given, suffix: None },
+        (None, None, None) => return Err(format!("name without `family` or `literal`: {value}")),
+    };
+    Author::builder().name(name).and_then(|b| b.build()).map_err(|e| e.to_string())
+}
+
+fn parse_names(item: &Value, role: &str) -> Result<Vec<Author>, String> {
+    match item.get(role) {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::Array(names)) => names
+            .iter()
+            .map(parse_name)
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("{role}: {e}")),
+        Some(other) => Err(format!("{role}: expected an array, got {other}")),
+    }
+}
+
+// `date-parts` first, then the `raw`/`literal` fallbacks some producers emit.
+fn parse_date(value: &Value) -> Option<(i64, Option<u32>, Option<u32>)> {
+    if let Some(parts) = value.pointer("/date-parts/0").and_then(Value::as_array) {
+        let mut numbers = parts.iter().map(|p| scalar(p).and_then(|s| s.parse::<i64>().ok()));
+        let year = numbers.next().flatten()?;
+        let month = numbers.next().flatten().map(|m| m as u32).filter(|m| (1..=12).contains(m));
+        let day = numbers.next().flatten().map(|d| d as u32).filter(|d| (1..=31).contains(d));
+        return Some((year, month, day.filter(|_| month.is_some())));
+    }
+    let text = value.get("raw").or(value.get("literal")).and_then(scalar)?;
+    Some((year_from(&text)?, None, None))
+}
+
+pub(crate) fn to_reference(item: &Value) -> Result<Reference, String> {
+    let Value::Object(map) = item else {
+        return Err(format!("expected an object, got {item}"));
+    };
+    let mut fields = BTreeMap::new();
+    for (name, value) in map {
+        let Some(value) = scalar(value) else {
+            continue;
+        };
+        match name.as_str() {
+            "id" | "type" | "title" | "container-title" | "keyword" | "issue" | "number" => {}
+            _ => {
+                let field = FIELD_NAMES
+                    .iter()
+                    .find(|(csl, _)| csl == name)
+                    .map_or(name.as_str(), |(_, f)| *f);
+                fields.insert(field.to_string(), value);
+            }
+        }
+    }
+    // journals number their issues, reports and patents their documents; both end up in `number`
+    if let Some(number) = map.get("issue").or(map.get("number")).and_then(scalar) {
+        fields.insert("number".to_string(), number);
+    }
+
+    let year = match map.get("issued") {
+        Some(issued) => {
+            let (year, month, day) = parse_date(issued).ok_or_else(|| format!("invalid `issued` date: {issued}"))?;
+            if let Some(m) = month {
+                fields.insert("month".to_string(), MONTHS[m as usize - 1].to_string());
+            }
+            if let Some(d) = day {
+                fields.insert("day".to_string(), d.to_string());
+            }
+            Some(year)
+        }
+        None => None,
+    };
+    if let Some((y, m, d)) = map.get("accessed").and_then(parse_date) {
+        let date = match (m, d) {
+            (Some(m), Some(d)) => format!("{y}-{m:02}-{d:02}"),
+            (Some(m), None) => format!("{y}-{m:02}"),
+            _ => y.to_string(),
+        };
+        fields.insert("urldate".to_string(), date);
+    }
+
+    let keywords = map
+        .get("keyword")
+        .and_then(scalar)
+        .map(|k| {
+            k.split([',', ';'])
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    let uri = fields
+        .get("url")
+        .cloned()
+        .or_else(|| fields.get("doi").map(|d| format!("https://doi.org/{d}")));
+
+    Ok(Reference {
+        key: map.get("id").and_then(scalar),
+        kind: kind_for(map.get("type").and_then(Value::as_str).unwrap_or_default()).to_string(),
+        title: map.get("title").and_then(scalar).unwrap_or_default(),
+        authors: parse_names(item, "author")?,
+        editors: parse_names(item, "editor")?,
+        year,
+        venue: map.get("container-title").and_then(scalar),
+        uri,
+        keywords,
+        fields,
+        extra: BTreeMap::new(),
+    })
+}
+
+fn line_of(input: &str, raw: &RawValue) -> usize {
+    let offset = raw.get().as_ptr() as usize - input.as_ptr() as usize;
+    input[..offset].matches('\n').count() + 1
+}
+
+/// Parses a CSL-JSON array (or a single item). Malformed JSON fails as a whole; items that are
+/// valid JSON but not valid CSL are reported in `errors` with the line they start on.
+pub fn parse(input: &str) -> Result<Parsed, SourceError> {
+    let items: Vec<&RawValue> = match input.trim_start().starts_with('{') {
+        true => vec![serde_json::from_str(input).map_err(|e| SourceError::Format(e.to_string()))?],
+        false => serde_json::from_str(input).map_err(|e| SourceError::Format(e.to_string()))?,
+    };
+    let mut parsed = Parsed::default();
+    for raw in items {
+        let item: Value = serde_json::from_str(raw.get()).map_err(|e| SourceError::Format(e.to_string()))?;
+        match to_reference(&item) {
+            Ok(r) => parsed.references.push(r),
+            Err(message) => parsed.errors.push(EntryError {
+                line: line_of(input, raw),
+                key: item.get("id").and_then(scalar),
+                message,
+            }),
+        }
+    }
+    Ok(parsed)
+}
+
+pub fn import_str(arm: &AcademicResourceManager, input: &str) -> Result<ImportReport, SourceError> {
+    import_parsed(arm, parse(input)?)
+}
+
+pub fn import_file(arm: &AcademicResourceManager, path: impl AsRef<Path>) -> Result<ImportReport, SourceError> {
+    let input = std::fs::read_to_string(path)?;
+    import_str(arm, &input)
+}
+
+// ---- export ----
+
+fn is_particle(word: &str) -> bool {
+    word.chars().next().is_some_and(char::is_lowercase)
+}
+
+fn format_name(author: &Author) -> Value {
+    let name = &author.name;
+    let given = name.given();
+    if given.is_empty() && name.particle.is_none() && name.suffix.is_none() {
+        return json!({ "literal": name.last });
+    }
+    // names stored before particles had their own field: "van Beethoven" -> "van", "Beethoven"
+    let words: Vec<&str> = name.last.split_whitespace().collect();
+    let split = match name.particle {
+        Some(_) => 0,
+        None => words[..words.len().saturating_sub(1)].iter().take_while(|w| is_particle(w)).count(),
+    };
+    let particle = name.particle.clone().or_else(|| Some(words[..split].join(" ")).filter(|p| !p.is_empty()));
+    let mut out = Map::new();
+    out.insert("family".to_string(), json!(words[split..].join(" ")));
+    out.insert("given".to_string(), json!(given));
+    if let Some(particle) = particle {
+        out.insert("non-dropping-particle".to_string(), json!(particle));
+    }
+    if let Some(suffix) = &name.suffix {
+        out.insert("suffix".to_string(), json!(suffix));
+    }
+    Value::Object(out)
+}
+
+fn date_parts(reference: &Reference) -> Option<Value> {
+    let year = reference.year?;
+    let mut parts = vec![json!(year)];
+    if let Some(month) = reference.field("month").and_then(month_number) {
+        parts.push(json!(month));
+        if let Some(day) = reference.field("day").and_then(|d| d.parse::<u32>().ok()) {
+            parts.push(json!(day));
+        }
+    }
+    Some(json!({ "date-parts": [parts] }))
+}
+
+/// Renders one reference as a CSL-JSON item with `key` as its id.
+pub fn format_item(reference: &Reference, key: &str) -> Value {
+    let mut item = Map::new();
+    item.insert("id".to_string(), json!(key));
+    item.insert("type".to_string(), json!(csl_type(&reference.kind)));
+    if !reference.title.is_empty() {
+        item.insert("title".to_string(), json!(reference.title));
+    }
+    if !reference.authors.is_empty() {
+        item.insert("author".to_string(), reference.authors.iter().map(format_name).collect());
+    }
+    if !reference.editors.is_empty() {
+        item.insert("editor".to_string(), reference.editors.iter().map(format_name).collect());
+    }
+    if let Some(venue) = &reference.venue {
+        item.insert("container-title".to_string(), json!(venue));
+    }
+    if let Some(issued) = date_parts(reference) {
+        item.insert("issued".to_string(), issued);
+    }
+    for (name, value) in &reference.fields {
+        let csl = match FIELD_NAMES.iter().find(|(_, f)| f == name) {
+            Some((csl, _)) => *csl,
+            None if name == "number" && reference.kind == JOURNAL_ARTICLE => "issue",
+            None if name == "number" => "number",
+            None if PASSTHROUGH.contains(&name.as_str()) => name.as_str(),
+            None => continue,
+        };
+        // a BibTeX thesis type is not a CSL genre
+        if csl == "genre" && matches!(value.as_str(), "phdthesis" | "mastersthesis") {
+            continue;
+        }
+        item.insert(csl.to_string(), json!(value));
+    }
+    // BibTeX keeps the degree-granting or issuing body apart from the publisher
+    if !item.contains_key("publisher")
+        && let Some(body) = reference.field("school").or(reference.field("institution"))
+    {
+        item.insert("publisher".to_string(), json!(body));
+    }
+    if !item.contains_key("URL")
+        && let Some(uri) = reference.uri.as_ref().filter(|u| !u.starts_with("https://doi.org/"))
+    {
+        item.insert("URL".to_string(), json!(uri));
+    }
+    if !reference.keywords.is_empty() {
+        item.insert("keyword".to_string(), json!(reference.keywords.join(", ")));
+    }
+    Value::Object(item)
+}
+
+/// Exports the works among `ids` as a CSL-JSON array, keyed like the BibTeX export so
+/// Pandoc citations resolve against either file.
+pub fn export(arm: &AcademicResourceManager, ids: &[String], format: &KeyFormat) -> Result<String, SourceError> {
+    let items: Vec<Value> = keyed_references(arm, ids, format)?
+        .iter()
+        .map(|(key, reference)| format_item(reference, key))
+        .collect();
+    serde_json::to_string_pretty(&items).map_err(|e| SourceError::Format(e.to_string()))
+}
+
+pub fn export_file(
+    arm: &AcademicResourceManager,
+    ids: &[String],
+    format: &KeyFormat,
+    path: impl AsRef<Path>,
+) -> Result<(), SourceError> {
+    std::fs::write(path, export(arm, ids, format)?)?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Engine;
+
+    const SAMPLE: &str = r#"[
+  {
+    "id": "vanbeethoven2020",
+    "type": "article-journal",
+    "title": "Symphonies as Data",
+    "author": [
+      { "family": "Beethoven", "given": "Ludwig", "non-dropping-particle": "van" },
+      { "family": "Smith", "given": "Jane H.", "suffix": "Jr." },
+      { "literal": "World Health Organization" }
+    ],
+    "container-title": "Journal of Music",
+    "issued": { "date-parts": [[2020, 3, 15]] },
+    "volume": 12,
+    "issue": "4",
+    "page": "101-120",
+    "DOI": "10.1000/Music.1",
+    "keyword": "music, data"
+  },
+  {
+    "id": "broken",
+    "type": "book",
+    "author": "Not An Array"
+  },
+  {
+    "id": "report1",
+    "type": "report",
+    "title": "Annual Report",
+    "publisher": "ACME",
+    "number": "TR-7",
+    "issued": { "raw": "2018" }
+  }
+]"#;
+
+    #[test]
+    fn test_parse_sample() {
+        let parsed = parse(SAMPLE).unwrap();
+        assert_eq!(parsed.references.len(), 2);
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].line, 19);
+        assert_eq!(parsed.errors[0].key.as_deref(), Some("broken"));
+
+        let article = &parsed.references[0];
+        assert_eq!(article.kind, JOURNAL_ARTICLE);
+        assert_eq!(article.key.as_deref(), Some("vanbeethoven2020"));
+        assert_eq!(article.year, Some(2020));
+        assert_eq!(article.field("month"), Some("March"));
+        assert_eq!(article.field("day"), Some("15"));
+        assert_eq!(article.field("volume"), Some("12"));
+        assert_eq!(article.field("number"), Some("4"));
+        assert_eq!(article.field("pages"), Some("101-120"));
+        assert_eq!(article.doi(), Some("10.1000/Music.1"));
+        assert_eq!(article.venue.as_deref(), Some("Journal of Music"));
+        assert_eq!(article.keywords, vec!["music", "data"]);
+        assert_eq!(article.authors[0].name.last, "Beethoven");
+        assert_eq!(article.authors[0].name.particle.as_deref(), Some("van"));
+        assert_eq!(article.authors[1].name.middle.as_deref(), Some("H."));
+        assert_eq!(article.authors[1].name.suffix.as_deref(), Some("Jr."));
+        assert_eq!(article.authors[2].name.last, "World Health Organization");
+
+        let report = &parsed.references[1];
+        assert_eq!(report.kind, REPORT);
+        assert_eq!(report.year, Some(2018));
+        assert_eq!(report.field("number"), Some("TR-7"));
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_json() {
+        assert!(matches!(parse("[{\"id\": 1,]"), Err(SourceError::Format(_))));
+        assert_eq!(parse(r#"{"id": "one", "type": "book", "title": "Single"}"#).unwrap().references.len(), 1);
+    }
+
+    #[test]
+    fn test_export_roundtrip() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let report = import_str(&arm, SAMPLE).unwrap();
+        let out = export(&arm, &report.imported, &KeyFormat::default()).unwrap();
+
+        let items: Value = serde_json::from_str(&out).unwrap();
+        let article = &items[0];
+        assert_eq!(article["id"], "vanbeethoven2020");
+        assert_eq!(article["type"], "article-journal");
+        assert_eq!(article["issue"], "4");
+        assert_eq!(article["issued"], json!({"date-parts": [[2020, 3, 15]]}));
+        assert_eq!(article["author"][0], json!({"family": "Beethoven", "given": "Ludwig", "non-dropping-particle": "van"}));
+        assert_eq!(article["author"][2], json!({"literal": "World Health Organization"}));
+        assert_eq!(items[1]["number"], "TR-7");
+
+        // keywords come back from tags, in tag order
+        let mut original = parse(SAMPLE).unwrap();
+        original.references.iter_mut().for_each(|r| r.keywords.sort());
+        let back = parse(&out).unwrap();
+        assert!(back.errors.is_empty());
+        assert_eq!(back.references, original.references);
+    }
+
+    #[test]
+    fn test_export_from_bibtex_import() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let bib = "@phdthesis{doe2001, author = {Doe, John}, title = {On {T}hings}, school = {MIT}, year = 2001, month = feb}";
+        let report = crate::domain::sources::bibtex::import_str(&arm, bib).unwrap();
+        let out = export(&arm, &report.imported, &KeyFormat::default()).unwrap();
+        let items: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(items[0]["id"], "doe2001");
+        assert_eq!(items[0]["type"], "thesis");
+        assert_eq!(items[0]["issued"], json!({"date-parts": [[2001, 2]]}));
+        assert!(items[0].get("genre").is_none());
+        assert_eq!(items[0]["publisher"], "MIT");
+    }
+}
@@ -0,0 +1,681 @@
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+use std::path::Path;
+use roxmltree::{Document, Node};
+use serde_json::{Map, Value};
+use zip::result::ZipError;
+use zip::ZipArchive;
+use crate::database::references::{work_id, CITES};
+use crate::database::{AcademicResourceManager, Edge, Entity};
+use crate::domain::author::{Author, Name};
+use crate::domain::sources::zotero::ZOTERO_KEY;
+use crate::domain::sources::{
+    csl, import_parsed, month_number, year_from, EntryError, ImportReport, Parsed, Reference, SourceError, BOOK,
+    CHAPTER, CONFERENCE_PAPER, JOURNAL_ARTICLE, MISC, MONTHS, REPORT, WEBPAGE,
+};
+use crate::utils::text::slugify;
+
+pub const DOCUMENT_KIND: &str = "document";
+
+// Word source elements without a `Reference` counterpart, stored as entity props under this key.
+pub const WORD_PROPS: &str = "word";
+
+// Parts of the package that can hold citation fields.
+const PARTS: [&str; 3] = ["word/document.xml", "word/footnotes.xml", "word/endnotes.xml"];
+
+// Word source elements stored under another name in `Reference::fields`.
+const FIELD_NAMES: &[(&str, &str)] = &[
+    ("Publisher", "publisher"),
+    ("City", "address"),
+    ("Volume", "volume"),
+    ("Issue", "number"),
+    ("Pages", "pages"),
+    ("Edition", "edition"),
+    ("Day", "day"),
+    ("DOI", "doi"),
+    ("URL", "url"),
+    ("ShortTitle", "shorttitle"),
+    ("Comments", "note"),
+    ("Institution", "institution"),
+    ("ThesisType", "type"),
+];
+
+// Elements naming the container a source appeared in.
+const VENUE_ELEMENTS: &[&str] =
+    &["JournalName", "PeriodicalTitle", "ConferenceName", "BookTitle", "InternetSiteTitle"];
+
+/// Citations found in a `.docx`, before they touch the graph.
+#[derive(Debug, Default)]
+pub struct DocxCitations {
+    /// `dc:title` from the document properties
+    pub title: Option<String>,
+    /// cited works, in order of first citation
+    pub references: Vec<Reference>,
+    /// tags of Word `CITATION` fields with no matching source in the document
+    pub missing: Vec<String>,
+    pub errors: Vec<EntryError>,
+}
+
+impl DocxCitations {
+    fn cite(&mut self, reference: Reference) {
+        let id = work_id(&reference);
+        if !self.references.iter().any(|r| work_id(r) == id) {
+            self.references.push(reference);
+        }
+    }
+}
+
+fn elements<'a, 'i>(node: Node<'a, 'i>) -> impl Iterator<Item = Node<'a, 'i>> {
+    node.children().filter(Node::is_element)
+}
+
+fn child<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    elements(node).find(|n| n.tag_name().name() == name)
+}
+
+fn text(node: Node) -> Option<String> {
+    let t = node.text()?.trim();
+    (!t.is_empty()).then(|| t.to_string())
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name).and_then(text)
+}
+
+// `w:instr`, `w:fldCharType`, ... regardless of the prefix.
+fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attributes().find(|a| a.name() == name).map(|a| a.value())
+}
+
+fn line_of(doc: &Document, node: Node) -> usize {
+    doc.text_pos_at(node.range().start).row as usize
+}
+
+// ---- Word sources (`b:Sources`) ----
+
+fn kind_for(source_type: &str) -> &'static str {
+    match source_type {
+        "JournalArticle" | "ArticleInAPeriodical" => JOURNAL_ARTICLE,
+        "ConferenceProceedings" => CONFERENCE_PAPER,
+        "Book" => BOOK,
+        "BookSection" => CHAPTER,
+        "Report" => REPORT,
+        "InternetSite" | "DocumentFromInternetSite" => WEBPAGE,
+        _ => MISC,
+    }
+}
+
+fn person(first: Option<String>, middle: Option<String>, last: Option<String>) -> Result<Author, String> {
+    let last = last.ok_or_else(|| format!("person `{}` without a last name", first.clone().unwrap_or_default()))?;
+    let name = Name { first: first.unwrap_or_default(), middle, particle: None, last, suffix: None };
+    Author::builder().name(name).and_then(|b| b.build()).map_err(|e| e.to_string())
+}
+
+// `b:Author/b:{role}` holds either a `b:NameList` of people or a single `b:Corporate` name.
+fn people(source: Node, role: &str) -> Result<Vec<Author>, String> {
+    let Some(node) = child(source, "Author").and_then(|a| child(a, role)) else {
+        return Ok(Vec::new());
+    };
+    if let Some(corporate) = child_text(node, "Corporate") {
+        return person(None, None, Some(corporate)).map(|a| vec![a]);
+    }
+    node.descendants()
+        .filter(|n| n.tag_name().name() == "Person")
+        .map(|p| person(child_text(p, "First"), child_text(p, "Middle"), child_text(p, "Last")))
+        .collect()
+}
+
+fn parse_source(source: Node) -> Result<Reference, String> {
+    let source_type = child_text(source, "SourceType").unwrap_or_default();
+    let kind = kind_for(&source_type);
+    let mut reference = Reference {
+        key: child_text(source, "Tag"),
+        kind: kind.to_string(),
+        authors: people(source, "Author")?,
+        editors: people(source, "Editor")?,
+        ..Default::default()
+    };
+    let mut other = Map::new();
+    if kind == MISC && !source_type.is_empty() && source_type != "Misc" {
+        other.insert("SourceType".to_string(), Value::String(source_type));
+    }
+    for node in elements(source) {
+        let name = node.tag_name().name();
+        let Some(value) = text(node) else {
+            continue;
+        };
+        match name {
+            "Tag" | "SourceType" | "Guid" | "LCID" | "RefOrder" => {}
+            "Title" => reference.title = value,
+            "Year" if year_from(&value).is_some() => reference.year = year_from(&value),
+            "Month" if month_number(&value).is_some() => {
+                let month = month_number(&value).map_or("", |m| MONTHS[m as usize - 1]);
+                reference.fields.insert("month".to_string(), month.to_string());
+            }
+            _ if VENUE_ELEMENTS.contains(&name) => {
+                reference.venue.get_or_insert(value);
+            }
+            _ => match FIELD_NAMES.iter().find(|(word, _)| *word == name) {
+                Some((_, field)) => {
+                    reference.fields.insert(field.to_string(), value);
+                }
+                None => {
+                    other.insert(name.to_string(), Value::String(value));
+                }
+            },
+        }
+    }
+    if !other.is_empty() {
+        reference.extra.insert(WORD_PROPS.to_string(), Value::Object(other));
+    }
+    reference.uri = reference
+        .field("url")
+        .map(str::to_string)
+        .or_else(|| reference.doi().map(|d| format!("https://doi.org/{d}")));
+    if reference.key.is_none() {
+        return Err("source without a `Tag`".to_string());
+    }
+    Ok(reference)
+}
+
+// Every `b:Source` of a `b:Sources` document; an empty list for any other XML.
+fn sources_of(doc: &Document, errors: &mut Vec<EntryError>) -> Vec<Reference> {
+    let root = doc.root_element();
+    if root.tag_name().name() != "Sources" {
+        return Vec::new();
+    }
+    let mut references = Vec::new();
+    for source in elements(root).filter(|n| n.tag_name().name() == "Source") {
+        match parse_source(source) {
+            Ok(r) => references.push(r),
+            Err(message) => errors.push(EntryError {
+                line: line_of(doc, source),
+                key: child_text(source, "Tag"),
+                message,
+            }),
+        }
+    }
+    references
+}
+
+/// Parses Word's source manager list (`Sources.xml`), the format documents embed as well.
+pub fn parse_sources(input: &str) -> Result<Parsed, SourceError> {
+    let doc = Document::parse(input).map_err(|e| SourceError::Format(e.to_string()))?;
+    if doc.root_element().tag_name().name() != "Sources" {
+        return Err(SourceError::Format("expected a `b:Sources` document".to_string()));
+    }
+    let mut parsed = Parsed::default();
+    parsed.references = sources_of(&doc, &mut parsed.errors);
+    Ok(parsed)
+}
+
+pub fn import_sources_str(arm: &AcademicResourceManager, input: &str) -> Result<ImportReport, SourceError> {
+    import_parsed(arm, parse_sources(input)?)
+}
+
+pub fn import_sources_file(arm: &AcademicResourceManager, path: impl AsRef<Path>) -> Result<ImportReport, SourceError> {
+    let input = std::fs::read_to_string(path)?;
+    import_sources_str(arm, &input)
+}
+
+// ---- citation fields ----
+
+enum Field {
+    // Zotero and Mendeley: `ADDIN ZOTERO_ITEM CSL_CITATION {json}`, `ADDIN CSL_CITATION {json}`
+    Csl(Value),
+    // Word: `CITATION Tag \l 1033 \m Other`
+    Word(Vec<String>),
+}
+
+// Instruction text of every field in a part with the line it starts on. Complex fields spread
+// the instruction over `w:instrText` runs between the `begin` and `separate` field characters.
+fn field_codes(doc: &Document) -> Vec<(usize, String)> {
+    let mut codes = Vec::new();
+    // fields still open, `None` once their instruction is complete
+    let mut open: Vec<Option<(usize, String)>> = Vec::new();
+    for node in doc.descendants().filter(Node::is_element) {
+        match node.tag_name().name() {
+            "fldSimple" => codes.extend(attribute(node, "instr").map(|i| (line_of(doc, node), i.to_string()))),
+            "fldChar" => match attribute(node, "fldCharType") {
+                Some("begin") => open.push(Some((line_of(doc, node), String::new()))),
+                Some("separate") => codes.extend(open.last_mut().and_then(Option::take)),
+                Some("end") => codes.extend(open.pop().flatten()),
+                _ => {}
+            },
+            "instrText" => {
+                if let Some(Some((_, code))) = open.last_mut() {
+                    code.push_str(node.text().unwrap_or_default());
+                }
+            }
+            _ => {}
+        }
+    }
+    codes
+}
+
+fn parse_field(code: &str) -> Result<Option<Field>, String> {
+    let code = code.trim();
+    let mut words = code.split_whitespace();
+    match words.next() {
+        Some("ADDIN") => {
+            let Some(start) = code.find("CSL_CITATION") else {
+                return Ok(None);
+            };
+            let rest = &code[start + "CSL_CITATION".len()..];
+            let json = rest.find('{').map(|i| &rest[i..]).ok_or("CSL_CITATION without data")?;
+            // the JSON may be followed by more field text
+            let value = serde_json::Deserializer::from_str(json)
+                .into_iter::<Value>()
+                .next()
+                .ok_or("CSL_CITATION without data")?
+                .map_err(|e| format!("invalid CSL_CITATION data: {e}"))?;
+            Ok(Some(Field::Csl(value)))
+        }
+        Some("CITATION") => {
+            let unquote = |w: &str| w.trim_matches('"').to_string();
+            let mut tags: Vec<String> = words.next().map(unquote).into_iter().collect();
+            while let Some(word) = words.next() {
+                if word.eq_ignore_ascii_case("\\m") {
+                    tags.extend(words.next().map(unquote));
+                }
+            }
+            Ok(Some(Field::Word(tags)))
+        }
+        _ => Ok(None),
+    }
+}
+
+// Zotero item key from a citation's `uris` (`http://zotero.org/users/1/items/ABCD2345`).
+fn zotero_key(item: &Value) -> Option<String> {
+    let uris = item.get("uris").or(item.get("uri"))?.as_array()?;
+    uris.iter()
+        .filter_map(Value::as_str)
+        .filter(|u| u.contains("zotero.org/") && u.contains("/items/"))
+        .find_map(|u| u.rsplit('/').next())
+        .map(str::to_string)
+}
+
+fn csl_items(citation: &Value) -> Result<Vec<Reference>, String> {
+    let items = citation
+        .get("citationItems")
+        .and_then(Value::as_array)
+        .ok_or("CSL_CITATION without `citationItems`")?;
+    let mut references = Vec::new();
+    for item in items {
+        let data = item.get("itemData").ok_or("citation item without `itemData`")?;
+        let mut reference = csl::to_reference(data)?;
+        // Zotero's numeric ids and Mendeley's `ITEM-1` only mean something inside the document
+        reference.key = None;
+        if let Some(key) = zotero_key(item) {
+            reference.extra.insert(ZOTERO_KEY.to_string(), Value::String(key));
+        }
+        references.push(reference);
+    }
+    Ok(references)
+}
+
+fn read_part<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<Option<String>, SourceError> {
+    match archive.by_name(name) {
+        Ok(mut file) => {
+            let mut content = String::new();
+            file.read_to_string(&mut content)?;
+            Ok(Some(content))
+        }
+        Err(ZipError::FileNotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn parse_part<'i>(name: &str, content: &'i str) -> Result<Document<'i>, SourceError> {
+    Document::parse(content).map_err(|e| SourceError::Format(format!("{name}: {e}")))
+}
+
+// Works a field cites directly and Word source tags it refers to.
+fn cited(code: &str) -> Result<(Vec<Reference>, Vec<String>), String> {
+    Ok(match parse_field(code)? {
+        Some(Field::Csl(citation)) => (csl_items(&citation)?, Vec::new()),
+        Some(Field::Word(tags)) => (Vec::new(), tags),
+        None => (Vec::new(), Vec::new()),
+    })
+}
+
+/// Reads the citations of a `.docx` package: Zotero and Mendeley field codes, which carry the
+/// cited items as CSL-JSON, and Word's own `CITATION` fields, resolved against the sources the
+/// document embeds. Footnotes and endnotes are searched as well.
+pub fn read_archive<R: Read + Seek>(reader: R) -> Result<DocxCitations, SourceError> {
+    let mut archive = ZipArchive::new(reader)?;
+    let mut citations = DocxCitations::default();
+
+    let mut sources: HashMap<String, Reference> = HashMap::new();
+    let custom: Vec<String> = archive
+        .file_names()
+        .filter(|n| n.starts_with("customXml/item") && !n.starts_with("customXml/itemProps"))
+        .map(str::to_string)
+        .collect();
+    for name in custom {
+        let Some(content) = read_part(&mut archive, &name)? else {
+            continue;
+        };
+        let doc = parse_part(&name, &content)?;
+        for source in sources_of(&doc, &mut citations.errors) {
+            sources.insert(source.key.clone().unwrap_or_default(), source);
+        }
+    }
+
+    if let Some(core) = read_part(&mut archive, "docProps/core.xml")? {
+        let doc = parse_part("docProps/core.xml", &core)?;
+        citations.title = child_text(doc.root_element(), "title");
+    }
+
+    for name in PARTS {
+        let Some(content) = read_part(&mut archive, name)? else {
+            if name == PARTS[0] {
+                return Err(SourceError::Format(format!("not a Word document: {name} is missing")));
+            }
+            continue;
+        };
+        let doc = parse_part(name, &content)?;
+        for (line, code) in field_codes(&doc) {
+            match cited(&code) {
+                Ok((references, tags)) => {
+                    references.into_iter().for_each(|r| citations.cite(r));
+                    for tag in tags {
+                        match sources.get(&tag) {
+                            Some(source) => citations.cite(source.clone()),
+                            None if !citations.missing.contains(&tag) => citations.missing.push(tag),
+                            None => {}
+                        }
+                    }
+                }
+                Err(message) => {
+                    let message = format!("{name}: {message}");
+                    citations.errors.push(EntryError { line, key: None, message });
+                }
+            }
+        }
+    }
+    Ok(citations)
+}
+
+pub fn read_file(path: impl AsRef<Path>) -> Result<DocxCitations, SourceError> {
+    read_archive(std::fs::File::open(path)?)
+}
+
+/// Entity id of the document stored at `path`, derived from its full path so that equally named
+/// files in different folders stay apart.
+pub fn document_id(path: impl AsRef<Path>) -> String {
+    let path = path.as_ref();
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    format!("document:{}", slugify(&path.to_string_lossy()))
+}
+
+// Completes the stored record with what the document adds; the citation data embedded in Word
+// files is sparse, so it never replaces anything the library already has.
+fn fill_missing(stored: &mut Reference, cited: &Reference) {
+    if stored.title.is_empty() {
+        stored.title = cited.title.clone();
+    }
+    if stored.authors.is_empty() {
+        stored.authors = cited.authors.clone();
+    }
+    if stored.editors.is_empty() {
+        stored.editors = cited.editors.clone();
+    }
+    stored.key = stored.key.take().or_else(|| cited.key.clone());
+    stored.year = stored.year.or(cited.year);
+    stored.venue = stored.venue.take().or_else(|| cited.venue.clone());
+    stored.uri = stored.uri.take().or_else(|| cited.uri.clone());
+    for keyword in &cited.keywords {
+        if !stored.keywords.contains(keyword) {
+            stored.keywords.push(keyword.clone());
+        }
+    }
+    for (name, value) in &cited.fields {
+        stored.fields.entry(name.clone()).or_insert_with(|| value.clone());
+    }
+    for (name, value) in &cited.extra {
+        stored.extra.entry(name.clone()).or_insert_with(|| value.clone());
+    }
+}
+
+/// Saves the cited works and links them from the `document` entity `id` with `cites` edges.
+/// Works cited through Zotero land on the entity imported from the same Zotero item, and
+/// citations removed from the document since an earlier import lose their edge.
+pub fn import_citations(
+    arm: &AcademicResourceManager,
+    id: &str,
+    citations: DocxCitations,
+) -> Result<ImportReport, SourceError> {
+    let known = arm.prop_index(ZOTERO_KEY)?;
+    let mut document = arm.get_entity(id)?.unwrap_or(Entity::builder().id(id).kind(DOCUMENT_KIND).build()?);
+    if let Some(title) = citations.title {
+        document.title = title;
+    }
+    arm.upsert_entity(&document)?;
+    for old in arm.edges_from(id, Some(CITES))? {
+        arm.delete_edge(&old.src, &old.dst, &old.kind)?;
+    }
+
+    let mut report = ImportReport { imported: Vec::new(), errors: citations.errors };
+    for reference in &citations.references {
+        let work = match reference.extra.get(ZOTERO_KEY).and_then(Value::as_str).and_then(|k| known.get(k)) {
+            Some(work) => work.clone(),
+            None => work_id(reference),
+        };
+        let work = match arm.load_reference(&work)? {
+            Some(mut stored) => {
+                // manual tags must not turn into keywords a later import could drop
+                stored.keywords = arm.keywords_of(&work)?;
+                fill_missing(&mut stored, reference);
+                arm.save_reference_at(&work, &stored)?
+            }
+            None => arm.save_reference_at(&work, reference)?,
+        };
+        arm.upsert_edge(&Edge::new(id, &work, CITES))?;
+        report.imported.push(work);
+    }
+    Ok(report)
+}
+
+/// Imports the citations of the `.docx` at `path`; the document entity is `document_id(path)`,
+/// titled after the file when the document has no title of its own.
+pub fn import_file(arm: &AcademicResourceManager, path: impl AsRef<Path>) -> Result<ImportReport, SourceError> {
+    let path = path.as_ref();
+    let mut citations = read_file(path)?;
+    if citations.title.is_none() {
+        citations.title = path.file_name().map(|n| n.to_string_lossy().to_string());
+    }
+    import_citations(arm, &document_id(path), citations)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+    use crate::database::Engine;
+
+    const W: &str = r#"xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main""#;
+
+    // a Zotero field split over two runs, as Word writes long instructions
+    fn zotero_field(item: &str, data: &str) -> String {
+        let code = format!(
+            r#" ADDIN ZOTERO_ITEM CSL_CITATION {{"citationID":"x1","citationItems":[{{"id":12,"uris":["http://zotero.org/users/7/items/{item}"],"itemData":{data}}}],"schema":"https://github.com/citation-style-language/schema/raw/master/csl-citation.json"}} "#
+        )
+        .replace('"', "&quot;");
+        let (head, tail) = code.split_at(40);
+        format!(
+            r#"<w:r><w:fldChar w:fldCharType="begin"/></w:r><w:r><w:instrText xml:space="preserve">{head}</w:instrText></w:r>
+<w:r><w:instrText xml:space="preserve">{tail}</w:instrText></w:r><w:r><w:fldChar w:fldCharType="separate"/></w:r>
+<w:r><w:t>(Smith 2020)</w:t></w:r><w:r><w:fldChar w:fldCharType="end"/></w:r>"#
+        )
+    }
+
+    const QUANTUM: &str = r#"{"id":12,"type":"article-journal","title":"Quantum Things","container-title":"Physical Review Letters","DOI":"10.1000/Q.1","author":[{"family":"Smith","given":"Jane"}],"issued":{"date-parts":[[2020]]}}"#;
+
+    fn document() -> String {
+        let mendeley = r#"ADDIN CSL_CITATION {"citationItems":[{"id":"ITEM-1","itemData":{"id":"ITEM-1","type":"book","title":"Harry Potter","author":[{"family":"Rowling","given":"J. K."}],"issued":{"date-parts":[["2003"]]}},"uris":["http://www.mendeley.com/documents/?uuid=1"]}],"mendeley":{"formattedCitation":"(Rowling 2003)"}}"#
+            .replace('"', "&quot;");
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document {W}><w:body>
+<w:p>{}</w:p>
+<w:p><w:fldSimple w:instr="{mendeley}"><w:r><w:t>(Rowling 2003)</w:t></w:r></w:fldSimple></w:p>
+<w:p><w:fldSimple w:instr=" CITATION Knu84 \l 1033 \m Gone99"/></w:p>
+<w:p><w:fldSimple w:instr=" ADDIN ZOTERO_ITEM CSL_CITATION {{broken"/></w:p>
+<w:p><w:fldSimple w:instr=" PAGE "/></w:p>
+<w:p>{}</w:p>
+<w:p><w:fldSimple w:instr=" ADDIN ZOTERO_BIBL {{&quot;uncited&quot;:[]}} CSL_BIBLIOGRAPHY "/></w:p>
+</w:body></w:document>"#,
+            zotero_field("ABCD2345", QUANTUM),
+            zotero_field("ABCD2345", QUANTUM),
+        )
+    }
+
+    const FOOTNOTES: &str = r#"<w:footnotes xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+<w:footnote w:id="1"><w:p><w:fldSimple w:instr="CITATION Mil05 \l 1033"/></w:p></w:footnote></w:footnotes>"#;
+
+    const SOURCES: &str = r#"<?xml version="1.0" standalone="yes"?>
+<b:Sources SelectedStyle="\APASixthEditionOfficeOnline.xsl" xmlns:b="http://schemas.openxmlformats.org/officeDocument/2006/bibliography" xmlns="http://schemas.openxmlformats.org/officeDocument/2006/bibliography">
+<b:Source><b:Tag>Knu84</b:Tag><b:SourceType>Book</b:SourceType><b:Guid>{0}</b:Guid>
+<b:Author><b:Author><b:NameList><b:Person><b:Last>Knuth</b:Last><b:First>Donald</b:First><b:Middle>E.</b:Middle></b:Person></b:NameList></b:Author>
+<b:Editor><b:NameList><b:Person><b:Last>Lamport</b:Last><b:First>Leslie</b:First></b:Person></b:NameList></b:Editor></b:Author>
+<b:Title>The TeXbook</b:Title><b:Year>1984</b:Year><b:Month>Jan</b:Month><b:City>Reading</b:City><b:Publisher>Addison-Wesley</b:Publisher>
+<b:StandardNumber>0-201-13447-0</b:StandardNumber></b:Source>
+<b:Source><b:Tag>Mil05</b:Tag><b:SourceType>ArticleInAPeriodical</b:SourceType>
+<b:Author><b:Author><b:Corporate>World Health Organization</b:Corporate></b:Author></b:Author>
+<b:Title>Report on Things</b:Title><b:PeriodicalTitle>The Lancet</b:PeriodicalTitle><b:Year>2005</b:Year><b:Pages>1-9</b:Pages></b:Source>
+<b:Source><b:SourceType>Patent</b:SourceType><b:Title>No tag</b:Title></b:Source>
+</b:Sources>"#;
+
+    fn docx(document: &str) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let core = r#"<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:title>My Thesis</dc:title></cp:coreProperties>"#;
+        let parts = [
+            ("word/document.xml", document),
+            ("word/footnotes.xml", FOOTNOTES),
+            ("customXml/item1.xml", SOURCES),
+            ("customXml/itemProps1.xml", "<ds:datastoreItem xmlns:ds=\"x\"/>"),
+            ("docProps/core.xml", core),
+        ];
+        for (name, content) in parts {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_read_archive() {
+        let citations = read_archive(Cursor::new(docx(&document()))).unwrap();
+        assert_eq!(citations.title.as_deref(), Some("My Thesis"));
+        let titles: Vec<_> = citations.references.iter().map(|r| r.title.as_str()).collect();
+        assert_eq!(titles, vec!["Quantum Things", "Harry Potter", "The TeXbook", "Report on Things"]);
+        assert_eq!(citations.missing, vec!["Gone99"]);
+
+        // the patent without a tag and the broken Zotero field
+        assert_eq!(citations.errors.len(), 2);
+        assert_eq!(citations.errors[0].message, "source without a `Tag`");
+        assert!(citations.errors[1].message.starts_with("word/document.xml: invalid CSL_CITATION data"));
+        assert_eq!(citations.errors[1].line, 8);
+
+        let quantum = &citations.references[0];
+        assert_eq!(quantum.key, None);
+        assert_eq!(quantum.extra[ZOTERO_KEY], "ABCD2345");
+        assert_eq!(quantum.venue.as_deref(), Some("Physical Review Letters"));
+        assert_eq!(citations.references[1].year, Some(2003));
+
+        let texbook = &citations.references[2];
+        assert_eq!(texbook.key.as_deref(), Some("Knu84"));
+        assert_eq!(texbook.kind, BOOK);
+        assert_eq!(texbook.authors[0].name.middle.as_deref(), Some("E."));
+        assert_eq!(texbook.editors[0].name.last, "Lamport");
+        assert_eq!(texbook.field("month"), Some("January"));
+        assert_eq!(texbook.field("address"), Some("Reading"));
+        assert_eq!(texbook.extra[WORD_PROPS]["StandardNumber"], "0-201-13447-0");
+
+        let report = &citations.references[3];
+        assert_eq!(report.authors[0].name.last, "World Health Organization");
+        assert_eq!(report.venue.as_deref(), Some("The Lancet"));
+    }
+
+    #[test]
+    fn test_import_links_document() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        // imported from Zotero before, under an id the document alone would not produce
+        let mut earlier = Reference { title: "Quantum Things".to_string(), kind: JOURNAL_ARTICLE.to_string(), ..Default::default() };
+        earlier.extra.insert(ZOTERO_KEY.to_string(), Value::String("ABCD2345".to_string()));
+        arm.save_reference_at("work:from-zotero", &earlier).unwrap();
+
+        let path = std::env::temp_dir().join("poirot thesis.docx");
+        std::fs::write(&path, docx(&document())).unwrap();
+        let report = import_file(&arm, &path).unwrap();
+        let id = document_id(&path);
+        assert!(id.ends_with("poirot-thesis-docx"));
+        assert_eq!(report.imported.len(), 4);
+        assert_eq!(report.imported[0], "work:from-zotero");
+        let document = arm.get_entity(&id).unwrap().unwrap();
+        assert_eq!((document.kind.as_str(), document.title.as_str()), (DOCUMENT_KIND, "My Thesis"));
+        assert_eq!(arm.edges_from(&id, Some(CITES)).unwrap().len(), 4);
+        let stored = arm.load_reference("work:from-zotero").unwrap().unwrap();
+        // the document only adds what the library lacked
+        assert_eq!(stored.title, "Quantum Things");
+        assert_eq!(stored.doi(), Some("10.1000/Q.1"));
+
+        // citations dropped from the document lose their edge on re-import
+        let shorter = format!(r#"<w:document {W}><w:body><w:p>{}</w:p></w:body></w:document>"#, zotero_field("ABCD2345", QUANTUM));
+        let citations = read_archive(Cursor::new(docx(&shorter))).unwrap();
+        import_citations(&arm, &id, citations).unwrap();
+        let edges = arm.edges_from(&id, Some(CITES)).unwrap();
+        assert_eq!(edges.len(), 2);
+        assert!(edges.iter().any(|e| e.dst == "work:from-zotero"));
+    }
+
+    #[test]
+    fn test_existing_work_keeps_its_fields() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let mut earlier = Reference {
+            title: "Quantum Things: A Longer Title".to_string(),
+            kind: JOURNAL_ARTICLE.to_string(),
+            authors: vec![Author::builder().name_from_str("John Doe").unwrap().build().unwrap()],
+            ..Default::default()
+        };
+        earlier.fields.insert("volume".to_string(), "7".to_string());
+        earlier.extra.insert(ZOTERO_KEY.to_string(), Value::String("ABCD2345".to_string()));
+        arm.save_reference_at("work:from-zotero", &earlier).unwrap();
+        arm.tag_entity("work:from-zotero", "to-read").unwrap();
+
+        let body = format!(r#"<w:document {W}><w:body><w:p>{}</w:p></w:body></w:document>"#, zotero_field("ABCD2345", QUANTUM));
+        let citations = read_archive(Cursor::new(docx(&body))).unwrap();
+        import_citations(&arm, "document:a", citations).unwrap();
+
+        let stored = arm.load_reference("work:from-zotero").unwrap().unwrap();
+        assert_eq!(stored.title, "Quantum Things: A Longer Title");
+        assert_eq!(stored.authors.len(), 1);
+        assert_eq!(stored.authors[0].name.last, "Doe");
+        assert_eq!(stored.field("volume"), Some("7"));
+        // missing data is still filled in
+        assert_eq!(stored.year, Some(2020));
+        assert_eq!(stored.venue.as_deref(), Some("Physical Review Letters"));
+        assert_eq!(arm.keywords_of("work:from-zotero").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_document_id_includes_folder() {
+        let a = std::env::temp_dir().join("poirot-a").join("report.docx");
+        let b = std::env::temp_dir().join("poirot-b").join("report.docx");
+        assert_ne!(document_id(&a), document_id(&b));
+        assert!(document_id(&a).ends_with("poirot-a-report-docx"));
+    }
+
+    #[test]
+    fn test_parse_sources() {
+        let parsed = parse_sources(SOURCES).unwrap();
+        assert_eq!(parsed.references.len(), 2);
+        assert_eq!(parsed.errors[0].line, 11);
+        assert!(matches!(parse_sources("<w:document xmlns:w=\"x\"/>"), Err(SourceError::Format(_))));
+        assert!(matches!(read_archive(Cursor::new(b"not a zip".to_vec())), Err(SourceError::Zip(_))));
+    }
+}
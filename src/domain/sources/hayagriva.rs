@@ -0,0 +1,554 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use serde_yaml::{Mapping, Value};
+use crate::database::AcademicResourceManager;
+use crate::domain::author::{Author, Name};
+use crate::domain::sources::keys::{keyed_references, KeyFormat};
+use crate::domain::sources::{
+    import_parsed, month_number, year_from, EntryError, ImportReport, Parsed, Reference, SourceError, BOOK,
+    CHAPTER, CONFERENCE_PAPER, DATASET, JOURNAL_ARTICLE, MISC, MONTHS, PREPRINT, PROCEEDINGS, REPORT, SOFTWARE,
+    THESIS, WEBPAGE,
+};
+
+// Hayagriva fields stored under their BibTeX name in `Reference::fields`.
+const FIELD_NAMES: &[(&str, &str)] = &[
+    ("page-range", "pages"),
+    ("issue", "number"),
+    ("location", "address"),
+    ("genre", "type"),
+];
+
+// Hayagriva fields kept under their own name; only these (and `FIELD_NAMES`) are exported.
+const PASSTHROUGH: &[&str] = &[
+    "abstract", "annote", "archive", "archive-location", "call-number", "edition", "language", "note",
+    "page-total", "publisher", "runtime", "volume", "volume-total",
+];
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn get<'a>(map: &'a Mapping, key: &str) -> Option<&'a Value> {
+    map.get(Value::String(key.to_string()))
+}
+
+fn entry_type(map: &Mapping) -> String {
+    get(map, "type").and_then(scalar).unwrap_or_default().to_lowercase()
+}
+
+fn parents(map: &Mapping) -> Vec<&Mapping> {
+    match get(map, "parent") {
+        Some(Value::Mapping(p)) => vec![p],
+        Some(Value::Sequence(ps)) => ps.iter().filter_map(Value::as_mapping).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn kind_for(map: &Mapping, has_arxiv: bool) -> &'static str {
+    let parent_type = parents(map).first().map(|p| entry_type(p)).unwrap_or_default();
+    match entry_type(map).as_str() {
+        "article" if matches!(parent_type.as_str(), "proceedings" | "conference") => CONFERENCE_PAPER,
+        "article" if parent_type.is_empty() && has_arxiv => PREPRINT,
+        "article" | "periodical" | "newspaper" => JOURNAL_ARTICLE,
+        "proceedings" | "conference" => PROCEEDINGS,
+        "book" | "anthology" | "reference" => BOOK,
+        "chapter" | "anthos" | "entry" => CHAPTER,
+        "thesis" => THESIS,
+        "report" => REPORT,
+        "repository" => SOFTWARE,
+        "web" | "blog" | "post" | "thread" => WEBPAGE,
+        "misc" if get(map, "genre").and_then(scalar).is_some_and(|g| g.eq_ignore_ascii_case("dataset")) => DATASET,
+        _ => MISC,
+    }
+}
+
+/// Parses a Hayagriva name: `prefix Last, Given, Suffix`, or a single name.
+pub fn parse_name(raw: &str) -> Result<Name, String> {
+    let parts: Vec<&str> = raw.split(',').map(str::trim).collect();
+    let (last, given, suffix) = match parts.as_slice() {
+        [last] => (*last, "", None),
+        [last, given] => (*last, *given, None),
+        [last, given, suffix] => (*last, *given, Some(*suffix)),
+        _ => return Err(format!("too many commas in name `{raw}`")),
+    };
+    if last.is_empty() {
+        return Err(format!("missing last name in `{raw}`"));
+    }
+    Name::from_parts(last, given, suffix).map_err(|e| e.to_string())
+}
+
+fn name_from_value(value: &Value) -> Result<Name, String> {
+    match value {
+        Value::String(s) => parse_name(s),
+        // long form: `name`, `given-name`, `prefix`, `suffix`
+        Value::Mapping(m) => {
+            let part = |k: &str| get(m, k).and_then(scalar);
+            let family = part("name").ok_or_else(|| "name without `name`".to_string())?;
+            let given = part("given-name").unwrap_or_default();
+            let mut name = Name::from_parts(&family, &given, part("suffix").as_deref()).map_err(|e| e.to_string())?;
+            if let Some(prefix) = part("prefix") {
+                name.particle = Some(prefix);
+                name.last = family;
+            }
+            Ok(name)
+        }
+        other => Err(format!("unsupported name {other:?}")),
+    }
+}
+
+fn parse_names(map: &Mapping, role: &str) -> Result<Vec<Author>, String> {
+    let values = match get(map, role) {
+        None | Some(Value::Null) => return Ok(Vec::new()),
+        Some(Value::Sequence(s)) => s.iter().collect(),
+        Some(single) => vec![single],
+    };
+    values
+        .into_iter()
+        .map(|v| {
+            let name = name_from_value(v)?;
+            Author::builder().name(name).and_then(|b| b.build()).map_err(|e| e.to_string())
+        })
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("{role}: {e}"))
+}
+
+// `2020`, `2020-03` or `2020-03-15`
+fn parse_date(value: &Value) -> Option<(i64, Option<u32>, Option<u32>)> {
+    let text = scalar(value)?;
+    let mut parts = text.split('-');
+    let year = year_from(parts.next()?)?;
+    let month = parts.next().and_then(|m| m.parse::<u32>().ok()).filter(|m| (1..=12).contains(m));
+    let day = parts.next().and_then(|d| d.parse::<u32>().ok()).filter(|d| (1..=31).contains(d));
+    Some((year, month, day.filter(|_| month.is_some())))
+}
+
+// Scalar fields of one entry (or parent) into `fields`, without overwriting what is there.
+fn collect_fields(map: &Mapping, kind_hint: &str, fields: &mut BTreeMap<String, String>) {
+    let mut put = |name: &str, value: String| {
+        fields.entry(name.to_string()).or_insert(value);
+    };
+    for (name, value) in map {
+        let Some(name) = name.as_str() else {
+            continue;
+        };
+        match (name, value) {
+            ("type" | "title" | "author" | "editor" | "date" | "parent", _) => {}
+            ("organization", v) => {
+                let field = if entry_type(map) == "thesis" || kind_hint == THESIS { "school" } else { "institution" };
+                if let Some(v) = scalar(v) {
+                    put(field, v);
+                }
+            }
+            ("publisher", Value::Mapping(p)) => {
+                if let Some(n) = get(p, "name").and_then(scalar) {
+                    put("publisher", n);
+                }
+                if let Some(l) = get(p, "location").and_then(scalar) {
+                    put("address", l);
+                }
+            }
+            ("url", Value::Mapping(u)) => {
+                if let Some(v) = get(u, "value").and_then(scalar) {
+                    put("url", v);
+                }
+                if let Some(d) = get(u, "date").and_then(scalar) {
+                    put("urldate", d);
+                }
+            }
+            ("serial-number", Value::Mapping(s)) => {
+                for (k, v) in s {
+                    let (Some(k), Some(v)) = (k.as_str(), scalar(v)) else {
+                        continue;
+                    };
+                    match k.to_lowercase().as_str() {
+                        "arxiv" => {
+                            put("eprint", v);
+                            put("eprinttype", "arxiv".to_string());
+                        }
+                        k => put(k, v),
+                    }
+                }
+            }
+            ("serial-number", v) => {
+                if let Some(v) = scalar(v) {
+                    let field = if v.starts_with("10.") { "doi" } else { "serial-number" };
+                    put(field, v);
+                }
+            }
+            (name, v) => {
+                if let Some(v) = scalar(v) {
+                    let field = FIELD_NAMES.iter().find(|(h, _)| *h == name).map_or(name, |(_, f)| *f);
+                    put(field, v);
+                }
+            }
+        }
+    }
+}
+
+fn to_reference(key: &str, map: &Mapping) -> Result<Reference, String> {
+    let mut fields = BTreeMap::new();
+    collect_fields(map, "", &mut fields);
+    let kind = kind_for(map, fields.contains_key("eprinttype"));
+    if kind == DATASET {
+        // the genre was only the dataset marker written by `format_entry`
+        fields.remove("type");
+    }
+
+    let authors = parse_names(map, "author")?;
+    let mut editors = parse_names(map, "editor")?;
+    let mut venue = None;
+    let mut date = get(map, "date");
+    for parent in parents(map) {
+        collect_fields(parent, kind, &mut fields);
+        venue = venue.or_else(|| get(parent, "title").and_then(scalar));
+        if editors.is_empty() {
+            editors = parse_names(parent, "editor")?;
+        }
+        date = date.or(get(parent, "date"));
+    }
+
+    let year = match date {
+        Some(d) => {
+            let (year, month, day) = parse_date(d).ok_or_else(|| format!("invalid date {d:?}"))?;
+            if let Some(m) = month {
+                fields.insert("month".to_string(), MONTHS[m as usize - 1].to_string());
+            }
+            if let Some(d) = day {
+                fields.insert("day".to_string(), d.to_string());
+            }
+            Some(year)
+        }
+        None => None,
+    };
+    let uri = fields
+        .get("url")
+        .cloned()
+        .or_else(|| fields.get("doi").map(|d| format!("https://doi.org/{d}")));
+
+    Ok(Reference {
+        key: Some(key.to_string()),
+        kind: kind.to_string(),
+        title: get(map, "title").and_then(scalar).unwrap_or_default(),
+        authors,
+        editors,
+        year,
+        venue,
+        uri,
+        keywords: Vec::new(),
+        fields,
+        extra: BTreeMap::new(),
+    })
+}
+
+// serde_yaml drops positions, so find the line of a top-level key in the source.
+fn line_of_key(input: &str, key: &str) -> usize {
+    input
+        .lines()
+        .position(|l| {
+            let l = l.trim_end();
+            [format!("{key}:"), format!("\"{key}\":"), format!("'{key}':")]
+                .iter()
+                .any(|k| l.starts_with(k.as_str()))
+        })
+        .map_or(0, |i| i + 1)
+}
+
+/// Parses a Hayagriva YAML bibliography. Invalid YAML fails as a whole; entries that do not
+/// make sense as Hayagriva are reported in `errors`.
+pub fn parse(input: &str) -> Result<Parsed, SourceError> {
+    let doc: Value = serde_yaml::from_str(input).map_err(|e| SourceError::Format(e.to_string()))?;
+    let entries = match doc {
+        Value::Mapping(m) => m,
+        Value::Null => Mapping::new(),
+        _ => return Err(SourceError::Format("expected a mapping of entries".to_string())),
+    };
+    let mut parsed = Parsed::default();
+    for (key, value) in &entries {
+        let key = scalar(key).unwrap_or_default();
+        let result = match value {
+            Value::Mapping(map) => to_reference(&key, map),
+            other => Err(format!("expected a mapping, got {other:?}")),
+        };
+        match result {
+            Ok(r) => parsed.references.push(r),
+            Err(message) => parsed.errors.push(EntryError {
+                line: line_of_key(input, &key),
+                key: Some(key),
+                message,
+            }),
+        }
+    }
+    Ok(parsed)
+}
+
+pub fn import_str(arm: &AcademicResourceManager, input: &str) -> Result<ImportReport, SourceError> {
+    import_parsed(arm, parse(input)?)
+}
+
+pub fn import_file(arm: &AcademicResourceManager, path: impl AsRef<Path>) -> Result<ImportReport, SourceError> {
+    let input = std::fs::read_to_string(path)?;
+    import_str(arm, &input)
+}
+
+// ---- export ----
+
+fn string(s: impl Into<String>) -> Value {
+    Value::String(s.into())
+}
+
+fn format_name(author: &Author) -> Value {
+    let name = &author.name;
+    let given = name.given();
+    match (given.is_empty(), &name.suffix) {
+        (true, None) => string(name.family()),
+        (_, Some(suffix)) => string(format!("{}, {}, {}", name.family(), given, suffix)),
+        (false, None) => string(format!("{}, {}", name.family(), given)),
+    }
+}
+
+fn format_names(people: &[Author]) -> Value {
+    match people {
+        [one] => format_name(one),
+        _ => Value::Sequence(people.iter().map(format_name).collect()),
+    }
+}
+
+fn format_date(reference: &Reference) -> Option<Value> {
+    let year = reference.year?;
+    let month = reference.field("month").and_then(month_number);
+    let day = reference.field("day").and_then(|d| d.parse::<u32>().ok());
+    Some(match (month, day) {
+        (Some(m), Some(d)) => string(format!("{year:04}-{m:02}-{d:02}")),
+        (Some(m), None) => string(format!("{year:04}-{m:02}")),
+        _ => Value::Number(year.into()),
+    })
+}
+
+fn insert(map: &mut Mapping, key: &str, value: Value) {
+    map.insert(string(key), value);
+}
+
+/// Renders one reference as a Hayagriva entry (the value under its key).
+pub fn format_entry(reference: &Reference) -> Value {
+    let (entry_type, parent_type) = match reference.kind.as_str() {
+        JOURNAL_ARTICLE => ("article", Some("periodical")),
+        CONFERENCE_PAPER => ("article", Some("proceedings")),
+        PREPRINT => ("article", None),
+        PROCEEDINGS => ("proceedings", None),
+        BOOK => ("book", None),
+        CHAPTER => ("chapter", Some("book")),
+        THESIS => ("thesis", None),
+        REPORT => ("report", None),
+        SOFTWARE => ("repository", None),
+        WEBPAGE => ("web", None),
+        _ => ("misc", None),
+    };
+    let parent_type = parent_type.filter(|_| reference.venue.is_some());
+    // journal volume/issue belong to the periodical, editors and publisher to the book or proceedings
+    let parent_fields: &[&str] = match parent_type {
+        Some("periodical") => &["volume", "number"],
+        Some(_) => &["publisher", "address", "volume", "edition"],
+        None => &[],
+    };
+
+    let mut entry = Mapping::new();
+    let mut parent = Mapping::new();
+    insert(&mut entry, "type", string(entry_type));
+    if !reference.title.is_empty() {
+        insert(&mut entry, "title", string(&reference.title));
+    }
+    if !reference.authors.is_empty() {
+        insert(&mut entry, "author", format_names(&reference.authors));
+    }
+    if let Some(date) = format_date(reference) {
+        insert(&mut entry, "date", date);
+    }
+    if !reference.editors.is_empty() {
+        let target = if parent_type.is_some_and(|t| t != "periodical") { &mut parent } else { &mut entry };
+        insert(target, "editor", format_names(&reference.editors));
+    }
+    if reference.kind == DATASET {
+        insert(&mut entry, "genre", string("Dataset"));
+    }
+
+    let mut serials = Mapping::new();
+    for (name, value) in &reference.fields {
+        let hayagriva = match name.as_str() {
+            "doi" | "isbn" | "issn" | "pmid" | "pmcid" => {
+                insert(&mut serials, name, string(value));
+                continue;
+            }
+            "eprint" if reference.field("eprinttype").is_some_and(|t| t.eq_ignore_ascii_case("arxiv")) => {
+                insert(&mut serials, "arxiv", string(value));
+                continue;
+            }
+            "school" | "institution" => "organization",
+            "type" if matches!(value.as_str(), "phdthesis" | "mastersthesis") => continue,
+            "type" if reference.kind == DATASET => continue,
+            _ => match FIELD_NAMES.iter().find(|(_, f)| f == name) {
+                Some((h, _)) => *h,
+                None if PASSTHROUGH.contains(&name.as_str()) => name.as_str(),
+                None => continue,
+            },
+        };
+        let target = if parent_fields.contains(&name.as_str()) { &mut parent } else { &mut entry };
+        insert(target, hayagriva, string(value));
+    }
+    match (reference.field("url"), reference.field("urldate")) {
+        (Some(url), Some(date)) => {
+            let mut u = Mapping::new();
+            insert(&mut u, "value", string(url));
+            insert(&mut u, "date", string(date));
+            insert(&mut entry, "url", Value::Mapping(u));
+        }
+        (Some(url), None) => insert(&mut entry, "url", string(url)),
+        _ => {}
+    }
+    if !serials.is_empty() {
+        insert(&mut entry, "serial-number", Value::Mapping(serials));
+    }
+
+    match (parent_type, &reference.venue) {
+        (Some(t), Some(venue)) => {
+            let mut p = Mapping::new();
+            insert(&mut p, "type", string(t));
+            insert(&mut p, "title", string(venue));
+            p.extend(parent);
+            insert(&mut entry, "parent", Value::Mapping(p));
+        }
+        _ => entry.extend(parent),
+    }
+    Value::Mapping(entry)
+}
+
+pub(crate) fn format_document(entries: &[(String, Reference)]) -> Result<String, SourceError> {
+    let doc: Mapping = entries
+        .iter()
+        .map(|(key, reference)| (string(key), format_entry(reference)))
+        .collect();
+    serde_yaml::to_string(&doc).map_err(|e| SourceError::Format(e.to_string()))
+}
+
+/// Exports the works among `ids` as a Hayagriva YAML bibliography.
+pub fn export(arm: &AcademicResourceManager, ids: &[String], format: &KeyFormat) -> Result<String, SourceError> {
+    format_document(&keyed_references(arm, ids, format)?)
+}
+
+pub fn export_file(
+    arm: &AcademicResourceManager,
+    ids: &[String],
+    format: &KeyFormat,
+    path: impl AsRef<Path>,
+) -> Result<(), SourceError> {
+    std::fs::write(path, export(arm, ids, format)?)?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Engine;
+
+    const SAMPLE: &str = r#"
+harry:
+  type: book
+  title: Harry Potter and the Order of the Phoenix
+  author: Rowling, J. K.
+  date: 2003-06-21
+  publisher:
+    name: Bloomsbury
+    location: London
+  serial-number:
+    isbn: 978-0-7475-5100-3
+
+quantum:
+  type: article
+  title: Quantum Things
+  author: ["van Beethoven, Ludwig", "Smith, Jane Helen", "CERN"]
+  date: 2020
+  page-range: 1-10
+  serial-number:
+    doi: 10.1000/Q.1
+  parent:
+    type: periodical
+    title: Physical Review Letters
+    volume: 12
+    issue: 3
+
+bad:
+  type: article
+  title: Broken
+  date: sometime
+
+talk:
+  type: article
+  title: A Talk
+  author: Doe, John
+  parent:
+    type: proceedings
+    title: Proceedings of the Symposium
+    editor: Roe, Richard
+    date: 2019
+    publisher: ACM
+"#;
+
+    #[test]
+    fn test_parse_sample() {
+        let parsed = parse(SAMPLE).unwrap();
+        assert_eq!(parsed.references.len(), 3);
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].key.as_deref(), Some("bad"));
+        assert_eq!(parsed.errors[0].line, 27);
+
+        let harry = &parsed.references[0];
+        assert_eq!(harry.kind, BOOK);
+        assert_eq!(harry.year, Some(2003));
+        assert_eq!(harry.field("month"), Some("June"));
+        assert_eq!(harry.field("address"), Some("London"));
+        assert_eq!(harry.field("isbn"), Some("978-0-7475-5100-3"));
+        assert_eq!(harry.authors[0].name.middle.as_deref(), Some("K."));
+
+        let quantum = &parsed.references[1];
+        assert_eq!(quantum.kind, JOURNAL_ARTICLE);
+        assert_eq!(quantum.venue.as_deref(), Some("Physical Review Letters"));
+        assert_eq!(quantum.field("volume"), Some("12"));
+        assert_eq!(quantum.field("number"), Some("3"));
+        assert_eq!(quantum.field("pages"), Some("1-10"));
+        assert_eq!(quantum.doi(), Some("10.1000/Q.1"));
+        assert_eq!(quantum.authors.len(), 3);
+        assert_eq!(quantum.authors[0].name.last, "Beethoven");
+        assert_eq!(quantum.authors[0].name.particle.as_deref(), Some("van"));
+        assert_eq!(quantum.authors[2].name.last, "CERN");
+
+        let talk = &parsed.references[2];
+        assert_eq!(talk.kind, CONFERENCE_PAPER);
+        assert_eq!(talk.year, Some(2019));
+        assert_eq!(talk.editors[0].name.last, "Roe");
+        assert_eq!(talk.field("publisher"), Some("ACM"));
+    }
+
+    #[test]
+    fn test_export_roundtrip() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let report = import_str(&arm, SAMPLE).unwrap();
+        assert_eq!(report.errors.len(), 1);
+        let out = export(&arm, &report.imported, &KeyFormat::default()).unwrap();
+
+        let doc: Value = serde_yaml::from_str(&out).unwrap();
+        assert_eq!(doc["quantum"]["parent"]["type"], string("periodical"));
+        assert_eq!(doc["quantum"]["parent"]["volume"], string("12"));
+        assert_eq!(doc["talk"]["parent"]["editor"], string("Roe, Richard"));
+        assert_eq!(doc["harry"]["date"], string("2003-06-21"));
+
+        let back = parse(&out).unwrap();
+        assert!(back.errors.is_empty(), "{:?}", back.errors);
+        let original = parse(SAMPLE).unwrap();
+        assert_eq!(back.references, original.references);
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashSet;
+use log::warn;
+use crate::database::AcademicResourceManager;
+use crate::domain::author::Author;
+use crate::domain::sources::{is_work_kind, Reference, SourceError};
+use crate::utils::text::ascii_fold;
+
+const KEY_STOPWORDS: &[&str] = &[
+    "a", "an", "the", "on", "of", "in", "for", "and", "to", "with", "at", "by", "from", "towards", "toward",
+];
+
+// Citation key pattern. Placeholders: `{author}` first author's last name, `{authors}` up to two
+// last names (`etal` beyond), `{year}`, `{title}` first significant title word and
+// `{shorttitle}` the first three. Output is ASCII; prefix a placeholder with `^` to capitalize it.
+#[derive(Debug, Clone)]
+pub struct KeyFormat {
+    pattern: String,
+}
+
+impl Default for KeyFormat {
+    fn default() -> Self {
+        KeyFormat::new("{author}{year}{title}")
+    }
+}
+
+impl KeyFormat {
+    pub fn new(pattern: impl Into<String>) -> Self {
+        KeyFormat { pattern: pattern.into() }
+    }
+
+    fn placeholder(&self, name: &str, reference: &Reference) -> Option<String> {
+        let last_name = |a: &Author| ascii_fold(&a.name.last).to_lowercase();
+        let people = match reference.authors.is_empty() {
+            true => &reference.editors,
+            false => &reference.authors,
+        };
+        let title_words: Vec<String> = reference
+            .title
+            .split_whitespace()
+            .map(|w| ascii_fold(w).to_lowercase())
+            .filter(|w| !w.is_empty() && !KEY_STOPWORDS.contains(&w.as_str()))
+            .collect();
+        Some(match name {
+            "author" => people.first().map(last_name).unwrap_or_else(|| "anon".to_string()),
+            "authors" => match people.len() {
+                0 => "anon".to_string(),
+                1 | 2 => people.iter().map(last_name).collect(),
+                _ => format!("{}etal", last_name(&people[0])),
+            },
+            "year" => reference.year.map(|y| y.to_string()).unwrap_or_else(|| "nd".to_string()),
+            "title" => title_words.first().cloned().unwrap_or_default(),
+            "shorttitle" => title_words.iter().take(3).cloned().collect(),
+            _ => return None,
+        })
+    }
+
+    pub fn render(&self, reference: &Reference) -> String {
+        let mut out = String::new();
+        let mut rest = self.pattern.as_str();
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            let token = &rest[start + 1..start + len];
+            let (capitalize, name) = match token.strip_prefix('^') {
+                Some(n) => (true, n),
+                None => (false, token),
+            };
+            match self.placeholder(name, reference) {
+                Some(v) if capitalize => {
+                    let mut chars = v.chars();
+                    if let Some(c) = chars.next() {
+                        out.extend(c.to_uppercase());
+                        out.push_str(chars.as_str());
+                    }
+                }
+                Some(v) => out.push_str(&v),
+                None => out.push_str(&rest[start..start + len + 1]),
+            }
+            rest = &rest[start + len + 1..];
+        }
+        out.push_str(rest);
+        let key: String = out
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || "-_:.".contains(*c))
+            .collect();
+        match key.is_empty() {
+            true => "ref".to_string(),
+            false => key,
+        }
+    }
+}
+
+fn next_suffix(n: usize) -> String {
+    // a..z, then aa, ab, ...
+    let mut n = n;
+    let mut s = String::new();
+    loop {
+        s.insert(0, (b'a' + (n % 26) as u8) as char);
+        if n < 26 {
+            break;
+        }
+        n = n / 26 - 1;
+    }
+    s
+}
+
+/// Loads the works among `ids` (duplicates and non-work entities skipped) with their citation
+/// keys. A key remembered in props is reused; works without one get a key from `format`,
+/// disambiguated with `a`, `b`, ... suffixes against every key in the database, and stored back
+/// so later exports hand out the same key.
+pub fn keyed_references(
+    arm: &AcademicResourceManager,
+    ids: &[String],
+    format: &KeyFormat,
+) -> Result<Vec<(String, Reference)>, SourceError> {
+    let mut owners = arm.citation_keys()?;
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for id in ids {
+        if !seen.insert(id) {
+            continue;
+        }
+        let Some(reference) = arm.load_reference(id)? else {
+            warn!("export: no entity `{}`", id);
+            continue;
+        };
+        if !is_work_kind(&reference.kind) {
+            continue;
+        }
+        let stored = reference.key.clone().filter(|k| owners.get(k) == Some(id));
+        let key = match stored {
+            Some(k) => k,
+            None => {
+                let base = format.render(&reference);
+                let mut key = base.clone();
+                let mut n = 0;
+                while owners.get(&key).is_some_and(|owner| owner != id) {
+                    key = format!("{base}{}", next_suffix(n));
+                    n += 1;
+                }
+                arm.set_citation_key(id, &key)?;
+                owners.insert(key.clone(), id.clone());
+                key
+            }
+        };
+        out.push((key, reference));
+    }
+    Ok(out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::sources::JOURNAL_ARTICLE;
+
+    fn work(title: &str, year: i64, author: &str) -> Reference {
+        Reference {
+            kind: JOURNAL_ARTICLE.to_string(),
+            title: title.to_string(),
+            authors: vec![Author::builder().name_from_str(author).unwrap().build().unwrap()],
+            year: Some(year),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_key_format() {
+        let r = work("On the Origin of Łódź Species", 2020, "Jörg Müller");
+        assert_eq!(KeyFormat::default().render(&r), "muller2020origin");
+        assert_eq!(KeyFormat::new("{^author}_{shorttitle}").render(&r), "Muller_originlodzspecies");
+        assert_eq!(KeyFormat::new("{nothing}").render(&Reference::default()), "nothing");
+        assert_eq!(KeyFormat::new("").render(&Reference::default()), "ref");
+    }
+
+    #[test]
+    fn test_next_suffix() {
+        assert_eq!(next_suffix(0), "a");
+        assert_eq!(next_suffix(25), "z");
+        assert_eq!(next_suffix(26), "aa");
+        assert_eq!(next_suffix(27), "ab");
+    }
+}
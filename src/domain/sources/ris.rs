@@ -0,0 +1,470 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use crate::database::AcademicResourceManager;
+use crate::domain::author::Author;
+use crate::domain::sources::hayagriva::parse_name;
+use crate::domain::sources::keys::{keyed_references, KeyFormat};
+use crate::domain::sources::{
+    import_parsed, month_number, year_from, EntryError, ImportReport, Parsed, Reference, SourceError, BOOK,
+    CHAPTER, CONFERENCE_PAPER, DATASET, JOURNAL_ARTICLE, MISC, MONTHS, PREPRINT, PROCEEDINGS, REPORT, SOFTWARE,
+    THESIS, WEBPAGE,
+};
+
+// Props key holding the tags this module does not map, as `{tag: [values]}`.
+pub const RIS_PROPS: &str = "ris";
+
+// RIS tags stored under a `Reference::fields` name.
+const FIELD_TAGS: &[(&str, &str)] = &[
+    ("VL", "volume"),
+    ("IS", "number"),
+    ("DO", "doi"),
+    ("PB", "publisher"),
+    ("CY", "address"),
+    ("AB", "abstract"),
+    ("N1", "note"),
+    ("ET", "edition"),
+    ("LA", "language"),
+];
+
+fn kind_for(ty: &str) -> &'static str {
+    match ty {
+        "JOUR" | "JFULL" | "EJOUR" | "MGZN" | "NEWS" | "INPR" | "ABST" => JOURNAL_ARTICLE,
+        "CPAPER" => CONFERENCE_PAPER,
+        "CONF" => PROCEEDINGS,
+        "BOOK" | "EBOOK" | "EDBOOK" => BOOK,
+        "CHAP" | "ECHAP" => CHAPTER,
+        "THES" => THESIS,
+        "RPRT" => REPORT,
+        "UNPB" => PREPRINT,
+        "COMP" => SOFTWARE,
+        "DATA" | "DBASE" => DATASET,
+        "ELEC" | "WEB" | "BLOG" => WEBPAGE,
+        _ => MISC,
+    }
+}
+
+fn ris_type(kind: &str) -> &'static str {
+    match kind {
+        JOURNAL_ARTICLE => "JOUR",
+        CONFERENCE_PAPER => "CPAPER",
+        PROCEEDINGS => "CONF",
+        BOOK => "BOOK",
+        CHAPTER => "CHAP",
+        THESIS => "THES",
+        REPORT => "RPRT",
+        PREPRINT => "UNPB",
+        SOFTWARE => "COMP",
+        DATASET => "DATA",
+        WEBPAGE => "ELEC",
+        _ => "GEN",
+    }
+}
+
+// `TY  - JOUR`; some exporters drop the space after the dash when the value is empty.
+fn split_line(line: &str) -> Option<(&str, &str)> {
+    let tag = line.get(..2)?;
+    let rest = line.get(2..)?;
+    let mut chars = tag.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_uppercase())
+        && chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+    if !valid {
+        return None;
+    }
+    let value = rest.trim_start_matches(' ').strip_prefix('-')?;
+    Some((tag, value.trim()))
+}
+
+#[derive(Debug)]
+struct Record {
+    line: usize,
+    tags: Vec<(String, String)>,
+}
+
+// `PY  - 2020///` or `DA  - 2020/03/15/other`
+fn parse_date(value: &str) -> Option<(i64, Option<u32>, Option<u32>)> {
+    let mut parts = value.split('/');
+    let year = year_from(parts.next()?)?;
+    let month = parts.next().and_then(|m| m.trim().parse::<u32>().ok()).filter(|m| (1..=12).contains(m));
+    let day = parts.next().and_then(|d| d.trim().parse::<u32>().ok()).filter(|d| (1..=31).contains(d));
+    Some((year, month, day.filter(|_| month.is_some())))
+}
+
+fn is_issn(value: &str) -> bool {
+    let chars: Vec<char> = value.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    chars.len() == 8 && chars[..7].iter().all(char::is_ascii_digit)
+}
+
+fn to_reference(record: &Record) -> Result<Reference, String> {
+    let mut r = Reference::default();
+    let mut unknown: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut start_page = None;
+    let mut end_page = None;
+    let mut date = None;
+    let person = |value: &str| -> Result<Author, String> {
+        let name = parse_name(value)?;
+        Author::builder().name(name).and_then(|b| b.build()).map_err(|e| e.to_string())
+    };
+
+    for (tag, value) in &record.tags {
+        if value.is_empty() {
+            continue;
+        }
+        match tag.as_str() {
+            "TY" => {
+                r.kind = kind_for(value).to_string();
+                if ris_type(&r.kind) != value {
+                    unknown.entry(tag.clone()).or_default().push(value.clone());
+                }
+            }
+            "ID" => r.key = Some(value.clone()),
+            "AU" | "A1" => r.authors.push(person(value).map_err(|e| format!("{tag}: {e}"))?),
+            "A2" | "ED" => r.editors.push(person(value).map_err(|e| format!("{tag}: {e}"))?),
+            "TI" | "T1" if r.title.is_empty() => r.title = value.clone(),
+            "T2" | "JF" | "JO" | "BT" if r.venue.is_none() => r.venue = Some(value.clone()),
+            "PY" | "Y1" if r.year.is_none() => {
+                r.year = Some(parse_date(value).ok_or_else(|| format!("{tag}: invalid year `{value}`"))?.0);
+            }
+            "DA" if date.is_none() => date = parse_date(value),
+            "SP" if start_page.is_none() => start_page = Some(value.clone()),
+            "EP" if end_page.is_none() => end_page = Some(value.clone()),
+            "UR" if r.uri.is_none() => {
+                r.uri = Some(value.clone());
+                r.fields.insert("url".to_string(), value.clone());
+            }
+            "SN" if !r.fields.contains_key("isbn") && !r.fields.contains_key("issn") => {
+                let field = if is_issn(value) { "issn" } else { "isbn" };
+                r.fields.insert(field.to_string(), value.clone());
+            }
+            "KW" => r.keywords.extend(
+                value.split(';').map(str::trim).filter(|k| !k.is_empty()).map(str::to_string),
+            ),
+            "N2" if !r.fields.contains_key("abstract") => {
+                r.fields.insert("abstract".to_string(), value.clone());
+            }
+            "ER" => {}
+            _ => match FIELD_TAGS.iter().find(|(t, _)| t == tag) {
+                Some((_, field)) if !r.fields.contains_key(*field) => {
+                    r.fields.insert(field.to_string(), value.clone());
+                }
+                _ => unknown.entry(tag.clone()).or_default().push(value.clone()),
+            },
+        }
+    }
+
+    if let Some((year, month, day)) = date {
+        r.year.get_or_insert(year);
+        if let Some(m) = month {
+            r.fields.insert("month".to_string(), MONTHS[m as usize - 1].to_string());
+        }
+        if let Some(d) = day {
+            r.fields.insert("day".to_string(), d.to_string());
+        }
+    }
+    let pages = match (start_page, end_page) {
+        (Some(s), Some(e)) => Some(format!("{s}-{e}")),
+        (s, e) => s.or(e),
+    };
+    if let Some(p) = pages {
+        r.fields.insert("pages".to_string(), p);
+    }
+    if r.uri.is_none() {
+        r.uri = r.doi().map(|d| format!("https://doi.org/{d}"));
+    }
+    if !unknown.is_empty() {
+        let value = serde_json::to_value(&unknown).map_err(|e| e.to_string())?;
+        r.extra.insert(RIS_PROPS.to_string(), value);
+    }
+    Ok(r)
+}
+
+/// Parses an RIS file. Each record runs from `TY` to `ER`; lines without a tag continue the
+/// previous value. Records left open or with unusable values are reported in `errors` and skipped.
+pub fn parse(input: &str) -> Parsed {
+    let mut parsed = Parsed::default();
+    let mut records: Vec<Record> = Vec::new();
+    let mut current: Option<Record> = None;
+    for (n, line) in input.trim_start_matches('\u{feff}').lines().enumerate() {
+        let line_no = n + 1;
+        match split_line(line) {
+            Some(("TY", value)) => {
+                if let Some(open) = current.take() {
+                    parsed.errors.push(EntryError {
+                        line: open.line,
+                        key: None,
+                        message: format!("record not closed with `ER` before line {line_no}"),
+                    });
+                }
+                current = Some(Record { line: line_no, tags: vec![("TY".to_string(), value.to_string())] });
+            }
+            Some(("ER", _)) => match current.take() {
+                Some(record) => records.push(record),
+                None => parsed.errors.push(EntryError { line: line_no, key: None, message: "`ER` outside a record".to_string() }),
+            },
+            Some((tag, value)) => match current.as_mut() {
+                Some(record) => record.tags.push((tag.to_string(), value.to_string())),
+                None => parsed.errors.push(EntryError {
+                    line: line_no,
+                    key: None,
+                    message: format!("tag `{tag}` outside a record"),
+                }),
+            },
+            None if line.trim().is_empty() => {}
+            None => match current.as_mut().and_then(|r| r.tags.last_mut()) {
+                Some((_, value)) => {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+                None => parsed.errors.push(EntryError { line: line_no, key: None, message: format!("unexpected line `{}`", line.trim()) }),
+            },
+        }
+    }
+    if let Some(open) = current {
+        parsed.errors.push(EntryError { line: open.line, key: None, message: "record not closed with `ER`".to_string() });
+    }
+
+    for record in &records {
+        match to_reference(record) {
+            Ok(r) => parsed.references.push(r),
+            Err(message) => {
+                let key = record.tags.iter().find(|(t, _)| t == "ID").map(|(_, v)| v.clone());
+                parsed.errors.push(EntryError { line: record.line, key, message });
+            }
+        }
+    }
+    parsed.errors.sort_by_key(|e| e.line);
+    parsed
+}
+
+pub fn import_str(arm: &AcademicResourceManager, input: &str) -> Result<ImportReport, SourceError> {
+    import_parsed(arm, parse(input))
+}
+
+pub fn import_file(arm: &AcademicResourceManager, path: impl AsRef<Path>) -> Result<ImportReport, SourceError> {
+    let input = std::fs::read_to_string(path)?;
+    import_str(arm, &input)
+}
+
+// ---- export ----
+
+// `Last, First, Suffix`
+fn format_name(author: &Author) -> String {
+    let name = &author.name;
+    let given = name.given();
+    match (given.is_empty(), &name.suffix) {
+        (true, None) => name.family(),
+        (_, Some(suffix)) => format!("{}, {}, {}", name.family(), given, suffix),
+        (false, None) => format!("{}, {}", name.family(), given),
+    }
+}
+
+/// Renders one reference as an RIS record, `key` going into `ID`.
+pub fn format_record(reference: &Reference, key: &str) -> String {
+    let unknown: BTreeMap<String, Vec<String>> = reference
+        .extra
+        .get(RIS_PROPS)
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    let mut lines: Vec<(String, String)> = Vec::new();
+    let mut put = |tag: &str, value: &str| {
+        if !value.is_empty() {
+            lines.push((tag.to_string(), value.to_string()));
+        }
+    };
+
+    let ty = unknown.get("TY").and_then(|t| t.first()).map_or(ris_type(&reference.kind), String::as_str);
+    put("TY", ty);
+    put("ID", key);
+    for author in &reference.authors {
+        put("AU", &format_name(author));
+    }
+    for editor in &reference.editors {
+        put("A2", &format_name(editor));
+    }
+    put("TI", &reference.title);
+    if let Some(venue) = &reference.venue {
+        put("T2", venue);
+    }
+    if let Some(year) = reference.year {
+        put("PY", &year.to_string());
+        if let Some(month) = reference.field("month").and_then(month_number) {
+            let day = reference.field("day").and_then(|d| d.parse::<u32>().ok());
+            let day = day.map(|d| format!("{d:02}")).unwrap_or_default();
+            put("DA", &format!("{year:04}/{month:02}/{day}/"));
+        }
+    }
+    for (tag, field) in FIELD_TAGS {
+        if let Some(value) = reference.field(field) {
+            put(tag, value);
+        }
+    }
+    if let Some(pages) = reference.field("pages") {
+        match pages.split_once(['-', '–']) {
+            Some((start, end)) => {
+                put("SP", start.trim_end_matches('-').trim());
+                put("EP", end.trim_start_matches('-').trim());
+            }
+            None => put("SP", pages),
+        }
+    }
+    if let Some(sn) = reference.field("isbn").or(reference.field("issn")) {
+        put("SN", sn);
+    }
+    if let Some(url) = reference.field("url").or(reference.uri.as_deref().filter(|u| !u.starts_with("https://doi.org/"))) {
+        put("UR", url);
+    }
+    for keyword in &reference.keywords {
+        put("KW", keyword);
+    }
+    for (tag, values) in &unknown {
+        if tag == "TY" {
+            continue;
+        }
+        for value in values {
+            put(tag, value);
+        }
+    }
+
+    let mut out = String::new();
+    for (tag, value) in &lines {
+        out.push_str(&format!("{tag}  - {value}\n"));
+    }
+    out.push_str("ER  - \n");
+    out
+}
+
+/// Exports the works among `ids` as an RIS file.
+pub fn export(arm: &AcademicResourceManager, ids: &[String], format: &KeyFormat) -> Result<String, SourceError> {
+    let records: Vec<String> = keyed_references(arm, ids, format)?
+        .iter()
+        .map(|(key, reference)| format_record(reference, key))
+        .collect();
+    Ok(records.join("\n"))
+}
+
+pub fn export_file(
+    arm: &AcademicResourceManager,
+    ids: &[String],
+    format: &KeyFormat,
+    path: impl AsRef<Path>,
+) -> Result<(), SourceError> {
+    std::fs::write(path, export(arm, ids, format)?)?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Engine;
+    use serde_json::json;
+
+    const SAMPLE: &str = "\u{feff}TY  - JOUR
+AU  - Smith, Jane H.
+AU  - van Beethoven, Ludwig
+AU  - World Health Organization
+TI  - The RNA World
+T2  - Physical Review Letters
+PY  - 2020///
+DA  - 2020/03/15/
+VL  - 12
+IS  - 3
+SP  - 101
+EP  - 120
+DO  - 10.1000/XYZ.1
+KW  - origin of life
+KW  - RNA
+AB  - A long abstract
+  that continues here.
+AN  - WOS:000123456
+C1  - Some address
+C1  - Another address
+ER  -
+
+TY  - MGZN
+TI  - Popular Science
+PY  - soon
+ER  -
+
+TY  - BOOK
+ID  - knuth1997
+AU  - Knuth, Donald E.
+TI  - The Art of Computer Programming
+PY  - 1997
+SN  - 978-0-201-89683-1
+PB  - Addison-Wesley
+ER  -
+";
+
+    #[test]
+    fn test_parse_sample() {
+        let parsed = parse(SAMPLE);
+        assert_eq!(parsed.references.len(), 2);
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].line, 23);
+
+        let article = &parsed.references[0];
+        assert_eq!(article.kind, JOURNAL_ARTICLE);
+        assert_eq!(article.year, Some(2020));
+        assert_eq!(article.field("month"), Some("March"));
+        assert_eq!(article.field("pages"), Some("101-120"));
+        assert_eq!(article.field("number"), Some("3"));
+        assert_eq!(article.field("abstract"), Some("A long abstract that continues here."));
+        assert_eq!(article.venue.as_deref(), Some("Physical Review Letters"));
+        assert_eq!(article.keywords, vec!["origin of life", "RNA"]);
+        assert_eq!(article.authors.len(), 3);
+        assert_eq!(article.authors[1].name.last, "Beethoven");
+        assert_eq!(article.authors[1].name.particle.as_deref(), Some("van"));
+        assert_eq!(article.authors[2].name.last, "World Health Organization");
+        assert_eq!(
+            article.extra.get(RIS_PROPS),
+            Some(&json!({"AN": ["WOS:000123456"], "C1": ["Some address", "Another address"]}))
+        );
+
+        let book = &parsed.references[1];
+        assert_eq!(book.key.as_deref(), Some("knuth1997"));
+        assert_eq!(book.kind, BOOK);
+        assert_eq!(book.field("isbn"), Some("978-0-201-89683-1"));
+    }
+
+    #[test]
+    fn test_unclosed_record_is_reported_and_dropped() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let input = "TY  - JOUR\nTI  - First\nTY  - BOOK\nTI  - Second\nER  -\nTY  - JOUR\nTI  - Third\n";
+        let report = import_str(&arm, input).unwrap();
+        assert_eq!(report.errors.iter().map(|e| e.line).collect::<Vec<_>>(), [1, 6]);
+        assert_eq!(report.imported.len(), 1);
+        let stored = arm.get_entity(&report.imported[0]).unwrap().unwrap();
+        assert_eq!(stored.title, "Second");
+    }
+
+    #[test]
+    fn test_roundtrip_keeps_unknown_tags() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let report = import_str(&arm, SAMPLE).unwrap();
+        assert_eq!(arm.entities_with_tag("RNA").unwrap().len(), 1);
+        let stored = arm.get_entity(&report.imported[0]).unwrap().unwrap();
+        assert_eq!(stored.props.unwrap()[RIS_PROPS]["AN"], json!(["WOS:000123456"]));
+
+        let out = export(&arm, &report.imported, &KeyFormat::default()).unwrap();
+        assert!(out.contains("AN  - WOS:000123456\n"), "{out}");
+        assert!(out.contains("SP  - 101\nEP  - 120\n"), "{out}");
+        assert!(out.contains("DA  - 2020/03/15/\n"), "{out}");
+
+        let back = parse(&out);
+        assert!(back.errors.is_empty(), "{:?}", back.errors);
+        let mut original = parse(SAMPLE);
+        original.references.iter_mut().for_each(|r| r.keywords.sort());
+        for (b, o) in back.references.iter().zip(&original.references) {
+            assert_eq!(b.title, o.title);
+            assert_eq!(b.authors, o.authors);
+            assert_eq!(b.fields, o.fields);
+            assert_eq!(b.keywords, o.keywords);
+            assert_eq!(b.extra, o.extra);
+        }
+
+        // a non-canonical type survives the round trip
+        let report = import_str(&arm, "TY  - MGZN\nTI  - Popular\nPY  - 2001\nER  - \n").unwrap();
+        let out = export(&arm, &report.imported, &KeyFormat::default()).unwrap();
+        assert!(out.starts_with("TY  - MGZN\n"), "{out}");
+    }
+}
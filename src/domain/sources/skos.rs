@@ -0,0 +1,557 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use crate::database::AcademicResourceManager;
+use crate::domain::sources::SourceError;
+use crate::domain::topic::Topic;
+
+const SKOS: &str = "http://www.w3.org/2004/02/skos/core#";
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+// Language whose preferred labels become topic labels; labels in other languages are synonyms.
+const LANGUAGE: &str = "en";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Iri(String),
+    Blank(String),
+    Literal { value: String, lang: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Iri(String),
+    Prefixed(String, String),
+    Blank(String),
+    Literal { value: String, lang: Option<String> },
+    // `a`, numbers, booleans and directives
+    Word(String),
+    Punct(char),
+}
+
+fn error(line: usize, message: impl std::fmt::Display) -> SourceError {
+    SourceError::Format(format!("line {line}: {message}"))
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl Lexer<'_> {
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn string(&mut self, quote: char) -> Result<String, SourceError> {
+        let start = self.line;
+        let mut long = false;
+        if self.chars.peek() == Some(&quote) {
+            self.bump();
+            if self.chars.peek() != Some(&quote) {
+                return Ok(String::new());
+            }
+            self.bump();
+            long = true;
+        }
+        let mut value = String::new();
+        loop {
+            let Some(c) = self.bump() else {
+                return Err(error(start, "unterminated string"));
+            };
+            match c {
+                '\\' => match self.bump() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('r') => value.push('\r'),
+                    Some('u') => value.push(self.unicode(4)?),
+                    Some('U') => value.push(self.unicode(8)?),
+                    Some(other) => value.push(other),
+                    None => return Err(error(start, "unterminated string")),
+                },
+                c if c == quote && !long => return Ok(value),
+                c if c == quote => {
+                    let mut run = 1;
+                    while run < 3 && self.chars.peek() == Some(&quote) {
+                        self.bump();
+                        run += 1;
+                    }
+                    if run == 3 {
+                        return Ok(value);
+                    }
+                    value.extend(std::iter::repeat_n(quote, run));
+                }
+                '\n' if !long => return Err(error(start, "newline in string")),
+                c => value.push(c),
+            }
+        }
+    }
+
+    fn unicode(&mut self, digits: usize) -> Result<char, SourceError> {
+        let hex: String = (0..digits).filter_map(|_| self.bump()).collect();
+        u32::from_str_radix(&hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| error(self.line, format!("bad escape `\\u{hex}`")))
+    }
+
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(&c) = self.chars.peek() {
+            // a dot inside a name belongs to it, a final one ends the statement
+            let ends = c.is_whitespace() || matches!(c, ';' | ',' | '(' | ')' | '[' | ']' | '<' | '"' | '#');
+            if ends || (c == '.' && !word.is_empty() && self.dot_ends_word()) {
+                break;
+            }
+            word.push(c);
+            self.bump();
+        }
+        word
+    }
+
+    fn dot_ends_word(&self) -> bool {
+        let mut rest = self.chars.clone();
+        rest.next();
+        rest.next().is_none_or(|c| c.is_whitespace() || c == '#')
+    }
+
+    fn next(&mut self) -> Result<Option<(usize, Token)>, SourceError> {
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.bump();
+            } else if c == '#' {
+                while self.chars.peek().is_some_and(|c| *c != '\n') {
+                    self.bump();
+                }
+            } else {
+                break;
+            }
+        }
+        let line = self.line;
+        let Some(c) = self.bump() else {
+            return Ok(None);
+        };
+        let token = match c {
+            '<' => {
+                let mut iri = String::new();
+                loop {
+                    match self.bump() {
+                        Some('>') => break,
+                        Some(c) => iri.push(c),
+                        None => return Err(error(line, "unterminated IRI")),
+                    }
+                }
+                Token::Iri(iri)
+            }
+            '"' | '\'' => {
+                let value = self.string(c)?;
+                let lang = match self.chars.peek() {
+                    Some('@') => {
+                        self.bump();
+                        Some(self.word().to_lowercase())
+                    }
+                    Some('^') => {
+                        self.bump();
+                        self.bump();
+                        // the datatype is read and dropped
+                        if self.chars.peek() == Some(&'<') {
+                            self.next()?;
+                        } else {
+                            self.word();
+                        }
+                        None
+                    }
+                    _ => None,
+                };
+                Token::Literal { value, lang }
+            }
+            '.' | ';' | ',' | '(' | ')' | '[' | ']' => Token::Punct(c),
+            '@' => Token::Word(format!("@{}", self.word())),
+            c => {
+                let word = format!("{c}{}", self.word());
+                match word.split_once(':') {
+                    Some(("_", label)) => Token::Blank(label.to_string()),
+                    Some((prefix, local)) if !word.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+') => {
+                        Token::Prefixed(prefix.to_string(), local.replace('\\', ""))
+                    }
+                    _ => Token::Word(word),
+                }
+            }
+        };
+        Ok(Some((line, token)))
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    prefixes: BTreeMap<String, String>,
+    base: String,
+    blanks: usize,
+    triples: Vec<(Term, String, Term)>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos).or(self.tokens.last()).map_or(1, |(l, _)| *l)
+    }
+
+    fn take(&mut self) -> Result<Token, SourceError> {
+        let token = self.tokens.get(self.pos).map(|(_, t)| t.clone());
+        self.pos += 1;
+        token.ok_or_else(|| error(self.line(), "unexpected end of input"))
+    }
+
+    fn expect(&mut self, punct: char) -> Result<(), SourceError> {
+        let line = self.line();
+        match self.take()? {
+            Token::Punct(c) if c == punct => Ok(()),
+            other => Err(error(line, format!("expected `{punct}`, found {other:?}"))),
+        }
+    }
+
+    fn iri(&self, iri: &str) -> String {
+        match iri.contains(':') {
+            true => iri.to_string(),
+            false => format!("{}{iri}", self.base),
+        }
+    }
+
+    fn resolve(&self, prefix: &str, local: &str) -> Result<String, SourceError> {
+        match self.prefixes.get(prefix) {
+            Some(ns) => Ok(format!("{ns}{local}")),
+            None => Err(error(self.line(), format!("unknown prefix `{prefix}:`"))),
+        }
+    }
+
+    fn fresh_blank(&mut self) -> Term {
+        self.blanks += 1;
+        Term::Blank(format!("b{}", self.blanks))
+    }
+
+    fn statement(&mut self) -> Result<(), SourceError> {
+        let line = self.line();
+        match self.peek() {
+            Some(Token::Word(w)) if w == "@prefix" || w.eq_ignore_ascii_case("prefix") => {
+                let turtle = w.starts_with('@');
+                self.pos += 1;
+                let prefix = match self.take()? {
+                    Token::Prefixed(prefix, local) if local.is_empty() => prefix,
+                    other => return Err(error(line, format!("expected a prefix name, found {other:?}"))),
+                };
+                let Token::Iri(iri) = self.take()? else {
+                    return Err(error(line, "expected the prefix IRI"));
+                };
+                let iri = self.iri(&iri);
+                self.prefixes.insert(prefix, iri);
+                if turtle {
+                    self.expect('.')?;
+                }
+            }
+            Some(Token::Word(w)) if w == "@base" || w.eq_ignore_ascii_case("base") => {
+                let turtle = w.starts_with('@');
+                self.pos += 1;
+                let Token::Iri(iri) = self.take()? else {
+                    return Err(error(line, "expected the base IRI"));
+                };
+                self.base = iri;
+                if turtle {
+                    self.expect('.')?;
+                }
+            }
+            _ => {
+                let subject = self.subject()?;
+                // `[ ... ] .` may stand alone
+                if !(matches!(subject, Term::Blank(_)) && self.peek() == Some(&Token::Punct('.'))) {
+                    self.predicate_objects(&subject)?;
+                }
+                self.expect('.')?;
+            }
+        }
+        Ok(())
+    }
+
+    fn subject(&mut self) -> Result<Term, SourceError> {
+        let line = self.line();
+        match self.take()? {
+            Token::Iri(iri) => Ok(Term::Iri(self.iri(&iri))),
+            Token::Prefixed(prefix, local) => Ok(Term::Iri(self.resolve(&prefix, &local)?)),
+            Token::Blank(label) => Ok(Term::Blank(label)),
+            Token::Punct('[') => self.blank_node(),
+            Token::Punct('(') => self.collection(),
+            other => Err(error(line, format!("unexpected {other:?}"))),
+        }
+    }
+
+    // After `[`: a blank node with its own predicates.
+    fn blank_node(&mut self) -> Result<Term, SourceError> {
+        let node = self.fresh_blank();
+        if self.peek() != Some(&Token::Punct(']')) {
+            self.predicate_objects(&node)?;
+        }
+        self.expect(']')?;
+        Ok(node)
+    }
+
+    // After `(`: list members are read and dropped, SKOS does not use them.
+    fn collection(&mut self) -> Result<Term, SourceError> {
+        while self.peek() != Some(&Token::Punct(')')) {
+            self.object()?;
+        }
+        self.expect(')')?;
+        Ok(self.fresh_blank())
+    }
+
+    fn predicate_objects(&mut self, subject: &Term) -> Result<(), SourceError> {
+        loop {
+            let line = self.line();
+            let predicate = match self.take()? {
+                Token::Iri(iri) => self.iri(&iri),
+                Token::Prefixed(prefix, local) => self.resolve(&prefix, &local)?,
+                Token::Word(w) if w == "a" => RDF_TYPE.to_string(),
+                other => return Err(error(line, format!("expected a predicate, found {other:?}"))),
+            };
+            loop {
+                let object = self.object()?;
+                self.triples.push((subject.clone(), predicate.clone(), object));
+                if self.peek() != Some(&Token::Punct(',')) {
+                    break;
+                }
+                self.pos += 1;
+            }
+            // `;` may repeat and may end the list
+            let mut more = false;
+            while self.peek() == Some(&Token::Punct(';')) {
+                self.pos += 1;
+                more = true;
+            }
+            if !more || matches!(self.peek(), Some(Token::Punct('.' | ']')) | None) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Term, SourceError> {
+        let line = self.line();
+        match self.take()? {
+            Token::Iri(iri) => Ok(Term::Iri(self.iri(&iri))),
+            Token::Prefixed(prefix, local) => Ok(Term::Iri(self.resolve(&prefix, &local)?)),
+            Token::Blank(label) => Ok(Term::Blank(label)),
+            Token::Literal { value, lang } => Ok(Term::Literal { value, lang }),
+            Token::Word(w) if w != "a" && !w.starts_with('@') => Ok(Term::Literal { value: w, lang: None }),
+            Token::Punct('[') => self.blank_node(),
+            Token::Punct('(') => self.collection(),
+            other => Err(error(line, format!("expected an object, found {other:?}"))),
+        }
+    }
+}
+
+// Subject, predicate and object of every triple in a Turtle document.
+fn triples(input: &str) -> Result<Vec<(Term, String, Term)>, SourceError> {
+    let mut lexer = Lexer { chars: input.trim_start_matches('\u{feff}').chars().peekable(), line: 1 };
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next()? {
+        tokens.push(token);
+    }
+    let mut parser = Parser { tokens, pos: 0, prefixes: BTreeMap::new(), base: String::new(), blanks: 0, triples: Vec::new() };
+    while parser.pos < parser.tokens.len() {
+        parser.statement()?;
+    }
+    Ok(parser.triples)
+}
+
+#[derive(Default)]
+struct Concept {
+    typed: bool,
+    labels: Vec<(String, Option<String>)>,
+    alt_labels: Vec<String>,
+    broader: Vec<String>,
+    scheme: Option<String>,
+}
+
+// The concept for `iri`, remembering the order concepts first appear in.
+fn concept<'a>(concepts: &'a mut BTreeMap<String, Concept>, order: &mut Vec<String>, iri: &str) -> &'a mut Concept {
+    if !concepts.contains_key(iri) {
+        order.push(iri.to_string());
+    }
+    concepts.entry(iri.to_string()).or_default()
+}
+
+/// Reads the `skos:Concept`s of a Turtle vocabulary (ACM CCS, a MeSH subset, ...) as topics.
+/// The English or untagged `skos:prefLabel` is the label, other preferred labels, `altLabel`s
+/// and `hiddenLabel`s are synonyms. `broader` and `narrower` both end up as broader labels;
+/// links to concepts the document does not define are dropped.
+pub fn parse(input: &str) -> Result<Vec<Topic>, SourceError> {
+    let skos = |name: &str| format!("{SKOS}{name}");
+    let mut concepts: BTreeMap<String, Concept> = BTreeMap::new();
+    let mut order: Vec<String> = Vec::new();
+    let mut narrower: Vec<(String, String)> = Vec::new();
+    for (subject, predicate, object) in triples(input)? {
+        let Term::Iri(subject) = subject else {
+            continue;
+        };
+        if predicate == RDF_TYPE && object == Term::Iri(skos("Concept")) {
+            concept(&mut concepts, &mut order, &subject).typed = true;
+            continue;
+        }
+        let Some(name) = predicate.strip_prefix(SKOS) else {
+            continue;
+        };
+        let entry = concept(&mut concepts, &mut order, &subject);
+        match (name, object) {
+            ("prefLabel", Term::Literal { value, lang }) => entry.labels.push((value, lang)),
+            ("altLabel" | "hiddenLabel", Term::Literal { value, .. }) => entry.alt_labels.push(value),
+            ("broader", Term::Iri(iri)) => entry.broader.push(iri),
+            ("narrower", Term::Iri(iri)) => narrower.push((iri, subject.clone())),
+            ("inScheme", Term::Iri(iri)) => entry.scheme = Some(iri),
+            _ => {}
+        }
+    }
+    for (child, parent) in narrower {
+        if let Some(concept) = concepts.get_mut(&child) {
+            concept.broader.push(parent);
+        }
+    }
+
+    let label = |iri: &str| -> Option<String> {
+        let concept = concepts.get(iri)?;
+        let preferred = concept
+            .labels
+            .iter()
+            .find(|(_, lang)| lang.as_deref().is_some_and(|l| l == LANGUAGE || l.starts_with(&format!("{LANGUAGE}-"))))
+            .or_else(|| concept.labels.iter().find(|(_, lang)| lang.is_none()))
+            .or(concept.labels.first());
+        Some(match preferred {
+            Some((value, _)) => value.clone(),
+            None => iri.rsplit(['/', '#']).next().unwrap_or(iri).to_string(),
+        })
+    };
+    // concepts the document defines, as opposed to ones it only links to
+    let defined = |iri: &str| concepts.get(iri).is_some_and(|c| c.typed || !c.labels.is_empty());
+    let mut topics = Vec::new();
+    for iri in order.iter().filter(|iri| defined(iri)) {
+        let (Some(concept), Some(preferred)) = (concepts.get(iri), label(iri)) else {
+            continue;
+        };
+        let mut alt_labels: Vec<String> = Vec::new();
+        for value in concept.labels.iter().map(|(v, _)| v).chain(&concept.alt_labels) {
+            if *value != preferred && !alt_labels.contains(value) {
+                alt_labels.push(value.clone());
+            }
+        }
+        let mut broader: Vec<String> = Vec::new();
+        for parent in concept.broader.iter().filter(|p| defined(p)).filter_map(|p| label(p)) {
+            if !broader.contains(&parent) {
+                broader.push(parent);
+            }
+        }
+        topics.push(Topic { label: preferred, alt_labels, broader, uri: Some(iri.clone()), scheme: concept.scheme.clone() });
+    }
+    Ok(topics)
+}
+
+/// Saves the topics of a SKOS vocabulary, returning their ids. Preferred labels are saved
+/// first, so an alternative label never stands in for a concept later in the file.
+pub fn import_str(arm: &AcademicResourceManager, input: &str) -> Result<Vec<String>, SourceError> {
+    let topics = parse(input)?;
+    for topic in &topics {
+        arm.save_topic(&Topic { alt_labels: Vec::new(), broader: Vec::new(), ..topic.clone() })?;
+    }
+    topics.iter().map(|topic| arm.save_topic(topic).map_err(SourceError::from)).collect()
+}
+
+pub fn import_file(arm: &AcademicResourceManager, path: impl AsRef<Path>) -> Result<Vec<String>, SourceError> {
+    let input = std::fs::read_to_string(path)?;
+    import_str(arm, &input)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::topics::topic_id;
+    use crate::database::Engine;
+
+    const VOCABULARY: &str = r#"
+        @prefix skos: <http://www.w3.org/2004/02/skos/core#> .
+        PREFIX ccs: <https://dl.acm.org/ccs#>
+
+        ccs:10002950 a skos:Concept ;
+            skos:prefLabel "Mathematics of computing"@en ;
+            skos:inScheme <https://dl.acm.org/ccs> ;
+            skos:narrower ccs:10003648 .
+
+        ccs:10003648 a skos:Concept ;
+            skos:prefLabel "Probability and statistics"@en, "Probabilités et statistiques"@fr ;
+            skos:inScheme <https://dl.acm.org/ccs> .
+
+        ccs:10003649 a skos:Concept ; # a comment
+            skos:prefLabel "Stochastic methods" ;
+            skos:broader ccs:10003648, ccs:99999999 ;
+            skos:altLabel 'Stochastic processes' .
+
+        ccs:10003651 skos:prefLabel """Monte Carlo "simulations\""""@en-GB ;
+            skos:altLabel "MC" ;
+            skos:broader ccs:10003649 ;
+            skos:note [ a skos:Note ; skos:editorialNote "x" ] .
+    "#;
+
+    #[test]
+    fn test_parse_skos() {
+        let topics = parse(VOCABULARY).unwrap();
+        let labels: Vec<&str> = topics.iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, vec!["Mathematics of computing", "Probability and statistics", "Stochastic methods", "Monte Carlo \"simulations\""]);
+        assert_eq!(topics[1].broader, vec!["Mathematics of computing"]);
+        assert_eq!(topics[1].alt_labels, vec!["Probabilités et statistiques"]);
+        assert_eq!(topics[1].uri.as_deref(), Some("https://dl.acm.org/ccs#10003648"));
+        assert_eq!(topics[1].scheme.as_deref(), Some("https://dl.acm.org/ccs"));
+        // the undefined concept is dropped
+        assert_eq!(topics[2].broader, vec!["Probability and statistics"]);
+        assert_eq!(topics[2].alt_labels, vec!["Stochastic processes"]);
+        assert_eq!(topics[3].alt_labels, vec!["MC"]);
+
+        let Err(SourceError::Format(message)) = parse("ex:a skos:prefLabel \"x\" .") else {
+            panic!("unknown prefix accepted");
+        };
+        assert!(message.contains("unknown prefix `ex:`"), "{message}");
+    }
+
+    #[test]
+    fn test_import_expands_tags() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        assert_eq!(import_str(&arm, VOCABULARY).unwrap().len(), 4);
+        let work = "work:mc";
+        arm.upsert_entity(&crate::database::Entity::builder().id(work).kind("journal_article").title("Sampling").build().unwrap())
+            .unwrap();
+        arm.tag_topic(work, "MC").unwrap();
+        let found = arm.entities_with_topic("Mathematics of computing").unwrap();
+        assert_eq!(found.into_iter().map(|e| e.id).collect::<Vec<_>>(), vec![work.to_string()]);
+    }
+
+    #[test]
+    fn test_alt_label_of_another_concept() {
+        let learning = r#"ex:ml skos:prefLabel "Machine learning" ; skos:altLabel "Statistical learning" ."#;
+        let statistics = r#"ex:bo skos:prefLabel "Boosting" ; skos:broader ex:sl .
+            ex:sl skos:prefLabel "Statistical learning" ; skos:broader ex:st .
+            ex:st skos:prefLabel "Statistics" ."#;
+        for vocabulary in [[learning, statistics], [statistics, learning]] {
+            let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+            let input = format!("@prefix skos: <http://www.w3.org/2004/02/skos/core#> .\n@prefix ex: <https://example.org/> .\n{}\n{}", vocabulary[0], vocabulary[1]);
+            assert_eq!(import_str(&arm, &input).unwrap().len(), 4);
+
+            let sl = arm.find_topic("Statistical learning").unwrap().unwrap();
+            let topic = arm.load_topic(&sl).unwrap().unwrap();
+            assert_eq!((topic.label.as_str(), topic.uri.as_deref()), ("Statistical learning", Some("https://example.org/sl")));
+            assert_eq!(topic.broader, vec!["Statistics"]);
+            assert_eq!(arm.load_topic(&topic_id("Boosting")).unwrap().unwrap().broader, vec!["Statistical learning"]);
+            let ml = arm.load_topic(&topic_id("Machine learning")).unwrap().unwrap();
+            assert_eq!(ml.alt_labels, vec!["Statistical learning"]);
+        }
+    }
+}
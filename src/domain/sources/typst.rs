@@ -0,0 +1,146 @@
+use std::path::Path;
+use crate::database::AcademicResourceManager;
+use crate::domain::sources::hayagriva::format_document;
+use crate::domain::sources::keys::{keyed_references, KeyFormat};
+use crate::domain::sources::SourceError;
+
+fn is_label_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '.')
+}
+
+fn read_label(chars: &[char], start: usize) -> (String, usize) {
+    let end = chars[start..].iter().position(|c| !is_label_char(*c)).map_or(chars.len(), |n| start + n);
+    let label: String = chars[start..end].iter().collect();
+    // `see @smith2020.` - trailing punctuation ends the sentence, not the key
+    let trimmed = label.trim_end_matches(['.', ':']);
+    (trimmed.to_string(), start + trimmed.chars().count())
+}
+
+/// Citation keys referenced in Typst markup, in order of first use: `@key` references and
+/// `#cite(<key>)` / `#cite(label("key"))` calls. Comments and raw blocks are skipped, as is
+/// `@` inside a word (e-mail addresses).
+pub fn cited_keys(source: &str) -> Vec<String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut keys: Vec<String> = Vec::new();
+    let mut push = |key: String| {
+        if !key.is_empty() && !keys.contains(&key) {
+            keys.push(key);
+        }
+    };
+    let starts_with = |i: usize, s: &str| s.chars().enumerate().all(|(n, c)| chars.get(i + n) == Some(&c));
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if starts_with(i, "//") {
+            i = chars[i..].iter().position(|c| *c == '\n').map_or(chars.len(), |n| i + n);
+        } else if starts_with(i, "/*") {
+            i = (i + 2..chars.len()).find(|j| starts_with(*j, "*/")).map_or(chars.len(), |j| j + 2);
+        } else if c == '`' {
+            let fence = chars[i..].iter().take_while(|c| **c == '`').count();
+            let close: String = "`".repeat(fence);
+            let body = i + fence;
+            i = (body..chars.len()).find(|j| starts_with(*j, &close)).map_or(chars.len(), |j| j + fence);
+        } else if c == '@' && (i == 0 || !chars[i - 1].is_alphanumeric()) {
+            let (key, end) = read_label(&chars, i + 1);
+            push(key);
+            i = end.max(i + 1);
+        } else if starts_with(i, "cite(<") {
+            let (key, end) = read_label(&chars, i + 6);
+            push(key);
+            i = end;
+        } else if starts_with(i, "cite(label(\"") {
+            let start = i + 12;
+            let end = chars[start..].iter().position(|c| *c == '"').map_or(chars.len(), |n| start + n);
+            push(chars[start..end].iter().collect());
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    keys
+}
+
+/// Hayagriva bibliography with exactly the works cited in a Typst document, matched by their
+/// stored citation keys. Keys with no work in the graph are returned alongside; labels of
+/// headings or figures referenced with `@` end up there too.
+pub fn export_cited(
+    arm: &AcademicResourceManager,
+    source: &str,
+    format: &KeyFormat,
+) -> Result<(String, Vec<String>), SourceError> {
+    let known = arm.citation_keys()?;
+    let mut ids = Vec::new();
+    let mut missing = Vec::new();
+    for key in cited_keys(source) {
+        match known.get(&key) {
+            Some(id) => ids.push(id.clone()),
+            None => missing.push(key),
+        }
+    }
+    let yaml = format_document(&keyed_references(arm, &ids, format)?)?;
+    Ok((yaml, missing))
+}
+
+/// Reads `typ`, writes the bibliography of the works it cites to `bib` and returns the
+/// citation keys that could not be resolved.
+pub fn export_cited_file(
+    arm: &AcademicResourceManager,
+    typ: impl AsRef<Path>,
+    format: &KeyFormat,
+    bib: impl AsRef<Path>,
+) -> Result<Vec<String>, SourceError> {
+    let source = std::fs::read_to_string(typ)?;
+    let (yaml, missing) = export_cited(arm, &source, format)?;
+    std::fs::write(bib, yaml)?;
+    Ok(missing)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Engine;
+    use crate::domain::sources::hayagriva;
+
+    const DOC: &str = r#"
+= Introduction <intro>
+As shown by @quantum and @harry[p. 7], see also #cite(<talk>).
+Mail me at someone@example.org. // @commented
+/* @blocked
+   still a comment */
+Raw `@inline` and
+```
+@fenced
+```
+See @intro. Again @quantum: #cite(label("smith:2020.b")).
+"#;
+
+    #[test]
+    fn test_cited_keys() {
+        assert_eq!(cited_keys(DOC), vec!["quantum", "harry", "talk", "intro", "smith:2020.b"]);
+    }
+
+    #[test]
+    fn test_export_cited() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let bib = "
+harry:
+  type: book
+  title: Harry Potter
+  date: 2003
+quantum:
+  type: article
+  title: Quantum Things
+  date: 2020
+unused:
+  type: book
+  title: Not Cited
+";
+        hayagriva::import_str(&arm, bib).unwrap();
+        let (yaml, missing) = export_cited(&arm, DOC, &KeyFormat::default()).unwrap();
+        assert_eq!(missing, vec!["talk", "intro", "smith:2020.b"]);
+        let parsed = hayagriva::parse(&yaml).unwrap();
+        let keys: Vec<_> = parsed.references.iter().map(|r| r.key.clone().unwrap()).collect();
+        assert_eq!(keys, vec!["quantum", "harry"]);
+    }
+}
@@ -0,0 +1,858 @@
+use std::collections::HashMap;
+use std::path::Path;
+use roxmltree::{Document, Node};
+use serde_json::{json, Value};
+use sqlite::{Connection, OpenFlags, State, Statement};
+use crate::database::references::work_id;
+use crate::database::{AcademicResourceManager, Edge, Entity};
+use crate::domain::author::{Author, Name};
+use crate::domain::sources::{
+    EntryError, ImportReport, Reference, SourceError, BOOK, CHAPTER, CONFERENCE_PAPER, DATASET, JOURNAL_ARTICLE,
+    MISC, MONTHS, PREPRINT, REPORT, SOFTWARE, THESIS, WEBPAGE,
+};
+
+pub const COLLECTION_KIND: &str = "collection";
+pub const IN_COLLECTION: &str = "in_collection";
+pub const SUBCOLLECTION_OF: &str = "subcollection_of";
+
+// Props written on imported works.
+pub const ZOTERO_KEY: &str = "zotero_key";
+const ZOTERO_COLLECTIONS: &str = "zotero_collections";
+const ATTACHMENTS: &str = "attachments";
+const NOTES: &str = "notes";
+
+/// How Zotero collections end up in the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollectionMode {
+    /// One tag per collection, named by its path (`Parent/Child`).
+    #[default]
+    Tags,
+    /// `collection` entities linked by `subcollection_of`, works linked by `in_collection`.
+    Edges,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collection {
+    pub key: String,
+    pub name: String,
+    pub parent: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoteroItem {
+    pub key: String,
+    pub reference: Reference,
+    pub collections: Vec<String>,
+    pub attachments: Vec<String>,
+    pub notes: Vec<String>,
+}
+
+/// A Zotero library read from `zotero.sqlite` or an RDF export, before it touches the graph.
+#[derive(Debug, Default)]
+pub struct Library {
+    pub items: Vec<ZoteroItem>,
+    pub collections: Vec<Collection>,
+    pub errors: Vec<EntryError>,
+}
+
+impl Library {
+    // `Parent/Child` for every collection key
+    fn collection_paths(&self) -> HashMap<String, String> {
+        let by_key: HashMap<&str, &Collection> = self.collections.iter().map(|c| (c.key.as_str(), c)).collect();
+        let mut paths = HashMap::new();
+        for c in &self.collections {
+            let mut names = vec![c.name.as_str()];
+            let mut parent = c.parent.as_deref();
+            // parent chains in a corrupt library could loop
+            while let Some(p) = parent.and_then(|p| by_key.get(p)).filter(|_| names.len() <= self.collections.len()) {
+                names.push(p.name.as_str());
+                parent = p.parent.as_deref();
+            }
+            names.reverse();
+            paths.insert(c.key.clone(), names.join("/"));
+        }
+        paths
+    }
+}
+
+fn kind_for(item_type: &str) -> &'static str {
+    match item_type {
+        "journalArticle" | "magazineArticle" | "newspaperArticle" => JOURNAL_ARTICLE,
+        "conferencePaper" => CONFERENCE_PAPER,
+        "book" => BOOK,
+        "bookSection" | "encyclopediaArticle" | "dictionaryEntry" => CHAPTER,
+        "thesis" => THESIS,
+        "report" => REPORT,
+        "preprint" => PREPRINT,
+        "computerProgram" => SOFTWARE,
+        "dataset" => DATASET,
+        "webpage" | "blogPost" | "forumPost" => WEBPAGE,
+        _ => MISC,
+    }
+}
+
+// Item types that are not works of their own.
+fn is_child_type(item_type: &str) -> bool {
+    matches!(item_type, "attachment" | "note" | "annotation")
+}
+
+const VENUE_FIELDS: &[&str] = &[
+    "publicationTitle", "proceedingsTitle", "bookTitle", "websiteTitle", "blogTitle", "forumTitle",
+    "encyclopediaTitle", "dictionaryTitle", "programTitle",
+];
+
+// Zotero field names stored under a `Reference::fields` name; others are lowercased.
+const FIELD_NAMES: &[(&str, &str)] = &[
+    ("DOI", "doi"),
+    ("ISBN", "isbn"),
+    ("ISSN", "issn"),
+    ("issue", "number"),
+    ("reportNumber", "number"),
+    ("place", "address"),
+    ("abstractNote", "abstract"),
+    ("university", "school"),
+    ("thesisType", "type"),
+    ("reportType", "type"),
+    ("shortTitle", "shorttitle"),
+    ("journalAbbreviation", "shortjournal"),
+    ("numPages", "pagetotal"),
+];
+
+// `2020-03-15 March 15, 2020` in the database, plain dates in RDF; `00` marks an unknown part.
+fn parse_date(value: &str) -> Option<(i64, Option<u32>, Option<u32>)> {
+    let head = value.split_whitespace().next()?;
+    let mut parts = head.split(['-', '/']);
+    let year = parts.next()?.parse::<i64>().ok().filter(|y| *y > 0)?;
+    let month = parts.next().and_then(|m| m.parse::<u32>().ok()).filter(|m| (1..=12).contains(m));
+    let day = parts.next().and_then(|d| d.parse::<u32>().ok()).filter(|d| (1..=31).contains(d));
+    Some((year, month, day.filter(|_| month.is_some())))
+}
+
+fn creator(first: &str, last: &str) -> Result<Author, String> {
+    if last.trim().is_empty() {
+        return Err(format!("creator `{first}` without a last name"));
+    }
+    let name = Name::from_parts(last, first, None).map_err(|e| e.to_string())?;
+    Author::builder().name(name).and_then(|b| b.build()).map_err(|e| e.to_string())
+}
+
+fn is_author_role(role: &str) -> bool {
+    matches!(
+        role,
+        "author" | "programmer" | "artist" | "presenter" | "inventor" | "director" | "podcaster" | "performer"
+            | "interviewee" | "cartographer" | "composer"
+    )
+}
+
+// Zotero fields, creators as (role, first, last) and tags into a `Reference`.
+fn to_reference(
+    key: &str,
+    item_type: &str,
+    fields: &[(String, String)],
+    creators: &[(String, String, String)],
+    tags: &[String],
+) -> Result<Reference, String> {
+    let mut r = Reference {
+        kind: kind_for(item_type).to_string(),
+        keywords: tags.to_vec(),
+        ..Default::default()
+    };
+    for (role, first, last) in creators {
+        match role.as_str() {
+            "editor" => r.editors.push(creator(first, last)?),
+            role if is_author_role(role) => r.authors.push(creator(first, last)?),
+            _ => {}
+        }
+    }
+    for (name, value) in fields {
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        match name.as_str() {
+            "title" => r.title = value.to_string(),
+            "date" => {
+                let (year, month, day) = parse_date(value).ok_or_else(|| format!("invalid date `{value}`"))?;
+                r.year = Some(year);
+                if let Some(m) = month {
+                    r.fields.insert("month".to_string(), MONTHS[m as usize - 1].to_string());
+                }
+                if let Some(d) = day {
+                    r.fields.insert("day".to_string(), d.to_string());
+                }
+            }
+            "accessDate" => {
+                let date = value.split_whitespace().next().unwrap_or(value);
+                r.fields.insert("urldate".to_string(), date.to_string());
+            }
+            name if VENUE_FIELDS.contains(&name) => {
+                r.venue.get_or_insert_with(|| value.to_string());
+            }
+            name => {
+                let field = FIELD_NAMES
+                    .iter()
+                    .find(|(z, _)| *z == name)
+                    .map_or_else(|| name.to_lowercase(), |(_, f)| f.to_string());
+                r.fields.entry(field).or_insert_with(|| value.to_string());
+            }
+        }
+    }
+    r.uri = r
+        .field("url")
+        .map(str::to_string)
+        .or_else(|| r.doi().map(|d| format!("https://doi.org/{d}")));
+    r.extra.insert(ZOTERO_KEY.to_string(), json!(key));
+    Ok(r)
+}
+
+// ---- zotero.sqlite ----
+
+fn rows<T>(
+    conn: &Connection,
+    sql: &str,
+    mut row: impl FnMut(&Statement) -> Result<T, sqlite::Error>,
+) -> Result<Vec<T>, SourceError> {
+    let mut statement = conn.prepare(sql)?;
+    let mut out = Vec::new();
+    while let State::Row = statement.next()? {
+        out.push(row(&statement)?);
+    }
+    Ok(out)
+}
+
+/// Reads a copy of a local `zotero.sqlite` (Zotero keeps the live one locked). Items in the
+/// trash are skipped; attachment paths inside Zotero storage come back as `storage/KEY/file`.
+pub fn read_sqlite(path: impl AsRef<Path>) -> Result<Library, SourceError> {
+    let conn = Connection::open_with_flags(path, OpenFlags::new().with_read_only())?;
+
+    let items = rows(
+        &conn,
+        "SELECT i.itemID, i.key, t.typeName FROM items i JOIN itemTypes t USING (itemTypeID)
+         WHERE i.itemID NOT IN (SELECT itemID FROM deletedItems) ORDER BY i.itemID",
+        |s| Ok((s.read::<i64, _>(0)?, s.read::<String, _>(1)?, s.read::<String, _>(2)?)),
+    )?;
+    let mut fields: HashMap<i64, Vec<(String, String)>> = HashMap::new();
+    for (id, name, value) in rows(
+        &conn,
+        "SELECT d.itemID, f.fieldName, v.value FROM itemData d JOIN fields f USING (fieldID)
+         JOIN itemDataValues v USING (valueID)",
+        |s| Ok((s.read::<i64, _>(0)?, s.read::<String, _>(1)?, s.read::<String, _>(2)?)),
+    )? {
+        fields.entry(id).or_default().push((name, value));
+    }
+    let mut creators: HashMap<i64, Vec<(String, String, String)>> = HashMap::new();
+    for (id, role, first, last, mode) in rows(
+        &conn,
+        "SELECT ic.itemID, ct.creatorType, c.firstName, c.lastName, c.fieldMode FROM itemCreators ic
+         JOIN creators c USING (creatorID) JOIN creatorTypes ct USING (creatorTypeID)
+         ORDER BY ic.itemID, ic.orderIndex",
+        |s| {
+            Ok((
+                s.read::<i64, _>(0)?,
+                s.read::<String, _>(1)?,
+                s.read::<Option<String>, _>(2)?.unwrap_or_default(),
+                s.read::<Option<String>, _>(3)?.unwrap_or_default(),
+                s.read::<Option<i64>, _>(4)?.unwrap_or(0),
+            ))
+        },
+    )? {
+        // fieldMode 1: single-field name (institutions, mononyms), kept in lastName
+        let first = if mode == 1 { String::new() } else { first };
+        creators.entry(id).or_default().push((role, first, last));
+    }
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    for (id, name) in rows(
+        &conn,
+        "SELECT it.itemID, t.name FROM itemTags it JOIN tags t USING (tagID) ORDER BY t.name",
+        |s| Ok((s.read::<i64, _>(0)?, s.read::<String, _>(1)?)),
+    )? {
+        tags.entry(id).or_default().push(name);
+    }
+    let mut attachments: HashMap<i64, Vec<String>> = HashMap::new();
+    for (parent, path, key) in rows(
+        &conn,
+        "SELECT a.parentItemID, a.path, i.key FROM itemAttachments a JOIN items i ON i.itemID = a.itemID
+         WHERE a.parentItemID IS NOT NULL AND a.path IS NOT NULL ORDER BY a.itemID",
+        |s| Ok((s.read::<i64, _>(0)?, s.read::<String, _>(1)?, s.read::<String, _>(2)?)),
+    )? {
+        let path = match path.strip_prefix("storage:") {
+            Some(file) => format!("storage/{key}/{file}"),
+            None => path,
+        };
+        attachments.entry(parent).or_default().push(path);
+    }
+    let mut notes: HashMap<i64, Vec<String>> = HashMap::new();
+    for (parent, note) in rows(
+        &conn,
+        "SELECT parentItemID, note FROM itemNotes WHERE parentItemID IS NOT NULL ORDER BY itemID",
+        |s| Ok((s.read::<i64, _>(0)?, s.read::<Option<String>, _>(1)?.unwrap_or_default())),
+    )? {
+        notes.entry(parent).or_default().push(note);
+    }
+
+    let collection_rows = rows(
+        &conn,
+        "SELECT c.collectionID, c.key, c.collectionName, p.key FROM collections c
+         LEFT JOIN collections p ON p.collectionID = c.parentCollectionID ORDER BY c.collectionID",
+        |s| Ok((s.read::<i64, _>(0)?, s.read::<String, _>(1)?, s.read::<String, _>(2)?, s.read::<Option<String>, _>(3)?)),
+    )?;
+    let collection_keys: HashMap<i64, String> = collection_rows.iter().map(|(id, key, _, _)| (*id, key.clone())).collect();
+    let mut item_collections: HashMap<i64, Vec<String>> = HashMap::new();
+    for (collection, item) in rows(
+        &conn,
+        "SELECT collectionID, itemID FROM collectionItems ORDER BY collectionID",
+        |s| Ok((s.read::<i64, _>(0)?, s.read::<i64, _>(1)?)),
+    )? {
+        if let Some(key) = collection_keys.get(&collection) {
+            item_collections.entry(item).or_default().push(key.clone());
+        }
+    }
+
+    let mut library = Library {
+        collections: collection_rows
+            .into_iter()
+            .map(|(_, key, name, parent)| Collection { key, name, parent })
+            .collect(),
+        ..Default::default()
+    };
+    for (id, key, item_type) in items {
+        if is_child_type(&item_type) {
+            continue;
+        }
+        let result = to_reference(
+            &key,
+            &item_type,
+            fields.get(&id).map_or(&[][..], Vec::as_slice),
+            creators.get(&id).map_or(&[][..], Vec::as_slice),
+            tags.get(&id).map_or(&[][..], Vec::as_slice),
+        );
+        match result {
+            Ok(reference) => library.items.push(ZoteroItem {
+                key,
+                reference,
+                collections: item_collections.remove(&id).unwrap_or_default(),
+                attachments: attachments.remove(&id).unwrap_or_default(),
+                notes: notes.remove(&id).unwrap_or_default(),
+            }),
+            // no line numbers in a database; the item id locates the row
+            Err(message) => library.errors.push(EntryError { line: id as usize, key: Some(key), message }),
+        }
+    }
+    Ok(library)
+}
+
+// ---- Zotero RDF ----
+
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+
+fn about<'a>(node: Node<'a, '_>) -> Option<&'a str> {
+    node.attribute((RDF_NS, "about"))
+}
+
+fn resource<'a>(node: Node<'a, '_>) -> Option<&'a str> {
+    node.attribute((RDF_NS, "resource"))
+}
+
+fn elements<'a, 'i>(node: Node<'a, 'i>) -> impl Iterator<Item = Node<'a, 'i>> {
+    node.children().filter(Node::is_element)
+}
+
+fn child<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    elements(node).find(|n| n.tag_name().name() == name)
+}
+
+fn text(node: Node) -> Option<String> {
+    let t = node.text()?.trim();
+    (!t.is_empty()).then(|| t.to_string())
+}
+
+// `http://zotero.org/users/1/items/ABCD2345` -> `ABCD2345`, `#item_12` -> `item_12`
+fn key_of(uri: &str) -> String {
+    uri.rsplit(['/', '#']).next().unwrap_or(uri).to_string()
+}
+
+// Resolves `rdf:resource` references to the top-level description they point at.
+fn target<'a, 'i>(node: Node<'a, 'i>, index: &HashMap<&str, Node<'a, 'i>>) -> Option<Node<'a, 'i>> {
+    match resource(node) {
+        Some(r) => index.get(r).copied(),
+        None => elements(node).next(),
+    }
+}
+
+const RDF_CREATORS: &[(&str, &str)] = &[
+    ("authors", "author"),
+    ("editors", "editor"),
+    ("programmers", "programmer"),
+    ("presenters", "presenter"),
+    ("inventors", "inventor"),
+    ("directors", "director"),
+    ("artists", "artist"),
+    ("contributors", "contributor"),
+    ("translators", "translator"),
+    ("seriesEditors", "seriesEditor"),
+];
+
+// Properties of one RDF description as Zotero field names.
+fn rdf_fields(node: Node, index: &HashMap<&str, Node>, fields: &mut Vec<(String, String)>) {
+    let mut put = |name: &str, value: Option<String>| {
+        if let Some(v) = value {
+            fields.push((name.to_string(), v));
+        }
+    };
+    for prop in elements(node) {
+        match prop.tag_name().name() {
+            "title" => put("title", text(prop)),
+            "date" => put("date", text(prop)),
+            "abstract" => put("abstractNote", text(prop)),
+            "pages" => put("pages", text(prop)),
+            "volume" => put("volume", text(prop)),
+            "number" => put("issue", text(prop)),
+            "edition" => put("edition", text(prop)),
+            "language" => put("language", text(prop)),
+            "shortTitle" => put("shortTitle", text(prop)),
+            "alternative" => put("journalAbbreviation", text(prop)),
+            "type" => put("thesisType", text(prop)),
+            "extra" | "description" => put("extra", text(prop)),
+            "libraryCatalog" | "rights" | "archive" | "callNumber" => put(prop.tag_name().name(), text(prop)),
+            "identifier" => match text(prop) {
+                Some(id) => match id.split_once(' ') {
+                    Some((scheme @ ("DOI" | "ISBN" | "ISSN"), value)) => put(scheme, Some(value.trim().to_string())),
+                    _ => put("identifier", Some(id)),
+                },
+                // <dc:identifier><dcterms:URI><rdf:value>https://...</rdf:value></dcterms:URI></dc:identifier>
+                None => put("url", elements(prop).next().and_then(|u| child(u, "value")).and_then(text)),
+            },
+            "publisher" => {
+                if let Some(org) = target(prop, index) {
+                    put("publisher", child(org, "name").and_then(text));
+                    let locality = org.descendants().find(|n| n.tag_name().name() == "locality");
+                    put("place", locality.and_then(text));
+                }
+            }
+            "presentedAt" => {
+                if let Some(conference) = target(prop, index) {
+                    put("conferenceName", child(conference, "title").and_then(text));
+                }
+            }
+            "isPartOf" => {
+                let Some(container) = target(prop, index) else {
+                    continue;
+                };
+                match container.tag_name().name() {
+                    "Series" => put("series", child(container, "title").and_then(text)),
+                    _ => {
+                        let mut inner = Vec::new();
+                        rdf_fields(container, index, &mut inner);
+                        for (name, value) in inner {
+                            let name = match name.as_str() {
+                                "title" => "publicationTitle",
+                                other => other,
+                            };
+                            put(name, Some(value));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn rdf_creators(node: Node) -> Vec<(String, String, String)> {
+    let mut out = Vec::new();
+    for prop in elements(node) {
+        let Some((_, role)) = RDF_CREATORS.iter().find(|(p, _)| *p == prop.tag_name().name()) else {
+            continue;
+        };
+        for person in prop.descendants().filter(|n| matches!(n.tag_name().name(), "Person" | "Organization")) {
+            let last = child(person, "surname").or(child(person, "name")).and_then(text).unwrap_or_default();
+            let first = child(person, "givenName").or(child(person, "givenname")).and_then(text).unwrap_or_default();
+            out.push((role.to_string(), first, last));
+        }
+    }
+    out
+}
+
+fn rdf_tags(node: Node) -> Vec<String> {
+    let mut tags: Vec<String> = elements(node)
+        .filter(|p| p.tag_name().name() == "subject")
+        .filter_map(|p| text(p).or_else(|| p.descendants().find(|n| n.tag_name().name() == "value").and_then(text)))
+        .collect();
+    tags.sort();
+    tags
+}
+
+/// Parses a Zotero RDF export (File > Export Library > Zotero RDF).
+pub fn parse_rdf(input: &str) -> Result<Library, SourceError> {
+    let doc = Document::parse(input).map_err(|e| SourceError::Format(e.to_string()))?;
+    let root = doc.root_element();
+    let top: Vec<Node> = elements(root).collect();
+    let index: HashMap<&str, Node> = top.iter().filter_map(|n| about(*n).map(|a| (a, *n))).collect();
+
+    let is_collection = |uri: &str| index.get(uri).is_some_and(|n| n.tag_name().name() == "Collection");
+    let mut library = Library::default();
+    let mut item_collections: HashMap<&str, Vec<String>> = HashMap::new();
+    // parents are only recorded on the parent side, as `hasPart` of another collection
+    let mut parents: HashMap<String, String> = HashMap::new();
+    for node in top.iter().filter(|n| n.tag_name().name() == "Collection") {
+        let key = about(*node).map(key_of).unwrap_or_default();
+        for part in elements(*node).filter(|p| p.tag_name().name() == "hasPart").filter_map(resource) {
+            match is_collection(part) {
+                true => parents.insert(key_of(part), key.clone()),
+                false => {
+                    item_collections.entry(part).or_default().push(key.clone());
+                    None
+                }
+            };
+        }
+        library.collections.push(Collection {
+            key,
+            name: child(*node, "title").and_then(text).unwrap_or_default(),
+            parent: None,
+        });
+    }
+    for c in &mut library.collections {
+        c.parent = parents.remove(&c.key);
+    }
+
+    for node in &top {
+        let Some(item_type) = child(*node, "itemType").and_then(text) else {
+            continue;
+        };
+        if is_child_type(&item_type) {
+            continue;
+        }
+        let uri = about(*node).unwrap_or_default();
+        let key = key_of(uri);
+        let mut fields = Vec::new();
+        rdf_fields(*node, &index, &mut fields);
+        let attachments = elements(*node)
+            .filter(|p| p.tag_name().name() == "link")
+            .filter_map(|p| target(p, &index))
+            .filter_map(|a| child(a, "resource").and_then(resource).map(str::to_string))
+            .collect();
+        let notes = elements(*node)
+            .filter(|p| p.tag_name().name() == "isReferencedBy")
+            .filter_map(|p| target(p, &index))
+            .filter_map(|m| child(m, "value").and_then(text))
+            .collect();
+        let line = doc.text_pos_at(node.range().start).row as usize;
+        match to_reference(&key, &item_type, &fields, &rdf_creators(*node), &rdf_tags(*node)) {
+            Ok(reference) => library.items.push(ZoteroItem {
+                key,
+                reference,
+                collections: item_collections.remove(uri).unwrap_or_default(),
+                attachments,
+                notes,
+            }),
+            Err(message) => library.errors.push(EntryError { line, key: Some(key), message }),
+        }
+    }
+    Ok(library)
+}
+
+// ---- into the graph ----
+
+fn collection_id(key: &str) -> String {
+    format!("collection:zotero:{key}")
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|a| a.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Saves a library. Works already imported from Zotero (matched by item key) are updated in
+/// place, so re-importing a newer copy of the library does not duplicate anything.
+pub fn import_library(
+    arm: &AcademicResourceManager,
+    library: Library,
+    mode: CollectionMode,
+) -> Result<ImportReport, SourceError> {
+    let known = arm.prop_index(ZOTERO_KEY)?;
+    let paths = library.collection_paths();
+
+    if mode == CollectionMode::Edges {
+        for c in &library.collections {
+            let id = collection_id(&c.key);
+            let mut node = arm.get_entity(&id)?.unwrap_or(Entity::builder().id(&id).kind(COLLECTION_KIND).build()?);
+            node.title = c.name.clone();
+            arm.upsert_entity(&node)?;
+            for old in arm.edges_from(&id, Some(SUBCOLLECTION_OF))? {
+                arm.delete_edge(&old.src, &old.dst, &old.kind)?;
+            }
+            if let Some(parent) = &c.parent {
+                arm.upsert_edge(&Edge::new(&id, collection_id(parent), SUBCOLLECTION_OF))?;
+            }
+        }
+    }
+
+    let mut report = ImportReport { imported: Vec::new(), errors: library.errors };
+    for item in library.items {
+        let mut reference = item.reference;
+        let id = known.get(&item.key).cloned().unwrap_or_else(|| work_id(&reference));
+        let previous = arm.get_entity(&id)?.and_then(|e| e.props);
+        let collection_tags: Vec<String> = item.collections.iter().filter_map(|k| paths.get(k.as_str()).cloned()).collect();
+
+        reference.extra.insert(ATTACHMENTS.to_string(), json!(item.attachments));
+        reference.extra.insert(NOTES.to_string(), json!(item.notes));
+        if mode == CollectionMode::Tags {
+            // drop collection tags the item has left since the last import
+            for old in string_list(previous.as_ref().and_then(|p| p.get(ZOTERO_COLLECTIONS))) {
+                if !collection_tags.contains(&old) {
+                    arm.untag_entity(&id, &old)?;
+                }
+            }
+            reference.keywords.extend(collection_tags.iter().cloned());
+            reference.extra.insert(ZOTERO_COLLECTIONS.to_string(), json!(collection_tags));
+        }
+        arm.save_reference_at(&id, &reference)?;
+
+        if mode == CollectionMode::Edges {
+            for old in arm.edges_from(&id, Some(IN_COLLECTION))? {
+                arm.delete_edge(&old.src, &old.dst, &old.kind)?;
+            }
+            for key in &item.collections {
+                arm.upsert_edge(&Edge::new(&id, collection_id(key), IN_COLLECTION))?;
+            }
+        }
+        report.imported.push(id);
+    }
+    Ok(report)
+}
+
+pub fn import_sqlite(
+    arm: &AcademicResourceManager,
+    path: impl AsRef<Path>,
+    mode: CollectionMode,
+) -> Result<ImportReport, SourceError> {
+    import_library(arm, read_sqlite(path)?, mode)
+}
+
+pub fn import_rdf_str(arm: &AcademicResourceManager, input: &str, mode: CollectionMode) -> Result<ImportReport, SourceError> {
+    import_library(arm, parse_rdf(input)?, mode)
+}
+
+pub fn import_rdf_file(
+    arm: &AcademicResourceManager,
+    path: impl AsRef<Path>,
+    mode: CollectionMode,
+) -> Result<ImportReport, SourceError> {
+    let input = std::fs::read_to_string(path)?;
+    import_rdf_str(arm, &input, mode)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Engine;
+
+    // The subset of the Zotero schema the importer reads.
+    const SCHEMA: &str = "
+        CREATE TABLE itemTypes (itemTypeID INTEGER PRIMARY KEY, typeName TEXT);
+        CREATE TABLE items (itemID INTEGER PRIMARY KEY, itemTypeID INT, libraryID INT, key TEXT);
+        CREATE TABLE deletedItems (itemID INTEGER PRIMARY KEY);
+        CREATE TABLE fields (fieldID INTEGER PRIMARY KEY, fieldName TEXT);
+        CREATE TABLE itemDataValues (valueID INTEGER PRIMARY KEY, value);
+        CREATE TABLE itemData (itemID INT, fieldID INT, valueID INT);
+        CREATE TABLE creators (creatorID INTEGER PRIMARY KEY, firstName TEXT, lastName TEXT, fieldMode INT);
+        CREATE TABLE creatorTypes (creatorTypeID INTEGER PRIMARY KEY, creatorType TEXT);
+        CREATE TABLE itemCreators (itemID INT, creatorID INT, creatorTypeID INT, orderIndex INT);
+        CREATE TABLE tags (tagID INTEGER PRIMARY KEY, name TEXT);
+        CREATE TABLE itemTags (itemID INT, tagID INT, type INT);
+        CREATE TABLE collections (collectionID INTEGER PRIMARY KEY, collectionName TEXT, parentCollectionID INT, key TEXT);
+        CREATE TABLE collectionItems (collectionID INT, itemID INT, orderIndex INT);
+        CREATE TABLE itemAttachments (itemID INTEGER PRIMARY KEY, parentItemID INT, path TEXT);
+        CREATE TABLE itemNotes (itemID INTEGER PRIMARY KEY, parentItemID INT, note TEXT, title TEXT);
+
+        INSERT INTO itemTypes VALUES (1, 'journalArticle'), (2, 'book'), (3, 'attachment'), (4, 'note');
+        INSERT INTO fields VALUES (1, 'title'), (2, 'date'), (3, 'publicationTitle'), (4, 'DOI'), (5, 'volume'), (6, 'publisher');
+        INSERT INTO creatorTypes VALUES (1, 'author'), (2, 'editor');
+        INSERT INTO items VALUES (1, 1, 1, 'AAAA1111'), (2, 2, 1, 'BBBB2222'), (3, 3, 1, 'CCCC3333'),
+                                 (4, 4, 1, 'DDDD4444'), (5, 2, 1, 'EEEE5555');
+        INSERT INTO deletedItems VALUES (5);
+        INSERT INTO itemDataValues VALUES (1, 'Quantum Things'), (2, '2020-03-15 March 15, 2020'),
+            (3, 'Physical Review Letters'), (4, '10.1000/Q.1'), (5, 12), (6, 'A Book'), (7, '1999-00-00 1999'),
+            (8, 'ACME'), (9, 'Trashed');
+        INSERT INTO itemData VALUES (1, 1, 1), (1, 2, 2), (1, 3, 3), (1, 4, 4), (1, 5, 5),
+                                    (2, 1, 6), (2, 2, 7), (2, 6, 8), (5, 1, 9);
+        INSERT INTO creators VALUES (1, 'Jane H.', 'Smith', 0), (2, '', 'CERN', 1), (3, 'Ed', 'Itor', 0);
+        INSERT INTO itemCreators VALUES (1, 1, 1, 0), (1, 2, 1, 1), (2, 3, 2, 0);
+        INSERT INTO tags VALUES (1, 'physics'), (2, 'reading');
+        INSERT INTO itemTags VALUES (1, 1, 0), (1, 2, 0), (2, 2, 0);
+        INSERT INTO collections VALUES (1, 'Thesis', NULL, 'COLL0001'), (2, 'Chapter 1', 1, 'COLL0002');
+        INSERT INTO collectionItems VALUES (2, 1, 0), (1, 2, 0);
+        INSERT INTO itemAttachments VALUES (3, 1, 'storage:paper.pdf');
+        INSERT INTO itemNotes VALUES (4, 1, '<p>Read again</p>', 'Read again');
+    ";
+
+    fn fixture(name: &str, extra: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_file(&path);
+        let conn = Connection::open(&path).unwrap();
+        conn.execute(SCHEMA).unwrap();
+        conn.execute(extra).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_read_sqlite() {
+        let path = fixture("poirot_zotero_read.sqlite", "");
+        let library = read_sqlite(&path).unwrap();
+        assert!(library.errors.is_empty(), "{:?}", library.errors);
+        assert_eq!(library.items.len(), 2);
+
+        let article = &library.items[0];
+        assert_eq!(article.key, "AAAA1111");
+        let r = &article.reference;
+        assert_eq!(r.kind, JOURNAL_ARTICLE);
+        assert_eq!(r.title, "Quantum Things");
+        assert_eq!(r.year, Some(2020));
+        assert_eq!(r.field("month"), Some("March"));
+        assert_eq!(r.field("volume"), Some("12"));
+        assert_eq!(r.venue.as_deref(), Some("Physical Review Letters"));
+        assert_eq!(r.doi(), Some("10.1000/Q.1"));
+        assert_eq!(r.authors[0].name.middle.as_deref(), Some("H."));
+        assert_eq!(r.authors[1].name.last, "CERN");
+        assert_eq!(r.keywords, vec!["physics", "reading"]);
+        assert_eq!(article.collections, vec!["COLL0002"]);
+        assert_eq!(article.attachments, vec!["storage/CCCC3333/paper.pdf"]);
+        assert_eq!(article.notes, vec!["<p>Read again</p>"]);
+
+        let book = &library.items[1].reference;
+        assert_eq!(book.year, Some(1999));
+        assert!(book.field("month").is_none());
+        assert_eq!(book.editors[0].name.last, "Itor");
+        assert_eq!(library.collections[1].parent.as_deref(), Some("COLL0001"));
+    }
+
+    #[test]
+    fn test_reimport_updates_in_place() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let path = fixture("poirot_zotero_first.sqlite", "");
+        let first = import_sqlite(&arm, &path, CollectionMode::Tags).unwrap();
+        assert_eq!(first.imported[0], "doi:10.1000/q.1");
+        assert_eq!(arm.entities_with_tag("Thesis/Chapter 1").unwrap().len(), 1);
+        let props = arm.get_entity(&first.imported[0]).unwrap().unwrap().props.unwrap();
+        assert_eq!(props[ZOTERO_KEY], "AAAA1111");
+        assert_eq!(props[ATTACHMENTS], json!(["storage/CCCC3333/paper.pdf"]));
+
+        // the book got a new title and moved collection; the DOI-less work keeps its id, and the
+        // article lost a tag in Zotero
+        let changed = fixture(
+            "poirot_zotero_second.sqlite",
+            "UPDATE itemDataValues SET value = 'A Better Book' WHERE valueID = 6;
+             UPDATE collectionItems SET collectionID = 2 WHERE itemID = 2;
+             DELETE FROM itemTags WHERE itemID = 1 AND tagID = 2;",
+        );
+        let before = arm.list_entities(None).unwrap().len();
+        let second = import_sqlite(&arm, &changed, CollectionMode::Tags).unwrap();
+        assert_eq!(second.imported, first.imported);
+        assert_eq!(arm.list_entities(None).unwrap().len(), before);
+        let book = arm.load_reference(&first.imported[1]).unwrap().unwrap();
+        assert_eq!(book.title, "A Better Book");
+        assert!(!book.keywords.contains(&"Thesis".to_string()));
+        assert!(book.keywords.contains(&"Thesis/Chapter 1".to_string()));
+        assert_eq!(arm.tags_of(&first.imported[0]).unwrap(), vec!["Thesis/Chapter 1", "physics"]);
+    }
+
+    #[test]
+    fn test_collections_as_edges() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let path = fixture("poirot_zotero_edges.sqlite", "");
+        let report = import_sqlite(&arm, &path, CollectionMode::Edges).unwrap();
+        let edges = arm.edges_from(&report.imported[0], Some(IN_COLLECTION)).unwrap();
+        assert_eq!(edges[0].dst, "collection:zotero:COLL0002");
+        let parent = arm.edges_from("collection:zotero:COLL0002", Some(SUBCOLLECTION_OF)).unwrap();
+        assert_eq!(parent[0].dst, "collection:zotero:COLL0001");
+        assert_eq!(arm.get_entity("collection:zotero:COLL0001").unwrap().unwrap().title, "Thesis");
+    }
+
+    const RDF: &str = r##"<rdf:RDF
+ xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"
+ xmlns:z="http://www.zotero.org/namespaces/export#"
+ xmlns:dc="http://purl.org/dc/elements/1.1/"
+ xmlns:vcard="http://nwalsh.com/rdf/vCard#"
+ xmlns:foaf="http://xmlns.com/foaf/0.1/"
+ xmlns:bib="http://purl.org/net/biblio#"
+ xmlns:link="http://purl.org/rss/1.0/modules/link/"
+ xmlns:dcterms="http://purl.org/dc/terms/"
+ xmlns:prism="http://prismstandard.org/namespaces/1.2/basic/">
+    <bib:Article rdf:about="http://zotero.org/users/local/abc/items/AAAA1111">
+        <z:itemType>journalArticle</z:itemType>
+        <dcterms:isPartOf rdf:resource="urn:issn:0031-9007"/>
+        <bib:authors>
+            <rdf:Seq>
+                <rdf:li><foaf:Person><foaf:surname>Smith</foaf:surname><foaf:givenName>Jane H.</foaf:givenName></foaf:Person></rdf:li>
+                <rdf:li><foaf:Person><foaf:surname>CERN</foaf:surname></foaf:Person></rdf:li>
+            </rdf:Seq>
+        </bib:authors>
+        <link:link rdf:resource="#item_3"/>
+        <dcterms:isReferencedBy rdf:resource="#item_4"/>
+        <dc:subject>physics</dc:subject>
+        <dc:subject><z:AutomaticTag><rdf:value>reading</rdf:value></z:AutomaticTag></dc:subject>
+        <dc:title>Quantum Things</dc:title>
+        <dc:date>2020-03-15</dc:date>
+        <bib:pages>1-10</bib:pages>
+        <dc:identifier>DOI 10.1000/Q.1</dc:identifier>
+    </bib:Article>
+    <bib:Journal rdf:about="urn:issn:0031-9007">
+        <prism:volume>12</prism:volume>
+        <dc:title>Physical Review Letters</dc:title>
+        <dc:identifier>ISSN 0031-9007</dc:identifier>
+    </bib:Journal>
+    <z:Attachment rdf:about="#item_3">
+        <z:itemType>attachment</z:itemType>
+        <rdf:resource rdf:resource="files/3/paper.pdf"/>
+    </z:Attachment>
+    <bib:Memo rdf:about="#item_4"><rdf:value>&lt;p&gt;Read again&lt;/p&gt;</rdf:value></bib:Memo>
+    <bib:Book rdf:about="http://zotero.org/users/local/abc/items/BBBB2222">
+        <z:itemType>book</z:itemType>
+        <dc:publisher><foaf:Organization><vcard:adr><vcard:Address><vcard:locality>Boston</vcard:locality></vcard:Address></vcard:adr><foaf:name>ACME</foaf:name></foaf:Organization></dc:publisher>
+        <dc:title>A Book</dc:title>
+        <dc:date>sometime</dc:date>
+    </bib:Book>
+    <z:Collection rdf:about="#collection_1">
+        <dc:title>Thesis</dc:title>
+        <dcterms:hasPart rdf:resource="#collection_2"/>
+    </z:Collection>
+    <z:Collection rdf:about="#collection_2">
+        <dc:title>Chapter 1</dc:title>
+        <dcterms:hasPart rdf:resource="http://zotero.org/users/local/abc/items/AAAA1111"/>
+    </z:Collection>
+</rdf:RDF>
+"##;
+
+    #[test]
+    fn test_parse_rdf() {
+        let library = parse_rdf(RDF).unwrap();
+        assert_eq!(library.items.len(), 1);
+        assert_eq!(library.errors.len(), 1);
+        assert_eq!(library.errors[0].key.as_deref(), Some("BBBB2222"));
+        assert_eq!(library.errors[0].line, 39);
+
+        let item = &library.items[0];
+        assert_eq!(item.key, "AAAA1111");
+        assert_eq!(item.collections, vec!["collection_2"]);
+        assert_eq!(item.attachments, vec!["files/3/paper.pdf"]);
+        assert_eq!(item.notes, vec!["<p>Read again</p>"]);
+        let r = &item.reference;
+        assert_eq!(r.venue.as_deref(), Some("Physical Review Letters"));
+        assert_eq!(r.field("volume"), Some("12"));
+        assert_eq!(r.field("issn"), Some("0031-9007"));
+        assert_eq!(r.doi(), Some("10.1000/Q.1"));
+        assert_eq!(r.keywords, vec!["physics", "reading"]);
+        assert_eq!(r.authors.len(), 2);
+        assert_eq!(library.collections[1].parent.as_deref(), Some("collection_1"));
+
+        // the same item from the database and from RDF lands on the same entity
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let from_db = import_sqlite(&arm, fixture("poirot_zotero_rdf.sqlite", ""), CollectionMode::Tags).unwrap();
+        let from_rdf = import_rdf_str(&arm, RDF, CollectionMode::Tags).unwrap();
+        assert_eq!(from_rdf.imported[0], from_db.imported[0]);
+        assert_eq!(arm.entities_with_tag("Thesis/Chapter 1").unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// A node in a topic hierarchy. The preferred label is the tag name entities are tagged with;
+/// alternative labels are synonyms that resolve to it.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Topic {
+    pub label: String,
+    #[serde(default)]
+    pub alt_labels: Vec<String>,
+    /// preferred labels of the broader topics
+    #[serde(default)]
+    pub broader: Vec<String>,
+    /// concept IRI in the source vocabulary
+    pub uri: Option<String>,
+    /// IRI of the concept scheme (ACM CCS, MeSH, ...)
+    pub scheme: Option<String>,
+}
+
+impl Topic {
+    pub fn new(label: impl Into<String>) -> Self {
+        Topic { label: label.into(), ..Default::default() }
+    }
+
+    /// The preferred label followed by the alternative ones.
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.label.as_str()).chain(self.alt_labels.iter().map(String::as_str))
+    }
+}
@@ -1,2 +1,773 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use thiserror::Error;
+use crate::database::references::normalize_doi;
+use crate::domain::author::Author;
+use crate::domain::sources::{
+    Reference, BOOK, CHAPTER, CONFERENCE_PAPER, DATASET, JOURNAL_ARTICLE, PREPRINT, REPORT, SOFTWARE, THESIS,
+};
 
+const ARXIV_PREFIXES: [&str; 5] = ["https://arxiv.org/abs/", "http://arxiv.org/abs/", "https://arxiv.org/pdf/", "http://arxiv.org/pdf/", "arxiv:"];
+const SWHID_OBJECTS: [&str; 5] = ["cnt", "dir", "rev", "rel", "snp"];
 
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum WorkError {
+    #[error("Missing title")]
+    MissingTitle,
+    #[error("Missing work kind")]
+    MissingKind,
+    #[error("Unsupported work kind `{0}`")]
+    UnsupportedKind(String),
+    #[error("Invalid {scheme} `{value}`")]
+    InvalidIdentifier { scheme: &'static str, value: String },
+    #[error("`{field}` does not apply to a {kind}")]
+    FieldNotApplicable { kind: WorkKind, field: String },
+}
+
+fn invalid(scheme: &'static str, value: &str) -> WorkError {
+    WorkError::InvalidIdentifier { scheme, value: value.trim().to_string() }
+}
+
+// Display, FromStr and string serde for an identifier newtype with a `parse` constructor.
+macro_rules! identifier {
+    ($name:ident) => {
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = WorkError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $name::parse(s)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                $name::parse(&s).map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+/// A DOI, lowercase and without resolver prefix (`10.1000/xyz`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Doi(String);
+
+impl Doi {
+    pub fn parse(doi: &str) -> Result<Self, WorkError> {
+        let normalized = normalize_doi(doi);
+        match normalized.split_once('/') {
+            Some((prefix, suffix)) if prefix.starts_with("10.") && prefix.len() > 3 && !suffix.is_empty() => Ok(Doi(normalized)),
+            _ => Err(invalid("DOI", doi)),
+        }
+    }
+
+    pub fn url(&self) -> String {
+        format!("https://doi.org/{}", self.0)
+    }
+}
+
+identifier!(Doi);
+
+/// An ISBN, always held as the 13 digits of ISBN-13; ISBN-10s are converted.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Isbn(String);
+
+impl Isbn {
+    pub fn parse(isbn: &str) -> Result<Self, WorkError> {
+        let chars: Vec<char> = isbn
+            .trim()
+            .chars()
+            .filter(|c| !matches!(c, '-' | ' '))
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        let digits = |cs: &[char]| cs.iter().map(|c| c.to_digit(10)).collect::<Option<Vec<u32>>>();
+        let body: Vec<u32> = match chars.len() {
+            13 => {
+                let all = digits(&chars).ok_or_else(|| invalid("ISBN", isbn))?;
+                let sum: u32 = all.iter().enumerate().map(|(i, d)| if i % 2 == 0 { *d } else { d * 3 }).sum();
+                if !sum.is_multiple_of(10) {
+                    return Err(invalid("ISBN", isbn));
+                }
+                all[..12].to_vec()
+            }
+            10 => {
+                let first = digits(&chars[..9]).ok_or_else(|| invalid("ISBN", isbn))?;
+                let check = match chars[9] {
+                    'X' => 10,
+                    c => c.to_digit(10).ok_or_else(|| invalid("ISBN", isbn))?,
+                };
+                let sum: u32 = first.iter().zip((2..=10).rev()).map(|(d, w)| d * w).sum::<u32>() + check;
+                if !sum.is_multiple_of(11) {
+                    return Err(invalid("ISBN", isbn));
+                }
+                [9, 7, 8].into_iter().chain(first).collect()
+            }
+            _ => return Err(invalid("ISBN", isbn)),
+        };
+        let sum: u32 = body.iter().enumerate().map(|(i, d)| if i % 2 == 0 { *d } else { d * 3 }).sum();
+        let check = (10 - sum % 10) % 10;
+        Ok(Isbn(body.iter().chain([&check]).filter_map(|d| char::from_digit(*d, 10)).collect()))
+    }
+}
+
+identifier!(Isbn);
+
+/// An arXiv id without prefix or version: `2101.00001` or, for old ids, `hep-th/9901001`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ArxivId(String);
+
+impl ArxivId {
+    /// Accepts `arXiv:` prefixes, abstract and PDF URLs and versioned ids (`2101.00001v3`).
+    pub fn parse(id: &str) -> Result<Self, WorkError> {
+        let lower = id.trim().to_lowercase();
+        let bare = ARXIV_PREFIXES
+            .iter()
+            .find_map(|p| lower.strip_prefix(p))
+            .unwrap_or(&lower)
+            .trim_end_matches(".pdf");
+        let bare = match bare.rfind('v') {
+            Some(i) if i > 0 && bare.len() > i + 1 && bare[i + 1..].chars().all(|c| c.is_ascii_digit()) => &bare[..i],
+            _ => bare,
+        };
+        let (archive, number) = bare.rsplit_once('/').unwrap_or(("", bare));
+        let new_style = archive.is_empty()
+            && number.split_once('.').is_some_and(|(a, b)| a.len() == 4 && (4..=5).contains(&b.len()))
+            && number.chars().all(|c| c.is_ascii_digit() || c == '.');
+        let old_style = !archive.is_empty() && number.len() == 7 && number.chars().all(|c| c.is_ascii_digit());
+        match new_style || old_style {
+            true => Ok(ArxivId(bare.to_string())),
+            false => Err(invalid("arXiv id", id)),
+        }
+    }
+
+    pub fn url(&self) -> String {
+        format!("https://arxiv.org/abs/{}", self.0)
+    }
+}
+
+identifier!(ArxivId);
+
+/// A PubMed id, digits without leading zeros.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Pmid(String);
+
+impl Pmid {
+    pub fn parse(pmid: &str) -> Result<Self, WorkError> {
+        let lower = pmid.trim().to_lowercase();
+        let digits = lower.strip_prefix("pmid:").unwrap_or(&lower).trim().trim_start_matches('0');
+        match !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+            true => Ok(Pmid(digits.to_string())),
+            false => Err(invalid("PMID", pmid)),
+        }
+    }
+}
+
+identifier!(Pmid);
+
+/// A Software Heritage id, `swh:1:<object type>:<40 hex digits>` with optional qualifiers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Swhid(String);
+
+impl Swhid {
+    pub fn parse(swhid: &str) -> Result<Self, WorkError> {
+        let trimmed = swhid.trim();
+        let (core, qualifiers) = trimmed.split_once(';').map_or((trimmed, None), |(c, q)| (c, Some(q)));
+        let parts: Vec<String> = core.split(':').map(str::to_lowercase).collect();
+        let valid = parts.len() == 4
+            && parts[0] == "swh"
+            && parts[1] == "1"
+            && SWHID_OBJECTS.contains(&parts[2].as_str())
+            && parts[3].len() == 40
+            && parts[3].chars().all(|c| c.is_ascii_hexdigit());
+        if !valid {
+            return Err(invalid("SWHID", swhid));
+        }
+        let core = parts.join(":");
+        Ok(Swhid(match qualifiers {
+            Some(q) => format!("{core};{q}"),
+            None => core,
+        }))
+    }
+}
+
+identifier!(Swhid);
+
+/// The identifiers a work can carry.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identifiers {
+    pub doi: Option<Doi>,
+    pub isbn: Option<Isbn>,
+    pub arxiv: Option<ArxivId>,
+    pub pmid: Option<Pmid>,
+    pub swhid: Option<Swhid>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum WorkKind {
+    JournalArticle,
+    ConferencePaper,
+    Book,
+    Chapter,
+    Thesis,
+    Preprint,
+    Software,
+    Dataset,
+    Report,
+}
+
+impl WorkKind {
+    /// The value stored in `entity.kind`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkKind::JournalArticle => JOURNAL_ARTICLE,
+            WorkKind::ConferencePaper => CONFERENCE_PAPER,
+            WorkKind::Book => BOOK,
+            WorkKind::Chapter => CHAPTER,
+            WorkKind::Thesis => THESIS,
+            WorkKind::Preprint => PREPRINT,
+            WorkKind::Software => SOFTWARE,
+            WorkKind::Dataset => DATASET,
+            WorkKind::Report => REPORT,
+        }
+    }
+
+    // Kind specific fields, by their name in `Reference::fields`.
+    fn fields(&self) -> &'static [&'static str] {
+        match self {
+            WorkKind::JournalArticle => &["volume", "number", "pages"],
+            WorkKind::ConferencePaper => &["eventtitle", "pages", "publisher"],
+            WorkKind::Book => &["publisher", "edition", "volume"],
+            WorkKind::Chapter => &["publisher", "edition", "pages"],
+            WorkKind::Thesis => &["school", "type"],
+            WorkKind::Preprint => &["version"],
+            WorkKind::Software => &["version", "license"],
+            WorkKind::Dataset => &["publisher", "version"],
+            WorkKind::Report => &["institution", "number"],
+        }
+    }
+}
+
+impl fmt::Display for WorkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WorkKind {
+    type Err = WorkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            JOURNAL_ARTICLE => WorkKind::JournalArticle,
+            CONFERENCE_PAPER => WorkKind::ConferencePaper,
+            BOOK => WorkKind::Book,
+            CHAPTER => WorkKind::Chapter,
+            THESIS => WorkKind::Thesis,
+            PREPRINT => WorkKind::Preprint,
+            SOFTWARE => WorkKind::Software,
+            DATASET => WorkKind::Dataset,
+            REPORT => WorkKind::Report,
+            other => return Err(WorkError::UnsupportedKind(other.to_string())),
+        })
+    }
+}
+
+/// What only some kinds of work have. The venue (journal, proceedings, book, preprint server)
+/// is common to all and lives on `Work`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkDetails {
+    JournalArticle { volume: Option<String>, issue: Option<String>, pages: Option<String> },
+    ConferencePaper { event: Option<String>, pages: Option<String>, publisher: Option<String> },
+    Book { publisher: Option<String>, edition: Option<String>, volume: Option<String> },
+    Chapter { publisher: Option<String>, edition: Option<String>, pages: Option<String> },
+    Thesis { school: Option<String>, degree: Option<String> },
+    Preprint { version: Option<String> },
+    Software { version: Option<String>, license: Option<String> },
+    Dataset { publisher: Option<String>, version: Option<String> },
+    Report { institution: Option<String>, number: Option<String> },
+}
+
+impl WorkDetails {
+    /// Takes the fields of `kind` out of `fields`, leaving the others.
+    fn take(kind: WorkKind, fields: &mut BTreeMap<String, String>) -> Self {
+        let mut values = kind.fields().iter().map(|name| fields.remove(*name));
+        let mut next = || values.next().flatten();
+        match kind {
+            WorkKind::JournalArticle => WorkDetails::JournalArticle { volume: next(), issue: next(), pages: next() },
+            WorkKind::ConferencePaper => WorkDetails::ConferencePaper { event: next(), pages: next(), publisher: next() },
+            WorkKind::Book => WorkDetails::Book { publisher: next(), edition: next(), volume: next() },
+            WorkKind::Chapter => WorkDetails::Chapter { publisher: next(), edition: next(), pages: next() },
+            WorkKind::Thesis => WorkDetails::Thesis { school: next(), degree: next() },
+            WorkKind::Preprint => WorkDetails::Preprint { version: next() },
+            WorkKind::Software => WorkDetails::Software { version: next(), license: next() },
+            WorkKind::Dataset => WorkDetails::Dataset { publisher: next(), version: next() },
+            WorkKind::Report => WorkDetails::Report { institution: next(), number: next() },
+        }
+    }
+
+    pub fn kind(&self) -> WorkKind {
+        match self {
+            WorkDetails::JournalArticle { .. } => WorkKind::JournalArticle,
+            WorkDetails::ConferencePaper { .. } => WorkKind::ConferencePaper,
+            WorkDetails::Book { .. } => WorkKind::Book,
+            WorkDetails::Chapter { .. } => WorkKind::Chapter,
+            WorkDetails::Thesis { .. } => WorkKind::Thesis,
+            WorkDetails::Preprint { .. } => WorkKind::Preprint,
+            WorkDetails::Software { .. } => WorkKind::Software,
+            WorkDetails::Dataset { .. } => WorkKind::Dataset,
+            WorkDetails::Report { .. } => WorkKind::Report,
+        }
+    }
+
+    // The values in the order of `WorkKind::fields`.
+    fn values(&self) -> Vec<&Option<String>> {
+        match self {
+            WorkDetails::JournalArticle { volume, issue, pages } => vec![volume, issue, pages],
+            WorkDetails::ConferencePaper { event, pages, publisher } => vec![event, pages, publisher],
+            WorkDetails::Book { publisher, edition, volume } => vec![publisher, edition, volume],
+            WorkDetails::Chapter { publisher, edition, pages } => vec![publisher, edition, pages],
+            WorkDetails::Thesis { school, degree } => vec![school, degree],
+            WorkDetails::Preprint { version } => vec![version],
+            WorkDetails::Software { version, license } => vec![version, license],
+            WorkDetails::Dataset { publisher, version } => vec![publisher, version],
+            WorkDetails::Report { institution, number } => vec![institution, number],
+        }
+    }
+
+    fn put(&self, fields: &mut BTreeMap<String, String>) {
+        for (name, value) in self.kind().fields().iter().zip(self.values()) {
+            if let Some(value) = value {
+                fields.insert(name.to_string(), value.clone());
+            }
+        }
+    }
+}
+
+/// A typed bibliographic work. `Reference` is the loose shape importers and exporters share;
+/// `Work` checks identifiers and keeps kind specific fields apart from the rest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Work {
+    pub key: Option<String>,
+    pub title: String,
+    pub authors: Vec<Author>,
+    pub editors: Vec<Author>,
+    pub year: Option<i64>,
+    pub venue: Option<String>,
+    pub uri: Option<String>,
+    pub keywords: Vec<String>,
+    pub identifiers: Identifiers,
+    pub details: WorkDetails,
+    /// fields the model has no place for, by their `Reference` name
+    pub fields: BTreeMap<String, String>,
+    /// other entity props
+    pub extra: BTreeMap<String, Value>,
+}
+
+// Identifiers found in `fields` are taken out; values that do not parse stay where they are.
+fn take_identifiers(fields: &mut BTreeMap<String, String>) -> Identifiers {
+    fn take<T>(fields: &mut BTreeMap<String, String>, name: &str, parse: fn(&str) -> Result<T, WorkError>) -> Option<T> {
+        let parsed = parse(fields.get(name)?).ok()?;
+        fields.remove(name);
+        Some(parsed)
+    }
+    let arxiv_marker = ["eprinttype", "archiveprefix"]
+        .into_iter()
+        .find(|m| fields.get(*m).is_some_and(|t| t.eq_ignore_ascii_case("arxiv")));
+    let arxiv = match arxiv_marker {
+        Some(marker) => take(fields, "eprint", ArxivId::parse).inspect(|_| {
+            fields.remove(marker);
+        }),
+        None => take(fields, "arxiv", ArxivId::parse),
+    };
+    Identifiers {
+        doi: take(fields, "doi", Doi::parse),
+        isbn: take(fields, "isbn", Isbn::parse),
+        arxiv,
+        pmid: take(fields, "pmid", Pmid::parse),
+        swhid: take(fields, "swhid", Swhid::parse),
+    }
+}
+
+impl Work {
+    pub fn builder() -> WorkBuilder {
+        WorkBuilder::default()
+    }
+
+    pub fn kind(&self) -> WorkKind {
+        self.details.kind()
+    }
+
+    /// Every field in its `Reference` form: the unmodelled ones, identifiers and details.
+    pub fn all_fields(&self) -> BTreeMap<String, String> {
+        let mut fields = self.fields.clone();
+        let ids = &self.identifiers;
+        let mut put = |name: &str, value: Option<&str>| {
+            if let Some(value) = value {
+                fields.insert(name.to_string(), value.to_string());
+            }
+        };
+        put("doi", ids.doi.as_ref().map(Doi::as_str));
+        put("isbn", ids.isbn.as_ref().map(Isbn::as_str));
+        put("pmid", ids.pmid.as_ref().map(Pmid::as_str));
+        put("swhid", ids.swhid.as_ref().map(Swhid::as_str));
+        if let Some(arxiv) = &ids.arxiv {
+            put("eprint", Some(arxiv.as_str()));
+            put("eprinttype", Some("arxiv"));
+        }
+        self.details.put(&mut fields);
+        fields
+    }
+}
+
+impl From<&Work> for Reference {
+    fn from(work: &Work) -> Self {
+        Reference {
+            key: work.key.clone(),
+            kind: work.kind().as_str().to_string(),
+            title: work.title.clone(),
+            authors: work.authors.clone(),
+            editors: work.editors.clone(),
+            year: work.year,
+            venue: work.venue.clone(),
+            uri: work.uri.clone(),
+            keywords: work.keywords.clone(),
+            fields: work.all_fields(),
+            extra: work.extra.clone(),
+        }
+    }
+}
+
+impl TryFrom<Reference> for Work {
+    type Error = WorkError;
+
+    fn try_from(reference: Reference) -> Result<Self, Self::Error> {
+        let kind: WorkKind = reference.kind.parse()?;
+        let mut fields = reference.fields;
+        Ok(Work {
+            key: reference.key,
+            title: reference.title,
+            authors: reference.authors,
+            editors: reference.editors,
+            year: reference.year,
+            venue: reference.venue,
+            uri: reference.uri,
+            keywords: reference.keywords,
+            identifiers: take_identifiers(&mut fields),
+            details: WorkDetails::take(kind, &mut fields),
+            fields,
+            extra: reference.extra,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct WorkBuilder {
+    kind: Option<WorkKind>,
+    key: Option<String>,
+    title: Option<String>,
+    authors: Vec<Author>,
+    editors: Vec<Author>,
+    year: Option<i64>,
+    venue: Option<String>,
+    uri: Option<String>,
+    keywords: Vec<String>,
+    identifiers: Identifiers,
+    details: BTreeMap<String, String>,
+    fields: BTreeMap<String, String>,
+}
+
+impl WorkBuilder {
+    pub fn kind(mut self, kind: WorkKind) -> Result<Self, WorkError> {
+        self.kind = Some(kind);
+        Ok(self)
+    }
+
+    pub fn kind_from_str(self, kind: &str) -> Result<Self, WorkError> {
+        self.kind(kind.parse()?)
+    }
+
+    pub fn key(mut self, key: impl Into<String>) -> Result<Self, WorkError> {
+        self.key = Some(key.into());
+        Ok(self)
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Result<Self, WorkError> {
+        self.title = Some(title.into());
+        Ok(self)
+    }
+
+    pub fn author(mut self, author: Author) -> Result<Self, WorkError> {
+        self.authors.push(author);
+        Ok(self)
+    }
+
+    pub fn editor(mut self, editor: Author) -> Result<Self, WorkError> {
+        self.editors.push(editor);
+        Ok(self)
+    }
+
+    pub fn year(mut self, year: i64) -> Result<Self, WorkError> {
+        self.year = Some(year);
+        Ok(self)
+    }
+
+    /// Journal, proceedings, book or preprint server.
+    pub fn venue(mut self, venue: impl Into<String>) -> Result<Self, WorkError> {
+        self.venue = Some(venue.into());
+        Ok(self)
+    }
+
+    pub fn uri(mut self, uri: impl Into<String>) -> Result<Self, WorkError> {
+        self.uri = Some(uri.into());
+        Ok(self)
+    }
+
+    pub fn keyword(mut self, keyword: impl Into<String>) -> Result<Self, WorkError> {
+        self.keywords.push(keyword.into());
+        Ok(self)
+    }
+
+    pub fn doi(mut self, doi: Doi) -> Result<Self, WorkError> {
+        self.identifiers.doi = Some(doi);
+        Ok(self)
+    }
+
+    pub fn doi_from_str(self, doi: &str) -> Result<Self, WorkError> {
+        self.doi(Doi::parse(doi)?)
+    }
+
+    pub fn isbn(mut self, isbn: Isbn) -> Result<Self, WorkError> {
+        self.identifiers.isbn = Some(isbn);
+        Ok(self)
+    }
+
+    pub fn isbn_from_str(self, isbn: &str) -> Result<Self, WorkError> {
+        self.isbn(Isbn::parse(isbn)?)
+    }
+
+    pub fn arxiv(mut self, arxiv: ArxivId) -> Result<Self, WorkError> {
+        self.identifiers.arxiv = Some(arxiv);
+        Ok(self)
+    }
+
+    pub fn arxiv_from_str(self, arxiv: &str) -> Result<Self, WorkError> {
+        self.arxiv(ArxivId::parse(arxiv)?)
+    }
+
+    pub fn pmid(mut self, pmid: Pmid) -> Result<Self, WorkError> {
+        self.identifiers.pmid = Some(pmid);
+        Ok(self)
+    }
+
+    pub fn pmid_from_str(self, pmid: &str) -> Result<Self, WorkError> {
+        self.pmid(Pmid::parse(pmid)?)
+    }
+
+    pub fn swhid(mut self, swhid: Swhid) -> Result<Self, WorkError> {
+        self.identifiers.swhid = Some(swhid);
+        Ok(self)
+    }
+
+    pub fn swhid_from_str(self, swhid: &str) -> Result<Self, WorkError> {
+        self.swhid(Swhid::parse(swhid)?)
+    }
+
+    fn detail(mut self, name: &str, value: impl Into<String>) -> Result<Self, WorkError> {
+        self.details.insert(name.to_string(), value.into());
+        Ok(self)
+    }
+
+    pub fn volume(self, volume: impl Into<String>) -> Result<Self, WorkError> {
+        self.detail("volume", volume)
+    }
+
+    pub fn issue(self, issue: impl Into<String>) -> Result<Self, WorkError> {
+        self.detail("number", issue)
+    }
+
+    pub fn pages(self, pages: impl Into<String>) -> Result<Self, WorkError> {
+        self.detail("pages", pages)
+    }
+
+    pub fn publisher(self, publisher: impl Into<String>) -> Result<Self, WorkError> {
+        self.detail("publisher", publisher)
+    }
+
+    pub fn edition(self, edition: impl Into<String>) -> Result<Self, WorkError> {
+        self.detail("edition", edition)
+    }
+
+    pub fn event(self, event: impl Into<String>) -> Result<Self, WorkError> {
+        self.detail("eventtitle", event)
+    }
+
+    pub fn school(self, school: impl Into<String>) -> Result<Self, WorkError> {
+        self.detail("school", school)
+    }
+
+    pub fn degree(self, degree: impl Into<String>) -> Result<Self, WorkError> {
+        self.detail("type", degree)
+    }
+
+    pub fn version(self, version: impl Into<String>) -> Result<Self, WorkError> {
+        self.detail("version", version)
+    }
+
+    pub fn license(self, license: impl Into<String>) -> Result<Self, WorkError> {
+        self.detail("license", license)
+    }
+
+    pub fn institution(self, institution: impl Into<String>) -> Result<Self, WorkError> {
+        self.detail("institution", institution)
+    }
+
+    /// Report number.
+    pub fn number(self, number: impl Into<String>) -> Result<Self, WorkError> {
+        self.detail("number", number)
+    }
+
+    /// Any other field, by its `Reference` name.
+    pub fn field(mut self, name: impl Into<String>, value: impl Into<String>) -> Result<Self, WorkError> {
+        self.fields.insert(name.into(), value.into());
+        Ok(self)
+    }
+
+    pub fn build(self) -> Result<Work, WorkError> {
+        let kind = self.kind.ok_or(WorkError::MissingKind)?;
+        let title = self.title.filter(|t| !t.trim().is_empty()).ok_or(WorkError::MissingTitle)?;
+        let mut details = self.details;
+        let taken = WorkDetails::take(kind, &mut details);
+        if let Some(field) = details.into_keys().next() {
+            return Err(WorkError::FieldNotApplicable { kind, field });
+        }
+        Ok(Work {
+            key: self.key,
+            title,
+            authors: self.authors,
+            editors: self.editors,
+            year: self.year,
+            venue: self.venue,
+            uri: self.uri,
+            keywords: self.keywords,
+            identifiers: self.identifiers,
+            details: taken,
+            fields: self.fields,
+            extra: BTreeMap::new(),
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn author(name: &str) -> Author {
+        Author::builder().name_from_str(name).unwrap().build().unwrap()
+    }
+
+    #[test]
+    fn test_identifiers() {
+        assert_eq!(Doi::parse("https://doi.org/10.1000/ABC").unwrap().as_str(), "10.1000/abc");
+        assert!(Doi::parse("11.1000/abc").is_err());
+        assert_eq!(Isbn::parse("0-201-89683-4").unwrap().as_str(), "9780201896831");
+        assert_eq!(Isbn::parse("978-0-201-89683-1").unwrap().as_str(), "9780201896831");
+        assert!(Isbn::parse("0-201-89683-5").is_err());
+        assert_eq!(ArxivId::parse("arXiv:2101.00001v3").unwrap().url(), "https://arxiv.org/abs/2101.00001");
+        assert_eq!(Pmid::parse("PMID: 0012345").unwrap().as_str(), "12345");
+        let swhid = "swh:1:rev:309CF2674EE7A0749978CF8265AB91A60AEA0F7D;origin=https://github.com/x/y";
+        assert_eq!(
+            Swhid::parse(swhid).unwrap().as_str(),
+            "swh:1:rev:309cf2674ee7a0749978cf8265ab91a60aea0f7d;origin=https://github.com/x/y"
+        );
+        assert_eq!(
+            Swhid::parse("swh:1:foo:309cf2674ee7a0749978cf8265ab91a60aea0f7d"),
+            Err(WorkError::InvalidIdentifier { scheme: "SWHID", value: "swh:1:foo:309cf2674ee7a0749978cf8265ab91a60aea0f7d".to_string() })
+        );
+        assert_eq!(serde_json::to_string(&Identifiers { pmid: Some(Pmid::parse("7").unwrap()), ..Default::default() }).unwrap(),
+            r#"{"doi":null,"isbn":null,"arxiv":null,"pmid":"7","swhid":null}"#);
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        let article = Work::builder()
+            .kind(WorkKind::JournalArticle).unwrap()
+            .title("A Study").unwrap()
+            .author(author("Jane Smith")).unwrap()
+            .venue("Nature").unwrap()
+            .volume("7").unwrap()
+            .issue("2").unwrap()
+            .doi_from_str("doi:10.1000/XYZ").unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            article.details,
+            WorkDetails::JournalArticle { volume: Some("7".to_string()), issue: Some("2".to_string()), pages: None }
+        );
+
+        assert_eq!(Work::builder().title("T").unwrap().build(), Err(WorkError::MissingKind));
+        assert_eq!(Work::builder().kind(WorkKind::Book).unwrap().title(" ").unwrap().build(), Err(WorkError::MissingTitle));
+        assert_eq!(
+            Work::builder().kind(WorkKind::Software).unwrap().title("tool").unwrap().pages("1-2").unwrap().build(),
+            Err(WorkError::FieldNotApplicable { kind: WorkKind::Software, field: "pages".to_string() })
+        );
+        assert!(matches!(Work::builder().isbn_from_str("123"), Err(WorkError::InvalidIdentifier { scheme: "ISBN", .. })));
+        assert_eq!("misc".parse::<WorkKind>(), Err(WorkError::UnsupportedKind("misc".to_string())));
+    }
+
+    #[test]
+    fn test_reference_roundtrip() {
+        let mut software = Work::builder()
+            .kind(WorkKind::Software).unwrap()
+            .key("tool2024").unwrap()
+            .title("Tool").unwrap()
+            .author(author("Jane Smith")).unwrap()
+            .author(author("Bob Lee")).unwrap()
+            .year(2024).unwrap()
+            .uri("https://github.com/x/tool").unwrap()
+            .version("1.2").unwrap()
+            .license("MIT").unwrap()
+            .swhid_from_str("swh:1:dir:309cf2674ee7a0749978cf8265ab91a60aea0f7d").unwrap()
+            .arxiv_from_str("2401.01234v2").unwrap()
+            .field("note", "fast").unwrap()
+            .build()
+            .unwrap();
+        software.extra.insert("zotero_key".to_string(), Value::String("ABCD".to_string()));
+
+        let reference = Reference::from(&software);
+        assert_eq!(reference.kind, SOFTWARE);
+        assert_eq!((reference.field("eprint"), reference.field("eprinttype")), (Some("2401.01234"), Some("arxiv")));
+        assert_eq!(reference.field("license"), Some("MIT"));
+        assert_eq!(Work::try_from(reference).unwrap(), software);
+
+        // invalid identifiers from loose imports stay in the unmodelled fields
+        let mut loose = Reference { kind: BOOK.to_string(), title: "Old".to_string(), ..Default::default() };
+        loose.fields.insert("isbn".to_string(), "n/a".to_string());
+        loose.fields.insert("publisher".to_string(), "Penguin".to_string());
+        let book = Work::try_from(loose).unwrap();
+        assert_eq!(book.identifiers.isbn, None);
+        assert_eq!(book.fields.get("isbn").map(String::as_str), Some("n/a"));
+        assert_eq!(book.details, WorkDetails::Book { publisher: Some("Penguin".to_string()), edition: None, volume: None });
+    }
+}
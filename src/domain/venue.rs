@@ -0,0 +1,257 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+// Words ISO 4 leaves out of abbreviated titles.
+const DROPPED: &[&str] = &["a", "an", "the", "of", "and", "for", "in", "on", "at", "to", "by", "with", "from", "&"];
+
+// A subset of the ISSN List of Title Word Abbreviations; a trailing `-` matches any ending.
+const LTWA: &[(&str, &str)] = &[
+    ("academ-", "Acad."),
+    ("advance-", "Adv."),
+    ("americ-", "Am."),
+    ("analy-", "Anal."),
+    ("annal-", "Ann."),
+    ("annu-", "Annu."),
+    ("applied", "Appl."),
+    ("artific-", "Artif."),
+    ("associat-", "Assoc."),
+    ("biochem-", "Biochem."),
+    ("biolog-", "Biol."),
+    ("biotechnolog-", "Biotechnol."),
+    ("bulletin", "Bull."),
+    ("chemi-", "Chem."),
+    ("communicat-", "Commun."),
+    ("comput-", "Comput."),
+    ("conferen-", "Conf."),
+    ("ecolog-", "Ecol."),
+    ("econom-", "Econ."),
+    ("educat-", "Educ."),
+    ("electr-", "Electr."),
+    ("engineer-", "Eng."),
+    ("environment-", "Environ."),
+    ("europ-", "Eur."),
+    ("genet-", "Genet."),
+    ("geophys-", "Geophys."),
+    ("histor-", "Hist."),
+    ("informat-", "Inf."),
+    ("intellig-", "Intell."),
+    ("internation-", "Int."),
+    ("journal-", "J."),
+    ("language-", "Lang."),
+    ("learn-", "Learn."),
+    ("letter-", "Lett."),
+    ("linguist-", "Linguist."),
+    ("machine-", "Mach."),
+    ("magazine", "Mag."),
+    ("manag-", "Manag."),
+    ("material-", "Mater."),
+    ("mathemat-", "Math."),
+    ("medic-", "Med."),
+    ("molecul-", "Mol."),
+    ("nation-", "Natl."),
+    ("optic-", "Opt."),
+    ("philosoph-", "Philos."),
+    ("physic-", "Phys."),
+    ("proceeding-", "Proc."),
+    ("process-", "Process."),
+    ("psycholog-", "Psychol."),
+    ("quarter-", "Q."),
+    ("recognition", "Recognit."),
+    ("research-", "Res."),
+    ("review-", "Rev."),
+    ("robot-", "Robot."),
+    ("scien-", "Sci."),
+    ("societ-", "Soc."),
+    ("software", "Softw."),
+    ("statist-", "Stat."),
+    ("symposium", "Symp."),
+    ("system-", "Syst."),
+    ("technolog-", "Technol."),
+    ("theor-", "Theor."),
+    ("transaction-", "Trans."),
+];
+
+/// Why a string is not an ISSN.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum IssnError {
+    #[error("expected 8 characters, found {0}")]
+    Length(usize),
+    #[error("unexpected character `{0}`")]
+    Character(char),
+    #[error("check digit is {found}, expected {expected}")]
+    Checksum { expected: char, found: char },
+}
+
+/// An ISSN, print or electronic, held as `NNNN-NNNC`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Issn(String);
+
+impl Issn {
+    /// Accepts the id with or without hyphen and an `ISSN` prefix, and verifies the MOD 11 check digit.
+    pub fn parse(issn_str: &str) -> Result<Self, IssnError> {
+        let upper = issn_str.trim().to_uppercase();
+        let chars: Vec<char> = upper
+            .strip_prefix("ISSN")
+            .unwrap_or(&upper)
+            .chars()
+            .filter(|c| !matches!(c, '-' | ' ' | ':'))
+            .collect();
+        if chars.len() != 8 {
+            return Err(IssnError::Length(chars.len()));
+        }
+        let mut sum = 0;
+        for (c, weight) in chars[..7].iter().zip((2..=8).rev()) {
+            sum += c.to_digit(10).ok_or(IssnError::Character(*c))? * weight;
+        }
+        let expected = match (11 - sum % 11) % 11 {
+            10 => 'X',
+            d => char::from_digit(d, 10).unwrap_or('0'),
+        };
+        if chars[7] != expected {
+            return match chars[7] {
+                c if c.is_ascii_digit() || c == 'X' => Err(IssnError::Checksum { expected, found: c }),
+                c => Err(IssnError::Character(c)),
+            };
+        }
+        let digits: String = chars.iter().collect();
+        Ok(Issn(format!("{}-{}", &digits[..4], &digits[4..])))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Issn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Issn {
+    type Err = IssnError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Issn::parse(s)
+    }
+}
+
+impl Serialize for Issn {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Issn {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Issn::parse(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// ISO 4 abbreviation of a title: `Journal of the American Chemical Society` gives
+/// `J. Am. Chem. Soc.`. Words missing from the built-in word list are kept whole, and
+/// single-word titles are not abbreviated.
+pub fn iso4_abbreviation(title: &str) -> String {
+    let words: Vec<&str> = title.split_whitespace().map(|w| w.trim_matches(|c| matches!(c, ',' | ':' | ';'))).collect();
+    if words.len() < 2 {
+        return title.trim().to_string();
+    }
+    words
+        .iter()
+        .filter(|w| !w.is_empty() && !DROPPED.contains(&w.to_lowercase().as_str()))
+        .map(|word| {
+            let lower = word.to_lowercase();
+            LTWA.iter()
+                .find(|(pattern, _)| match pattern.strip_suffix('-') {
+                    Some(stem) => lower.starts_with(stem),
+                    None => lower == *pattern,
+                })
+                .map_or(word.to_string(), |(_, abbreviation)| abbreviation.to_string())
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VenueKind {
+    Journal,
+    /// a conference series or one of its editions
+    Conference,
+    BookSeries,
+    /// preprint servers and data or software archives
+    Repository,
+    #[default]
+    Other,
+}
+
+/// Where works are published: a journal, a conference (series or edition), a book series or a repository.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Venue {
+    pub name: String,
+    #[serde(default)]
+    pub kind: VenueKind,
+    pub issn: Option<Issn>,
+    pub eissn: Option<Issn>,
+    /// ISO 4 abbreviated title
+    pub abbreviation: Option<String>,
+    /// other names, including former titles
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub publisher: Option<String>,
+    /// the series an edition belongs to, e.g. `NeurIPS` for `NeurIPS 2023`
+    pub series: Option<String>,
+    pub edition_year: Option<i64>,
+}
+
+impl Venue {
+    pub fn new(name: impl Into<String>, kind: VenueKind) -> Self {
+        Venue { name: name.into(), kind, ..Default::default() }
+    }
+
+    /// The name followed by aliases and the abbreviation.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.name.as_str())
+            .chain(self.aliases.iter().map(String::as_str))
+            .chain(self.abbreviation.as_deref())
+    }
+
+    /// The recorded abbreviation, or one derived from the name.
+    pub fn iso4(&self) -> String {
+        self.abbreviation.clone().unwrap_or_else(|| iso4_abbreviation(&self.name))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issn() {
+        for input in ["0028-0836", "00280836", "ISSN 0028-0836"] {
+            assert_eq!(Issn::parse(input).unwrap().as_str(), "0028-0836");
+        }
+        assert_eq!(Issn::parse("2434-561x").unwrap().as_str(), "2434-561X");
+        assert_eq!(Issn::parse("0028-0837"), Err(IssnError::Checksum { expected: '6', found: '7' }));
+        assert_eq!(Issn::parse("0028-083"), Err(IssnError::Length(7)));
+        assert_eq!(Issn::parse("0028-O836"), Err(IssnError::Character('O')));
+    }
+
+    #[test]
+    fn test_iso4_abbreviation() {
+        assert_eq!(iso4_abbreviation("Journal of the American Chemical Society"), "J. Am. Chem. Soc.");
+        assert_eq!(iso4_abbreviation("Physical Review Letters"), "Phys. Rev. Lett.");
+        assert_eq!(
+            iso4_abbreviation("IEEE Transactions on Pattern Analysis and Machine Intelligence"),
+            "IEEE Trans. Pattern Anal. Mach. Intell."
+        );
+        assert_eq!(iso4_abbreviation("Proceedings of the National Academy of Sciences"), "Proc. Natl. Acad. Sci.");
+        assert_eq!(iso4_abbreviation(" Nature "), "Nature");
+        let venue = Venue { abbreviation: Some("PRL".to_string()), ..Venue::new("Physical Review Letters", VenueKind::Journal) };
+        assert_eq!(venue.iso4(), "PRL");
+    }
+}
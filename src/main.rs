@@ -4,12 +4,10 @@ use gpui::{
     Window, WindowBounds, WindowKind, WindowOptions, actions, div, prelude::*, px, rgb, size,
 };
 */
-use cozo::ScriptMutability;
 use poirot::database::{Engine,
     AcademicResourceManager};
 
 use log::{info}; // logging
-use env_logger;
 //use std::io::Write;
 /*
 struct SubWindow {
@@ -266,12 +264,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // TODO inesert new entries in the database
 
-    let query_script = "?[id, kind, title, autors, uri, year, props] <- *entity[id, kind, title, autors, uri, year, props]";
-    let entries = arm
-        .db
-        .run_script(query_script, Default::default(), ScriptMutability::Immutable)?;
-    info!("Fetched {} entries", entries.rows.len());
-    if let Some(first_entry) = entries.rows.first() {
+    let entries = arm.list_entities(None)?;
+    info!("Fetched {} entries", entries.len());
+    if let Some(first_entry) = entries.first() {
         info!("First entry sample: {:?}", first_entry);
     }
 
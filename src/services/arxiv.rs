@@ -0,0 +1,407 @@
+use std::time::Duration;
+use roxmltree::{Document, Node};
+use serde_json::{json, Map, Value};
+use crate::database::AcademicResourceManager;
+use crate::domain::affiliation::Affiliation;
+use crate::domain::author::{Author, Name};
+use crate::domain::sources::{year_from, Reference, PREPRINT};
+use crate::domain::types::ArxivId;
+use crate::services::http::{user_agent, ClientError, Fetcher, RetryPolicy};
+use crate::services::works::WorkResolver;
+
+/// Source name recorded in provenance for fields taken from arXiv.
+pub const ARXIV: &str = "arxiv";
+/// Work prop holding the versionless arXiv id.
+pub const ARXIV_ID: &str = "arxiv_id";
+/// Work prop listing the versions seen so far, `{"version": n, "date": ...}` from v1 up.
+pub const ARXIV_VERSIONS: &str = "arxiv_versions";
+const BASE_URL: &str = "https://export.arxiv.org/api";
+const ATOM_NS: &str = "http://www.w3.org/2005/Atom";
+const ARXIV_NS: &str = "http://arxiv.org/schemas/atom";
+
+/// One paper of an arXiv Atom feed, at its latest version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArxivEntry {
+    /// without version, e.g. `1706.03762` or `hep-th/9711200`
+    pub id: String,
+    pub version: u32,
+    pub title: String,
+    pub summary: String,
+    pub authors: Vec<Author>,
+    /// submission date of v1
+    pub published: String,
+    /// submission date of the latest version
+    pub updated: String,
+    pub primary_category: Option<String>,
+    pub categories: Vec<String>,
+    pub journal_ref: Option<String>,
+    pub doi: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl ArxivEntry {
+    pub fn url(&self) -> String {
+        format!("https://arxiv.org/abs/{}", self.id)
+    }
+
+    /// The paper as a preprint whose categories are keywords, so each becomes a tag.
+    pub fn reference(&self) -> Reference {
+        let mut reference = Reference {
+            kind: PREPRINT.to_string(),
+            title: self.title.clone(),
+            authors: self.authors.clone(),
+            year: year_from(&self.published),
+            uri: Some(self.url()),
+            keywords: self.categories.clone(),
+            ..Default::default()
+        };
+        let fields = [
+            ("eprint", Some(self.id.clone())),
+            ("eprinttype", Some(ARXIV.to_string())),
+            ("eprintclass", self.primary_category.clone()),
+            ("version", Some(format!("v{}", self.version))),
+            ("abstract", Some(self.summary.clone()).filter(|s| !s.is_empty())),
+            ("doi", self.doi.as_deref().map(str::to_lowercase)),
+            ("journalref", self.journal_ref.clone()),
+            ("note", self.comment.clone()),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                reference.fields.insert(name.to_string(), value);
+            }
+        }
+        reference.extra.insert(ARXIV_ID.to_string(), Value::String(self.id.clone()));
+        reference
+    }
+}
+
+/// How a synced paper compares to what the graph held before.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Synced {
+    /// work id in the graph
+    pub id: String,
+    pub arxiv_id: String,
+    pub version: u32,
+    /// latest version stored before this sync; `None` for papers new to the graph
+    pub previous: Option<u32>,
+}
+
+impl Synced {
+    pub fn is_new(&self) -> bool {
+        self.previous.is_none()
+    }
+
+    pub fn has_new_version(&self) -> bool {
+        self.previous.is_some_and(|p| p < self.version)
+    }
+}
+
+// `http://arxiv.org/abs/hep-th/9711200v3` -> (`hep-th/9711200`, 3); ids without version are v1.
+fn split_version(url: &str) -> Option<(String, u32)> {
+    let id = ArxivId::parse(url).ok()?.to_string();
+    let version = url
+        .rsplit_once('v')
+        .filter(|(head, _)| head.ends_with(id.as_str()))
+        .and_then(|(_, n)| n.parse().ok())
+        .unwrap_or(1);
+    Some((id, version))
+}
+
+fn child<'a, 'i>(node: Node<'a, 'i>, ns: &str, name: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|n| n.is_element() && n.has_tag_name((ns, name)))
+}
+
+// Text of a child element with runs of whitespace (titles and abstracts wrap) collapsed.
+fn text(node: Node, ns: &str, name: &str) -> Option<String> {
+    let text = child(node, ns, name)?.text()?.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+fn author(node: Node) -> Option<Author> {
+    let mut builder = Author::builder().name(Name::parse(&text(node, ATOM_NS, "name")?).ok()?).ok()?;
+    if let Some(affiliation) = text(node, ARXIV_NS, "affiliation") {
+        builder = builder.affiliation(Affiliation::parse(&affiliation)).ok()?;
+    }
+    builder.build().ok()
+}
+
+fn entry(node: Node) -> Result<Option<ArxivEntry>, ClientError> {
+    let url = text(node, ATOM_NS, "id").unwrap_or_default();
+    // a failed query answers with a single entry describing the error
+    if url.contains("/api/errors") {
+        let message = text(node, ATOM_NS, "summary").unwrap_or(url);
+        return Err(ClientError::Format(format!("arXiv rejected the query: {message}")));
+    }
+    let Some((id, version)) = split_version(&url) else {
+        return Ok(None);
+    };
+    let elements = |ns: &'static str, name: &'static str| {
+        node.children().filter(move |n| n.is_element() && n.has_tag_name((ns, name)))
+    };
+    Ok(Some(ArxivEntry {
+        id,
+        version,
+        title: text(node, ATOM_NS, "title").unwrap_or_default(),
+        summary: text(node, ATOM_NS, "summary").unwrap_or_default(),
+        authors: elements(ATOM_NS, "author").filter_map(author).collect(),
+        published: text(node, ATOM_NS, "published").unwrap_or_default(),
+        updated: text(node, ATOM_NS, "updated").unwrap_or_default(),
+        primary_category: child(node, ARXIV_NS, "primary_category").and_then(|n| n.attribute("term")).map(str::to_string),
+        categories: elements(ATOM_NS, "category").filter_map(|n| n.attribute("term")).map(str::to_string).collect(),
+        journal_ref: text(node, ARXIV_NS, "journal_ref"),
+        doi: text(node, ARXIV_NS, "doi"),
+        comment: text(node, ARXIV_NS, "comment"),
+    }))
+}
+
+/// Papers of an arXiv API response. Entries without a usable id are skipped; an error feed
+/// gives `ClientError::Format`.
+pub fn parse_feed(xml: &str) -> Result<Vec<ArxivEntry>, ClientError> {
+    let doc = Document::parse(xml).map_err(|e| ClientError::Format(format!("invalid Atom feed: {e}")))?;
+    let mut entries = Vec::new();
+    for node in doc.root_element().children().filter(|n| n.has_tag_name((ATOM_NS, "entry"))) {
+        entries.extend(entry(node)?);
+    }
+    Ok(entries)
+}
+
+// Versions 1..=`latest`, keeping the dates already known and filling in those the entry gives.
+// An entry older than what is stored (a stale mirror, say) never shrinks the list.
+fn versions(previous: Option<&Value>, entry: &ArxivEntry) -> Value {
+    let known = |n: u32| {
+        previous
+            .and_then(Value::as_array)
+            .and_then(|list| list.iter().find(|v| v.get("version").and_then(Value::as_u64) == Some(n.into())))
+            .and_then(|v| v.get("date"))
+            .filter(|d| !d.is_null())
+            .cloned()
+    };
+    let latest = previous
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.get("version")?.as_u64())
+        .fold(entry.version, |max, n| max.max(n as u32));
+    let list: Vec<Value> = (1..=latest)
+        .map(|n| {
+            let date = known(n).or_else(|| match n {
+                1 => Some(Value::String(entry.published.clone())),
+                n if n == entry.version => Some(Value::String(entry.updated.clone())),
+                _ => None,
+            });
+            json!({ "version": n, "date": date })
+        })
+        .collect();
+    Value::Array(list)
+}
+
+fn latest_version(props: Option<&Value>) -> Option<u32> {
+    props?.get(ARXIV_VERSIONS)?.as_array()?.iter().filter_map(|v| v.get("version")?.as_u64()).max().map(|n| n as u32)
+}
+
+/// Client for the arXiv export API.
+#[derive(Debug)]
+pub struct ArxivClient {
+    fetcher: Fetcher,
+    base_url: String,
+}
+
+impl ArxivClient {
+    pub fn builder() -> ArxivClientBuilder {
+        ArxivClientBuilder::default()
+    }
+
+    async fn query(&self, params: &[(&str, String)]) -> Result<Vec<ArxivEntry>, ClientError> {
+        let url = format!("{}/query", self.base_url);
+        match self.fetcher.get_text(&url, params).await? {
+            Some(body) => parse_feed(&body),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Papers matching an arXiv `search_query` (e.g. `ti:attention AND cat:cs.CL`), from
+    /// `start` on.
+    pub async fn search(&self, query: &str, start: usize, max_results: usize) -> Result<Vec<ArxivEntry>, ClientError> {
+        let params = [("search_query", query.to_string()), ("start", start.to_string()), ("max_results", max_results.to_string())];
+        self.query(&params).await
+    }
+
+    /// The latest version of each paper in `ids`; unknown ids are left out.
+    pub async fn fetch(&self, ids: &[&str]) -> Result<Vec<ArxivEntry>, ClientError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let params = [("id_list", ids.join(",")), ("max_results", ids.len().to_string())];
+        self.query(&params).await
+    }
+
+    /// Saves each paper through `resolver`, so it merges into the work it matches, and records
+    /// its versions. A paper stored at v2 and now at v4 comes back with `previous: Some(2)`.
+    pub fn store(&self, arm: &AcademicResourceManager, resolver: &WorkResolver, entries: &[ArxivEntry]) -> Result<Vec<Synced>, ClientError> {
+        let known = arm.prop_index(ARXIV_ID)?;
+        let mut synced = Vec::new();
+        for entry in entries {
+            let reference = entry.reference();
+            let id = match known.get(&entry.id) {
+                Some(id) if arm.get_entity(id)?.is_some() => resolver.save_into(arm, id, &reference, ARXIV)?,
+                _ => resolver.save(arm, &reference, ARXIV)?,
+            };
+            let mut work = arm.get_entity(&id)?.ok_or_else(|| ClientError::Format(format!("work `{id}` vanished")))?;
+            let previous = latest_version(work.props.as_ref());
+            let mut props = match work.props.take() {
+                Some(Value::Object(map)) => map,
+                _ => Map::new(),
+            };
+            let versions = versions(props.get(ARXIV_VERSIONS), entry);
+            props.insert(ARXIV_VERSIONS.to_string(), versions);
+            work.props = Some(Value::Object(props));
+            arm.upsert_entity(&work)?;
+            synced.push(Synced { id, arxiv_id: entry.id.clone(), version: entry.version, previous });
+        }
+        Ok(synced)
+    }
+
+    /// Fetches and stores the papers in `ids`.
+    pub async fn sync(&self, arm: &AcademicResourceManager, resolver: &WorkResolver, ids: &[&str]) -> Result<Vec<Synced>, ClientError> {
+        let entries = self.fetch(ids).await?;
+        self.store(arm, resolver, &entries)
+    }
+
+    /// Re-syncs every work imported from arXiv; `Synced::has_new_version` tells which moved on.
+    pub async fn resync(&self, arm: &AcademicResourceManager, resolver: &WorkResolver, batch: usize) -> Result<Vec<Synced>, ClientError> {
+        let ids: Vec<String> = arm.prop_index(ARXIV_ID)?.into_keys().collect();
+        let mut synced = Vec::new();
+        for chunk in ids.chunks(batch.max(1)) {
+            let chunk: Vec<&str> = chunk.iter().map(String::as_str).collect();
+            synced.extend(self.sync(arm, resolver, &chunk).await?);
+        }
+        Ok(synced)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ArxivClientBuilder {
+    base_url: Option<String>,
+    policy: Option<RetryPolicy>,
+    timeout: Option<Duration>,
+}
+
+impl ArxivClientBuilder {
+    /// API root, `https://export.arxiv.org/api` by default; requests go to `{base_url}/query`.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Pacing and retries; by default one request every 3 seconds, as arXiv asks of API users.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn build(self) -> Result<ArxivClient, ClientError> {
+        let client = reqwest::Client::builder()
+            .user_agent(user_agent(None))
+            .timeout(self.timeout.unwrap_or(Duration::from_secs(30)))
+            .build()?;
+        let policy = self.policy.unwrap_or(RetryPolicy { min_interval: Duration::from_secs(3), ..RetryPolicy::default() });
+        Ok(ArxivClient {
+            fetcher: Fetcher::new(client, policy),
+            base_url: self.base_url.unwrap_or_else(|| BASE_URL.to_string()).trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use crate::database::Engine;
+
+    const FEED: &str = include_str!("fixtures/arxiv_query.xml");
+
+    #[test]
+    fn test_parse_feed() {
+        let entries = parse_feed(FEED).unwrap();
+        assert_eq!(entries.len(), 2);
+        let attention = &entries[0];
+        assert_eq!((attention.id.as_str(), attention.version), ("1706.03762", 5));
+        assert_eq!(attention.title, "Attention Is All You Need");
+        assert_eq!(attention.categories, vec!["cs.CL", "cs.LG"]);
+        assert_eq!(attention.authors.len(), 3);
+        assert_eq!(attention.authors[0].affiliation.as_ref().and_then(|a| a.institution.as_deref()), Some("Google Brain"));
+
+        let maldacena = entries[1].reference();
+        assert_eq!(maldacena.field("eprint"), Some("hep-th/9711200"));
+        assert_eq!(maldacena.field("version"), Some("v3"));
+        assert_eq!(maldacena.doi(), Some("10.1023/a:1026654312961"));
+        assert_eq!(maldacena.field("journalref"), Some("Adv.Theor.Math.Phys.2:231-252,1998"));
+        assert_eq!(maldacena.year, Some(1997));
+
+        let error = r#"<feed xmlns="http://www.w3.org/2005/Atom"><entry>
+            <id>http://arxiv.org/api/errors#incorrect_id_format_for_1234</id>
+            <summary>incorrect id format for 1234</summary></entry></feed>"#;
+        assert!(matches!(parse_feed(error), Err(ClientError::Format(_))));
+    }
+
+    #[test]
+    fn test_older_entry_keeps_versions() {
+        let mut entry = parse_feed(FEED).unwrap().remove(0);
+        let stored = versions(None, &entry);
+        assert_eq!(stored.as_array().unwrap().len(), 5);
+
+        // a stale answer still at v3 neither drops v4 and v5 nor their dates
+        entry.version = 3;
+        entry.updated = "2017-06-20T00:00:00Z".to_string();
+        let merged = versions(Some(&stored), &entry);
+        assert_eq!(merged.as_array().unwrap().len(), 5);
+        assert_eq!(merged[4], stored[4]);
+        assert_eq!(latest_version(Some(&json!({ ARXIV_VERSIONS: merged }))), Some(5));
+    }
+
+    #[tokio::test]
+    async fn test_resync_detects_new_versions() {
+        let server = MockServer::start().await;
+        let respond = |feed: String| ResponseTemplate::new(200).set_body_raw(feed, "application/atom+xml");
+        Mock::given(method("GET"))
+            .and(path("/api/query"))
+            .and(query_param("id_list", "1706.03762,hep-th/9711200"))
+            .respond_with(respond(FEED.to_string()))
+            .mount(&server)
+            .await;
+        let policy = RetryPolicy { min_interval: Duration::ZERO, ..RetryPolicy::default() };
+        let client = ArxivClient::builder().base_url(format!("{}/api", server.uri())).retry_policy(policy).build().unwrap();
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let resolver = WorkResolver::default();
+
+        let synced = client.sync(&arm, &resolver, &["1706.03762", "hep-th/9711200"]).await.unwrap();
+        assert!(synced.iter().all(Synced::is_new));
+        let id = synced[0].id.clone();
+        assert_eq!(arm.tags_of(&id).unwrap(), vec!["cs.CL", "cs.LG"]);
+        let versions = &arm.get_entity(&id).unwrap().unwrap().props.unwrap()[ARXIV_VERSIONS];
+        assert_eq!(versions.as_array().unwrap().len(), 5);
+        assert_eq!(versions[0]["date"], "2017-06-12T17:57:34Z");
+
+        // a sixth version appears
+        server.reset().await;
+        let updated = FEED.replace("1706.03762v5", "1706.03762v6").replace("2017-12-06T03:30:32Z", "2023-08-02T00:41:18Z");
+        Mock::given(method("GET")).and(path("/api/query")).respond_with(respond(updated)).mount(&server).await;
+        let synced = client.resync(&arm, &resolver, 10).await.unwrap();
+        let attention = synced.iter().find(|s| s.id == id).unwrap();
+        assert!(attention.has_new_version());
+        assert_eq!((attention.previous, attention.version), (Some(5), 6));
+        assert!(!synced.iter().find(|s| s.arxiv_id == "hep-th/9711200").unwrap().has_new_version());
+
+        let work = arm.get_entity(&id).unwrap().unwrap().props.unwrap();
+        assert_eq!(work[ARXIV_VERSIONS][4]["date"], "2017-12-06T03:30:32Z");
+        assert_eq!(work[ARXIV_VERSIONS][5]["date"], "2023-08-02T00:41:18Z");
+        assert_eq!(arm.load_reference(&id).unwrap().unwrap().field("version"), Some("v6"));
+    }
+}
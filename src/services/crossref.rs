@@ -0,0 +1,367 @@
+use std::time::Duration;
+use serde_json::Value;
+use crate::database::AcademicResourceManager;
+use crate::domain::affiliation::Affiliation;
+use crate::domain::author::{Author, Name};
+use crate::domain::sources::{
+    is_work_kind, Reference, BOOK, CHAPTER, CONFERENCE_PAPER, DATASET, JOURNAL_ARTICLE, MISC, PREPRINT, PROCEEDINGS,
+    REPORT, THESIS,
+};
+use crate::services::http::{user_agent, ClientError, Fetcher, RetryPolicy};
+use crate::services::works::WorkResolver;
+use crate::utils::text::{fold, similarity};
+
+/// Source name recorded in provenance for fields taken from Crossref.
+pub const CROSSREF: &str = "crossref";
+const BASE_URL: &str = "https://api.crossref.org";
+// Least folded title similarity for a search hit to count as the work being enriched.
+const MATCH_THRESHOLD: f64 = 0.9;
+
+fn kind_for(crossref_type: &str) -> &'static str {
+    match crossref_type {
+        "journal-article" => JOURNAL_ARTICLE,
+        "proceedings-article" => CONFERENCE_PAPER,
+        "proceedings" => PROCEEDINGS,
+        "book" | "monograph" | "edited-book" | "reference-book" => BOOK,
+        "book-chapter" | "book-section" | "book-part" => CHAPTER,
+        "dissertation" => THESIS,
+        "posted-content" => PREPRINT,
+        "report" => REPORT,
+        "dataset" => DATASET,
+        _ => MISC,
+    }
+}
+
+// First string of a Crossref list field (`title`, `container-title`, `ISSN`, ...).
+fn first<'a>(item: &'a Value, name: &str) -> Option<&'a str> {
+    item.get(name)?.as_array()?.iter().find_map(Value::as_str).filter(|s| !s.trim().is_empty())
+}
+
+// Year of the first date Crossref gives, from `issued` to `created`.
+fn year(item: &Value) -> Option<i64> {
+    ["issued", "published", "published-print", "published-online", "created"]
+        .iter()
+        .find_map(|name| item.get(*name)?.get("date-parts")?.get(0)?.get(0)?.as_i64())
+}
+
+fn person(value: &Value) -> Option<Author> {
+    let name = match (value.get("family").and_then(Value::as_str), value.get("given").and_then(Value::as_str)) {
+        (Some(family), Some(given)) => Name::from_parts(family, given, value.get("suffix").and_then(Value::as_str)),
+        (Some(family), None) => Name::parse(family),
+        // consortia come as a bare `name`
+        _ => Name::parse(value.get("name")?.as_str()?),
+    };
+    let mut builder = Author::builder().name(name.ok()?).ok()?;
+    if let Some(orcid) = value.get("ORCID").and_then(Value::as_str) {
+        builder = builder.orcid_if_valid(orcid);
+    }
+    let affiliation = value
+        .get("affiliation")
+        .and_then(Value::as_array)
+        .and_then(|list| list.iter().find_map(|a| a.get("name")?.as_str()));
+    if let Some(affiliation) = affiliation {
+        builder = builder.affiliation(Affiliation::parse(affiliation)).ok()?;
+    }
+    builder.build().ok()
+}
+
+/// Maps one Crossref work (the `message` of `/works/{doi}` or an item of a search) onto a
+/// reference. Works without a title are skipped.
+pub fn reference_from(item: &Value) -> Option<Reference> {
+    let title = first(item, "title")?.to_string();
+    let people = |name: &str| -> Vec<Author> {
+        item.get(name).and_then(Value::as_array).map_or(Vec::new(), |list| list.iter().filter_map(person).collect())
+    };
+    let mut reference = Reference {
+        kind: kind_for(item.get("type").and_then(Value::as_str).unwrap_or_default()).to_string(),
+        title,
+        authors: people("author"),
+        editors: people("editor"),
+        year: year(item),
+        venue: first(item, "container-title").map(str::to_string),
+        uri: item.get("URL").and_then(Value::as_str).map(str::to_string),
+        keywords: item
+            .get("subject")
+            .and_then(Value::as_array)
+            .map_or(Vec::new(), |list| list.iter().filter_map(Value::as_str).map(str::to_string).collect()),
+        ..Default::default()
+    };
+    let text = |name: &str| item.get(name).and_then(Value::as_str).filter(|s| !s.trim().is_empty());
+    let fields = [
+        ("doi", text("DOI").map(str::to_lowercase)),
+        ("volume", text("volume").map(str::to_string)),
+        ("number", text("issue").map(str::to_string)),
+        ("pages", text("page").map(str::to_string)),
+        ("publisher", text("publisher").map(str::to_string)),
+        ("abstract", text("abstract").map(str::to_string)),
+        ("issn", first(item, "ISSN").map(str::to_string)),
+        ("isbn", first(item, "ISBN").map(str::to_string)),
+    ];
+    for (name, value) in fields {
+        if let Some(value) = value {
+            reference.fields.insert(name.to_string(), value);
+        }
+    }
+    Some(reference)
+}
+
+// DOIs go into the path; the few characters that would end it are escaped.
+fn doi_path(doi: &str) -> String {
+    doi.trim()
+        .replace('%', "%25")
+        .replace('#', "%23")
+        .replace('?', "%3F")
+        .replace(' ', "%20")
+}
+
+/// Client for the Crossref REST API.
+#[derive(Debug)]
+pub struct CrossrefClient {
+    fetcher: Fetcher,
+    base_url: String,
+}
+
+impl CrossrefClient {
+    pub fn builder() -> CrossrefClientBuilder {
+        CrossrefClientBuilder::default()
+    }
+
+    /// The work registered under `doi`, or `None` when Crossref does not know it.
+    pub async fn work(&self, doi: &str) -> Result<Option<Reference>, ClientError> {
+        let url = format!("{}/works/{}", self.base_url, doi_path(doi));
+        let Some(body) = self.fetcher.get_json(&url, &[]).await? else {
+            return Ok(None);
+        };
+        let message = body.get("message").ok_or_else(|| ClientError::Format("work without `message`".to_string()))?;
+        Ok(reference_from(message))
+    }
+
+    /// Best `rows` matches of a free-text citation (title, authors, venue, year) by
+    /// `query.bibliographic`.
+    pub async fn search(&self, query: &str, rows: usize) -> Result<Vec<Reference>, ClientError> {
+        let url = format!("{}/works", self.base_url);
+        let params = [("query.bibliographic", query.to_string()), ("rows", rows.to_string())];
+        let Some(body) = self.fetcher.get_json(&url, &params).await? else {
+            return Ok(Vec::new());
+        };
+        let items = body
+            .pointer("/message/items")
+            .and_then(Value::as_array)
+            .ok_or_else(|| ClientError::Format("search without `message.items`".to_string()))?;
+        Ok(items.iter().filter_map(reference_from).collect())
+    }
+
+    // The search hit that is `reference`: close enough in title and not years apart.
+    async fn best_match(&self, reference: &Reference) -> Result<Option<Reference>, ClientError> {
+        let title = fold(&reference.title);
+        if title.is_empty() {
+            return Ok(None);
+        }
+        let mut query = reference.title.clone();
+        if let Some(author) = reference.authors.first() {
+            query = format!("{query} {}", author.name.family());
+        }
+        if let Some(year) = reference.year {
+            query = format!("{query} {year}");
+        }
+        Ok(self.search(&query, 5).await?.into_iter().find(|hit| {
+            let close_years = match (hit.year, reference.year) {
+                (Some(a), Some(b)) => (a - b).abs() <= 1,
+                _ => true,
+            };
+            close_years && similarity(&fold(&hit.title), &title) >= MATCH_THRESHOLD
+        }))
+    }
+
+    /// Fills in the DOI, year and other missing fields of the stored work `id` from Crossref:
+    /// by DOI when the work has one, by bibliographic search otherwise. Fields merge through
+    /// `resolver`, so sources it ranks above `crossref` keep their values. Returns whether a
+    /// Crossref record was found.
+    pub async fn enrich(&self, arm: &AcademicResourceManager, resolver: &WorkResolver, id: &str) -> Result<bool, ClientError> {
+        let Some(current) = arm.load_reference(id)? else {
+            return Ok(false);
+        };
+        let found = match current.doi() {
+            Some(_) if current.year.is_some() => return Ok(false),
+            Some(doi) => self.work(doi).await?,
+            None => self.best_match(&current).await?,
+        };
+        let Some(found) = found else {
+            return Ok(false);
+        };
+        resolver.save_into(arm, id, &found, CROSSREF)?;
+        Ok(true)
+    }
+
+    /// Enriches every work missing a DOI or a year, returning the ids Crossref had a record for.
+    pub async fn enrich_missing(&self, arm: &AcademicResourceManager, resolver: &WorkResolver) -> Result<Vec<String>, ClientError> {
+        let missing: Vec<String> = arm
+            .list_entities(None)?
+            .into_iter()
+            .filter(|e| is_work_kind(&e.kind))
+            .filter(|e| e.year.is_none() || e.props.as_ref().and_then(|p| p.pointer("/fields/doi")).is_none())
+            .map(|e| e.id)
+            .collect();
+        let mut enriched = Vec::new();
+        for id in missing {
+            if self.enrich(arm, resolver, &id).await? {
+                enriched.push(id);
+            }
+        }
+        Ok(enriched)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CrossrefClientBuilder {
+    mailto: Option<String>,
+    base_url: Option<String>,
+    policy: Option<RetryPolicy>,
+    timeout: Option<Duration>,
+}
+
+impl CrossrefClientBuilder {
+    /// Contact address sent in the `User-Agent`, which routes requests to the polite pool.
+    pub fn mailto(mut self, mailto: impl Into<String>) -> Self {
+        self.mailto = Some(mailto.into());
+        self
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn build(self) -> Result<CrossrefClient, ClientError> {
+        let client = reqwest::Client::builder()
+            .user_agent(user_agent(self.mailto.as_deref()))
+            .timeout(self.timeout.unwrap_or(Duration::from_secs(30)))
+            .build()?;
+        Ok(CrossrefClient {
+            fetcher: Fetcher::new(client, self.policy.unwrap_or_default()),
+            base_url: self.base_url.unwrap_or_else(|| BASE_URL.to_string()).trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use crate::database::Engine;
+
+    const WORK: &str = include_str!("fixtures/crossref_work.json");
+    const SEARCH: &str = include_str!("fixtures/crossref_search.json");
+
+    fn fixture(body: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_raw(body, "application/json")
+    }
+
+    fn client(server: &MockServer) -> CrossrefClient {
+        let policy = RetryPolicy { min_interval: Duration::ZERO, max_retries: 2, backoff: Duration::from_millis(1) };
+        CrossrefClient::builder().mailto("jane@example.org").base_url(server.uri()).retry_policy(policy).build().unwrap()
+    }
+
+    #[test]
+    fn test_bad_orcid_keeps_author() {
+        let item = serde_json::json!({
+            "title": ["Deep learning"],
+            "author": [
+                { "family": "LeCun", "given": "Yann", "ORCID": "http://orcid.org/0000-0002-1825-0098" },
+                { "family": "Hinton", "given": "Geoffrey", "ORCID": "http://orcid.org/0000-0002-1825-0097" },
+            ],
+        });
+        let work = reference_from(&item).unwrap();
+        let authors: Vec<(&str, Option<&str>)> = work.authors.iter().map(|a| (a.name.last.as_str(), a.orcid.as_ref().map(|o| o.as_str()))).collect();
+        assert_eq!(authors, vec![("LeCun", None), ("Hinton", Some("0000-0002-1825-0097"))]);
+    }
+
+    #[tokio::test]
+    async fn test_work_by_doi() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/works/10.1038/nature14539"))
+            .and(header("user-agent", user_agent(Some("jane@example.org")).as_str()))
+            .respond_with(fixture(WORK))
+            .mount(&server)
+            .await;
+        let client = client(&server);
+
+        let work = client.work("10.1038/nature14539").await.unwrap().unwrap();
+        assert_eq!(work.kind, JOURNAL_ARTICLE);
+        assert_eq!(work.title, "Deep learning");
+        assert_eq!(work.authors.iter().map(|a| a.name.last.as_str()).collect::<Vec<_>>(), vec!["LeCun", "Bengio", "Hinton"]);
+        assert_eq!((work.year, work.venue.as_deref()), (Some(2015), Some("Nature")));
+        assert_eq!(work.field("number"), Some("7553"));
+        assert_eq!(work.field("pages"), Some("436-444"));
+        assert_eq!(work.field("issn"), Some("0028-0836"));
+        assert_eq!(work.keywords, vec!["Multidisciplinary"]);
+
+        assert!(client.work("10.1038/missing").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_search_retries() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/works"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/works"))
+            .and(query_param("query.bibliographic", "silly string theory"))
+            .and(query_param("rows", "5"))
+            .respond_with(fixture(SEARCH))
+            .mount(&server)
+            .await;
+        let client = client(&server);
+
+        let hits = client.search("silly string theory", 5).await.unwrap();
+        assert_eq!(hits.len(), 2);
+        let carberry = &hits[0].authors[0];
+        assert_eq!(carberry.orcid.as_ref().map(|o| o.as_str()), Some("0000-0002-1825-0097"));
+        assert_eq!(carberry.affiliation.as_ref().and_then(|a| a.country_code.as_deref()), Some("US"));
+        assert_eq!(hits[1].field("doi"), Some("10.1016/0370-2693(84)91565-x"));
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+
+        Mock::given(method("GET")).and(path("/works/10.1/down")).respond_with(ResponseTemplate::new(500)).mount(&server).await;
+        assert!(matches!(client.work("10.1/down").await, Err(ClientError::Status { status: 500, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_enrich_missing() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).and(path("/works")).respond_with(fixture(SEARCH)).mount(&server).await;
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let reference = Reference {
+            kind: JOURNAL_ARTICLE.to_string(),
+            title: "Toward a unified theory of high-energy metaphysics: silly string theory".to_string(),
+            authors: vec![Author::builder().name_from_str("Josiah Carberry").unwrap().build().unwrap()],
+            ..Default::default()
+        };
+        let id = arm.save_reference(&reference).unwrap();
+
+        let enriched = client(&server).enrich_missing(&arm, &WorkResolver::default()).await.unwrap();
+        assert_eq!(enriched, vec![id.clone()]);
+        let stored = arm.load_reference(&id).unwrap().unwrap();
+        assert_eq!(stored.doi(), Some("10.5555/12345678"));
+        assert_eq!(stored.year, Some(2008));
+        // the stored title is kept; new fields are credited to Crossref
+        assert_eq!(stored.title, reference.title);
+        assert_eq!(stored.extra["provenance"]["doi"], CROSSREF);
+    }
+}
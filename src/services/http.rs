@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, Response, StatusCode};
+use serde_json::Value;
+use thiserror::Error;
+use tokio::sync::Mutex;
+use crate::database::RepositoryError;
+use crate::services::works::WorkResolverError;
+
+// Longest wait a `Retry-After` header is trusted with.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+// Longest wait between two attempts when the server does not say.
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("{url} answered {status}")]
+    Status { status: u16, url: String },
+    #[error("Unexpected response: {0}")]
+    Format(String),
+    #[error("Repository error: {0}")]
+    Repository(#[from] RepositoryError),
+    #[error("Resolver error: {0}")]
+    Resolver(#[from] WorkResolverError),
+}
+
+/// How a client paces its requests and retries throttled or failed ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// least time between two requests
+    pub min_interval: Duration,
+    pub max_retries: u32,
+    /// first retry delay, doubled on every further attempt unless the server sends `Retry-After`
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    // `backoff` doubled for every attempt made so far, up to `MAX_BACKOFF`.
+    fn delay(&self, attempt: u32) -> Duration {
+        2u32.checked_pow(attempt)
+            .and_then(|factor| self.backoff.checked_mul(factor))
+            .map_or(MAX_BACKOFF, |d| d.min(MAX_BACKOFF))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy { min_interval: Duration::from_millis(100), max_retries: 3, backoff: Duration::from_secs(1) }
+    }
+}
+
+/// `poirot/<version>`, with a contact address for services that route identified clients to a
+/// politer pool.
+pub fn user_agent(mailto: Option<&str>) -> String {
+    match mailto {
+        Some(mail) => format!("poirot/{} (mailto:{mail})", env!("CARGO_PKG_VERSION")),
+        None => format!("poirot/{}", env!("CARGO_PKG_VERSION")),
+    }
+}
+
+fn retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || matches!(status.as_u16(), 500 | 502 | 503 | 504)
+}
+
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds: u64 = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim().parse().ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER))
+}
+
+// A shared HTTP client with rate limiting and retries, behind the service clients.
+#[derive(Debug)]
+pub(crate) struct Fetcher {
+    client: Client,
+    policy: RetryPolicy,
+    last: Mutex<Option<Instant>>,
+}
+
+impl Fetcher {
+    pub(crate) fn new(client: Client, policy: RetryPolicy) -> Self {
+        Fetcher { client, policy, last: Mutex::new(None) }
+    }
+
+    async fn wait_turn(&self) {
+        let mut last = self.last.lock().await;
+        if let Some(previous) = *last {
+            let next = previous + self.policy.min_interval;
+            let now = Instant::now();
+            if next > now {
+                tokio::time::sleep(next - now).await;
+            }
+        }
+        *last = Some(Instant::now());
+    }
+
+    /// GETs `url`, keeping to the rate limit and retrying 429s, 5xxs, timeouts and refused
+    /// connections. A 404 gives `None`.
+    pub(crate) async fn get(&self, url: &str, query: &[(&str, String)]) -> Result<Option<Response>, ClientError> {
+        let mut attempt = 0;
+        loop {
+            self.wait_turn().await;
+            let last_try = attempt >= self.policy.max_retries;
+            let wait = match self.client.get(url).query(query).send().await {
+                Ok(response) if response.status() == StatusCode::NOT_FOUND => return Ok(None),
+                Ok(response) if response.status().is_success() => return Ok(Some(response)),
+                Ok(response) if retryable(response.status()) && !last_try => retry_after(&response),
+                Ok(response) => {
+                    return Err(ClientError::Status { status: response.status().as_u16(), url: response.url().to_string() });
+                }
+                Err(e) if (e.is_timeout() || e.is_connect()) && !last_try => None,
+                Err(e) => return Err(e.into()),
+            };
+            tokio::time::sleep(wait.unwrap_or_else(|| self.policy.delay(attempt))).await;
+            attempt += 1;
+        }
+    }
+
+    pub(crate) async fn get_json(&self, url: &str, query: &[(&str, String)]) -> Result<Option<Value>, ClientError> {
+        match self.get(url, query).await? {
+            Some(response) => Ok(Some(response.json().await?)),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) async fn get_text(&self, url: &str, query: &[(&str, String)]) -> Result<Option<String>, ClientError> {
+        match self.get(url, query).await? {
+            Some(response) => Ok(Some(response.text().await?)),
+            None => Ok(None),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_is_capped() {
+        let policy = RetryPolicy { max_retries: 100, ..Default::default() };
+        assert_eq!(policy.delay(0), Duration::from_secs(1));
+        assert_eq!(policy.delay(3), Duration::from_secs(8));
+        assert_eq!(policy.delay(40), MAX_BACKOFF);
+        assert_eq!(policy.delay(u32::MAX), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::Read;
+use std::path::Path;
+use serde_json::Value;
+use thiserror::Error;
+use zip::ZipArchive;
+use crate::database::authors::INSTITUTION_KIND;
+use crate::database::{AcademicResourceManager, RepositoryError};
+use crate::domain::affiliation::Affiliation;
+use crate::domain::institution::Institution;
+use crate::domain::ror::RorId;
+use crate::utils::text::{fold, similarity};
+
+// Words too common in organization names to narrow down fuzzy candidates.
+const STOPWORDS: [&str; 14] = [
+    "of", "the", "and", "for", "de", "la", "university", "institute", "college", "national", "research",
+    "center", "centre", "school",
+];
+
+#[derive(Error, Debug)]
+pub enum InstitutionError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Repository error: {0}")]
+    Repository(#[from] RepositoryError),
+    #[error("Format error: {0}")]
+    Format(String),
+}
+
+/// An organization of a ROR data dump with its parent organizations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RorRecord {
+    pub institution: Institution,
+    pub parents: Vec<RorId>,
+}
+
+fn strings(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|a| a.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+// Reads a record of either dump schema: v1 (`name`, `aliases`, `acronyms`, `labels`, `country`)
+// or v2 (`names` with `types`, `locations`). Withdrawn records are skipped.
+fn record(value: &Value) -> Result<Option<RorRecord>, InstitutionError> {
+    if value.get("status").and_then(Value::as_str) == Some("withdrawn") {
+        return Ok(None);
+    }
+    let id = value.get("id").and_then(Value::as_str).ok_or_else(|| InstitutionError::Format("record without id".to_string()))?;
+    let ror = RorId::parse(id).map_err(|e| InstitutionError::Format(format!("{id}: {e}")))?;
+
+    let mut institution = Institution { ror: Some(ror), ..Default::default() };
+    match value.get("names").and_then(Value::as_array) {
+        Some(names) => {
+            for name in names {
+                let Some(text) = name.get("value").and_then(Value::as_str).map(str::to_string) else {
+                    continue;
+                };
+                let types = strings(name.get("types"));
+                if types.iter().any(|t| t == "ror_display") {
+                    institution.name = text;
+                } else if types.iter().any(|t| t == "acronym") {
+                    institution.acronyms.push(text);
+                } else {
+                    institution.aliases.push(text);
+                }
+            }
+            institution.country_code = value
+                .pointer("/locations/0/geonames_details/country_code")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+        }
+        None => {
+            institution.name = value.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+            institution.aliases = strings(value.get("aliases"));
+            let labels = value.get("labels").and_then(Value::as_array).into_iter().flatten();
+            institution.aliases.extend(labels.filter_map(|l| l.get("label").and_then(Value::as_str)).map(str::to_string));
+            institution.acronyms = strings(value.get("acronyms"));
+            institution.country_code = value.pointer("/country/country_code").and_then(Value::as_str).map(str::to_string);
+        }
+    }
+    if institution.name.is_empty() {
+        return Err(InstitutionError::Format(format!("{id}: record without a name")));
+    }
+    institution.types = strings(value.get("types")).iter().map(|t| t.to_lowercase()).collect();
+
+    let parents = value
+        .get("relationships")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|r| r.get("type").and_then(Value::as_str).is_some_and(|t| t.eq_ignore_ascii_case("parent")))
+        .filter_map(|r| r.get("id").and_then(Value::as_str))
+        .filter_map(|id| RorId::parse(id).ok())
+        .collect();
+    Ok(Some(RorRecord { institution, parents }))
+}
+
+/// Parses the JSON array of a ROR data dump.
+pub fn parse_dump(json: &str) -> Result<Vec<RorRecord>, InstitutionError> {
+    let values: Vec<Value> = serde_json::from_str(json)?;
+    let mut records = Vec::with_capacity(values.len());
+    for value in &values {
+        records.extend(record(value)?);
+    }
+    Ok(records)
+}
+
+/// Reads a ROR dump as published on Zenodo, either the `.zip` or the `.json` inside it.
+pub fn read_dump(path: impl AsRef<Path>) -> Result<Vec<RorRecord>, InstitutionError> {
+    let path = path.as_ref();
+    let mut json = String::new();
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("zip")) {
+        let mut archive = ZipArchive::new(std::fs::File::open(path)?)?;
+        let names: Vec<String> = archive.file_names().filter(|n| n.ends_with(".json")).map(str::to_string).collect();
+        // recent dumps ship a v1 and a v2 file; both parse, prefer the newer schema
+        let name = names
+            .iter()
+            .find(|n| n.contains("schema_v2"))
+            .or(names.first())
+            .ok_or_else(|| InstitutionError::Format("no JSON file in the archive".to_string()))?;
+        archive.by_name(name)?.read_to_string(&mut json)?;
+    } else {
+        std::fs::File::open(path)?.read_to_string(&mut json)?;
+    }
+    parse_dump(&json)
+}
+
+/// How an institution name was matched to a ROR record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RorMatch {
+    pub ror: RorId,
+    /// 1.0 for a name or alias, 0.9 for an acronym, the title similarity for fuzzy matches
+    pub score: f64,
+}
+
+/// Resolves free-text institution names against a ROR dump loaded in memory. Names and aliases
+/// match exactly after folding, acronyms when they are unambiguous, and anything else by
+/// similarity above the threshold. A country code, when known, breaks ties.
+#[derive(Debug)]
+pub struct RorMatcher {
+    records: Vec<RorRecord>,
+    by_ror: HashMap<RorId, usize>,
+    names: HashMap<String, Vec<usize>>,
+    acronyms: HashMap<String, Vec<usize>>,
+    tokens: HashMap<String, Vec<usize>>,
+    threshold: f64,
+}
+
+impl RorMatcher {
+    pub fn new(records: Vec<RorRecord>) -> Self {
+        let mut matcher = RorMatcher {
+            by_ror: HashMap::new(),
+            names: HashMap::new(),
+            acronyms: HashMap::new(),
+            tokens: HashMap::new(),
+            threshold: 0.9,
+            records: Vec::new(),
+        };
+        for (i, record) in records.iter().enumerate() {
+            let institution = &record.institution;
+            if let Some(ror) = &institution.ror {
+                matcher.by_ror.insert(ror.clone(), i);
+            }
+            for name in std::iter::once(&institution.name).chain(&institution.aliases) {
+                let key = fold(name);
+                for token in key.split_whitespace().filter(|t| t.len() > 2 && !STOPWORDS.contains(t)) {
+                    matcher.tokens.entry(token.to_string()).or_default().push(i);
+                }
+                matcher.names.entry(key).or_default().push(i);
+            }
+            for acronym in &institution.acronyms {
+                matcher.acronyms.entry(fold(acronym)).or_default().push(i);
+            }
+        }
+        matcher.records = records;
+        matcher
+    }
+
+    pub fn from_dump(path: impl AsRef<Path>) -> Result<Self, InstitutionError> {
+        Ok(RorMatcher::new(read_dump(path)?))
+    }
+
+    /// Minimum similarity of a fuzzy match, 0.9 by default.
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn get(&self, ror: &RorId) -> Option<&RorRecord> {
+        self.by_ror.get(ror).map(|i| &self.records[*i])
+    }
+
+    // Narrows `found` to records in `country` when that leaves any.
+    fn in_country(&self, found: &[usize], country: Option<&str>) -> Vec<usize> {
+        let local: Vec<usize> = found
+            .iter()
+            .copied()
+            .filter(|i| country.is_some_and(|c| self.records[*i].institution.country_code.as_deref() == Some(c)))
+            .collect();
+        if local.is_empty() { found.to_vec() } else { local }
+    }
+
+    fn ror_of(&self, i: usize, score: f64) -> Option<RorMatch> {
+        self.records[i].institution.ror.clone().map(|ror| RorMatch { ror, score })
+    }
+
+    pub fn match_name(&self, name: &str, country: Option<&str>) -> Option<RorMatch> {
+        let key = fold(name);
+        if key.is_empty() {
+            return None;
+        }
+        // several institutions by this name (and in this country) are left to the fuzzy pass,
+        // which gives up on ties as well
+        if let Some(found) = self.names.get(&key)
+            && let [i] = self.in_country(found, country)[..]
+        {
+            return self.ror_of(i, 1.0);
+        }
+        if let Some(found) = self.acronyms.get(&key)
+            && let [i] = self.in_country(found, country)[..]
+        {
+            return self.ror_of(i, 0.9);
+        }
+
+        let candidates: BTreeSet<usize> = key
+            .split_whitespace()
+            .filter_map(|t| self.tokens.get(t))
+            .flatten()
+            .copied()
+            .collect();
+        let mut best: Option<(f64, bool, usize)> = None;
+        let mut tied = false;
+        for i in candidates {
+            let institution = &self.records[i].institution;
+            let score = std::iter::once(&institution.name)
+                .chain(&institution.aliases)
+                .map(|n| similarity(&key, &fold(n)))
+                .fold(0.0, f64::max);
+            let local = country.is_some() && institution.country_code.as_deref() == country;
+            if score < self.threshold {
+                continue;
+            }
+            match best {
+                Some((s, l, _)) if (score, local) == (s, l) => tied = true,
+                Some((s, l, _)) if (score, local) < (s, l) => {}
+                _ => {
+                    best = Some((score, local, i));
+                    tied = false;
+                }
+            }
+        }
+        best.filter(|_| !tied).and_then(|(score, _, i)| self.ror_of(i, score))
+    }
+
+    pub fn match_affiliation(&self, affiliation: &Affiliation) -> Option<RorMatch> {
+        let name = affiliation.institution.as_deref()?;
+        self.match_name(name, affiliation.country_code.as_deref())
+    }
+
+    /// Saves the record of `ror` and, through `part_of` edges, its parents as far as the dump
+    /// knows them. Returns the institution id.
+    pub fn save(&self, arm: &AcademicResourceManager, ror: &RorId) -> Result<String, InstitutionError> {
+        let record = self.get(ror).ok_or_else(|| InstitutionError::Format(format!("{ror} is not in the dump")))?;
+        let id = arm.save_institution(&record.institution)?;
+        let mut pending: Vec<(String, &RorRecord)> = vec![(id.clone(), record)];
+        let mut seen = BTreeSet::from([ror.clone()]);
+        while let Some((child, record)) = pending.pop() {
+            for parent in &record.parents {
+                let Some(parent_record) = self.get(parent) else {
+                    continue;
+                };
+                let parent_id = arm.save_institution(&parent_record.institution)?;
+                arm.set_parent_institution(&child, &parent_id)?;
+                if seen.insert(parent.clone()) {
+                    pending.push((parent_id, parent_record));
+                }
+            }
+        }
+        Ok(id)
+    }
+
+    /// Matches every institution node without a ROR id against the dump. Matched nodes are
+    /// replaced by the registry entry, which takes over their edges; the old id stays an alias.
+    /// Returns the old ids with the match each got.
+    pub fn link_institutions(&self, arm: &AcademicResourceManager) -> Result<Vec<(String, RorMatch)>, InstitutionError> {
+        let mut linked = Vec::new();
+        for entity in arm.list_entities(Some(INSTITUTION_KIND))? {
+            let has_ror = entity.props.as_ref().and_then(|p| p.get("ror")).is_some_and(|r| !r.is_null());
+            if has_ror || arm.resolve_alias(&entity.id)? != entity.id {
+                continue;
+            }
+            // free-text nodes only know their name; the country comes from an affiliation if any
+            let country = arm
+                .edges_to(&entity.id, None)?
+                .iter()
+                .filter_map(|e| e.props.as_ref()?.get("country_code")?.as_str().map(str::to_string))
+                .next();
+            let Some(found) = self.match_name(&entity.title, country.as_deref()) else {
+                continue;
+            };
+            // saving merges the node already when its name is one of the record's names
+            let id = self.save(arm, &found.ror)?;
+            if arm.get_entity(&entity.id)?.is_some() {
+                arm.merge_entities(&id, &entity.id)?;
+            }
+            linked.push((entity.id, found));
+        }
+        Ok(linked)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::authors::AFFILIATED_WITH;
+    use crate::database::Engine;
+    use crate::domain::Author;
+
+    const DUMP_V1: &str = r#"[
+        {"id": "https://ror.org/042nb2s44", "name": "Massachusetts Institute of Technology", "status": "active",
+         "aliases": [], "acronyms": ["MIT"], "labels": [{"label": "Institut de technologie du Massachusetts", "iso639": "fr"}],
+         "types": ["Education"], "country": {"country_code": "US", "country_name": "United States"},
+         "relationships": [{"type": "Child", "label": "Lincoln Laboratory", "id": "https://ror.org/022z6jk58"}]},
+        {"id": "https://ror.org/022z6jk58", "name": "MIT Lincoln Laboratory", "status": "active",
+         "aliases": ["Lincoln Laboratory"], "acronyms": [], "labels": [], "types": ["Facility"],
+         "country": {"country_code": "US"},
+         "relationships": [{"type": "Parent", "label": "MIT", "id": "https://ror.org/042nb2s44"}]}
+    ]"#;
+
+    const DUMP_V2: &str = r#"[
+        {"id": "https://ror.org/02y72wh86", "status": "active", "types": ["education"],
+         "names": [{"value": "Queen's University", "types": ["ror_display", "label"], "lang": "en"},
+                   {"value": "Queens", "types": ["alias"], "lang": null}],
+         "locations": [{"geonames_details": {"country_code": "CA", "name": "Kingston"}}],
+         "relationships": []},
+        {"id": "https://ror.org/00hswnk62", "status": "active", "types": ["education"],
+         "names": [{"value": "Queen's University Belfast", "types": ["ror_display"], "lang": "en"},
+                   {"value": "QUB", "types": ["acronym"], "lang": null}],
+         "locations": [{"geonames_details": {"country_code": "GB", "name": "Belfast"}}],
+         "relationships": []},
+        {"id": "https://ror.org/00000000x", "status": "withdrawn", "names": []}
+    ]"#;
+
+    #[test]
+    fn test_parse_dump() {
+        let v1 = parse_dump(DUMP_V1).unwrap();
+        assert_eq!(v1[0].institution.acronyms, vec!["MIT"]);
+        assert_eq!(v1[0].institution.aliases, vec!["Institut de technologie du Massachusetts"]);
+        assert_eq!(v1[0].institution.types, vec!["education"]);
+        assert_eq!(v1[1].parents, vec![RorId::parse("042nb2s44").unwrap()]);
+
+        let v2 = parse_dump(DUMP_V2).unwrap();
+        assert_eq!(v2.len(), 2);
+        assert_eq!(v2[0].institution.name, "Queen's University");
+        assert_eq!(v2[0].institution.aliases, vec!["Queens"]);
+        assert_eq!(v2[1].institution.acronyms, vec!["QUB"]);
+        assert_eq!(v2[1].institution.country_code.as_deref(), Some("GB"));
+    }
+
+    #[test]
+    fn test_match_names() {
+        let mut records = parse_dump(DUMP_V1).unwrap();
+        records.extend(parse_dump(DUMP_V2).unwrap());
+        let matcher = RorMatcher::new(records);
+        let ror = |name: &str, country: Option<&str>| matcher.match_name(name, country).map(|m| m.ror.to_string());
+
+        assert_eq!(ror("massachusetts institute of technology", None).as_deref(), Some("042nb2s44"));
+        assert_eq!(ror("MIT", None).as_deref(), Some("042nb2s44"));
+        assert_eq!(ror("Massachusets Institute of Technology", None).as_deref(), Some("042nb2s44"));
+        assert_eq!(ror("Queens University Belfast", Some("GB")).as_deref(), Some("00hswnk62"));
+        assert_eq!(ror("Queen’s University", Some("CA")).as_deref(), Some("02y72wh86"));
+        assert_eq!(ror("Stanford University", None), None);
+
+        let affiliation = Affiliation::parse("Dept. of Physics, Massachusetts Institute of Technology, Cambridge, MA, USA");
+        assert_eq!(matcher.match_affiliation(&affiliation).unwrap().score, 1.0);
+    }
+
+    #[test]
+    fn test_same_name_needs_country() {
+        let dump = r#"[
+            {"id": "https://ror.org/02tyrky19", "name": "Trinity College Dublin", "status": "active", "aliases": ["Trinity College"],
+             "acronyms": [], "labels": [], "types": ["Education"], "country": {"country_code": "IE"}, "relationships": []},
+            {"id": "https://ror.org/03gwbs553", "name": "Trinity College", "status": "active", "aliases": [],
+             "acronyms": [], "labels": [], "types": ["Education"], "country": {"country_code": "US"}, "relationships": []}
+        ]"#;
+        let matcher = RorMatcher::new(parse_dump(dump).unwrap());
+        let ror = |name: &str, country: Option<&str>| matcher.match_name(name, country).map(|m| m.ror.to_string());
+
+        assert_eq!(ror("Trinity College", None), None);
+        assert_eq!(ror("Trinity College", Some("IE")).as_deref(), Some("02tyrky19"));
+        assert_eq!(ror("Trinity College", Some("US")).as_deref(), Some("03gwbs553"));
+        assert_eq!(ror("Trinity College", Some("FR")), None);
+    }
+
+    #[test]
+    fn test_link_institutions() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let matcher = RorMatcher::new(parse_dump(DUMP_V1).unwrap());
+        let jane = Author::builder()
+            .name_from_str("Jane Smith").unwrap()
+            .affiliation(Affiliation::parse("Lincoln Laboratory; Group 89")).unwrap()
+            .build()
+            .unwrap();
+        let jane = arm.save_author(&jane).unwrap();
+
+        let linked = matcher.link_institutions(&arm).unwrap();
+        assert_eq!(linked.len(), 1);
+        assert_eq!(linked[0].0, "institution:lincoln-laboratory");
+        let lab = arm.edges_from(&jane, Some(AFFILIATED_WITH)).unwrap()[0].dst.clone();
+        assert_eq!(lab, "institution:ror:022z6jk58");
+        assert_eq!(arm.parent_institutions(&lab).unwrap(), vec!["institution:ror:042nb2s44"]);
+        assert_eq!(arm.find_institution("MIT").unwrap().as_deref(), Some("institution:ror:042nb2s44"));
+        assert_eq!(arm.find_institution("Lincoln Laboratory").unwrap(), Some(lab));
+    }
+}
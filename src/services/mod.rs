@@ -0,0 +1,8 @@
+pub mod arxiv;
+pub mod crossref;
+pub mod http;
+pub mod institutions;
+pub mod openalex;
+pub mod resolver;
+pub mod semantic_scholar;
+pub mod works;
@@ -0,0 +1,838 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::time::Duration;
+use flate2::read::MultiGzDecoder;
+use serde_json::{json, Map, Value};
+use crate::database::authors::{author_id, institution_id, with_current_affiliation, AFFILIATED_WITH, AUTHORED, AUTHOR_KIND, INSTITUTION_KIND};
+use crate::database::institutions::{ror_institution_id, PART_OF};
+use crate::database::records::to_json;
+use crate::database::references::{normalize_doi, reference_entity, work_id, CITES, PUBLISHED_IN};
+use crate::database::topics::{topic_id, BROADER, TOPIC_KIND};
+use crate::database::{AcademicResourceManager, Edge, Entity, RepositoryError};
+use crate::domain::affiliation::Affiliation;
+use crate::domain::author::{Author, Name};
+use crate::domain::ror::RorId;
+use crate::domain::sources::{
+    EntryError, Reference, SourceError, BOOK, CHAPTER, CONFERENCE_PAPER, DATASET, JOURNAL_ARTICLE, MISC, PREPRINT,
+    REPORT, THESIS,
+};
+use crate::services::http::{user_agent, ClientError, Fetcher, RetryPolicy};
+
+/// Source name of everything loaded from OpenAlex.
+pub const OPENALEX: &str = "openalex";
+/// Prop holding the OpenAlex id (`W2741809807`, `A5023888391`, ...) of loaded entities.
+pub const OPENALEX_ID: &str = "openalex_id";
+/// Least score a work's concept needs to become one of its tags.
+pub const MIN_CONCEPT_SCORE: f64 = 0.3;
+const BASE_URL: &str = "https://api.openalex.org";
+const ID_PREFIX: &str = "https://openalex.org/";
+// Largest page the API serves.
+const PER_PAGE: usize = 200;
+
+// `https://openalex.org/W2741809807` -> `W2741809807`
+fn short_id(value: Option<&Value>) -> Option<String> {
+    let id = value?.as_str()?.trim();
+    let id = id.strip_prefix(ID_PREFIX).unwrap_or(id);
+    (!id.is_empty()).then(|| id.to_string())
+}
+
+fn text<'a>(value: &'a Value, pointer: &str) -> Option<&'a str> {
+    value.pointer(pointer).and_then(Value::as_str).filter(|s| !s.trim().is_empty())
+}
+
+fn list<'a>(value: &'a Value, name: &str) -> impl Iterator<Item = &'a Value> {
+    value.get(name).and_then(Value::as_array).into_iter().flatten()
+}
+
+fn kind_for(work_type: &str, source_type: Option<&str>) -> &'static str {
+    match (work_type, source_type) {
+        ("article", Some("repository")) | ("preprint" | "posted-content", _) => PREPRINT,
+        ("article", Some("conference")) => CONFERENCE_PAPER,
+        ("article" | "review" | "letter" | "editorial", _) => JOURNAL_ARTICLE,
+        ("book", _) => BOOK,
+        ("book-chapter", _) => CHAPTER,
+        ("dissertation", _) => THESIS,
+        ("dataset", _) => DATASET,
+        ("report", _) => REPORT,
+        _ => MISC,
+    }
+}
+
+// OpenAlex ships abstracts as `{word: [positions]}`.
+fn abstract_text(index: &Value) -> Option<String> {
+    let mut words: Vec<(u64, &str)> = index
+        .as_object()?
+        .iter()
+        .flat_map(|(word, at)| at.as_array().into_iter().flatten().filter_map(Value::as_u64).map(move |p| (p, word.as_str())))
+        .collect();
+    words.sort_unstable();
+    (!words.is_empty()).then(|| words.into_iter().map(|(_, w)| w).collect::<Vec<_>>().join(" "))
+}
+
+// Display names of the concepts scored at least `min`.
+fn concepts(work: &Value, min: f64) -> Vec<String> {
+    list(work, "concepts")
+        .filter(|c| c.get("score").and_then(Value::as_f64).is_some_and(|s| s >= min))
+        .filter_map(|c| text(c, "/display_name").map(str::to_string))
+        .collect()
+}
+
+fn affiliation(institution: &Value) -> Option<Affiliation> {
+    Some(Affiliation {
+        institution: Some(text(institution, "/display_name")?.to_string()),
+        department: None,
+        address: None,
+        country: None,
+        country_code: text(institution, "/country_code").map(str::to_string),
+    })
+}
+
+fn person(author: &Value) -> Option<Author> {
+    let mut builder = Author::builder().name(Name::parse(text(author, "/display_name")?).ok()?).ok()?;
+    if let Some(orcid) = text(author, "/orcid") {
+        builder = builder.orcid_if_valid(orcid);
+    }
+    builder.build().ok()
+}
+
+/// Maps an OpenAlex work onto a reference; concepts scored at least `MIN_CONCEPT_SCORE` become
+/// keywords.
+pub fn reference_from(work: &Value) -> Option<Reference> {
+    let title = text(work, "/title").or(text(work, "/display_name"))?.to_string();
+    let source = work.pointer("/primary_location/source").filter(|s| s.is_object());
+    let mut reference = Reference {
+        kind: kind_for(text(work, "/type").unwrap_or_default(), source.and_then(|s| text(s, "/type"))).to_string(),
+        title,
+        authors: list(work, "authorships")
+            .filter_map(|a| {
+                let mut author = person(a.get("author")?)?;
+                author.affiliation = list(a, "institutions").find_map(affiliation);
+                Some(author)
+            })
+            .collect(),
+        year: work.get("publication_year").and_then(Value::as_i64),
+        venue: source.and_then(|s| text(s, "/display_name")).map(str::to_string),
+        uri: text(work, "/primary_location/landing_page_url").or(text(work, "/id")).map(str::to_string),
+        keywords: concepts(work, MIN_CONCEPT_SCORE),
+        ..Default::default()
+    };
+    let pages = match (text(work, "/biblio/first_page"), text(work, "/biblio/last_page")) {
+        (Some(first), Some(last)) if first != last => Some(format!("{first}-{last}")),
+        (first, last) => first.or(last).map(str::to_string),
+    };
+    let fields = [
+        ("doi", text(work, "/doi").map(normalize_doi)),
+        ("pmid", text(work, "/ids/pmid").map(|p| p.trim_end_matches('/').rsplit('/').next().unwrap_or(p).to_string())),
+        ("volume", text(work, "/biblio/volume").map(str::to_string)),
+        ("number", text(work, "/biblio/issue").map(str::to_string)),
+        ("pages", pages),
+        ("issn", source.and_then(|s| text(s, "/issn_l")).map(str::to_string)),
+        ("publisher", source.and_then(|s| text(s, "/host_organization_name")).map(str::to_string)),
+        ("abstract", work.get("abstract_inverted_index").and_then(abstract_text)),
+    ];
+    for (name, value) in fields {
+        if let Some(value) = value {
+            reference.fields.insert(name.to_string(), value);
+        }
+    }
+    if let Some(id) = short_id(work.get("id")) {
+        reference.extra.insert(OPENALEX_ID.to_string(), Value::String(id));
+    }
+    Some(reference)
+}
+
+/// What a load wrote, by record type.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadReport {
+    pub works: usize,
+    pub authors: usize,
+    pub institutions: usize,
+    pub concepts: usize,
+    /// records of other types (sources, funders, ...) and works without a title
+    pub skipped: usize,
+    pub cites: usize,
+    /// references to works neither loaded nor in the graph before
+    pub dangling: usize,
+    pub errors: Vec<EntryError>,
+}
+
+// An entity waiting for the next flush. Stubs, made from the copy of a record nested in
+// another one, only fill gaps; full records replace what OpenAlex wrote earlier.
+struct Pending {
+    entity: Entity,
+    stub: bool,
+}
+
+/// Maps OpenAlex works, authors, institutions and concepts onto the graph and writes them in
+/// batches, one transaction each.
+///
+/// Works become entities (under their DOI id when they have one) with `authored`,
+/// `published_in` and, once everything is read, `cites` edges between loaded works; authors
+/// link to institutions with `affiliated_with`, institutions to their parents with `part_of`,
+/// and concepts become topics with `broader` edges, their names tagging the works. Entities
+/// that other importers created are left as they are and only gain edges and tags, apart from
+/// `authored` edges, which would mix a second author list into theirs. Ids of merged-away
+/// entities resolve to the survivor.
+pub struct OpenAlexLoader<'a> {
+    arm: &'a AcademicResourceManager,
+    batch_size: usize,
+    min_concept_score: f64,
+    // OpenAlex id -> entity id
+    index: HashMap<String, String>,
+    // entity id -> the OpenAlex record that claimed it
+    claims: HashMap<String, String>,
+    // entities written from OpenAlex records, by this load or an earlier one
+    ours: HashSet<String>,
+    venues: HashMap<String, String>,
+    entities: BTreeMap<String, Pending>,
+    edges: Vec<Edge>,
+    // (author, institution) -> the affiliation made current there, merged into the stored edge
+    affiliations: BTreeMap<(String, String), Affiliation>,
+    // `authored` edges, kept for the works this loader writes
+    authorships: Vec<Edge>,
+    tags: Vec<String>,
+    entity_tags: Vec<(String, String)>,
+    cites: Vec<(String, String)>,
+    pending: usize,
+    report: LoadReport,
+}
+
+impl<'a> OpenAlexLoader<'a> {
+    pub fn new(arm: &'a AcademicResourceManager) -> Result<Self, RepositoryError> {
+        let index: HashMap<String, String> = arm.prop_index(OPENALEX_ID)?.into_iter().collect();
+        let claims = index.iter().map(|(oa, id)| (id.clone(), oa.clone())).collect();
+        let ours = index.values().cloned().collect();
+        Ok(OpenAlexLoader {
+            arm,
+            batch_size: 1000,
+            min_concept_score: MIN_CONCEPT_SCORE,
+            index,
+            claims,
+            ours,
+            venues: HashMap::new(),
+            entities: BTreeMap::new(),
+            edges: Vec::new(),
+            affiliations: BTreeMap::new(),
+            authorships: Vec::new(),
+            tags: Vec::new(),
+            entity_tags: Vec::new(),
+            cites: Vec::new(),
+            pending: 0,
+            report: LoadReport::default(),
+        })
+    }
+
+    /// Records per transaction, 1000 by default.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn min_concept_score(mut self, score: f64) -> Self {
+        self.min_concept_score = score;
+        self
+    }
+
+    // The entity an OpenAlex record maps to: the one it claimed before, or else the id computed
+    // for it after following `alias_of` edges, so that merged-away entities are not recreated.
+    fn id_for(&mut self, openalex: &str, computed: impl FnOnce() -> String) -> Result<String, RepositoryError> {
+        match self.index.get(openalex) {
+            Some(id) => Ok(id.clone()),
+            None => self.arm.resolve_alias(&computed()),
+        }
+    }
+
+    fn claim(&mut self, openalex: &str, id: &str) {
+        self.index.insert(openalex.to_string(), id.to_string());
+        self.claims.entry(id.to_string()).or_insert_with(|| openalex.to_string());
+    }
+
+    fn stage(&mut self, entity: Entity, stub: bool) {
+        if stub && self.entities.contains_key(&entity.id) {
+            return;
+        }
+        self.entities.insert(entity.id.clone(), Pending { entity, stub });
+    }
+
+    fn props(openalex: &str, fields: Value) -> Value {
+        let mut props = Map::new();
+        if let Value::Object(fields) = fields {
+            props.extend(fields.into_iter().filter(|(_, v)| !v.is_null()));
+        }
+        props.insert(OPENALEX_ID.to_string(), Value::String(openalex.to_string()));
+        Value::Object(props)
+    }
+
+    fn institution(&mut self, record: &Value, stub: bool) -> Result<Option<String>, RepositoryError> {
+        let (Some(openalex), Some(name)) = (short_id(record.get("id")), text(record, "/display_name")) else {
+            return Ok(None);
+        };
+        let ror = text(record, "/ror").and_then(|r| RorId::parse(r).ok());
+        let id = self.id_for(&openalex, || match &ror {
+            Some(ror) => ror_institution_id(ror),
+            None => institution_id(name),
+        })?;
+        self.claim(&openalex, &id);
+        let strings = |field: &str| list(record, field).filter_map(Value::as_str).collect::<Vec<_>>();
+        let props = Self::props(
+            &openalex,
+            json!({
+                "ror": ror.as_ref().map(RorId::as_str),
+                "country_code": text(record, "/country_code"),
+                "types": text(record, "/type").map(|t| vec![t]),
+                "aliases": Some(strings("display_name_alternatives")).filter(|a| !a.is_empty()),
+                "acronyms": Some(strings("display_name_acronyms")).filter(|a| !a.is_empty()),
+            }),
+        );
+        let mut builder = Entity::builder().id(&id).kind(INSTITUTION_KIND).title(name).props(props);
+        if let Some(ror) = &ror {
+            builder = builder.uri(ror.url());
+        }
+        self.stage(builder.build()?, stub);
+        Ok(Some(id))
+    }
+
+    fn author(&mut self, record: &Value, institutions: Vec<&Value>, stub: bool) -> Result<Option<String>, RepositoryError> {
+        let (Some(openalex), Some(person)) = (short_id(record.get("id")), person(record)) else {
+            return Ok(None);
+        };
+        let id = self.id_for(&openalex, || author_id(&person))?;
+        self.claim(&openalex, &id);
+        let props = Self::props(
+            &openalex,
+            json!({ "name": to_json(&person.name)?, "orcid": person.orcid.as_ref().map(|o| o.as_str()) }),
+        );
+        let mut builder = Entity::builder().id(&id).kind(AUTHOR_KIND).title(person.name.to_string()).props(props);
+        if let Some(orcid) = &person.orcid {
+            builder = builder.uri(orcid.url());
+        }
+        self.stage(builder.build()?, stub);
+        for institution in institutions {
+            if let (Some(inst), Some(affiliation)) = (self.institution(institution, true)?, affiliation(institution)) {
+                self.affiliations.insert((id.clone(), inst), affiliation);
+            }
+        }
+        Ok(Some(id))
+    }
+
+    fn topic(&mut self, record: &Value, stub: bool) -> Result<Option<String>, RepositoryError> {
+        let (Some(openalex), Some(label)) = (short_id(record.get("id")), text(record, "/display_name")) else {
+            return Ok(None);
+        };
+        let id = self.id_for(&openalex, || topic_id(label))?;
+        self.claim(&openalex, &id);
+        let props = Self::props(&openalex, json!({ "scheme": OPENALEX, "level": record.get("level") }));
+        let entity = Entity::builder().id(&id).kind(TOPIC_KIND).title(label).uri(format!("{ID_PREFIX}{openalex}")).props(props);
+        self.stage(entity.build()?, stub);
+        self.tags.push(label.to_string());
+        Ok(Some(id))
+    }
+
+    fn venue(&mut self, name: &str, issn: Option<&str>) -> Result<String, RepositoryError> {
+        let key = format!("{name}\u{0}{}", issn.unwrap_or_default());
+        if let Some(id) = self.venues.get(&key) {
+            return Ok(id.clone());
+        }
+        let id = self.arm.venue_for(name, issn)?;
+        self.venues.insert(key, id.clone());
+        Ok(id)
+    }
+
+    fn add_work(&mut self, record: &Value) -> Result<(), RepositoryError> {
+        let (Some(openalex), Some(mut reference)) = (short_id(record.get("id")), reference_from(record)) else {
+            self.report.skipped += 1;
+            return Ok(());
+        };
+        reference.keywords = concepts(record, self.min_concept_score);
+        let mut id = self.id_for(&openalex, || work_id(&reference))?;
+        // two works sharing year and title
+        if self.claims.get(&id).is_some_and(|other| *other != openalex) {
+            id = format!("work:openalex:{}", openalex.to_lowercase());
+        }
+        self.claim(&openalex, &id);
+        self.stage(reference_entity(&id, &reference, Map::new())?, false);
+
+        let mut position = 0;
+        for authorship in list(record, "authorships") {
+            let Some(author) = authorship.get("author") else { continue };
+            if let Some(author) = self.author(author, list(authorship, "institutions").collect(), true)? {
+                position += 1;
+                self.authorships.push(Edge::new(&author, &id, AUTHORED).with_props(json!({ "position": position })));
+            }
+        }
+        if let Some(venue) = &reference.venue {
+            let venue = self.venue(venue, reference.field("issn"))?;
+            self.edges.push(Edge::new(&id, &venue, PUBLISHED_IN));
+        }
+        for tag in &reference.keywords {
+            self.entity_tags.push((id.clone(), tag.clone()));
+        }
+        for cited in list(record, "referenced_works") {
+            if let Some(cited) = short_id(Some(cited)) {
+                self.cites.push((id.clone(), cited));
+            }
+        }
+        self.report.works += 1;
+        Ok(())
+    }
+
+    fn add_concept(&mut self, record: &Value) -> Result<(), RepositoryError> {
+        let Some(id) = self.topic(record, false)? else {
+            self.report.skipped += 1;
+            return Ok(());
+        };
+        // `ancestors` reaches the root; the parents are one level up
+        let level = record.get("level").and_then(Value::as_i64);
+        for ancestor in list(record, "ancestors") {
+            let parent = ancestor.get("level").and_then(Value::as_i64);
+            if level.is_none_or(|l| parent == Some(l - 1))
+                && let Some(parent) = self.topic(ancestor, true)?
+            {
+                self.edges.push(Edge::new(&id, &parent, BROADER));
+            }
+        }
+        self.report.concepts += 1;
+        Ok(())
+    }
+
+    /// Adds one OpenAlex record, as found in a snapshot line or an API response; the type comes
+    /// from the id. Records of other types are counted as skipped.
+    pub fn add(&mut self, record: &Value) -> Result<(), RepositoryError> {
+        match short_id(record.get("id")).and_then(|id| id.chars().next()) {
+            Some('W') => self.add_work(record)?,
+            Some('A') => {
+                let institutions = list(record, "last_known_institutions").chain(record.get("last_known_institution")).collect();
+                match self.author(record, institutions, false)? {
+                    Some(_) => self.report.authors += 1,
+                    None => self.report.skipped += 1,
+                }
+            }
+            Some('I') => match self.institution(record, false)? {
+                Some(id) => {
+                    for parent in list(record, "associated_institutions").filter(|a| text(a, "/relationship") == Some("parent")) {
+                        if let Some(parent) = self.institution(parent, true)? {
+                            self.edges.push(Edge::new(&id, &parent, PART_OF));
+                        }
+                    }
+                    self.report.institutions += 1;
+                }
+                None => self.report.skipped += 1,
+            },
+            Some('C') => self.add_concept(record)?,
+            _ => self.report.skipped += 1,
+        }
+        self.pending += 1;
+        if self.pending >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), RepositoryError> {
+        let pending = std::mem::take(&mut self.entities);
+        let ids: Vec<String> = pending.keys().cloned().collect();
+        let mut existing: HashMap<String, Option<Value>> = self.arm.get_entities(&ids)?.into_iter().map(|e| (e.id, e.props)).collect();
+        let mut entities = Vec::with_capacity(pending.len());
+        for (id, Pending { mut entity, stub }) in pending {
+            let mut props = match existing.remove(&id) {
+                None => Map::new(),
+                Some(_) if stub || !self.ours.contains(&id) => continue,
+                // props set by others, e.g. a citation key, outlive the rewrite
+                Some(Some(Value::Object(props))) => props,
+                Some(_) => Map::new(),
+            };
+            if let Some(Value::Object(fresh)) = entity.props.take() {
+                props.extend(fresh);
+            }
+            entity.props = Some(Value::Object(props));
+            self.ours.insert(id);
+            entities.push(entity);
+        }
+        let mut edges = std::mem::take(&mut self.edges);
+        let authorships = std::mem::take(&mut self.authorships);
+        edges.extend(authorships.into_iter().filter(|e| self.ours.contains(&e.dst)));
+        let affiliations = std::mem::take(&mut self.affiliations);
+        let mut authors: Vec<String> = affiliations.keys().map(|(author, _)| author.clone()).collect();
+        authors.dedup();
+        let mut stored: HashMap<(String, String), Option<Value>> =
+            self.arm.edges_from_any(&authors, AFFILIATED_WITH)?.into_iter().map(|e| ((e.src, e.dst), e.props)).collect();
+        for ((author, inst), affiliation) in affiliations {
+            // periods recorded at the institution by other importers stay on the edge
+            let props = with_current_affiliation(stored.remove(&(author.clone(), inst.clone())).flatten(), &affiliation)?;
+            edges.push(Edge::new(&author, &inst, AFFILIATED_WITH).with_props(props));
+        }
+        self.arm.upsert_batch(
+            &entities,
+            &edges,
+            &std::mem::take(&mut self.tags),
+            &std::mem::take(&mut self.entity_tags),
+        )?;
+        self.pending = 0;
+        Ok(())
+    }
+
+    /// Reads one record per line; lines that are not JSON are reported and skipped.
+    pub fn read(&mut self, reader: impl BufRead) -> Result<(), SourceError> {
+        for (n, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<Value>(&line) {
+                Ok(record) => self.add(&record)?,
+                Err(e) => self.report.errors.push(EntryError { line: n + 1, key: None, message: e.to_string() }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a snapshot part, gunzipping it when it ends in `.gz`.
+    pub fn read_file(&mut self, path: impl AsRef<Path>) -> Result<(), SourceError> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        match path.extension().is_some_and(|e| e == "gz") {
+            true => self.read(BufReader::new(MultiGzDecoder::new(file))),
+            false => self.read(BufReader::new(file)),
+        }
+    }
+
+    /// Reads every `.gz` and `.jsonl` part below `dir`, as laid out in the snapshot
+    /// (`works/updated_date=.../part_000.gz`), in path order.
+    pub fn read_dir(&mut self, dir: impl AsRef<Path>) -> Result<(), SourceError> {
+        let mut parts = Vec::new();
+        let mut dirs = vec![dir.as_ref().to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|e| e == "gz" || e == "jsonl") {
+                    parts.push(path);
+                }
+            }
+        }
+        parts.sort();
+        for part in parts {
+            self.read_file(part)?;
+        }
+        Ok(())
+    }
+
+    /// Writes what is left and the `cites` edges between works now in the graph.
+    pub fn finish(mut self) -> Result<LoadReport, RepositoryError> {
+        self.flush()?;
+        let mut edges = Vec::new();
+        for (src, cited) in std::mem::take(&mut self.cites) {
+            match self.index.get(&cited) {
+                Some(dst) if *dst != src => {
+                    edges.push(Edge::new(&src, dst, CITES));
+                    self.report.cites += 1;
+                }
+                Some(_) => {}
+                None => self.report.dangling += 1,
+            }
+            if edges.len() >= self.batch_size {
+                self.arm.upsert_edges(&std::mem::take(&mut edges))?;
+            }
+        }
+        self.arm.upsert_edges(&edges)?;
+        Ok(self.report)
+    }
+}
+
+/// Loads an OpenAlex snapshot part (`.gz` or plain JSON lines) or a whole snapshot directory.
+pub fn load_snapshot(arm: &AcademicResourceManager, path: impl AsRef<Path>) -> Result<LoadReport, SourceError> {
+    let path = path.as_ref();
+    let mut loader = OpenAlexLoader::new(arm)?;
+    match path.is_dir() {
+        true => loader.read_dir(path)?,
+        false => loader.read_file(path)?,
+    }
+    Ok(loader.finish()?)
+}
+
+/// Client for the OpenAlex REST API.
+#[derive(Debug)]
+pub struct OpenAlexClient {
+    fetcher: Fetcher,
+    base_url: String,
+    mailto: Option<String>,
+    api_key: Option<String>,
+}
+
+impl OpenAlexClient {
+    pub fn builder() -> OpenAlexClientBuilder {
+        OpenAlexClientBuilder::default()
+    }
+
+    fn identify(&self, query: &mut Vec<(&str, String)>) {
+        if let Some(mailto) = &self.mailto {
+            query.push(("mailto", mailto.clone()));
+        }
+        if let Some(key) = &self.api_key {
+            query.push(("api_key", key.clone()));
+        }
+    }
+
+    /// One record of `entity` (`works`, `authors`, `institutions`, `concepts`) by OpenAlex id,
+    /// URL or external id such as `doi:10.7717/peerj.4375`.
+    pub async fn record(&self, entity: &str, id: &str) -> Result<Option<Value>, ClientError> {
+        let id = id.trim();
+        let url = format!("{}/{entity}/{}", self.base_url, id.strip_prefix(ID_PREFIX).unwrap_or(id));
+        let mut query = Vec::new();
+        self.identify(&mut query);
+        self.fetcher.get_json(&url, &query).await
+    }
+
+    pub async fn work(&self, id: &str) -> Result<Option<Reference>, ClientError> {
+        Ok(self.record("works", id).await?.as_ref().and_then(reference_from))
+    }
+
+    /// Up to `max` records of `entity` matching an OpenAlex `filter` (e.g.
+    /// `concepts.id:C41008148,publication_year:2020`), paging with cursors.
+    pub async fn list(&self, entity: &str, filter: &str, max: usize) -> Result<Vec<Value>, ClientError> {
+        let url = format!("{}/{entity}", self.base_url);
+        let mut records = Vec::new();
+        let mut cursor = "*".to_string();
+        while records.len() < max {
+            let mut query = vec![
+                ("filter", filter.to_string()),
+                ("per-page", PER_PAGE.min(max - records.len()).to_string()),
+                ("cursor", cursor.clone()),
+            ];
+            self.identify(&mut query);
+            let Some(page) = self.fetcher.get_json(&url, &query).await? else {
+                break;
+            };
+            let results = page
+                .get("results")
+                .and_then(Value::as_array)
+                .ok_or_else(|| ClientError::Format(format!("{entity} page without `results`")))?;
+            records.extend(results.iter().take(max - records.len()).cloned());
+            match text(&page, "/meta/next_cursor") {
+                Some(next) if !results.is_empty() => cursor = next.to_string(),
+                _ => break,
+            }
+        }
+        Ok(records)
+    }
+
+    /// Loads up to `max` records of `entity` matching `filter` into the graph, like a snapshot.
+    pub async fn import(&self, arm: &AcademicResourceManager, entity: &str, filter: &str, max: usize) -> Result<LoadReport, ClientError> {
+        let records = self.list(entity, filter, max).await?;
+        let mut loader = OpenAlexLoader::new(arm)?;
+        for record in &records {
+            loader.add(record)?;
+        }
+        Ok(loader.finish()?)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct OpenAlexClientBuilder {
+    mailto: Option<String>,
+    api_key: Option<String>,
+    base_url: Option<String>,
+    policy: Option<RetryPolicy>,
+    timeout: Option<Duration>,
+}
+
+impl OpenAlexClientBuilder {
+    /// Contact address sent with every request, which routes it to the polite pool.
+    pub fn mailto(mut self, mailto: impl Into<String>) -> Self {
+        self.mailto = Some(mailto.into());
+        self
+    }
+
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn build(self) -> Result<OpenAlexClient, ClientError> {
+        let client = reqwest::Client::builder()
+            .user_agent(user_agent(self.mailto.as_deref()))
+            .timeout(self.timeout.unwrap_or(Duration::from_secs(30)))
+            .build()?;
+        Ok(OpenAlexClient {
+            fetcher: Fetcher::new(client, self.policy.unwrap_or_default()),
+            base_url: self.base_url.unwrap_or_else(|| BASE_URL.to_string()).trim_end_matches('/').to_string(),
+            mailto: self.mailto,
+            api_key: self.api_key,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use crate::database::authors::PERIODS;
+    use crate::database::Engine;
+    use crate::domain::topic::Topic;
+
+    const SNAPSHOT: &str = include_str!("fixtures/openalex_snapshot.jsonl");
+    const DEEP_LEARNING: &str = "doi:10.1038/nature14539";
+
+    fn gzipped(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name).join("works").join("updated_date=2024-01-01");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut encoder = GzEncoder::new(File::create(dir.join("part_000.gz")).unwrap(), Compression::default());
+        encoder.write_all(SNAPSHOT.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+        std::env::temp_dir().join(name)
+    }
+
+    #[test]
+    fn test_reference_from() {
+        let work: Value = serde_json::from_str(SNAPSHOT.lines().nth(2).unwrap()).unwrap();
+        let reference = reference_from(&work).unwrap();
+        assert_eq!((reference.kind.as_str(), reference.year), (JOURNAL_ARTICLE, Some(2015)));
+        assert_eq!(reference.doi(), Some("10.1038/nature14539"));
+        assert_eq!(reference.field("pmid"), Some("26017442"));
+        assert_eq!(reference.field("pages"), Some("436-444"));
+        assert_eq!(reference.field("abstract"), Some("Deep learning allows learning"));
+        assert_eq!(reference.keywords, vec!["Artificial intelligence", "Computer science"]);
+        assert_eq!(reference.authors[0].affiliation.as_ref().and_then(|a| a.country_code.as_deref()), Some("US"));
+    }
+
+    #[test]
+    fn test_bad_orcid_keeps_author() {
+        let work = json!({
+            "title": "Deep learning",
+            "authorships": [
+                { "author": { "id": "https://openalex.org/A1", "display_name": "Yann LeCun", "orcid": "https://orcid.org/0000-0002-1825-0098" } },
+                { "author": { "id": "https://openalex.org/A2", "display_name": "Geoffrey Hinton", "orcid": "https://orcid.org/0000-0002-1825-0097" } },
+            ],
+        });
+        let reference = reference_from(&work).unwrap();
+        let authors: Vec<(&str, Option<&str>)> = reference.authors.iter().map(|a| (a.name.last.as_str(), a.orcid.as_ref().map(|o| o.as_str()))).collect();
+        assert_eq!(authors, vec![("LeCun", None), ("Hinton", Some("0000-0002-1825-0097"))]);
+    }
+
+    #[test]
+    fn test_load_snapshot() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        // imported earlier from BibTeX: kept as is, but linked
+        let backprop = Reference {
+            kind: JOURNAL_ARTICLE.to_string(),
+            title: "Backprop".to_string(),
+            fields: BTreeMap::from([("doi".to_string(), "10.1038/323533a0".to_string())]),
+            ..Default::default()
+        };
+        let backprop = arm.save_reference(&backprop).unwrap();
+        // an earlier stint at MIT, from another importer
+        let yann = author_id(&Author::builder().name_from_str("Yann LeCun").unwrap().build().unwrap());
+        let mit = ror_institution_id(&RorId::parse("042nb2s44").unwrap());
+        let periods = json!({ PERIODS: [{ "institution": "MIT", "start": 1985, "end": 1987 }] });
+        arm.upsert_edge(&Edge::new(&yann, &mit, AFFILIATED_WITH).with_props(periods)).unwrap();
+        let dir = gzipped("poirot-openalex-snapshot");
+
+        let report = load_snapshot(&arm, &dir).unwrap();
+        assert_eq!((report.works, report.authors, report.institutions, report.concepts), (2, 1, 1, 1));
+        assert_eq!((report.skipped, report.cites, report.dangling), (1, 1, 1));
+        assert_eq!(report.errors.iter().map(|e| e.line).collect::<Vec<_>>(), vec![6]);
+
+        let work = arm.load_reference(DEEP_LEARNING).unwrap().unwrap();
+        assert_eq!(work.venue.as_deref(), Some("Nature"));
+        assert_eq!(work.field("number"), Some("7553"));
+        assert_eq!(work.keywords, vec!["Artificial intelligence", "Computer science"]);
+        assert!(arm.get_edge(DEEP_LEARNING, &backprop, CITES).unwrap().is_some());
+        assert_eq!(arm.load_reference(&backprop).unwrap().unwrap().title, "Backprop");
+        // its author list stays the one BibTeX gave it
+        assert!(arm.authors_of(&backprop).unwrap().is_empty());
+
+        // the author record replaced the stub its works made, under the same id
+        let hinton = &work.authors[1];
+        assert_eq!(hinton.orcid.as_ref().map(|o| o.as_str()), Some("0000-0002-1825-0097"));
+        assert_eq!(arm.load_institution(&mit).unwrap().unwrap().acronyms, vec!["MIT"]);
+        let lecun = arm.edges_to(DEEP_LEARNING, Some(AUTHORED)).unwrap().into_iter().find(|e| e.src.contains("lecun")).unwrap();
+        assert_eq!(lecun.src, yann);
+        let lecun = arm.load_author(&yann).unwrap().unwrap();
+        assert_eq!(lecun.affiliation.and_then(|a| a.institution).as_deref(), Some("Massachusetts Institute of Technology"));
+        assert_eq!(lecun.history.iter().map(|p| p.start).collect::<Vec<_>>(), vec![Some(1985)]);
+
+        let ai = arm.find_topic("Artificial intelligence").unwrap().unwrap();
+        assert_eq!(arm.broader_topics(&ai).unwrap(), vec![topic_id("Computer science")]);
+        assert_eq!(arm.entities_with_topic("Computer science").unwrap().len(), 1);
+
+        // loading again changes nothing, and keeps props set since
+        let mut entity = arm.get_entity(DEEP_LEARNING).unwrap().unwrap();
+        entity.props.as_mut().unwrap()["citation_key"] = json!("lecun2015");
+        arm.upsert_entity(&entity).unwrap();
+        let count = arm.list_entities(None).unwrap().len();
+        assert_eq!(load_snapshot(&arm, &dir).unwrap(), report);
+        assert_eq!(arm.list_entities(None).unwrap().len(), count);
+        assert_eq!(arm.load_reference(DEEP_LEARNING).unwrap().unwrap().key.as_deref(), Some("lecun2015"));
+    }
+
+    #[test]
+    fn test_load_follows_aliases() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        // merged away before OpenAlex was loaded
+        let yann = arm.save_author(&Author::builder().name_from_str("Yann LeCun").unwrap().build().unwrap()).unwrap();
+        let lecun = arm.save_author(&Author::builder().name_from_str("Y. LeCun").unwrap().build().unwrap()).unwrap();
+        arm.merge_entities(&lecun, &yann).unwrap();
+        let ai = arm.save_topic(&Topic::new("Artificial intelligence")).unwrap();
+        let machine = arm.save_topic(&Topic::new("Machine intelligence")).unwrap();
+        arm.merge_entities(&machine, &ai).unwrap();
+
+        let mut loader = OpenAlexLoader::new(&arm).unwrap();
+        loader.read(SNAPSHOT.as_bytes()).unwrap();
+        loader.finish().unwrap();
+        assert!(arm.get_entity(&yann).unwrap().is_none());
+        assert!(arm.get_entity(&ai).unwrap().is_none());
+        assert!(arm.get_edge(&lecun, DEEP_LEARNING, AUTHORED).unwrap().is_some());
+        assert_eq!(arm.broader_topics(&machine).unwrap(), vec![topic_id("Computer science")]);
+    }
+
+    #[tokio::test]
+    async fn test_import_pages() {
+        let server = MockServer::start().await;
+        let records: Vec<Value> = SNAPSHOT.lines().filter_map(|l| serde_json::from_str(l).ok()).collect();
+        let page = |results: &[Value], next: Option<&str>| {
+            ResponseTemplate::new(200).set_body_json(json!({ "meta": { "next_cursor": next }, "results": results }))
+        };
+        let filter = "concepts.id:C154945302";
+        for (cursor, results, next) in [("*", &records[2..3], Some("c2")), ("c2", &records[3..4], None)] {
+            Mock::given(method("GET"))
+                .and(path("/works"))
+                .and(query_param("filter", filter))
+                .and(query_param("cursor", cursor))
+                .and(query_param("mailto", "jane@example.org"))
+                .respond_with(page(results, next))
+                .mount(&server)
+                .await;
+        }
+        let policy = RetryPolicy { min_interval: Duration::ZERO, ..RetryPolicy::default() };
+        let client = OpenAlexClient::builder().mailto("jane@example.org").base_url(server.uri()).retry_policy(policy).build().unwrap();
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+
+        let report = client.import(&arm, "works", filter, 10).await.unwrap();
+        assert_eq!((report.works, report.cites, report.dangling), (2, 1, 1));
+        assert!(arm.get_edge(DEEP_LEARNING, "doi:10.1038/323533a0", CITES).unwrap().is_some());
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+use crate::database::authors::{AFFILIATED_WITH, AUTHORED, AUTHOR_KIND};
+use crate::database::repository::params;
+use crate::database::{AcademicResourceManager, RepositoryError};
+use crate::domain::author::{Author, COMPATIBLE_NAMES};
+
+// Weights of the evidence in a pair score; names alone never reach the default merge threshold.
+const NAME_WEIGHT: f64 = 0.7;
+const AFFILIATION_WEIGHT: f64 = 0.15;
+const COAUTHOR_WEIGHT: f64 = 0.15;
+
+#[derive(Error, Debug)]
+pub enum ResolverError {
+    #[error("Repository error: {0}")]
+    Repository(#[from] RepositoryError),
+    #[error("Invalid thresholds: review {review} must not exceed auto-merge {auto_merge}, both within 0..=1")]
+    Thresholds { review: f64, auto_merge: f64 },
+}
+
+/// Why two author nodes look like the same person.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Evidence {
+    Orcid,
+    Name(f64),
+    /// id of the shared institution
+    Affiliation(String),
+    /// number of shared co-authors
+    Coauthors(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeCandidate {
+    pub survivor: String,
+    pub duplicate: String,
+    pub score: f64,
+    pub evidence: Vec<Evidence>,
+}
+
+#[derive(Debug, Default)]
+pub struct Resolution {
+    pub merged: Vec<MergeCandidate>,
+    /// pairs scoring between the review and auto-merge thresholds, best first
+    pub review: Vec<MergeCandidate>,
+}
+
+// An author node with what the graph knows around it.
+struct Profile {
+    id: String,
+    author: Author,
+    works: BTreeSet<String>,
+    coauthors: BTreeSet<String>,
+    institutions: BTreeSet<String>,
+}
+
+impl Profile {
+    // survivors are the best documented node of a cluster
+    fn rank(&self) -> (bool, usize, usize) {
+        (self.author.orcid.is_some(), self.works.len(), self.author.name.given().len())
+    }
+}
+
+/// Finds author nodes that denote the same person. Candidates share the first word of their
+/// folded last name; pairs are scored on ORCID, name similarity, shared institutions and
+/// co-author overlap. Authors of the same work, or with different ORCID iDs, never match.
+#[derive(Debug, Clone)]
+pub struct AuthorResolver {
+    auto_merge: f64,
+    review: f64,
+}
+
+impl Default for AuthorResolver {
+    fn default() -> Self {
+        AuthorResolver { auto_merge: 0.85, review: 0.6 }
+    }
+}
+
+impl AuthorResolver {
+    pub fn builder() -> AuthorResolverBuilder {
+        AuthorResolverBuilder::default()
+    }
+
+    fn score(&self, a: &Profile, b: &Profile) -> Option<(f64, Vec<Evidence>)> {
+        match (&a.author.orcid, &b.author.orcid) {
+            (Some(x), Some(y)) if x == y => return Some((1.0, vec![Evidence::Orcid])),
+            (Some(_), Some(_)) => return None,
+            _ => {}
+        }
+        if !a.works.is_disjoint(&b.works) {
+            return None;
+        }
+        let name = a.author.name.similarity(&b.author.name);
+        if name < COMPATIBLE_NAMES {
+            return None;
+        }
+        let mut evidence = vec![Evidence::Name(name)];
+        let mut score = NAME_WEIGHT * name;
+        if let Some(institution) = a.institutions.intersection(&b.institutions).next() {
+            evidence.push(Evidence::Affiliation(institution.clone()));
+            score += AFFILIATION_WEIGHT;
+        }
+        let shared = a.coauthors.intersection(&b.coauthors).count();
+        if shared > 0 {
+            let smaller = a.coauthors.len().min(b.coauthors.len());
+            evidence.push(Evidence::Coauthors(shared));
+            score += COAUTHOR_WEIGHT * shared as f64 / smaller as f64;
+        }
+        Some((score, evidence))
+    }
+
+    fn profiles(&self, arm: &AcademicResourceManager) -> Result<Vec<Profile>, ResolverError> {
+        let edges = |kind: &str| -> Result<Vec<(String, String)>, RepositoryError> {
+            let script = "?[src, dst] := *edge{src, dst, kind}, kind = $kind";
+            let rows = arm.run_immutable(script, params([("kind", kind.into())]))?.rows;
+            Ok(rows
+                .iter()
+                .filter_map(|r| Some((r[0].get_str()?.to_string(), r[1].get_str()?.to_string())))
+                .collect())
+        };
+        let mut works: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        let mut authors_of: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for (author, work) in edges(AUTHORED)? {
+            works.entry(author.clone()).or_default().insert(work.clone());
+            authors_of.entry(work).or_default().insert(author);
+        }
+        let mut institutions: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for (author, institution) in edges(AFFILIATED_WITH)? {
+            institutions.entry(author).or_default().insert(institution);
+        }
+
+        let mut profiles = Vec::new();
+        for entity in arm.list_entities(Some(AUTHOR_KIND))? {
+            let Some(author) = arm.load_author(&entity.id)? else {
+                continue;
+            };
+            let works = works.remove(&entity.id).unwrap_or_default();
+            let coauthors = works
+                .iter()
+                .flat_map(|w| authors_of.get(w).into_iter().flatten())
+                .filter(|a| **a != entity.id)
+                .cloned()
+                .collect();
+            profiles.push(Profile {
+                institutions: institutions.remove(&entity.id).unwrap_or_default(),
+                id: entity.id,
+                author,
+                works,
+                coauthors,
+            });
+        }
+        Ok(profiles)
+    }
+
+    /// Every pair scoring at or above the review threshold, best first. Nothing is changed.
+    pub fn candidates(&self, arm: &AcademicResourceManager) -> Result<Vec<MergeCandidate>, ResolverError> {
+        let profiles = self.profiles(arm)?;
+        let mut blocks: BTreeMap<String, Vec<&Profile>> = BTreeMap::new();
+        for p in &profiles {
+            let last = p.author.name.last_key();
+            let block = last.split_whitespace().next().unwrap_or_default().to_string();
+            blocks.entry(block).or_default().push(p);
+        }
+
+        let mut candidates = Vec::new();
+        for block in blocks.values() {
+            for (i, a) in block.iter().enumerate() {
+                for b in &block[i + 1..] {
+                    let Some((score, evidence)) = self.score(a, b) else {
+                        continue;
+                    };
+                    if score < self.review {
+                        continue;
+                    }
+                    let (survivor, duplicate) = match (a.rank(), std::cmp::Reverse(&a.id)) >= (b.rank(), std::cmp::Reverse(&b.id)) {
+                        true => (a, b),
+                        false => (b, a),
+                    };
+                    candidates.push(MergeCandidate {
+                        survivor: survivor.id.clone(),
+                        duplicate: duplicate.id.clone(),
+                        score,
+                        evidence,
+                    });
+                }
+            }
+        }
+        candidates.sort_by(|x, y| y.score.total_cmp(&x.score).then_with(|| x.duplicate.cmp(&y.duplicate)));
+        Ok(candidates)
+    }
+
+    /// Merges every pair at or above the auto-merge threshold, clusters included (A~B and B~C
+    /// fold into one survivor), and returns the remaining pairs for review with merged ids
+    /// already replaced by their survivors.
+    pub fn resolve(&self, arm: &AcademicResourceManager) -> Result<Resolution, ResolverError> {
+        let candidates = self.candidates(arm)?;
+        let mut survivor_of: BTreeMap<String, String> = BTreeMap::new();
+        let find = |map: &BTreeMap<String, String>, id: &str| {
+            let mut current = id.to_string();
+            while let Some(next) = map.get(&current) {
+                current = next.clone();
+            }
+            current
+        };
+
+        let mut resolution = Resolution::default();
+        let (automatic, review): (Vec<_>, Vec<_>) = candidates.into_iter().partition(|c| c.score >= self.auto_merge);
+        for candidate in automatic {
+            let survivor = find(&survivor_of, &candidate.survivor);
+            let duplicate = find(&survivor_of, &candidate.duplicate);
+            if survivor == duplicate {
+                continue;
+            }
+            merge_authors(arm, &survivor, &duplicate)?;
+            survivor_of.insert(duplicate.clone(), survivor.clone());
+            resolution.merged.push(MergeCandidate { survivor, duplicate, ..candidate });
+        }
+        for candidate in review {
+            let survivor = find(&survivor_of, &candidate.survivor);
+            let duplicate = find(&survivor_of, &candidate.duplicate);
+            let seen = resolution.review.iter().any(|c| {
+                (c.survivor == survivor && c.duplicate == duplicate) || (c.survivor == duplicate && c.duplicate == survivor)
+            });
+            if survivor != duplicate && !seen {
+                resolution.review.push(MergeCandidate { survivor, duplicate, ..candidate });
+            }
+        }
+        Ok(resolution)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AuthorResolverBuilder {
+    auto_merge: Option<f64>,
+    review: Option<f64>,
+}
+
+impl AuthorResolverBuilder {
+    pub fn auto_merge(mut self, threshold: f64) -> Self {
+        self.auto_merge = Some(threshold);
+        self
+    }
+
+    pub fn review(mut self, threshold: f64) -> Self {
+        self.review = Some(threshold);
+        self
+    }
+
+    pub fn build(self) -> Result<AuthorResolver, ResolverError> {
+        let defaults = AuthorResolver::default();
+        let auto_merge = self.auto_merge.unwrap_or(defaults.auto_merge);
+        let review = self.review.unwrap_or(defaults.review.min(auto_merge));
+        if !(0.0..=1.0).contains(&auto_merge) || !(0.0..=1.0).contains(&review) || review > auto_merge {
+            return Err(ResolverError::Thresholds { review, auto_merge });
+        }
+        Ok(AuthorResolver { auto_merge, review })
+    }
+}
+
+/// Folds the author `duplicate` into `survivor`, e.g. after reviewing a candidate. Its edges
+/// move to the survivor and an `alias_of` edge makes later imports of the duplicate's name
+/// resolve to the survivor; the survivor gains the duplicate's ORCID if it had none.
+pub fn merge_authors(arm: &AcademicResourceManager, survivor: &str, duplicate: &str) -> Result<(), ResolverError> {
+    arm.merge_entities(survivor, duplicate)?;
+    // the survivor's entity uri follows its ORCID, which the prop merge may just have supplied
+    if let Some(author) = arm.load_author(survivor)?
+        && author.orcid.is_some()
+        && let Some(mut entity) = arm.get_entity(survivor)?
+    {
+        entity.uri = author.orcid.map(|o| o.url());
+        arm.upsert_entity(&entity)?;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::references::CITES;
+    use crate::database::repository::ALIAS_OF;
+    use crate::database::{Edge, Engine};
+    use crate::domain::affiliation::Affiliation;
+
+    fn author(name: &str) -> Author {
+        Author::builder().name_from_str(name).unwrap().build().unwrap()
+    }
+
+    fn at(name: &str, institution: &str) -> Author {
+        let mut a = author(name);
+        a.affiliation = Some(Affiliation {
+            institution: Some(institution.to_string()),
+            department: None,
+            address: None,
+            country: None,
+            country_code: None,
+        });
+        a
+    }
+
+    fn work(arm: &AcademicResourceManager, id: &str, authors: &[Author]) -> Vec<String> {
+        let entity = crate::database::Entity::builder().id(id).kind("journal_article").title(id).build().unwrap();
+        arm.upsert_entity(&entity).unwrap();
+        arm.save_work_authors(id, authors).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_merges_and_reviews() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let full = work(&arm, "w1", &[at("Jane H. Smith", "CERN"), author("Bob Lee")])[0].clone();
+        let short = work(&arm, "w2", &[at("J. Smith", "CERN"), author("Bob Lee")])[0].clone();
+        // same name as a co-author on the same paper: two people
+        work(&arm, "w3", &[author("Jane Smith"), author("Jane A. Smith"), author("Ann Roe")]);
+        arm.upsert_edge(&Edge::new("w9", "w2", CITES)).unwrap();
+
+        let resolution = AuthorResolver::default().resolve(&arm).unwrap();
+        assert_eq!(resolution.merged.len(), 1);
+        let merge = &resolution.merged[0];
+        assert_eq!((merge.survivor.as_str(), merge.duplicate.as_str()), (full.as_str(), short.as_str()));
+        assert!(merge.evidence.contains(&Evidence::Affiliation("institution:cern".to_string())));
+        assert!(merge.evidence.contains(&Evidence::Coauthors(1)));
+
+        assert!(arm.get_entity(&short).unwrap().is_none());
+        let works: Vec<String> = arm.works_of(&full).unwrap().into_iter().map(|w| w.id).collect();
+        assert_eq!(works, vec!["w1", "w2"]);
+        assert_eq!(arm.authors_of("w2").unwrap()[0].name.first, "Jane");
+        assert!(arm.get_edge("w9", "w2", CITES).unwrap().is_some());
+
+        // a plausible variant with nothing else in common goes to review, paired with the survivor;
+        // Jane A. Smith shares a paper with Jane Smith and is never proposed
+        assert_eq!(resolution.review.len(), 1);
+        let review = &resolution.review[0];
+        assert_eq!((review.survivor.as_str(), review.duplicate.as_str()), (full.as_str(), "author:smith-jane"));
+        assert!(review.score < 0.85 && review.score >= 0.6);
+
+        // importing the merged variant again lands on the survivor
+        let again = work(&arm, "w5", &[at("J. Smith", "CERN")]);
+        assert_eq!(again[0], full);
+        assert_eq!(arm.load_author(&full).unwrap().unwrap().name.first, "Jane");
+    }
+
+    #[test]
+    fn test_orcid_evidence() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let mut with_orcid = author("J. Smith");
+        with_orcid.orcid = Some("0000-0002-1825-0097".parse().unwrap());
+        let mut other_orcid = author("Jane Smith");
+        other_orcid.orcid = Some("0000-0002-1694-233X".parse().unwrap());
+        work(&arm, "w1", &[with_orcid]);
+        work(&arm, "w2", &[other_orcid]);
+        work(&arm, "w3", &[author("Jane Smith")]);
+
+        let candidates = AuthorResolver::builder().review(0.5).build().unwrap().candidates(&arm).unwrap();
+        // the two ORCID holders are never paired; the ORCID node survives
+        assert!(candidates.iter().all(|c| c.duplicate == "author:smith-jane"));
+        assert!(candidates.iter().any(|c| c.survivor == "author:orcid:0000-0002-1825-0097"));
+
+        assert!(matches!(
+            AuthorResolver::builder().auto_merge(0.5).review(0.7).build(),
+            Err(ResolverError::Thresholds { .. })
+        ));
+    }
+
+    #[test]
+    fn test_merge_authors_takes_orcid() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let mut with_orcid = author("J. Smith");
+        with_orcid.orcid = Some("0000-0002-1825-0097".parse().unwrap());
+        let plain = work(&arm, "w1", &[author("Jane Smith")])[0].clone();
+        let orcid = work(&arm, "w2", &[with_orcid])[0].clone();
+        merge_authors(&arm, &plain, &orcid).unwrap();
+        let survivor = arm.get_entity(&plain).unwrap().unwrap();
+        assert_eq!(survivor.uri.as_deref(), Some("https://orcid.org/0000-0002-1825-0097"));
+        assert_eq!(arm.resolve_alias(&orcid).unwrap(), plain);
+        // only the alias edge is left on the merged id
+        let left: Vec<Edge> = arm.edges_from(&orcid, None).unwrap().into_iter().chain(arm.edges_to(&orcid, None).unwrap()).collect();
+        assert_eq!(left.iter().map(|e| (e.dst.as_str(), e.kind.as_str())).collect::<Vec<_>>(), [(plain.as_str(), ALIAS_OF)]);
+        assert_eq!(arm.authors_of("w2").unwrap()[0].orcid.as_ref().map(|o| o.as_str()), Some("0000-0002-1825-0097"));
+
+        // a later import of the survivor's own name keeps what the merge gave it
+        assert_eq!(work(&arm, "w3", &[author("Jane Smith")])[0], plain);
+        let survivor = arm.get_entity(&plain).unwrap().unwrap();
+        assert_eq!(survivor.uri.as_deref(), Some("https://orcid.org/0000-0002-1825-0097"));
+        assert_eq!(arm.load_author(&plain).unwrap().unwrap().orcid, Some("0000-0002-1825-0097".parse().unwrap()));
+    }
+}
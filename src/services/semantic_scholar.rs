@@ -0,0 +1,441 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde_json::Value;
+use crate::database::references::CITES;
+use crate::database::{AcademicResourceManager, Edge};
+use crate::domain::author::{Author, Name};
+use crate::domain::sources::{
+    Reference, BOOK, CHAPTER, CONFERENCE_PAPER, DATASET, JOURNAL_ARTICLE, MISC, PREPRINT,
+};
+use crate::services::http::{user_agent, ClientError, Fetcher, RetryPolicy};
+use crate::services::works::{WorkIds, WorkResolver};
+
+/// Source name recorded in provenance for fields taken from Semantic Scholar.
+pub const SEMANTIC_SCHOLAR: &str = "semantic_scholar";
+/// Work prop holding the Semantic Scholar paper id.
+pub const S2_PAPER_ID: &str = "s2_paper_id";
+/// Work prop set to `true` on works created from Semantic Scholar alone.
+pub const STUB: &str = "stub";
+const BASE_URL: &str = "https://api.semanticscholar.org/graph/v1";
+const PAPER_FIELDS: &str = "paperId,externalIds,title,year,venue,journal,authors,publicationTypes";
+// Most results the API hands out per page.
+const MAX_PAGE: usize = 1000;
+
+fn kind_for(paper: &Value) -> &'static str {
+    let types: Vec<&str> = paper
+        .get("publicationTypes")
+        .and_then(Value::as_array)
+        .map_or(Vec::new(), |list| list.iter().filter_map(Value::as_str).collect());
+    let has = |name: &str| types.contains(&name);
+    if has("Conference") {
+        CONFERENCE_PAPER
+    } else if has("JournalArticle") || has("Review") {
+        JOURNAL_ARTICLE
+    } else if has("BookSection") {
+        CHAPTER
+    } else if has("Book") {
+        BOOK
+    } else if has("Dataset") {
+        DATASET
+    } else if paper.pointer("/externalIds/ArXiv").is_some() {
+        PREPRINT
+    } else {
+        MISC
+    }
+}
+
+/// Maps a Semantic Scholar paper onto a reference. Papers without a title, which the API
+/// returns for references it could not resolve, are skipped.
+pub fn reference_from(paper: &Value) -> Option<Reference> {
+    let text = |pointer: &str| paper.pointer(pointer).and_then(Value::as_str).filter(|s| !s.trim().is_empty());
+    let title = text("/title")?.to_string();
+    let authors = paper.get("authors").and_then(Value::as_array).map_or(Vec::new(), |list| {
+        list.iter()
+            .filter_map(|a| Name::parse(a.get("name")?.as_str()?).ok())
+            .filter_map(|name| Author::builder().name(name).ok()?.build().ok())
+            .collect()
+    });
+    let mut reference = Reference {
+        kind: kind_for(paper).to_string(),
+        title,
+        authors,
+        year: paper.get("year").and_then(Value::as_i64),
+        venue: text("/journal/name").or(text("/venue")).map(str::to_string),
+        ..Default::default()
+    };
+    let arxiv = text("/externalIds/ArXiv");
+    let fields = [
+        ("doi", text("/externalIds/DOI").map(str::to_lowercase)),
+        ("eprint", arxiv.map(str::to_string)),
+        ("eprinttype", arxiv.map(|_| "arxiv".to_string())),
+        ("pmid", text("/externalIds/PubMed").map(str::to_string)),
+        ("volume", text("/journal/volume").map(|v| v.trim().to_string())),
+        ("pages", text("/journal/pages").map(|p| p.trim().to_string())),
+    ];
+    for (name, value) in fields {
+        if let Some(value) = value {
+            reference.fields.insert(name.to_string(), value);
+        }
+    }
+    if let Some(id) = text("/paperId") {
+        reference.extra.insert(S2_PAPER_ID.to_string(), Value::String(id.to_string()));
+    }
+    Some(reference)
+}
+
+/// The id Semantic Scholar knows a work by: its paper id when an earlier import stored one,
+/// otherwise `DOI:`, `ARXIV:` or `PMID:` followed by the identifier.
+pub fn paper_id(reference: &Reference) -> Option<String> {
+    if let Some(id) = reference.extra.get(S2_PAPER_ID).and_then(Value::as_str) {
+        return Some(id.to_string());
+    }
+    let ids = WorkIds::of(reference);
+    ids.doi
+        .map(|doi| format!("DOI:{doi}"))
+        .or_else(|| ids.arxiv.map(|arxiv| format!("ARXIV:{arxiv}")))
+        .or_else(|| ids.pmid.map(|pmid| format!("PMID:{pmid}")))
+}
+
+/// What `import_citations` wrote for one work.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CitationImport {
+    /// works the imported work cites
+    pub references: Vec<String>,
+    /// works citing it
+    pub citations: Vec<String>,
+    /// works that were not in the graph before and were created from Semantic Scholar alone
+    pub stubs: Vec<String>,
+    pub embedded: bool,
+}
+
+/// Client for the Semantic Scholar Graph API.
+#[derive(Debug)]
+pub struct SemanticScholarClient {
+    fetcher: Fetcher,
+    base_url: String,
+    page_size: usize,
+    max_results: Option<usize>,
+    embeddings: bool,
+}
+
+impl SemanticScholarClient {
+    pub fn builder() -> SemanticScholarClientBuilder {
+        SemanticScholarClientBuilder::default()
+    }
+
+    pub async fn paper(&self, id: &str) -> Result<Option<Reference>, ClientError> {
+        let url = format!("{}/paper/{id}", self.base_url);
+        let paper = self.fetcher.get_json(&url, &[("fields", PAPER_FIELDS.to_string())]).await?;
+        Ok(paper.as_ref().and_then(reference_from))
+    }
+
+    /// The SPECTER v2 embedding of a paper, if Semantic Scholar has one.
+    pub async fn embedding(&self, id: &str) -> Result<Option<Vec<f32>>, ClientError> {
+        let url = format!("{}/paper/{id}", self.base_url);
+        let Some(paper) = self.fetcher.get_json(&url, &[("fields", "embedding.specter_v2".to_string())]).await? else {
+            return Ok(None);
+        };
+        let Some(vector) = paper.pointer("/embedding/vector").and_then(Value::as_array) else {
+            return Ok(None);
+        };
+        let values: Option<Vec<f32>> = vector.iter().map(|v| v.as_f64().map(|v| v as f32)).collect();
+        values.map(Some).ok_or_else(|| ClientError::Format(format!("embedding of `{id}` is not a list of numbers")))
+    }
+
+    // Every page of `/paper/{id}/{edge}`, taking the paper under `side` from each row.
+    async fn pages(&self, id: &str, edge: &str, side: &str) -> Result<Vec<Reference>, ClientError> {
+        let url = format!("{}/paper/{id}/{edge}", self.base_url);
+        let mut papers = Vec::new();
+        let mut offset = 0;
+        loop {
+            let limit = match self.max_results {
+                Some(max) => self.page_size.min(max.saturating_sub(offset)),
+                None => self.page_size,
+            };
+            if limit == 0 {
+                break;
+            }
+            let query = [("fields", PAPER_FIELDS.to_string()), ("offset", offset.to_string()), ("limit", limit.to_string())];
+            let Some(page) = self.fetcher.get_json(&url, &query).await? else {
+                break;
+            };
+            let rows = page
+                .get("data")
+                .and_then(Value::as_array)
+                .ok_or_else(|| ClientError::Format(format!("{edge} page without `data`")))?;
+            papers.extend(rows.iter().filter_map(|row| reference_from(row.get(side)?)));
+            match page.get("next").and_then(Value::as_u64) {
+                Some(next) if !rows.is_empty() => offset = next as usize,
+                _ => break,
+            }
+        }
+        Ok(papers)
+    }
+
+    /// Works cited by the paper `id`, following pagination.
+    pub async fn references(&self, id: &str) -> Result<Vec<Reference>, ClientError> {
+        self.pages(id, "references", "citedPaper").await
+    }
+
+    /// Works citing the paper `id`, following pagination.
+    pub async fn citations(&self, id: &str) -> Result<Vec<Reference>, ClientError> {
+        self.pages(id, "citations", "citingPaper").await
+    }
+
+    // The graph id of `paper`: the work holding its Semantic Scholar id, the work `resolver`
+    // matches, or a new one.
+    fn place(
+        arm: &AcademicResourceManager,
+        resolver: &WorkResolver,
+        known: &mut BTreeMap<String, String>,
+        paper: &Reference,
+        stubs: &mut Vec<String>,
+    ) -> Result<String, ClientError> {
+        let s2 = paper.extra.get(S2_PAPER_ID).and_then(Value::as_str).map(str::to_string);
+        if let Some(id) = s2.as_ref().and_then(|s2| known.get(s2))
+            && arm.get_entity(id)?.is_some()
+        {
+            return Ok(id.clone());
+        }
+        let id = match resolver.find(arm, paper)? {
+            Some((id, _)) => resolver.save_into(arm, &id, paper, SEMANTIC_SCHOLAR)?,
+            None => {
+                let mut stub = paper.clone();
+                stub.extra.insert(STUB.to_string(), Value::Bool(true));
+                let id = resolver.save_new(arm, &stub, SEMANTIC_SCHOLAR)?;
+                stubs.push(id.clone());
+                id
+            }
+        };
+        if let Some(s2) = s2 {
+            known.insert(s2, id.clone());
+        }
+        Ok(id)
+    }
+
+    /// Imports the citation neighbourhood of the stored work `id` as `cites` edges, in both
+    /// directions. Cited and citing works already in the graph are linked where they are; the
+    /// others are created from what Semantic Scholar knows of them and marked `stub`. With embeddings enabled,
+    /// the SPECTER vector of the work goes into `entity_vec`.
+    pub async fn import_citations(&self, arm: &AcademicResourceManager, resolver: &WorkResolver, id: &str) -> Result<CitationImport, ClientError> {
+        let work = arm.load_reference(id)?.ok_or_else(|| ClientError::Format(format!("no work `{id}`")))?;
+        let paper = paper_id(&work)
+            .ok_or_else(|| ClientError::Format(format!("work `{id}` has no identifier Semantic Scholar knows")))?;
+        let references = self.references(&paper).await?;
+        let citations = self.citations(&paper).await?;
+
+        let mut known = arm.prop_index(S2_PAPER_ID)?;
+        let mut report = CitationImport::default();
+        let mut edges = Vec::new();
+        for cited in &references {
+            let cited = Self::place(arm, resolver, &mut known, cited, &mut report.stubs)?;
+            if cited != id {
+                edges.push(Edge::new(id, &cited, CITES));
+                report.references.push(cited);
+            }
+        }
+        for citing in &citations {
+            let citing = Self::place(arm, resolver, &mut known, citing, &mut report.stubs)?;
+            if citing != id {
+                edges.push(Edge::new(&citing, id, CITES));
+                report.citations.push(citing);
+            }
+        }
+        arm.upsert_edges(&edges)?;
+
+        if self.embeddings && let Some(embedding) = self.embedding(&paper).await? {
+            arm.upsert_embedding(id, &embedding)?;
+            report.embedded = true;
+        }
+        Ok(report)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SemanticScholarClientBuilder {
+    api_key: Option<String>,
+    base_url: Option<String>,
+    policy: Option<RetryPolicy>,
+    timeout: Option<Duration>,
+    page_size: Option<usize>,
+    max_results: Option<usize>,
+    embeddings: bool,
+}
+
+impl SemanticScholarClientBuilder {
+    /// Key sent as `x-api-key`; keyed clients get a dedicated rate limit.
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Pacing and retries; by default one request a second, the limit for keyed clients.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Papers requested per page of references or citations, 100 by default and at most 1000.
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    /// Caps the references and the citations fetched per work; heavily cited papers have
+    /// tens of thousands.
+    pub fn max_results(mut self, max_results: usize) -> Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    /// Also stores the SPECTER embedding of every work whose citations are imported.
+    pub fn embeddings(mut self, embeddings: bool) -> Self {
+        self.embeddings = embeddings;
+        self
+    }
+
+    pub fn build(self) -> Result<SemanticScholarClient, ClientError> {
+        let mut headers = HeaderMap::new();
+        if let Some(key) = &self.api_key {
+            let value = HeaderValue::from_str(key).map_err(|_| ClientError::Format("API key is not a valid header value".to_string()))?;
+            headers.insert("x-api-key", value);
+        }
+        let client = reqwest::Client::builder()
+            .user_agent(user_agent(None))
+            .default_headers(headers)
+            .timeout(self.timeout.unwrap_or(Duration::from_secs(30)))
+            .build()?;
+        let policy = self.policy.unwrap_or(RetryPolicy { min_interval: Duration::from_secs(1), ..RetryPolicy::default() });
+        Ok(SemanticScholarClient {
+            fetcher: Fetcher::new(client, policy),
+            base_url: self.base_url.unwrap_or_else(|| BASE_URL.to_string()).trim_end_matches('/').to_string(),
+            page_size: self.page_size.unwrap_or(100).clamp(1, MAX_PAGE),
+            max_results: self.max_results,
+            embeddings: self.embeddings,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use crate::database::Engine;
+    use crate::database::repository::EMBEDDING_DIM;
+
+    const PAPER: &str = "/paper/DOI:10.1038/nature14539";
+
+    fn paper(id: &str, title: &str, year: i64, doi: Option<&str>) -> Value {
+        json!({
+            "paperId": id,
+            "externalIds": { "DOI": doi },
+            "title": title,
+            "year": year,
+            "venue": "",
+            "journal": { "name": "Nature", "volume": " 323", "pages": "533 - 536" },
+            "authors": [{ "authorId": "1", "name": "David E. Rumelhart" }],
+            "publicationTypes": ["JournalArticle"],
+        })
+    }
+
+    async fn mount(server: &MockServer, route: &str, offset: &str, body: Value) {
+        Mock::given(method("GET"))
+            .and(path(route))
+            .and(query_param("offset", offset))
+            .and(header("x-api-key", "secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(server)
+            .await;
+    }
+
+    #[test]
+    fn test_reference_from() {
+        let reference = reference_from(&paper("abc", "Learning representations", 1986, Some("10.1038/323533A0"))).unwrap();
+        assert_eq!((reference.kind.as_str(), reference.year), (JOURNAL_ARTICLE, Some(1986)));
+        assert_eq!(reference.venue.as_deref(), Some("Nature"));
+        assert_eq!(reference.doi(), Some("10.1038/323533a0"));
+        assert_eq!(reference.field("volume"), Some("323"));
+        assert_eq!(paper_id(&reference).as_deref(), Some("abc"));
+        assert!(reference_from(&json!({ "paperId": null, "title": null })).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_import_citations() {
+        let server = MockServer::start().await;
+        let refs = format!("{PAPER}/references");
+        let cited = |p: Value| json!({ "citedPaper": p });
+        mount(&server, &refs, "0", json!({ "offset": 0, "next": 2, "data": [
+            cited(paper("s2-backprop", "Learning representations by back-propagating errors", 1986, Some("10.1038/323533a0"))),
+            cited(paper("s2-lstm", "Long Short-Term Memory", 1997, None)),
+        ]}))
+        .await;
+        mount(&server, &refs, "2", json!({ "offset": 2, "data": [
+            cited(json!({ "paperId": null, "title": null })),
+            cited(paper("s2-dropout", "Dropout", 2014, None)),
+        ]}))
+        .await;
+        mount(&server, &format!("{PAPER}/citations"), "0", json!({ "offset": 0, "data": [
+            { "citingPaper": paper("s2-resnet", "Deep Residual Learning for Image Recognition", 2016, None) },
+        ]}))
+        .await;
+        let vector = vec![0.5; EMBEDDING_DIM];
+        Mock::given(method("GET"))
+            .and(path(PAPER))
+            .and(query_param("fields", "embedding.specter_v2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "embedding": { "model": "specter_v2", "vector": vector } })))
+            .mount(&server)
+            .await;
+
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let deep = Reference {
+            kind: JOURNAL_ARTICLE.to_string(),
+            title: "Deep learning".to_string(),
+            fields: BTreeMap::from([("doi".to_string(), "10.1038/nature14539".to_string())]),
+            ..Default::default()
+        };
+        let id = arm.save_reference(&deep).unwrap();
+        let backprop = Reference { title: "Learning representations by back-propagating errors".to_string(), ..deep.clone() };
+        let backprop = arm.save_reference(&Reference { fields: BTreeMap::from([("doi".to_string(), "10.1038/323533A0".to_string())]), ..backprop }).unwrap();
+
+        let policy = RetryPolicy { min_interval: Duration::ZERO, ..RetryPolicy::default() };
+        let client = SemanticScholarClient::builder()
+            .api_key("secret")
+            .base_url(server.uri())
+            .retry_policy(policy)
+            .page_size(2)
+            .embeddings(true)
+            .build()
+            .unwrap();
+        let report = client.import_citations(&arm, &WorkResolver::default(), &id).await.unwrap();
+
+        assert_eq!(report.references.len(), 3);
+        assert_eq!(report.references[0], backprop);
+        assert_eq!(report.stubs.len(), 3);
+        assert!(report.stubs.iter().all(|s| arm.get_entity(s).unwrap().unwrap().props.unwrap()[STUB] == Value::Bool(true)));
+        assert!(arm.get_entity(&backprop).unwrap().unwrap().props.unwrap().get(STUB).is_none());
+        assert_eq!(arm.edges_from(&id, Some(CITES)).unwrap().len(), 3);
+        let citing = &report.citations[0];
+        assert!(arm.get_edge(citing, &id, CITES).unwrap().is_some());
+        assert_eq!(arm.get_entity(citing).unwrap().unwrap().title, "Deep Residual Learning for Image Recognition");
+        assert_eq!(arm.get_embedding(&id).unwrap(), Some(vector));
+
+        // a second import links the same works instead of creating new ones
+        let again = client.import_citations(&arm, &WorkResolver::default(), &id).await.unwrap();
+        assert!(again.stubs.is_empty());
+        assert_eq!(again.references, report.references);
+    }
+}
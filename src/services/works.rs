@@ -0,0 +1,615 @@
+use std::collections::{BTreeMap, BTreeSet};
+use cozo::DataValue;
+use serde_json::{Map, Value};
+use thiserror::Error;
+use crate::database::references::{normalize_doi, work_id};
+use crate::database::repository::Params;
+use crate::database::{AcademicResourceManager, Entity, RepositoryError};
+use crate::domain::author::Name;
+use crate::domain::sources::{is_work_kind, Reference, CHAPTER, MISC};
+use crate::domain::types::{ArxivId, Isbn, Pmid};
+use crate::utils::text::{fold, similarity};
+
+/// Prop holding, for each field of a work, the source its current value came from.
+pub const PROVENANCE: &str = "provenance";
+
+const ARXIV_DOI_PREFIX: &str = "10.48550/arxiv.";
+
+#[derive(Error, Debug)]
+pub enum WorkResolverError {
+    #[error("Repository error: {0}")]
+    Repository(#[from] RepositoryError),
+    #[error("Title threshold must be within 0..=1, got {0}")]
+    Threshold(f64),
+}
+
+/// arXiv id without prefix or version: `arXiv:2101.00001v3` and `https://arxiv.org/abs/hep-th/9901001v1`
+/// give `2101.00001` and `hep-th/9901001`.
+pub fn normalize_arxiv(id: &str) -> Option<String> {
+    ArxivId::parse(id).ok().map(|id| id.to_string())
+}
+
+/// ISBN-13 digits; ISBN-10s are converted so both forms of one book compare equal.
+pub fn normalize_isbn(isbn: &str) -> Option<String> {
+    Isbn::parse(isbn).ok().map(|isbn| isbn.to_string())
+}
+
+pub fn normalize_pmid(pmid: &str) -> Option<String> {
+    Pmid::parse(pmid).ok().map(|pmid| pmid.to_string())
+}
+
+/// Normalized identifiers of a work, read from its fields and uri.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkIds {
+    pub doi: Option<String>,
+    pub arxiv: Option<String>,
+    pub pmid: Option<String>,
+    pub isbn: Option<String>,
+}
+
+impl WorkIds {
+    pub fn of(reference: &Reference) -> Self {
+        WorkIds::from_fields(&reference.fields, reference.uri.as_deref())
+    }
+
+    pub fn from_fields(fields: &BTreeMap<String, String>, uri: Option<&str>) -> Self {
+        let field = |name: &str| fields.get(name).map(String::as_str).filter(|v| !v.trim().is_empty());
+        let mut ids = WorkIds::default();
+        // arXiv DOIs name the preprint, not a published version
+        match field("doi").map(normalize_doi) {
+            Some(doi) if doi.starts_with(ARXIV_DOI_PREFIX) => ids.arxiv = normalize_arxiv(&doi[ARXIV_DOI_PREFIX.len()..]),
+            doi => ids.doi = doi,
+        }
+        let eprint_is_arxiv = ["eprinttype", "archiveprefix"]
+            .iter()
+            .any(|f| field(f).is_some_and(|t| t.eq_ignore_ascii_case("arxiv")));
+        ids.arxiv = ids
+            .arxiv
+            .or_else(|| field("arxiv").and_then(normalize_arxiv))
+            .or_else(|| field("eprint").filter(|_| eprint_is_arxiv).and_then(normalize_arxiv))
+            .or_else(|| uri.filter(|u| u.contains("arxiv.org/")).and_then(normalize_arxiv));
+        ids.pmid = field("pmid").and_then(normalize_pmid);
+        ids.isbn = field("isbn").and_then(normalize_isbn);
+        ids
+    }
+}
+
+/// How a reference was matched to a stored work.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkMatch {
+    Doi(String),
+    Arxiv(String),
+    Pmid(String),
+    Isbn(String),
+    /// folded title similarity; year and first author agree
+    Fuzzy(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkMerge {
+    pub survivor: String,
+    pub duplicate: String,
+    pub reason: WorkMatch,
+}
+
+// The parts of a work that matching looks at.
+struct Candidate {
+    id: String,
+    kind: String,
+    ids: WorkIds,
+    title: String,
+    year: Option<i64>,
+    first_author: Option<String>,
+}
+
+impl Candidate {
+    fn of(reference: &Reference) -> Self {
+        Candidate {
+            id: String::new(),
+            kind: reference.kind.clone(),
+            ids: WorkIds::of(reference),
+            title: fold(&reference.title),
+            year: reference.year,
+            first_author: reference.authors.first().map(|a| a.name.last_key()),
+        }
+    }
+
+    fn from_entity(entity: &Entity) -> Self {
+        let fields: BTreeMap<String, String> = entity
+            .props
+            .as_ref()
+            .and_then(|p| p.get("fields"))
+            .and_then(|f| serde_json::from_value(f.clone()).ok())
+            .unwrap_or_default();
+        let first_author = entity
+            .autors
+            .split(';')
+            .map(str::trim)
+            .find(|a| !a.is_empty())
+            .and_then(|a| Name::parse(a).ok())
+            .map(|n| n.last_key());
+        Candidate {
+            id: entity.id.clone(),
+            kind: entity.kind.clone(),
+            ids: WorkIds::from_fields(&fields, entity.uri.as_deref()),
+            title: fold(&entity.title),
+            year: entity.year,
+            first_author,
+        }
+    }
+
+    // first key for blocking: pairs can only match on an identifier or within one block
+    fn block(&self) -> String {
+        self.first_author
+            .clone()
+            .unwrap_or_else(|| self.title.split_whitespace().next().unwrap_or_default().to_string())
+    }
+}
+
+/// Finds works imported more than once and merges them. Works match on DOI, arXiv id
+/// (any version), PMID or ISBN, and otherwise on a similar title with close years and the same
+/// first author. Differing DOIs or arXiv ids always keep works apart.
+///
+/// When merged works disagree, each field keeps the value from the source ranked highest in
+/// `priority`; the source behind every field is recorded under the `provenance` prop.
+#[derive(Debug, Clone)]
+pub struct WorkResolver {
+    priority: Vec<String>,
+    title_threshold: f64,
+    year_tolerance: i64,
+}
+
+impl Default for WorkResolver {
+    fn default() -> Self {
+        WorkResolver { priority: Vec::new(), title_threshold: 0.9, year_tolerance: 1 }
+    }
+}
+
+impl WorkResolver {
+    pub fn builder() -> WorkResolverBuilder {
+        WorkResolverBuilder::default()
+    }
+
+    // position in the priority list; unknown and unlisted sources rank last
+    fn rank(&self, source: Option<&str>) -> usize {
+        source
+            .and_then(|s| self.priority.iter().position(|p| p == s))
+            .unwrap_or(self.priority.len())
+    }
+
+    fn compare(&self, a: &Candidate, b: &Candidate) -> Option<WorkMatch> {
+        let (x, y) = (&a.ids, &b.ids);
+        if let (Some(d1), Some(d2)) = (&x.doi, &y.doi) {
+            return (d1 == d2).then(|| WorkMatch::Doi(d1.clone()));
+        }
+        if let (Some(a1), Some(a2)) = (&x.arxiv, &y.arxiv) {
+            return (a1 == a2).then(|| WorkMatch::Arxiv(a1.clone()));
+        }
+        if let (Some(p1), Some(p2)) = (&x.pmid, &y.pmid)
+            && p1 == p2
+        {
+            return Some(WorkMatch::Pmid(p1.clone()));
+        }
+        // chapters share the ISBN of their book
+        if let (Some(i1), Some(i2)) = (&x.isbn, &y.isbn)
+            && i1 == i2
+            && a.kind != CHAPTER
+            && b.kind != CHAPTER
+        {
+            return Some(WorkMatch::Isbn(i1.clone()));
+        }
+
+        if a.title.is_empty() || a.first_author != b.first_author {
+            return None;
+        }
+        if let (Some(y1), Some(y2)) = (a.year, b.year)
+            && (y1 - y2).abs() > self.year_tolerance
+        {
+            return None;
+        }
+        let score = similarity(&a.title, &b.title);
+        (score >= self.title_threshold).then_some(WorkMatch::Fuzzy(score))
+    }
+
+    fn candidates(&self, arm: &AcademicResourceManager) -> Result<Vec<Candidate>, RepositoryError> {
+        Ok(arm
+            .list_entities(None)?
+            .iter()
+            .filter(|e| is_work_kind(&e.kind))
+            .map(Candidate::from_entity)
+            .collect())
+    }
+
+    // Works that can match `reference`: those whose props or uri mention one of its identifiers
+    // and those in its block, the same one `dedupe` groups by. The engine does the filtering, so
+    // an import does not read the whole graph for every record.
+    fn candidates_for(&self, arm: &AcademicResourceManager, reference: &Reference) -> Result<Vec<Candidate>, RepositoryError> {
+        let ids = WorkIds::of(reference);
+        // stored values are raw, so only look for the part every spelling contains
+        let needles = [ids.doi, ids.arxiv, ids.pmid, ids.isbn.map(|i| i[3..12].to_string())]
+            .into_iter()
+            .flatten()
+            .map(|n| n.replace(['-', ' '], ""));
+        let mut conditions = Vec::new();
+        let mut bound = Params::new();
+        for (n, needle) in needles.enumerate() {
+            conditions.push(format!(
+                "str_includes(regex_replace_all(lowercase(concat(to_string(props), ' ', coalesce(uri, ''))), '[- ]', ''), $id{n})"
+            ));
+            bound.insert(format!("id{n}"), DataValue::from(needle));
+        }
+        if !reference.title.trim().is_empty() {
+            match reference.authors.first() {
+                Some(author) => {
+                    conditions.push("str_includes(lowercase(autors), $author)".to_string());
+                    bound.insert("author".to_string(), DataValue::from(author.name.last.to_lowercase()));
+                }
+                None => conditions.push("autors == ''".to_string()),
+            }
+        }
+        Ok(arm
+            .entities_where(&conditions, bound)?
+            .iter()
+            .filter(|e| is_work_kind(&e.kind))
+            .map(Candidate::from_entity)
+            .collect())
+    }
+
+    /// The stored work `reference` denotes, if any. Identifier matches win over fuzzy ones.
+    pub fn find(&self, arm: &AcademicResourceManager, reference: &Reference) -> Result<Option<(String, WorkMatch)>, WorkResolverError> {
+        let incoming = Candidate::of(reference);
+        let mut best: Option<(String, WorkMatch)> = None;
+        for candidate in self.candidates_for(arm, reference)? {
+            let found = self.compare(&incoming, &candidate);
+            let better = match (&found, &best) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(WorkMatch::Fuzzy(new)), Some((_, WorkMatch::Fuzzy(old)))) => new > old,
+                (Some(WorkMatch::Fuzzy(_)), Some(_)) => false,
+                (Some(_), Some((_, WorkMatch::Fuzzy(_)))) => true,
+                (Some(_), Some(_)) => false,
+            };
+            if better && let Some(found) = found {
+                best = Some((candidate.id, found));
+            }
+        }
+        Ok(best)
+    }
+
+    /// Saves `reference` as imported from `source`, merging it into the work it matches. Fields
+    /// from a higher ranked source are kept; re-importing from the same source updates them.
+    pub fn save(&self, arm: &AcademicResourceManager, reference: &Reference, source: &str) -> Result<String, WorkResolverError> {
+        if let Some((id, _)) = self.find(arm, reference)?
+            && arm.get_entity(&id)?.is_some()
+        {
+            return self.save_into(arm, &id, reference, source);
+        }
+        self.save_new(arm, reference, source)
+    }
+
+    /// Saves `reference` as a new work without matching, for callers that already know it is
+    /// not in the graph (e.g. after `find` came back empty).
+    pub fn save_new(&self, arm: &AcademicResourceManager, reference: &Reference, source: &str) -> Result<String, WorkResolverError> {
+        // starting empty records the source of every field
+        let mut fresh = Reference { kind: reference.kind.clone(), ..Default::default() };
+        let mut id = work_id(reference);
+        // an unrelated work already holds the natural id
+        let base = id.clone();
+        let mut n = 1;
+        while arm.get_entity(&id)?.is_some() {
+            n += 1;
+            id = format!("{base}-{n}");
+        }
+        self.merge(&mut fresh, reference, |_| Some(source.to_string()), true);
+        Ok(arm.save_reference_at(&id, &fresh)?)
+    }
+
+    /// Merges `reference`, imported from `source`, into the stored work `id` without matching,
+    /// for callers that already know which work it describes (e.g. enrichment by lookup).
+    pub fn save_into(&self, arm: &AcademicResourceManager, id: &str, reference: &Reference, source: &str) -> Result<String, WorkResolverError> {
+        let mut current = arm.load_reference(id)?.ok_or_else(|| RepositoryError::NotFound(id.to_string()))?;
+        self.merge(&mut current, reference, |_| Some(source.to_string()), true);
+        Ok(arm.save_reference_at(id, &current)?)
+    }
+
+    /// Merges every group of duplicate works already in the graph. The survivor is the work with
+    /// the most fields; cites, tags and other edges of the duplicates move to it and their ids
+    /// stay resolvable through `alias_of` edges.
+    pub fn dedupe(&self, arm: &AcademicResourceManager) -> Result<Vec<WorkMerge>, WorkResolverError> {
+        let candidates = self.candidates(arm)?;
+        let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (i, c) in candidates.iter().enumerate() {
+            let keys = [
+                Some(format!("block:{}", c.block())),
+                c.ids.doi.as_ref().map(|d| format!("doi:{d}")),
+                c.ids.arxiv.as_ref().map(|a| format!("arxiv:{a}")),
+                c.ids.pmid.as_ref().map(|p| format!("pmid:{p}")),
+                c.ids.isbn.as_ref().map(|i| format!("isbn:{i}")),
+            ];
+            for key in keys.into_iter().flatten() {
+                groups.entry(key).or_default().push(i);
+            }
+        }
+        let mut pairs = BTreeSet::new();
+        for members in groups.values() {
+            for (n, i) in members.iter().enumerate() {
+                for j in &members[n + 1..] {
+                    pairs.insert((*i, *j));
+                }
+            }
+        }
+
+        let mut survivor_of: BTreeMap<String, String> = BTreeMap::new();
+        let find = |map: &BTreeMap<String, String>, id: &str| {
+            let mut current = id.to_string();
+            while let Some(next) = map.get(&current) {
+                current = next.clone();
+            }
+            current
+        };
+        let mut merges = Vec::new();
+        for (i, j) in pairs {
+            let Some(reason) = self.compare(&candidates[i], &candidates[j]) else {
+                continue;
+            };
+            let (a, b) = (find(&survivor_of, &candidates[i].id), find(&survivor_of, &candidates[j].id));
+            if a == b {
+                continue;
+            }
+            let (Some(first), Some(second)) = (arm.load_reference(&a)?, arm.load_reference(&b)?) else {
+                continue;
+            };
+            let ((survivor, mut kept), (duplicate, merged)) = match second.fields.len() > first.fields.len() {
+                true => ((b, second), (a, first)),
+                false => ((a, first), (b, second)),
+            };
+            let provenance = provenance_of(&merged);
+            self.merge(&mut kept, &merged, |field| provenance.get(field).cloned(), false);
+            arm.merge_entities(&survivor, &duplicate)?;
+            arm.save_reference_at(&survivor, &kept)?;
+            survivor_of.insert(duplicate.clone(), survivor.clone());
+            merges.push(WorkMerge { survivor, duplicate, reason });
+        }
+        Ok(merges)
+    }
+
+    // Folds `incoming` into `current` field by field. A value replaces the current one when the
+    // current one is missing or its source ranks lower; with `refresh` the same source also wins.
+    fn merge(&self, current: &mut Reference, incoming: &Reference, source_of: impl Fn(&str) -> Option<String>, refresh: bool) {
+        let mut provenance = provenance_of(current);
+        let mut take = |field: &str, present: bool| {
+            let new = source_of(field);
+            let old = provenance.get(field).cloned();
+            let wins = !present
+                || self.rank(new.as_deref()) < self.rank(old.as_deref())
+                || (refresh && new.is_some() && new == old);
+            if wins && let Some(new) = new {
+                provenance.insert(field.to_string(), new);
+            }
+            wins
+        };
+
+        if !incoming.title.is_empty() && take("title", !current.title.is_empty()) {
+            current.title = incoming.title.clone();
+        }
+        if !incoming.kind.is_empty() && incoming.kind != MISC && take("kind", !current.kind.is_empty() && current.kind != MISC) {
+            current.kind = incoming.kind.clone();
+        }
+        if incoming.year.is_some() && take("year", current.year.is_some()) {
+            current.year = incoming.year;
+        }
+        if incoming.venue.is_some() && take("venue", current.venue.is_some()) {
+            current.venue = incoming.venue.clone();
+        }
+        if incoming.uri.is_some() && take("uri", current.uri.is_some()) {
+            current.uri = incoming.uri.clone();
+        }
+        if !incoming.authors.is_empty() && take("authors", !current.authors.is_empty()) {
+            current.authors = incoming.authors.clone();
+        }
+        if !incoming.editors.is_empty() && take("editors", !current.editors.is_empty()) {
+            current.editors = incoming.editors.clone();
+        }
+        for (name, value) in &incoming.fields {
+            if take(name, current.fields.contains_key(name)) {
+                current.fields.insert(name.clone(), value.clone());
+            }
+        }
+        for keyword in &incoming.keywords {
+            if !current.keywords.contains(keyword) {
+                current.keywords.push(keyword.clone());
+            }
+        }
+        current.key = current.key.take().or_else(|| incoming.key.clone());
+        for (name, value) in incoming.extra.iter().filter(|(n, _)| *n != PROVENANCE) {
+            current.extra.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+        let provenance: Map<String, Value> = provenance.into_iter().map(|(k, v)| (k, Value::String(v))).collect();
+        current.extra.insert(PROVENANCE.to_string(), Value::Object(provenance));
+    }
+}
+
+fn provenance_of(reference: &Reference) -> BTreeMap<String, String> {
+    reference
+        .extra
+        .get(PROVENANCE)
+        .and_then(Value::as_object)
+        .map(|map| {
+            map.iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Default)]
+pub struct WorkResolverBuilder {
+    priority: Vec<String>,
+    title_threshold: Option<f64>,
+    year_tolerance: Option<i64>,
+}
+
+impl WorkResolverBuilder {
+    /// Sources from most to least trusted, e.g. `["crossref", "zotero", "bibtex", "arxiv"]`.
+    pub fn priority<I, S>(mut self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.priority = sources.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn title_threshold(mut self, threshold: f64) -> Self {
+        self.title_threshold = Some(threshold);
+        self
+    }
+
+    pub fn year_tolerance(mut self, years: i64) -> Self {
+        self.year_tolerance = Some(years);
+        self
+    }
+
+    pub fn build(self) -> Result<WorkResolver, WorkResolverError> {
+        let defaults = WorkResolver::default();
+        let title_threshold = self.title_threshold.unwrap_or(defaults.title_threshold);
+        if !(0.0..=1.0).contains(&title_threshold) {
+            return Err(WorkResolverError::Threshold(title_threshold));
+        }
+        Ok(WorkResolver {
+            priority: self.priority,
+            title_threshold,
+            year_tolerance: self.year_tolerance.unwrap_or(defaults.year_tolerance).abs(),
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::references::CITES;
+    use crate::database::{Edge, Engine};
+    use crate::domain::sources::{BOOK, JOURNAL_ARTICLE, PREPRINT};
+    use crate::domain::Author;
+
+    fn reference(title: &str, year: i64, author: &str, fields: &[(&str, &str)]) -> Reference {
+        Reference {
+            kind: JOURNAL_ARTICLE.to_string(),
+            title: title.to_string(),
+            year: Some(year),
+            authors: vec![Author::builder().name_from_str(author).unwrap().build().unwrap()],
+            fields: fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_normalize_identifiers() {
+        assert_eq!(normalize_arxiv("arXiv:2101.00001v3").as_deref(), Some("2101.00001"));
+        assert_eq!(normalize_arxiv("https://arxiv.org/abs/hep-th/9901001v1").as_deref(), Some("hep-th/9901001"));
+        assert_eq!(normalize_arxiv("https://arxiv.org/pdf/2101.12345v2.pdf").as_deref(), Some("2101.12345"));
+        assert_eq!(normalize_arxiv("not an id"), None);
+        assert_eq!(normalize_isbn("0-201-89683-4").as_deref(), Some("9780201896831"));
+        assert_eq!(normalize_isbn("978-0-201-89683-1").as_deref(), Some("9780201896831"));
+        assert_eq!(normalize_isbn("978-0-201-89683-2"), None);
+        assert_eq!(normalize_pmid("PMID: 00123").as_deref(), Some("123"));
+
+        let ids = WorkIds::from_fields(&BTreeMap::from([("doi".to_string(), "10.48550/arXiv.2101.00001".to_string())]), None);
+        assert_eq!((ids.doi, ids.arxiv.as_deref()), (None, Some("2101.00001")));
+    }
+
+    #[test]
+    fn test_save_merges_by_priority() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let resolver = WorkResolver::builder().priority(["zotero", "bibtex", "arxiv"]).build().unwrap();
+
+        let mut preprint = reference("Quantum Things", 2020, "Jane Smith", &[("eprint", "2001.01234v1"), ("eprinttype", "arxiv")]);
+        preprint.kind = PREPRINT.to_string();
+        let id = resolver.save(&arm, &preprint, "arxiv").unwrap();
+
+        // a later version of the preprint, with the published DOI added by BibTeX
+        let bib = reference("Quantum things", 2021, "Smith, J.", &[("eprint", "2001.01234v2"), ("eprinttype", "arxiv"), ("doi", "10.1000/QT"), ("volume", "7")]);
+        assert_eq!(resolver.save(&arm, &bib, "bibtex").unwrap(), id);
+        // Zotero has no identifiers; title, year and first author still match
+        let mut zotero = reference("Quantum Things!", 2021, "Jane Smith", &[("volume", "8")]);
+        zotero.venue = Some("Nature".to_string());
+        assert_eq!(resolver.find(&arm, &zotero).unwrap().map(|(i, _)| i), Some(id.clone()));
+        assert_eq!(resolver.save(&arm, &zotero, "zotero").unwrap(), id);
+        // lower ranked sources do not override
+        let again = reference("Quantum Things (v3)", 2022, "Jane Smith", &[("eprint", "2001.01234v3"), ("eprinttype", "arxiv")]);
+        assert_eq!(resolver.save(&arm, &again, "arxiv").unwrap(), id);
+
+        assert_eq!(arm.list_entities(None).unwrap().iter().filter(|e| is_work_kind(&e.kind)).count(), 1);
+        let work = arm.load_reference(&id).unwrap().unwrap();
+        assert_eq!(work.title, "Quantum Things!");
+        assert_eq!((work.kind.as_str(), work.year), (JOURNAL_ARTICLE, Some(2021)));
+        assert_eq!(work.field("volume"), Some("8"));
+        assert_eq!(work.field("doi"), Some("10.1000/QT"));
+        assert_eq!(work.authors[0].name.first, "Jane");
+        let provenance = provenance_of(&work);
+        assert_eq!(provenance["title"], "zotero");
+        assert_eq!(provenance["doi"], "bibtex");
+        assert_eq!(provenance["eprint"], "bibtex");
+        assert_eq!(provenance["eprinttype"], "bibtex");
+
+        // a different DOI is a different work, however close the title
+        let other = reference("Quantum Things", 2021, "Jane Smith", &[("doi", "10.1000/other")]);
+        assert_ne!(resolver.save(&arm, &other, "bibtex").unwrap(), id);
+    }
+
+    #[test]
+    fn test_save_records_provenance_of_new_work() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let work = reference("Graph Things", 2022, "Ann Lee", &[("doi", "10.1000/GT"), ("volume", "3")]);
+        let id = WorkResolver::default().save(&arm, &work, "crossref").unwrap();
+
+        let saved = arm.load_reference(&id).unwrap().unwrap();
+        assert_eq!((saved.title.as_str(), saved.year), ("Graph Things", Some(2022)));
+        assert_eq!(saved.field("volume"), Some("3"));
+        let provenance = provenance_of(&saved);
+        for field in ["title", "year", "authors", "doi", "volume"] {
+            assert_eq!(provenance.get(field).map(String::as_str), Some("crossref"), "{field}");
+        }
+    }
+
+    #[test]
+    fn test_find_reads_only_candidates() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let resolver = WorkResolver::default();
+        let mut book = reference("A Book", 2001, "Zoe Ray", &[("isbn", "0-201-89683-4")]);
+        book.kind = BOOK.to_string();
+        let id = arm.save_reference(&book).unwrap();
+        arm.save_reference(&reference("Deep Nets", 2019, "Ann Lee", &[("doi", "10.1000/DN")])).unwrap();
+
+        let isbn13 = reference("Another Title", 2005, "Bob Roe", &[("isbn", "978-0-201-89683-1")]);
+        assert_eq!(resolver.find(&arm, &isbn13).unwrap(), Some((id.clone(), WorkMatch::Isbn("9780201896831".to_string()))));
+        let candidates = resolver.candidates_for(&arm, &isbn13).unwrap();
+        assert_eq!(candidates.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(), [id.as_str()]);
+        // the same first author blocks together, whatever the title
+        let lee = reference("Something Else", 2019, "Ann Lee", &[]);
+        assert_eq!(resolver.candidates_for(&arm, &lee).unwrap().len(), 1);
+        assert_eq!(resolver.find(&arm, &lee).unwrap(), None);
+    }
+
+    #[test]
+    fn test_dedupe_existing_works() {
+        let arm = AcademicResourceManager::new(Engine::Mem, ":memory:").unwrap();
+        let a = arm.save_reference(&reference("Deep Nets", 2019, "Ann Lee", &[("pmid", "123")])).unwrap();
+        let b = arm.save_reference(&reference("Deep Networks", 2019, "Bob Roe", &[("pmid", "PMID:0123"), ("pages", "1-9")])).unwrap();
+        let mut book = reference("A Book", 2001, "Zoe Ray", &[("isbn", "0-201-89683-4")]);
+        book.kind = BOOK.to_string();
+        let c = arm.save_reference(&book).unwrap();
+        book.kind = CHAPTER.to_string();
+        book.title = "A Chapter".to_string();
+        let d = arm.save_reference(&book).unwrap();
+        arm.upsert_edge(&Edge::new(&c, &a, CITES)).unwrap();
+
+        let merges = WorkResolver::default().dedupe(&arm).unwrap();
+        assert_eq!(merges, vec![WorkMerge { survivor: b.clone(), duplicate: a.clone(), reason: WorkMatch::Pmid("123".to_string()) }]);
+        assert!(arm.get_edge(&c, &b, CITES).unwrap().is_some());
+        assert_eq!(arm.resolve_alias(&a).unwrap(), b);
+        assert!(arm.get_entity(&d).unwrap().is_some());
+        assert_eq!(arm.authors_of(&b).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,362 @@
+use unicode_normalization::UnicodeNormalization;
+
+// Decoding of the LaTeX subset found in bibliography fields: accents, escaped specials,
+// ligature commands, dashes and case-protecting braces. Math (`$...$`) is left untouched.
+
+fn accent_mark(cmd: &str) -> Option<char> {
+    Some(match cmd {
+        "`" => '\u{0300}',
+        "'" => '\u{0301}',
+        "^" => '\u{0302}',
+        "~" => '\u{0303}',
+        "=" => '\u{0304}',
+        "u" => '\u{0306}',
+        "." => '\u{0307}',
+        "\"" => '\u{0308}',
+        "r" => '\u{030A}',
+        "H" => '\u{030B}',
+        "v" => '\u{030C}',
+        "d" => '\u{0323}',
+        "c" => '\u{0327}',
+        "k" => '\u{0328}',
+        "b" => '\u{0331}',
+        _ => return None,
+    })
+}
+
+fn symbol(cmd: &str) -> Option<&'static str> {
+    Some(match cmd {
+        "ss" => "ß",
+        "o" => "ø",
+        "O" => "Ø",
+        "aa" => "å",
+        "AA" => "Å",
+        "ae" => "æ",
+        "AE" => "Æ",
+        "oe" => "œ",
+        "OE" => "Œ",
+        "l" => "ł",
+        "L" => "Ł",
+        "i" => "ı",
+        "j" => "ȷ",
+        "dh" => "ð",
+        "DH" => "Ð",
+        "th" => "þ",
+        "TH" => "Þ",
+        "textendash" => "–",
+        "textemdash" => "—",
+        "textquoteright" => "’",
+        "textquoteleft" => "‘",
+        "ldots" | "dots" | "textellipsis" => "…",
+        "S" => "§",
+        "P" => "¶",
+        "copyright" => "©",
+        "textasciitilde" => "~",
+        "textasciicircum" => "^",
+        "textbackslash" => "\\",
+        "LaTeX" => "LaTeX",
+        "TeX" => "TeX",
+        "&" => "&",
+        "%" => "%",
+        "$" => "$",
+        "#" => "#",
+        "_" => "_",
+        "{" => "{",
+        "}" => "}",
+        " " => " ",
+        "," => " ",
+        "\\" => " ",
+        "-" => "",
+        _ => return None,
+    })
+}
+
+struct Decoder {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Decoder {
+    fn new(src: &str) -> Self {
+        Decoder { chars: src.chars().collect(), pos: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    // Reads `{...}` (without the outer braces) or a single token.
+    fn argument(&mut self) -> String {
+        self.skip_spaces();
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                let start = self.pos;
+                let mut depth = 1;
+                while let Some(c) = self.peek() {
+                    match c {
+                        '{' => depth += 1,
+                        '}' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    self.pos += 1;
+                }
+                let arg: String = self.chars[start..self.pos.min(self.chars.len())].iter().collect();
+                self.pos += 1;
+                arg
+            }
+            Some('\\') => {
+                let start = self.pos;
+                self.pos += 1;
+                while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                    self.pos += 1;
+                }
+                self.chars[start..self.pos].iter().collect()
+            }
+            Some(c) => {
+                self.pos += 1;
+                c.to_string()
+            }
+            None => String::new(),
+        }
+    }
+
+    fn command(&mut self, out: &mut String) {
+        // at the backslash
+        self.pos += 1;
+        let Some(first) = self.peek() else {
+            return;
+        };
+        let name: String = if first.is_ascii_alphabetic() {
+            let start = self.pos;
+            while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                self.pos += 1;
+            }
+            let name = self.chars[start..self.pos].iter().collect();
+            // TeX swallows spaces after a control word
+            self.skip_spaces();
+            name
+        } else {
+            self.pos += 1;
+            first.to_string()
+        };
+
+        if let Some(mark) = accent_mark(&name) {
+            let arg = self.argument();
+            let base = match arg.as_str() {
+                "\\i" | "i" => "i".to_string(),
+                "\\j" | "j" => "j".to_string(),
+                other => decode_inner(other),
+            };
+            let mut chars = base.chars();
+            if let Some(c) = chars.next() {
+                out.push(c);
+                out.push(mark);
+                out.extend(chars);
+            }
+        } else if let Some(s) = symbol(&name) {
+            out.push_str(s);
+        } else if self.peek() == Some('{') {
+            // formatting commands like \emph{..}, \textbf{..}: keep the argument
+            let arg = self.argument();
+            out.push_str(&decode_inner(&arg));
+        }
+    }
+
+    fn run(mut self) -> String {
+        let mut out = String::with_capacity(self.chars.len());
+        while let Some(c) = self.peek() {
+            match c {
+                '\\' => self.command(&mut out),
+                '{' | '}' => self.pos += 1,
+                '~' => {
+                    out.push(' ');
+                    self.pos += 1;
+                }
+                '$' => {
+                    // copy math verbatim
+                    let start = self.pos;
+                    self.pos += 1;
+                    while self.peek().is_some_and(|c| c != '$') {
+                        self.pos += 1;
+                    }
+                    self.pos += 1;
+                    out.extend(&self.chars[start..self.pos.min(self.chars.len())]);
+                }
+                '-' if self.chars.get(self.pos + 1) == Some(&'-') => {
+                    if self.chars.get(self.pos + 2) == Some(&'-') {
+                        out.push('—');
+                        self.pos += 3;
+                    } else {
+                        out.push('–');
+                        self.pos += 2;
+                    }
+                }
+                _ => {
+                    out.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        out
+    }
+}
+
+fn decode_inner(s: &str) -> String {
+    Decoder::new(s).run()
+}
+
+/// Decodes a LaTeX-encoded field value into NFC Unicode with collapsed whitespace.
+pub fn decode(s: &str) -> String {
+    let decoded: String = decode_inner(s).nfc().collect();
+    decoded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn accent_command(mark: char) -> Option<&'static str> {
+    Some(match mark {
+        '\u{0300}' => "`",
+        '\u{0301}' => "'",
+        '\u{0302}' => "^",
+        '\u{0303}' => "~",
+        '\u{0304}' => "=",
+        '\u{0306}' => "u",
+        '\u{0307}' => ".",
+        '\u{0308}' => "\"",
+        '\u{030A}' => "r",
+        '\u{030B}' => "H",
+        '\u{030C}' => "v",
+        '\u{0323}' => "d",
+        '\u{0327}' => "c",
+        '\u{0328}' => "k",
+        '\u{0331}' => "b",
+        _ => return None,
+    })
+}
+
+fn symbol_command(c: char) -> Option<&'static str> {
+    Some(match c {
+        'ß' => "{\\ss}",
+        'ø' => "{\\o}",
+        'Ø' => "{\\O}",
+        'å' => "{\\aa}",
+        'Å' => "{\\AA}",
+        'æ' => "{\\ae}",
+        'Æ' => "{\\AE}",
+        'œ' => "{\\oe}",
+        'Œ' => "{\\OE}",
+        'ł' => "{\\l}",
+        'Ł' => "{\\L}",
+        'ı' => "{\\i}",
+        'ð' => "{\\dh}",
+        'Ð' => "{\\DH}",
+        'þ' => "{\\th}",
+        'Þ' => "{\\TH}",
+        '–' => "--",
+        '—' => "---",
+        '…' => "\\ldots{}",
+        '§' => "\\S{}",
+        '¶' => "\\P{}",
+        '©' => "\\copyright{}",
+        '&' => "\\&",
+        '%' => "\\%",
+        '#' => "\\#",
+        '_' => "\\_",
+        '{' => "\\{",
+        '}' => "\\}",
+        '~' => "\\textasciitilde{}",
+        '^' => "\\textasciicircum{}",
+        '\\' => "\\textbackslash{}",
+        _ => return None,
+    })
+}
+
+/// Encodes text for a BibTeX field: specials are escaped, accented Latin letters become
+/// accent commands (`é` -> `{\'e}`), other characters stay UTF-8. `$...$` math is kept as is.
+pub fn encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut in_math = false;
+    for c in s.nfc() {
+        if c == '$' {
+            in_math = !in_math;
+            out.push(c);
+            continue;
+        }
+        if in_math || (c.is_ascii() && symbol_command(c).is_none()) {
+            out.push(c);
+            continue;
+        }
+        if let Some(cmd) = symbol_command(c) {
+            out.push_str(cmd);
+            continue;
+        }
+        let decomposed: Vec<char> = c.to_string().nfd().collect();
+        match decomposed.as_slice() {
+            [base, mark] if base.is_ascii_alphabetic() => match accent_command(*mark) {
+                Some(cmd) => {
+                    let base = match base {
+                        'i' => "\\i".to_string(),
+                        b => b.to_string(),
+                    };
+                    let sep = if cmd.chars().all(|c| c.is_ascii_alphabetic()) { " " } else { "" };
+                    out.push_str(&format!("{{\\{cmd}{sep}{base}}}"));
+                }
+                None => out.push(c),
+            },
+            _ => out.push(c),
+        }
+    }
+    // an unterminated `$` was not math after all
+    if in_math {
+        return encode(&s.replace('$', "\u{0}")).replace('\u{0}', "\\$");
+    }
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_accents() {
+        assert_eq!(decode(r"Gabriel Garc{\'\i}a M{\'a}rquez"), "Gabriel García Márquez");
+        assert_eq!(decode(r#"Erd\H{o}s and G\"{o}del"#), "Erdős and Gödel");
+        assert_eq!(decode(r"Fran\c{c}ois \v{S}koda"), "François Škoda");
+        assert_eq!(decode(r#"Stra{\ss}e {\AA}ngstr{\"o}m \o"#), "Straße Ångström ø");
+    }
+
+    #[test]
+    fn test_decode_specials_and_braces() {
+        assert_eq!(decode(r"{The {RNA} World} \& more"), "The RNA World & more");
+        assert_eq!(decode("pages 12--15 --- done"), "pages 12–15 — done");
+        assert_eq!(decode(r"\emph{Deep}   learning"), "Deep learning");
+        assert_eq!(decode(r"Energy $E=mc^2$"), "Energy $E=mc^2$");
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("García Márquez"), r"Garc{\'\i}a M{\'a}rquez");
+        assert_eq!(encode("Erdős & Škoda"), r"Erd{\H o}s \& {\v S}koda");
+        assert_eq!(encode("Straße 50% off_"), r"Stra{\ss}e 50\% off\_");
+        assert_eq!(encode("Energy $E=mc^2$ costs $5"), r"Energy \$E=mc\textasciicircum{}2\$ costs \$5");
+        assert_eq!(encode("Energy $E=mc^2$"), "Energy $E=mc^2$");
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        for s in ["Gödel, Escher, Bach", "Ångström ø æ", "pages 12–15", "R&D {braces} ~^"] {
+            assert_eq!(decode(&encode(s)), s);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use unicode_normalization::UnicodeNormalization;
+
+// Lowercases and replaces every run of non-alphanumeric characters with a single `-`,
+// giving ids that are stable across imports of the same name.
+pub fn slugify(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut pending_dash = false;
+    for c in s.chars() {
+        if c.is_alphanumeric() {
+            if pending_dash && !out.is_empty() {
+                out.push('-');
+            }
+            pending_dash = false;
+            out.extend(c.to_lowercase());
+        } else {
+            pending_dash = true;
+        }
+    }
+    out
+}
+
+// Letters with no canonical decomposition to ASCII.
+fn latin_letter(c: char) -> Option<&'static str> {
+    Some(match c {
+        'ł' => "l",
+        'Ł' => "L",
+        'ø' => "o",
+        'Ø' => "O",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'œ' => "oe",
+        'Œ' => "OE",
+        'ß' => "ss",
+        'đ' | 'ð' => "d",
+        'Đ' | 'Ð' => "D",
+        'þ' => "th",
+        'Þ' => "Th",
+        'ı' => "i",
+        _ => return None,
+    })
+}
+
+// Lowercase Cyrillic and Greek to Latin, close to the scientific/ISO 9 and ELOT 743 schemes.
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'а' => "a", 'б' => "b", 'в' => "v", 'г' => "g", 'ґ' => "g", 'д' => "d", 'е' => "e", 'ё' => "e",
+        'є' => "ie", 'ж' => "zh", 'з' => "z", 'и' => "i", 'і' => "i", 'ї' => "i", 'й' => "i", 'к' => "k",
+        'л' => "l", 'м' => "m", 'н' => "n", 'о' => "o", 'п' => "p", 'р' => "r", 'с' => "s", 'т' => "t",
+        'у' => "u", 'ф' => "f", 'х' => "kh", 'ц' => "ts", 'ч' => "ch", 'ш' => "sh", 'щ' => "shch",
+        'ъ' | 'ь' => "", 'ы' => "y", 'э' => "e", 'ю' => "iu", 'я' => "ia",
+        'α' => "a", 'β' => "v", 'γ' => "g", 'δ' => "d", 'ε' => "e", 'ζ' => "z", 'η' => "i", 'θ' => "th",
+        'ι' => "i", 'κ' => "k", 'λ' => "l", 'μ' => "m", 'ν' => "n", 'ξ' => "x", 'ο' => "o", 'π' => "p",
+        'ρ' => "r", 'σ' | 'ς' => "s", 'τ' => "t", 'υ' => "y", 'φ' => "f", 'χ' => "ch", 'ψ' => "ps",
+        'ω' => "o",
+        _ => return None,
+    })
+}
+
+// Drops diacritics and anything else outside ASCII letters and digits ("Gödel" -> "Godel").
+pub fn ascii_fold(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.nfd() {
+        match latin_letter(c) {
+            Some(replacement) => out.push_str(replacement),
+            None if c.is_ascii_alphanumeric() => out.push(c),
+            None => {}
+        }
+    }
+    out
+}
+
+// Comparison form of a string: NFKD with accents dropped, case folded, Cyrillic and Greek
+// transliterated, apostrophes removed and any other punctuation turned into single spaces
+// ("Jean-Paul O’Brien" -> "jean paul obrien", "Мельник" -> "melnik").
+pub fn fold(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut pending_space = false;
+    for c in s.nfkd().flat_map(char::to_lowercase) {
+        let letters = match (latin_letter(c), transliterate(c)) {
+            (Some(l), _) | (None, Some(l)) => l,
+            _ if unicode_normalization::char::is_combining_mark(c) || matches!(c, '\'' | '’' | 'ʼ') => continue,
+            _ if c.is_alphanumeric() => {
+                if pending_space && !out.is_empty() {
+                    out.push(' ');
+                }
+                pending_space = false;
+                out.push(c);
+                continue;
+            }
+            _ => {
+                pending_space = true;
+                continue;
+            }
+        };
+        if pending_space && !out.is_empty() {
+            out.push(' ');
+        }
+        pending_space = false;
+        out.push_str(letters);
+    }
+    out
+}
+
+// Edit distance in characters.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1).min(row[j] + 1).min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+// 1.0 for identical strings down to 0.0, relative to the longer one.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    match longest {
+        0 => 1.0,
+        n => 1.0 - levenshtein(a, b) as f64 / n as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Smith, Jane H."), "smith-jane-h");
+        assert_eq!(slugify("  Université de Genève "), "université-de-genève");
+        assert_eq!(slugify("--"), "");
+    }
+
+    #[test]
+    fn test_ascii_fold() {
+        assert_eq!(ascii_fold("García Márquez"), "GarciaMarquez");
+        assert_eq!(ascii_fold("Łukasz Ørsted"), "LukaszOrsted");
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("Jean-Paul  O’Brien"), "jean paul obrien");
+        assert_eq!(fold("GARCÍA MÁRQUEZ"), "garcia marquez");
+        assert_eq!(fold("Łukasz Ørsted-ﬁeld"), "lukasz orsted field");
+        assert_eq!(fold("Мельник"), "melnik");
+        assert_eq!(fold("Αλεξάνδρα"), "alexandra");
+        assert_eq!(fold("Straße"), "strasse");
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(similarity("smith", "smith"), 1.0);
+        assert!((similarity("smith", "smyth") - 0.8).abs() < 1e-9);
+    }
+}